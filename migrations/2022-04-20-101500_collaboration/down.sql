@@ -0,0 +1 @@
+DROP TABLE sheet_updates;
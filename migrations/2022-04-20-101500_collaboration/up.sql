@@ -0,0 +1,10 @@
+-- log of collaborative editing updates, compacted into sheets.content by the clients
+CREATE TABLE sheet_updates (
+    id BIGSERIAL PRIMARY KEY,
+    sheet_id uuid NOT NULL REFERENCES sheets ON UPDATE CASCADE ON DELETE CASCADE,
+    author_id INTEGER NULL REFERENCES users ON UPDATE CASCADE ON DELETE SET NULL,
+    created TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    payload BYTEA NOT NULL
+);
+
+CREATE INDEX sheet_updates_sheet_id_idx ON sheet_updates (sheet_id, id);
@@ -0,0 +1,3 @@
+DROP TABLE sheet_shares;
+
+DROP TYPE sheet_permission;
@@ -0,0 +1,13 @@
+CREATE TYPE sheet_permission AS ENUM (
+    'viewer',
+    'grader',
+    'editor'
+);
+
+-- the owner of a sheet implicitly has all permissions and is not listed here
+CREATE TABLE sheet_shares (
+    sheet_id uuid NOT NULL REFERENCES sheets ON UPDATE CASCADE ON DELETE CASCADE,
+    user_id INTEGER NOT NULL REFERENCES users ON UPDATE CASCADE ON DELETE CASCADE,
+    permission sheet_permission NOT NULL,
+    PRIMARY KEY (sheet_id, user_id)
+);
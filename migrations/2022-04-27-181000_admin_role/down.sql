@@ -0,0 +1,13 @@
+-- enum values cannot be dropped, so the type has to be recreated
+DELETE FROM roles WHERE role = 'admin';
+
+ALTER TYPE role RENAME TO role_old;
+
+CREATE TYPE role AS ENUM (
+    'teacher',
+    'student'
+);
+
+ALTER TABLE roles ALTER COLUMN role TYPE role USING role::text::role;
+
+DROP TYPE role_old;
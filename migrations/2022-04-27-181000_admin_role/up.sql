@@ -0,0 +1,3 @@
+-- needs PostgreSQL 12 or newer to run inside the migration transaction,
+-- the new value can only be used once that transaction is committed
+ALTER TYPE role ADD VALUE 'admin';
@@ -0,0 +1,5 @@
+DROP TABLE sheet_tags;
+
+ALTER TABLE sheets DROP COLUMN folder_id;
+
+DROP TABLE folders;
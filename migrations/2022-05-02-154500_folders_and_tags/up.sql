@@ -0,0 +1,18 @@
+CREATE TABLE folders (
+    id SERIAL PRIMARY KEY,
+    name VARCHAR NOT NULL,
+    owner_id INTEGER NOT NULL REFERENCES users ON UPDATE CASCADE ON DELETE CASCADE,
+    -- top level folders have no parent, deleting a folder deletes all subfolders
+    parent_id INTEGER NULL REFERENCES folders ON UPDATE CASCADE ON DELETE CASCADE
+);
+
+-- sheets in deleted folders are moved to the top level
+ALTER TABLE sheets ADD COLUMN folder_id INTEGER NULL REFERENCES folders ON UPDATE CASCADE ON DELETE SET NULL;
+
+CREATE TABLE sheet_tags (
+    sheet_id uuid NOT NULL REFERENCES sheets ON UPDATE CASCADE ON DELETE CASCADE,
+    tag VARCHAR NOT NULL,
+    PRIMARY KEY (sheet_id, tag)
+);
+
+CREATE INDEX sheet_tags_tag_idx ON sheet_tags (tag);
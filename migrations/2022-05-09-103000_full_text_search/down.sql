@@ -0,0 +1,6 @@
+ALTER TABLE solutions DROP COLUMN answer_vector;
+ALTER TABLE solutions DROP COLUMN search_vector;
+ALTER TABLE sheets DROP COLUMN search_vector;
+
+DROP FUNCTION solution_answers(JSONB);
+DROP FUNCTION document_text(JSONB);
@@ -0,0 +1,39 @@
+-- concatenated text of all text nodes in a document
+CREATE FUNCTION document_text(content JSONB)
+RETURNS TEXT
+LANGUAGE sql
+IMMUTABLE
+AS $$
+    SELECT coalesce(string_agg(t #>> '{}', ' '), '')
+    FROM jsonb_path_query(content, 'strict $.** ? (@.type == "text").text') AS r(t)
+$$;
+
+-- concatenated answers of a student in all gaps of a solution
+CREATE FUNCTION solution_answers(content JSONB)
+RETURNS TEXT
+LANGUAGE sql
+IMMUTABLE
+AS $$
+    SELECT coalesce(string_agg(t #>> '{}', ' '), '')
+    FROM jsonb_path_query(content, 'strict $.** ? (@.type == "gap" && exists(@.answer)).answer') AS r(t)
+$$;
+
+ALTER TABLE sheets ADD COLUMN search_vector TSVECTOR GENERATED ALWAYS AS (
+    setweight(to_tsvector('german', title), 'A') ||
+    setweight(to_tsvector('german', document_text(content)), 'B')
+) STORED;
+CREATE INDEX sheets_search_idx ON sheets USING GIN (search_vector);
+
+-- used by students searching their own solutions
+ALTER TABLE solutions ADD COLUMN search_vector TSVECTOR GENERATED ALWAYS AS (
+    setweight(to_tsvector('german', title), 'A') ||
+    setweight(to_tsvector('german', solution_answers(content)), 'B') ||
+    setweight(to_tsvector('german', document_text(content)), 'C')
+) STORED;
+CREATE INDEX solutions_search_idx ON solutions USING GIN (search_vector);
+
+-- used by teachers searching student answers
+ALTER TABLE solutions ADD COLUMN answer_vector TSVECTOR GENERATED ALWAYS AS (
+    to_tsvector('german', solution_answers(content))
+) STORED;
+CREATE INDEX solutions_answer_idx ON solutions USING GIN (answer_vector);
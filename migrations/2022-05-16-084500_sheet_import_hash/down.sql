@@ -0,0 +1,2 @@
+DROP INDEX sheets_import_hash_idx;
+ALTER TABLE sheets DROP COLUMN import_hash;
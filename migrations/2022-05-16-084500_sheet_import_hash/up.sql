@@ -0,0 +1,2 @@
+ALTER TABLE sheets ADD COLUMN import_hash TEXT;
+CREATE INDEX sheets_import_hash_idx ON sheets(owner_id, import_hash) WHERE import_hash IS NOT NULL;
@@ -0,0 +1,3 @@
+DROP TABLE sheet_media;
+
+DROP TABLE media;
@@ -0,0 +1,21 @@
+-- uploaded files are stored outside of the database, identical files share the stored content
+CREATE TABLE media (
+    id uuid PRIMARY KEY DEFAULT gen_random_uuid(),
+    owner_id INTEGER NOT NULL REFERENCES users ON UPDATE CASCADE ON DELETE RESTRICT,
+    filename VARCHAR NOT NULL,
+    mimetype VARCHAR NOT NULL,
+    size BIGINT NOT NULL,
+    content_hash VARCHAR(64) NOT NULL,
+    created TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX media_content_hash_idx ON media (content_hash);
+
+-- media used in the content of a sheet can be accessed by everyone who can view the sheet
+CREATE TABLE sheet_media (
+    sheet_id uuid NOT NULL REFERENCES sheets ON UPDATE CASCADE ON DELETE CASCADE,
+    media_id uuid NOT NULL REFERENCES media ON UPDATE CASCADE ON DELETE CASCADE,
+    PRIMARY KEY (sheet_id, media_id)
+);
+
+CREATE INDEX sheet_media_media_id_idx ON sheet_media (media_id);
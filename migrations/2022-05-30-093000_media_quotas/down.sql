@@ -0,0 +1,3 @@
+ALTER TABLE media DROP COLUMN unused_since;
+DROP INDEX media_owner_id_idx;
+DROP TABLE media_quotas;
@@ -0,0 +1,10 @@
+-- teachers without an entry get the quota configured for the server
+CREATE TABLE media_quotas (
+    user_id INTEGER PRIMARY KEY REFERENCES users ON UPDATE CASCADE ON DELETE CASCADE,
+    quota BIGINT NOT NULL CHECK (quota >= 0)
+);
+
+CREATE INDEX media_owner_id_idx ON media (owner_id);
+
+-- set by the garbage collection when it first finds the media unused
+ALTER TABLE media ADD COLUMN unused_since TIMESTAMPTZ;
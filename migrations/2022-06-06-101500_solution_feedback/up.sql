@@ -0,0 +1,2 @@
+-- written by teachers, shown to the student and in the solution PDF
+ALTER TABLE solutions ADD COLUMN feedback TEXT NOT NULL DEFAULT '';
@@ -0,0 +1,23 @@
+CREATE OR REPLACE FUNCTION solution_answers(content JSONB)
+RETURNS TEXT
+LANGUAGE sql
+IMMUTABLE
+AS $$
+    SELECT coalesce(string_agg(t #>> '{}', ' '), '')
+    FROM jsonb_path_query(content, 'strict $.** ? (@.type == "gap" && exists(@.answer)).answer') AS r(t)
+$$;
+
+ALTER TABLE solutions DROP COLUMN answer_vector;
+ALTER TABLE solutions DROP COLUMN search_vector;
+
+ALTER TABLE solutions ADD COLUMN search_vector TSVECTOR GENERATED ALWAYS AS (
+    setweight(to_tsvector('german', title), 'A') ||
+    setweight(to_tsvector('german', solution_answers(content)), 'B') ||
+    setweight(to_tsvector('german', document_text(content)), 'C')
+) STORED;
+CREATE INDEX solutions_search_idx ON solutions USING GIN (search_vector);
+
+ALTER TABLE solutions ADD COLUMN answer_vector TSVECTOR GENERATED ALWAYS AS (
+    to_tsvector('german', solution_answers(content))
+) STORED;
+CREATE INDEX solutions_answer_idx ON solutions USING GIN (answer_vector);
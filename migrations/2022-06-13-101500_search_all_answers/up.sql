@@ -0,0 +1,29 @@
+-- concatenated answers of a student in all gaps, dropdowns, numeric and formula tasks and essays
+-- of a solution
+CREATE OR REPLACE FUNCTION solution_answers(content JSONB)
+RETURNS TEXT
+LANGUAGE sql
+IMMUTABLE
+AS $$
+    SELECT coalesce(string_agg(t #>> '{}', ' '), '')
+    FROM jsonb_path_query(
+        content,
+        'strict $.** ? ((@.type == "gap" || @.type == "dropdown" || @.type == "numeric" || @.type == "formula" || @.type == "essay") && @.answer.type() == "string").answer'
+    ) AS r(t)
+$$;
+
+-- generated columns are only computed on writes, so they are added again for existing solutions
+ALTER TABLE solutions DROP COLUMN answer_vector;
+ALTER TABLE solutions DROP COLUMN search_vector;
+
+ALTER TABLE solutions ADD COLUMN search_vector TSVECTOR GENERATED ALWAYS AS (
+    setweight(to_tsvector('german', title), 'A') ||
+    setweight(to_tsvector('german', solution_answers(content)), 'B') ||
+    setweight(to_tsvector('german', document_text(content)), 'C')
+) STORED;
+CREATE INDEX solutions_search_idx ON solutions USING GIN (search_vector);
+
+ALTER TABLE solutions ADD COLUMN answer_vector TSVECTOR GENERATED ALWAYS AS (
+    to_tsvector('german', solution_answers(content))
+) STORED;
+CREATE INDEX solutions_answer_idx ON solutions USING GIN (answer_vector);
@@ -0,0 +1 @@
+DROP TABLE solution_media;
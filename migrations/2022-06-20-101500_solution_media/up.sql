@@ -0,0 +1,17 @@
+-- solutions keep a copy of the sheet, so the media it uses must outlive the sheet
+CREATE TABLE solution_media (
+    solution_id INTEGER NOT NULL REFERENCES solutions ON UPDATE CASCADE ON DELETE CASCADE,
+    media_id uuid NOT NULL REFERENCES media ON UPDATE CASCADE ON DELETE CASCADE,
+    PRIMARY KEY (solution_id, media_id)
+);
+
+CREATE INDEX solution_media_media_id_idx ON solution_media (media_id);
+
+INSERT INTO solution_media (solution_id, media_id)
+SELECT DISTINCT solutions.id, media.id
+FROM solutions
+CROSS JOIN LATERAL jsonb_path_query(
+    solutions.content,
+    'strict $.** ? (@.type == "audio" && @.source.type() == "string").source'
+) AS source
+JOIN media ON media.id::text = substring(source #>> '{}' FROM '/media/([0-9a-f-]{36})$');
@@ -1,5 +1,7 @@
 use hci_bildung::crypt;
 
+const ROLES: [&str; 3] = ["teacher", "student", "admin"];
+
 fn main() {
     if let Err(e) = run() {
         eprintln!("{}", e);
@@ -9,20 +11,37 @@ fn main() {
 
 fn run() -> Result<(), Box<dyn std::error::Error>> {
     let username = std::env::args().nth(1).ok_or("Please provide a username")?;
+    let roles: Vec<String> = std::env::args().skip(2).collect();
+    if let Some(role) = roles.iter().find(|role| !ROLES.contains(&role.as_str())) {
+        return Err(format!("Unknown role {}, use one of {}", role, ROLES.join(", ")).into());
+    }
     let password = rpassword::prompt_password("New Password: ")?;
     let password_repeat = rpassword::prompt_password("Repeat Password: ")?;
     if password == password_repeat {
         let password_hash = crypt::hash_password(&password)?;
-        print_sql(&username, &password_hash);
+        print_sql(&username, &password_hash, &roles);
         Ok(())
     } else {
         Err("Passwords do not match".into())
     }
 }
 
-fn print_sql(username: &str, password_hash: &str) {
-    println!(
+fn print_sql(username: &str, password_hash: &str, roles: &[String]) {
+    let insert_user = format!(
         "INSERT INTO users(username, password_hash) VALUES ('{}', '{}') RETURNING id",
         username, password_hash
     );
+    if roles.is_empty() {
+        println!("{}", insert_user);
+    } else {
+        let roles = roles
+            .iter()
+            .map(|role| format!("('{}'::role)", role))
+            .collect::<Vec<_>>()
+            .join(", ");
+        println!(
+            "WITH new_user AS ({}) INSERT INTO roles(user_id, role) SELECT id, role FROM new_user, (VALUES {}) AS r(role)",
+            insert_user, roles
+        );
+    }
 }
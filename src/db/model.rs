@@ -1,8 +1,11 @@
 use chrono::{DateTime, NaiveDateTime, Utc};
 use rocket::serde::uuid::Uuid;
 
-use super::schema::{roles, sessions, sheets, solutions, users};
-use super::sql_types::RoleDb;
+use super::schema::{
+    folders, media, roles, sessions, sheet_shares, sheet_tags, sheet_updates, sheets, solutions,
+    users,
+};
+use super::sql_types::{RoleDb, SheetPermissionDb};
 
 #[derive(Debug, Identifiable, PartialEq, Queryable)]
 #[table_name = "users"]
@@ -55,6 +58,8 @@ pub struct SheetDiesel {
     pub changed: DateTime<Utc>,
     pub content: serde_json::Value,
     pub trashed: Option<DateTime<Utc>>,
+    pub folder_id: Option<i32>,
+    pub import_hash: Option<String>,
 }
 
 #[derive(Debug, PartialEq, Queryable)]
@@ -65,6 +70,7 @@ pub struct SheetMetadataDiesel {
     pub created: DateTime<Utc>,
     pub changed: DateTime<Utc>,
     pub trashed: Option<DateTime<Utc>>,
+    pub folder_id: Option<i32>,
 }
 
 impl SheetMetadataDiesel {
@@ -75,6 +81,7 @@ impl SheetMetadataDiesel {
         sheets::created,
         sheets::changed,
         sheets::trashed,
+        sheets::folder_id,
     ) {
         (
             sheets::id,
@@ -83,10 +90,66 @@ impl SheetMetadataDiesel {
             sheets::created,
             sheets::changed,
             sheets::trashed,
+            sheets::folder_id,
         )
     }
 }
 
+#[derive(Associations, Debug, Identifiable, PartialEq, Queryable)]
+#[belongs_to(UserDiesel, foreign_key = "owner_id")]
+#[table_name = "folders"]
+pub struct FolderDiesel {
+    pub id: i32,
+    pub name: String,
+    pub owner_id: i32,
+    pub parent_id: Option<i32>,
+}
+
+#[derive(Associations, Debug, Identifiable, PartialEq, Queryable)]
+#[belongs_to(SheetDiesel, foreign_key = "sheet_id")]
+#[primary_key(sheet_id, tag)]
+#[table_name = "sheet_tags"]
+pub struct SheetTagDiesel {
+    pub sheet_id: Uuid,
+    pub tag: String,
+}
+
+#[derive(Associations, Debug, Identifiable, PartialEq, Queryable)]
+#[belongs_to(SheetDiesel, foreign_key = "sheet_id")]
+#[belongs_to(UserDiesel, foreign_key = "user_id")]
+#[primary_key(sheet_id, user_id)]
+#[table_name = "sheet_shares"]
+pub struct SheetShareDiesel {
+    pub sheet_id: Uuid,
+    pub user_id: i32,
+    pub permission: SheetPermissionDb,
+}
+
+#[derive(Associations, Debug, Identifiable, PartialEq, Queryable)]
+#[belongs_to(UserDiesel, foreign_key = "owner_id")]
+#[table_name = "media"]
+pub struct MediaDiesel {
+    pub id: Uuid,
+    pub owner_id: i32,
+    pub filename: String,
+    pub mimetype: String,
+    pub size: i64,
+    pub content_hash: String,
+    pub created: DateTime<Utc>,
+    pub unused_since: Option<DateTime<Utc>>,
+}
+
+#[derive(Associations, Debug, Identifiable, PartialEq, Queryable)]
+#[belongs_to(SheetDiesel, foreign_key = "sheet_id")]
+#[table_name = "sheet_updates"]
+pub struct SheetUpdateDiesel {
+    pub id: i64,
+    pub sheet_id: Uuid,
+    pub author_id: Option<i32>,
+    pub created: DateTime<Utc>,
+    pub payload: Vec<u8>,
+}
+
 #[derive(Associations, Debug, Identifiable, Insertable, PartialEq, Queryable)]
 #[belongs_to(UserDiesel, foreign_key = "owner_id")]
 #[belongs_to(SheetDiesel, foreign_key = "sheet_id")]
@@ -101,6 +164,7 @@ pub struct SolutionDiesel {
     pub changed: DateTime<Utc>,
     pub trashed: Option<DateTime<Utc>>,
     pub content: serde_json::Value,
+    pub feedback: String,
 }
 
 #[derive(Debug, PartialEq, Queryable)]
@@ -138,3 +202,55 @@ impl SolutionMetadataDiesel {
         )
     }
 }
+
+#[derive(Debug, PartialEq, QueryableByName)]
+pub struct SheetSearchResultDiesel {
+    #[sql_type = "diesel::sql_types::Uuid"]
+    pub id: Uuid,
+    #[sql_type = "diesel::sql_types::Varchar"]
+    pub title: String,
+    #[sql_type = "diesel::sql_types::Int4"]
+    pub owner_id: i32,
+    #[sql_type = "diesel::sql_types::Varchar"]
+    pub owner_name: String,
+    #[sql_type = "diesel::sql_types::Timestamptz"]
+    pub created: DateTime<Utc>,
+    #[sql_type = "diesel::sql_types::Timestamptz"]
+    pub changed: DateTime<Utc>,
+    #[sql_type = "diesel::sql_types::Nullable<diesel::sql_types::Timestamptz>"]
+    pub trashed: Option<DateTime<Utc>>,
+    #[sql_type = "diesel::sql_types::Nullable<diesel::sql_types::Int4>"]
+    pub folder_id: Option<i32>,
+    #[sql_type = "diesel::sql_types::Nullable<super::sql_types::SheetPermission>"]
+    pub permission: Option<SheetPermissionDb>,
+    #[sql_type = "diesel::sql_types::Float4"]
+    pub rank: f32,
+    #[sql_type = "diesel::sql_types::Text"]
+    pub snippet: String,
+}
+
+#[derive(Debug, PartialEq, QueryableByName)]
+pub struct SolutionSearchResultDiesel {
+    #[sql_type = "diesel::sql_types::Int4"]
+    pub id: i32,
+    #[sql_type = "diesel::sql_types::Varchar"]
+    pub title: String,
+    #[sql_type = "diesel::sql_types::Nullable<diesel::sql_types::Uuid>"]
+    pub sheet_id: Option<Uuid>,
+    #[sql_type = "diesel::sql_types::Timestamptz"]
+    pub sheet_version: DateTime<Utc>,
+    #[sql_type = "diesel::sql_types::Int4"]
+    pub owner_id: i32,
+    #[sql_type = "diesel::sql_types::Varchar"]
+    pub owner_name: String,
+    #[sql_type = "diesel::sql_types::Timestamptz"]
+    pub created: DateTime<Utc>,
+    #[sql_type = "diesel::sql_types::Timestamptz"]
+    pub changed: DateTime<Utc>,
+    #[sql_type = "diesel::sql_types::Nullable<diesel::sql_types::Timestamptz>"]
+    pub trashed: Option<DateTime<Utc>>,
+    #[sql_type = "diesel::sql_types::Float4"]
+    pub rank: f32,
+    #[sql_type = "diesel::sql_types::Text"]
+    pub snippet: String,
+}
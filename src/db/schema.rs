@@ -1,3 +1,38 @@
+table! {
+    use diesel::sql_types::*;
+
+    folders (id) {
+        id -> Int4,
+        name -> Varchar,
+        owner_id -> Int4,
+        parent_id -> Nullable<Int4>,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+
+    media (id) {
+        id -> Uuid,
+        owner_id -> Int4,
+        filename -> Varchar,
+        mimetype -> Varchar,
+        size -> Int8,
+        content_hash -> Varchar,
+        created -> Timestamptz,
+        unused_since -> Nullable<Timestamptz>,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+
+    media_quotas (user_id) {
+        user_id -> Int4,
+        quota -> Int8,
+    }
+}
+
 table! {
     use diesel::sql_types::*;
     use crate::db::sql_types::*;
@@ -18,6 +53,47 @@ table! {
     }
 }
 
+table! {
+    use diesel::sql_types::*;
+
+    sheet_media (sheet_id, media_id) {
+        sheet_id -> Uuid,
+        media_id -> Uuid,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use crate::db::sql_types::*;
+
+    sheet_shares (sheet_id, user_id) {
+        sheet_id -> Uuid,
+        user_id -> Int4,
+        permission -> SheetPermission,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+
+    sheet_tags (sheet_id, tag) {
+        sheet_id -> Uuid,
+        tag -> Varchar,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+
+    sheet_updates (id) {
+        id -> Int8,
+        sheet_id -> Uuid,
+        author_id -> Nullable<Int4>,
+        created -> Timestamptz,
+        payload -> Bytea,
+    }
+}
+
 table! {
     use diesel::sql_types::*;
 
@@ -29,6 +105,17 @@ table! {
         changed -> Timestamptz,
         content -> Jsonb,
         trashed -> Nullable<Timestamptz>,
+        folder_id -> Nullable<Int4>,
+        import_hash -> Nullable<Text>,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+
+    solution_media (solution_id, media_id) {
+        solution_id -> Int4,
+        media_id -> Uuid,
     }
 }
 
@@ -45,6 +132,7 @@ table! {
         changed -> Timestamptz,
         trashed -> Nullable<Timestamptz>,
         content -> Jsonb,
+        feedback -> Text,
     }
 }
 
@@ -58,10 +146,37 @@ table! {
     }
 }
 
+joinable!(folders -> users (owner_id));
+joinable!(media -> users (owner_id));
+joinable!(media_quotas -> users (user_id));
 joinable!(roles -> users (user_id));
 joinable!(sessions -> users (user_id));
+joinable!(sheet_media -> media (media_id));
+joinable!(sheet_media -> sheets (sheet_id));
+joinable!(sheet_shares -> sheets (sheet_id));
+joinable!(sheet_shares -> users (user_id));
+joinable!(sheet_tags -> sheets (sheet_id));
+joinable!(sheet_updates -> sheets (sheet_id));
+joinable!(sheet_updates -> users (author_id));
+joinable!(sheets -> folders (folder_id));
 joinable!(sheets -> users (owner_id));
+joinable!(solution_media -> media (media_id));
+joinable!(solution_media -> solutions (solution_id));
 joinable!(solutions -> sheets (sheet_id));
 joinable!(solutions -> users (owner_id));
 
-allow_tables_to_appear_in_same_query!(roles, sessions, sheets, solutions, users,);
+allow_tables_to_appear_in_same_query!(
+    folders,
+    media,
+    media_quotas,
+    roles,
+    sessions,
+    sheet_media,
+    sheet_shares,
+    sheet_tags,
+    sheet_updates,
+    sheets,
+    solution_media,
+    solutions,
+    users,
+);
@@ -6,4 +6,14 @@ use diesel_derive_enum::DbEnum;
 pub enum RoleDb {
     Teacher,
     Student,
+    Admin,
+}
+
+#[derive(DbEnum, Debug, Eq, Hash, PartialEq)]
+#[PgType = "sheet_permission"]
+#[DieselType = "SheetPermission"]
+pub enum SheetPermissionDb {
+    Viewer,
+    Grader,
+    Editor,
 }
@@ -0,0 +1,37 @@
+use std::io::Cursor;
+
+use rocket::http::{ContentType, RawStr};
+use rocket::request::Request;
+use rocket::response::{self, Responder, Response};
+
+pub struct Download {
+    filename: String,
+    content_type: ContentType,
+    body: Vec<u8>,
+}
+
+impl Download {
+    pub fn new<F: Into<String>>(filename: F, content_type: ContentType, body: Vec<u8>) -> Self {
+        Self {
+            filename: filename.into(),
+            content_type,
+            body,
+        }
+    }
+}
+
+impl<'r> Responder<'r, 'static> for Download {
+    fn respond_to(self, _: &'r Request<'_>) -> response::Result<'static> {
+        Response::build()
+            .header(self.content_type)
+            .raw_header(
+                "Content-Disposition",
+                format!(
+                    "attachment; filename*=UTF-8''{}",
+                    RawStr::new(&self.filename).percent_encode()
+                ),
+            )
+            .sized_body(self.body.len(), Cursor::new(self.body))
+            .ok()
+    }
+}
@@ -15,9 +15,11 @@ use rocket_dyn_templates::Template;
 
 pub mod crypt;
 mod db;
+mod download;
 mod flash;
 mod landing_page;
 mod login;
+mod partial;
 mod sheets;
 mod status;
 mod templating;
@@ -71,15 +73,51 @@ pub fn build_rocket() -> Rocket<Build> {
                 sheets::routes::sheet::new_sheet,
                 sheets::routes::sheet::import_sheet,
                 sheets::routes::sheet::view_sheet,
+                sheets::routes::sheet::worksheet_pdf,
+                sheets::routes::sheet::answer_key_pdf,
+                sheets::routes::sheet::export_sheet,
+                sheets::routes::sheet::export_markdown,
+                sheets::routes::sheet::export_moodle,
+                sheets::routes::sheet::export_qti,
+                sheets::routes::sheet::export_scorm,
                 sheets::routes::sheet::edit_sheet,
                 sheets::routes::sheet::save_sheet,
                 sheets::routes::sheet::delete_sheet,
                 sheets::routes::sheet::restore_sheet,
+                sheets::routes::archive::export_sheets,
+                sheets::routes::archive::import_sheets,
+                sheets::routes::collaboration::collaboration_updates,
+                sheets::routes::collaboration::collaboration_events,
+                sheets::routes::collaboration::push_collaboration_update,
+                sheets::routes::collaboration::compact_collaboration_updates,
+                sheets::routes::folder::new_folder,
+                sheets::routes::folder::rename_folder,
+                sheets::routes::folder::move_folder,
+                sheets::routes::folder::delete_folder,
+                sheets::routes::folder::move_sheet,
+                sheets::routes::folder::add_tag,
+                sheets::routes::folder::remove_tag,
+                sheets::routes::media::upload_media,
+                sheets::routes::search::search_teacher,
+                sheets::routes::search::search_student,
+                sheets::routes::sharing::share_sheet,
+                sheets::routes::sharing::unshare_sheet,
+                sheets::routes::transfer::transfer_sheet,
+                sheets::routes::transfer::transfer_all_sheets,
+                sheets::routes::transfer::transfer_overview,
+                sheets::routes::transfer::transfer_sheet_as_admin,
+                sheets::routes::transfer::transfer_all_sheets_as_admin,
+                sheets::routes::transfer::delete_teacher_as_admin,
                 sheets::routes::sheet_tree::assignment_overview,
+                sheets::routes::sheet_tree::folder_overview,
+                sheets::routes::sheet_tree::tag_overview,
                 sheets::routes::sheet_tree::trashed_sheets,
                 sheets::routes::sheet_tree::recent_sheets,
+                sheets::routes::sheet_tree::shared_sheets,
                 sheets::routes::solution::solution_overview,
+                sheets::routes::solution::grading_queue,
                 sheets::routes::solution::sheet_solutions,
+                sheets::routes::solution::sheet_solutions_pdf,
                 sheets::routes::solution::start_solve,
                 sheets::routes::solution::my_solution_overview,
                 sheets::routes::solution::trashed_solutions,
@@ -87,42 +125,74 @@ pub fn build_rocket() -> Rocket<Build> {
                 sheets::routes::solution::my_sheet_solutions,
                 sheets::routes::solution::latest_solution,
                 sheets::routes::solution::my_solution,
+                sheets::routes::solution::my_solution_pdf,
                 sheets::routes::solution::save_solution,
+                sheets::routes::solution::check_solution,
                 sheets::routes::solution::latest_student_solution,
                 sheets::routes::solution::student_solution,
+                sheets::routes::solution::student_solution_pdf,
                 sheets::routes::solution::delete_solution,
                 sheets::routes::solution::restore_solution,
+                sheets::routes::solution::grade_essay,
+                sheets::routes::solution::give_feedback,
                 sheets::routes::sheet::login_sheet_overview,
                 sheets::routes::sheet::login_edit_sheet,
+                sheets::routes::sheet::login_answer_key_pdf,
+                sheets::routes::sheet::login_export_sheet,
+                sheets::routes::sheet::login_export_markdown,
+                sheets::routes::sheet::login_export_moodle,
+                sheets::routes::sheet::login_export_qti,
+                sheets::routes::sheet::login_export_scorm,
+                sheets::routes::search::login_search,
                 sheets::routes::sheet_tree::login_assignment_overview,
+                sheets::routes::sheet_tree::login_folder_overview,
+                sheets::routes::sheet_tree::login_tag_overview,
                 sheets::routes::sheet_tree::login_trashed_sheets,
                 sheets::routes::sheet_tree::login_recent_sheets,
+                sheets::routes::sheet_tree::login_shared_sheets,
+                sheets::routes::transfer::login_transfer_overview,
                 sheets::routes::solution::login_solution_overview,
+                sheets::routes::solution::login_grading_queue,
                 sheets::routes::solution::login_sheet_solutions,
+                sheets::routes::solution::login_sheet_solutions_pdf,
                 sheets::routes::solution::login_my_solution_overview,
                 sheets::routes::solution::login_trashed_solutions,
                 sheets::routes::solution::login_recent_solutions,
                 sheets::routes::solution::login_my_sheet_solutions,
                 sheets::routes::solution::login_latest_solution,
                 sheets::routes::solution::login_my_solution,
+                sheets::routes::solution::login_my_solution_pdf,
                 sheets::routes::solution::login_latest_student_solution,
-                sheets::routes::solution::login_student_solution
+                sheets::routes::solution::login_student_solution,
+                sheets::routes::solution::login_student_solution_pdf
             ],
         )
+        .mount(
+            sheets::routes::media::MOUNT,
+            routes![sheets::routes::media::media],
+        )
         .mount("/vue", FileServer::from(relative!("vue_dist/vue")))
         .mount("/assets", FileServer::from(relative!("assets")));
     let map: HashMap<String, RouteUri> = r
         .routes()
         .map(|route| (route.name.clone().unwrap().into(), route.uri.clone()))
         .collect();
-    r.attach(Template::custom(move |engines| {
-        engines
-            .tera
-            .register_function("url_for", templating::make_url_for(map.clone()));
-    }))
-    .attach(Db::fairing())
-    .attach(AdHoc::try_on_ignite(
-        "Database Migrations",
-        db::setup::migrate,
-    ))
+    r.manage(sheets::logic::collaboration::CollaborationHub::default())
+        .attach(Template::custom(move |engines| {
+            engines
+                .tera
+                .register_function("url_for", templating::make_url_for(map.clone()));
+        }))
+        .attach(Db::fairing())
+        .attach(AdHoc::try_on_ignite(
+            "Media Storage",
+            sheets::routes::media::configure,
+        ))
+        .attach(AdHoc::try_on_ignite(
+            "Database Migrations",
+            db::setup::migrate,
+        ))
+        .attach(AdHoc::on_liftoff("Media Garbage Collection", |rocket| {
+            Box::pin(sheets::routes::media::spawn_garbage_collection(rocket))
+        }))
 }
@@ -18,6 +18,7 @@ impl From<sql_types::RoleDb> for Role {
         match r {
             sql_types::RoleDb::Teacher => Self::Teacher,
             sql_types::RoleDb::Student => Self::Student,
+            sql_types::RoleDb::Admin => Self::Admin,
         }
     }
 }
@@ -107,3 +107,25 @@ impl<'r> FromRequest<'r> for Student<'r> {
         }
     }
 }
+
+pub struct Admin<'a>(&'a AuthenticatedUser);
+
+impl<'a> Admin<'a> {
+    pub fn into_inner(self) -> &'a AuthenticatedUser {
+        self.0
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Admin<'r> {
+    type Error = std::convert::Infallible;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let user = try_outcome!(request.guard::<&'r AuthenticatedUser>().await);
+        if user.roles.contains(&Role::Admin) {
+            Outcome::Success(Admin(user))
+        } else {
+            Outcome::Forward(())
+        }
+    }
+}
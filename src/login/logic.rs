@@ -35,6 +35,7 @@ impl From<data::Error> for Error {
 pub enum Role {
     Teacher,
     Student,
+    Admin,
 }
 
 #[derive(Debug)]
@@ -2,7 +2,7 @@ use rocket::serde::{Deserialize, Serialize};
 
 use super::logic::User;
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct UserInfo {
     pub id: i32,
     pub username: String,
@@ -0,0 +1,125 @@
+//! Support for range requests, which browsers use to seek in audio and video files.
+
+use std::io::Cursor;
+use std::ops::Range;
+
+use rocket::http::{ContentType, Status};
+use rocket::request::{FromRequest, Outcome, Request};
+use rocket::response::{self, Responder, Response};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ByteRange {
+    From(u64),
+    FromTo(u64, u64),
+    Suffix(u64),
+}
+
+/// The byte range requested in the `Range` header. Requests for multiple ranges are answered
+/// with the complete content, which the standard allows.
+#[derive(Debug)]
+pub struct RequestedRange(Option<ByteRange>);
+
+pub enum Resolved {
+    Complete,
+    Partial(Range<u64>),
+    Unsatisfiable,
+}
+
+fn parse_range(header: &str) -> Option<ByteRange> {
+    let spec = header.trim().strip_prefix("bytes=")?;
+    if spec.contains(',') {
+        return None;
+    }
+    let mut bounds = spec.splitn(2, '-').map(str::trim);
+    let start = bounds.next()?;
+    let end = bounds.next()?;
+    match (start.is_empty(), end.is_empty()) {
+        (false, true) => start.parse().ok().map(ByteRange::From),
+        (false, false) => {
+            let (start, end) = (start.parse().ok()?, end.parse().ok()?);
+            if start <= end {
+                Some(ByteRange::FromTo(start, end))
+            } else {
+                None
+            }
+        }
+        (true, false) => end.parse().ok().map(ByteRange::Suffix),
+        (true, true) => None,
+    }
+}
+
+impl RequestedRange {
+    /// Determines the requested part of content with the given length
+    pub fn resolve(&self, length: u64) -> Resolved {
+        let range = match self.0 {
+            None => return Resolved::Complete,
+            Some(ByteRange::From(start)) => start..length,
+            // Ranges are inclusive and may extend beyond the end of the content
+            Some(ByteRange::FromTo(start, end)) => start..end.saturating_add(1).min(length),
+            Some(ByteRange::Suffix(suffix)) => length.saturating_sub(suffix)..length,
+        };
+        if range.start >= length || range.is_empty() {
+            Resolved::Unsatisfiable
+        } else if range == (0..length) {
+            Resolved::Complete
+        } else {
+            Resolved::Partial(range)
+        }
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for RequestedRange {
+    type Error = std::convert::Infallible;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        Outcome::Success(RequestedRange(
+            request.headers().get_one("Range").and_then(parse_range),
+        ))
+    }
+}
+
+pub enum PartialContent {
+    Complete {
+        content_type: ContentType,
+        body: Vec<u8>,
+    },
+    Partial {
+        content_type: ContentType,
+        range: Range<u64>,
+        length: u64,
+        body: Vec<u8>,
+    },
+    Unsatisfiable {
+        length: u64,
+    },
+}
+
+impl<'r> Responder<'r, 'static> for PartialContent {
+    fn respond_to(self, _: &'r Request<'_>) -> response::Result<'static> {
+        let mut response = Response::build();
+        response.raw_header("Accept-Ranges", "bytes");
+        match self {
+            Self::Complete { content_type, body } => response
+                .header(content_type)
+                .sized_body(body.len(), Cursor::new(body)),
+            Self::Partial {
+                content_type,
+                range,
+                length,
+                body,
+            } => response
+                .status(Status::PartialContent)
+                .header(content_type)
+                .raw_header(
+                    "Content-Range",
+                    format!("bytes {}-{}/{}", range.start, range.end - 1, length),
+                )
+                .sized_body(body.len(), Cursor::new(body)),
+            Self::Unsatisfiable { length } => response
+                .status(Status::RangeNotSatisfiable)
+                .raw_header("Content-Range", format!("bytes */{}", length)),
+        };
+        response.ok()
+    }
+}
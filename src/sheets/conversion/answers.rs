@@ -0,0 +1,257 @@
+//! Answers submitted by students.
+//!
+//! Students send their whole solution, but only their answers are taken from it. Everything else,
+//! like the solutions of gaps and tasks, points and grading, stays as stored, so a forged
+//! submission cannot change how it is graded. Nodes and marks are matched by position and type,
+//! the solution view submits them in the order it got them.
+//!
+//! Like [`super::shuffle`] this works on the JSON content.
+
+use serde_json::{json, Value};
+
+use super::document::Mark;
+use super::grading;
+
+/// Marks holding a text answer
+const ANSWER_MARKS: [&str; 4] = ["gap", "dropdown", "numeric", "formula"];
+
+fn type_of(value: &Value) -> Option<&str> {
+    value.get("type").and_then(Value::as_str)
+}
+
+/// Takes the answers from the content a student submitted into the stored solution.
+/// Points awarded for an essay are dropped once its answer changes.
+pub fn take_answers(stored: &mut Value, submitted: &Value) {
+    let kind = type_of(stored);
+    if kind.is_none() || kind != type_of(submitted) {
+        return;
+    }
+    let answer = submitted.get("answer");
+    match kind {
+        Some("multipleChoiceAnswer") => {
+            if let Some(answer) = answer.filter(|answer| answer.is_boolean()) {
+                stored["answer"] = answer.clone();
+            }
+        }
+        Some("essay") => {
+            if let Some(answer) = answer.filter(|answer| answer.is_string()) {
+                if stored.get("answer") != Some(answer) {
+                    if let Some(essay) = stored.as_object_mut() {
+                        essay.remove("awarded");
+                    }
+                    stored["answer"] = answer.clone();
+                }
+            }
+        }
+        _ => {}
+    }
+    if let (Some(marks), Some(submitted_marks)) = (
+        stored.get_mut("marks").and_then(Value::as_array_mut),
+        submitted.get("marks").and_then(Value::as_array),
+    ) {
+        for (mark, submitted) in marks.iter_mut().zip(submitted_marks) {
+            let kind = type_of(mark);
+            if matches!(kind, Some(kind) if ANSWER_MARKS.contains(&kind))
+                && kind == type_of(submitted)
+            {
+                if let Some(answer) = submitted.get("answer").filter(|answer| answer.is_string()) {
+                    mark["answer"] = answer.clone();
+                }
+            }
+        }
+    }
+    if let (Some(children), Some(submitted_children)) = (
+        stored.get_mut("content").and_then(Value::as_array_mut),
+        submitted.get("content").and_then(Value::as_array),
+    ) {
+        for (child, submitted) in children.iter_mut().zip(submitted_children) {
+            take_answers(child, submitted);
+        }
+    }
+}
+
+fn dropdowns_mut<F: FnMut(&mut Value)>(value: &mut Value, f: &mut F) {
+    if let Some(marks) = value.get_mut("marks").and_then(Value::as_array_mut) {
+        marks
+            .iter_mut()
+            .filter(|mark| type_of(mark) == Some("dropdown"))
+            .for_each(&mut *f);
+    }
+    if let Some(children) = value.get_mut("content").and_then(Value::as_array_mut) {
+        for child in children {
+            dropdowns_mut(child, f);
+        }
+    }
+}
+
+fn dropdowns<'a>(value: &'a Value, found: &mut Vec<&'a Value>) {
+    if let Some(marks) = value.get("marks").and_then(Value::as_array) {
+        found.extend(
+            marks
+                .iter()
+                .filter(|mark| type_of(mark) == Some("dropdown")),
+        );
+    }
+    if let Some(children) = value.get("content").and_then(Value::as_array) {
+        for child in children {
+            dropdowns(child, found);
+        }
+    }
+}
+
+/// Numbers the dropdowns in document order. Students do not get to see the correct options, so
+/// their view matches the grading by [`dropdown_credits`] to the dropdowns with it.
+pub fn number_dropdowns(value: &mut Value) {
+    let mut index = 0;
+    dropdowns_mut(value, &mut |mark| {
+        mark["index"] = json!(index);
+        index += 1;
+    });
+}
+
+/// Grades the answers to all dropdowns in document order
+pub fn dropdown_credits(value: &Value) -> Vec<f64> {
+    let mut found = Vec::new();
+    dropdowns(value, &mut found);
+    found
+        .into_iter()
+        .map(|mark| grading::mark_credit(&Mark::from_json(mark)).unwrap_or_default())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::document::Node;
+    use super::*;
+
+    fn gap(solution: &str, answer: &str) -> Value {
+        json!({"type": "doc", "marks": [], "content": [
+            {"type": "paragraph", "marks": [], "content": [
+                {"type": "text", "text": solution, "content": [], "marks": [
+                    {"type": "gap", "solution": solution, "answer": answer}
+                ]}
+            ]}
+        ]})
+    }
+
+    fn essay(answer: &str, awarded: Option<f64>) -> Value {
+        let mut essay = json!({
+            "type": "essay",
+            "points": 2,
+            "modelAnswer": "Weil die Erde sich dreht.",
+            "answer": answer,
+            "marks": [],
+            "content": [],
+        });
+        if let Some(awarded) = awarded {
+            essay["awarded"] = json!(awarded);
+        }
+        essay
+    }
+
+    #[test]
+    fn forged_solutions_do_not_change_the_score() {
+        let mut stored = gap("Paris", "");
+        let mut forged = gap("Rom", "Rom");
+        forged["content"][0]["content"][0]["marks"][0]["alternatives"] =
+            json!([{"answer": "Rom", "credit": 1.0}]);
+        take_answers(&mut stored, &forged);
+        let mark = &stored["content"][0]["content"][0]["marks"][0];
+        assert_eq!(mark["solution"], json!("Paris"));
+        assert_eq!(mark["answer"], json!("Rom"));
+        assert!(mark.get("alternatives").is_none());
+        assert_eq!(grading::grade(&Node::from_json(&stored)).achieved, 0.0);
+
+        take_answers(&mut stored, &gap("Rom", "Paris"));
+        assert_eq!(grading::grade(&Node::from_json(&stored)).achieved, 1.0);
+    }
+
+    #[test]
+    fn forged_choices_do_not_change_the_score() {
+        let choice = |solution: bool, answer: bool| {
+            json!({"type": "multipleChoice", "marks": [], "content": [
+                {"type": "multipleChoiceAnswer", "marks": [], "content": [],
+                    "solution": solution, "answer": answer}
+            ]})
+        };
+        let mut stored = choice(false, false);
+        take_answers(&mut stored, &choice(true, true));
+        assert_eq!(stored["content"][0]["solution"], json!(false));
+        assert_eq!(stored["content"][0]["answer"], json!(true));
+        assert_eq!(grading::grade(&Node::from_json(&stored)).achieved, 0.0);
+    }
+
+    #[test]
+    fn keeps_grading_of_unchanged_essays() {
+        let mut stored = json!({"type": "doc", "marks": [], "content": [
+            essay("Wegen der Drehung", Some(1.5)),
+            essay("Keine Ahnung", Some(0.0)),
+        ]});
+        let submitted = json!({"type": "doc", "marks": [], "content": [
+            {"type": "essay", "points": 10, "answer": "Wegen der Drehung", "awarded": 2},
+            {"type": "essay", "answer": "Wegen der Erdrotation"},
+        ]});
+        take_answers(&mut stored, &submitted);
+        let first = &stored["content"][0];
+        assert_eq!(first["points"], json!(2));
+        assert_eq!(first["awarded"], json!(1.5));
+        assert_eq!(first["modelAnswer"], json!("Weil die Erde sich dreht."));
+        let second = &stored["content"][1];
+        assert_eq!(second["answer"], json!("Wegen der Erdrotation"));
+        assert!(second.get("awarded").is_none());
+    }
+
+    #[test]
+    fn ignores_submitted_grading_of_essays() {
+        let mut stored = json!({"type": "doc", "marks": [], "content": [
+            essay("", None),
+            essay("Wegen der Drehung", Some(0.5)),
+        ]});
+        let forged = |answer: &str| {
+            json!({"type": "essay", "answer": answer, "awarded": 2,
+                "modelAnswer": answer, "marks": [], "content": []})
+        };
+        let submitted = json!({"type": "doc", "marks": [], "content": [
+            forged("Weil sie sich dreht"),
+            forged("Wegen der Erdrotation"),
+        ]});
+        take_answers(&mut stored, &submitted);
+        for (essay, answer) in stored["content"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .zip(["Weil sie sich dreht", "Wegen der Erdrotation"].iter())
+        {
+            assert_eq!(essay["answer"], json!(answer));
+            assert!(essay.get("awarded").is_none());
+            assert_eq!(essay["modelAnswer"], json!("Weil die Erde sich dreht."));
+        }
+        assert_eq!(grading::grade(&Node::from_json(&stored)).achieved, 0.0);
+    }
+
+    #[test]
+    fn grades_dropdowns_in_the_order_they_are_numbered() {
+        let dropdown = |answer: &str| {
+            json!({"type": "text", "text": "Berlin", "content": [], "marks": [
+                {"type": "dropdown", "answer": answer, "options": [
+                    {"text": "Berlin", "correct": true},
+                    {"text": "Bonn", "correct": false}
+                ]}
+            ]})
+        };
+        let mut solution = json!({"type": "doc", "marks": [], "content": [
+            {"type": "paragraph", "marks": [], "content": [dropdown("Bonn"), dropdown("Berlin")]},
+            {"type": "paragraph", "marks": [], "content": [dropdown("")]}
+        ]});
+        assert_eq!(dropdown_credits(&solution), [0.0, 1.0, 0.0]);
+        number_dropdowns(&mut solution);
+        assert_eq!(
+            solution["content"][0]["content"][1]["marks"][0]["index"],
+            json!(1)
+        );
+        assert_eq!(
+            solution["content"][1]["content"][0]["marks"][0]["index"],
+            json!(2)
+        );
+    }
+}
@@ -0,0 +1,782 @@
+use serde_json::{json, Map, Value};
+
+use super::essay;
+use super::formula;
+use super::grading;
+use super::numeric::NumericTask;
+use super::variables::{self, Definition};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Node {
+    pub kind: NodeKind,
+    pub content: Vec<Node>,
+    pub marks: Vec<Mark>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum NodeKind {
+    Doc,
+    Paragraph,
+    Heading {
+        level: u8,
+    },
+    Text(String),
+    CodeBlock {
+        language: String,
+    },
+    Audio {
+        source: String,
+        mimetype: String,
+    },
+    /// With `shuffle` every student gets the answers in their own order, see [`super::shuffle`]
+    MultipleChoice {
+        mode: ChoiceMode,
+        scoring: Scoring,
+        shuffle: bool,
+    },
+    MultipleChoiceAnswer {
+        solution: bool,
+        answer: bool,
+    },
+    /// Every student gets `draw` of its items, see [`super::pool`]. In solutions only the drawn
+    /// items are left and `drawn` holds their positions in the sheet.
+    Pool {
+        draw: usize,
+        drawn: Option<Vec<usize>>,
+    },
+    PoolItem,
+    /// Variables with values drawn for every student, see [`super::variables`]
+    Variables(Vec<Variable>),
+    /// A written answer graded by a teacher, see [`super::essay`]. `awarded` holds the points given
+    /// once it is graded.
+    Essay {
+        min_words: Option<u32>,
+        max_words: Option<u32>,
+        points: u32,
+        /// Only shown to teachers
+        model_answer: String,
+        answer: String,
+        awarded: Option<f64>,
+    },
+    BulletList,
+    OrderedList,
+    ListItem,
+    Blockquote,
+    HardBreak,
+    HorizontalRule,
+    Other(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChoiceMode {
+    /// Exactly one answer is correct and only one can be ticked
+    Single,
+    Multiple,
+}
+
+/// How the point of a multiple choice block is split among its answers
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Scoring {
+    /// The point is only granted if every answer is ticked correctly
+    AllOrNothing,
+    /// Every ticked correct answer is worth its share of the point
+    PartialCredit,
+    /// Like partial credit, but every ticked wrong answer deducts its share of the point, down to
+    /// zero
+    NegativeMarking,
+}
+
+impl ChoiceMode {
+    /// Reads the name used in the JSON representation, falling back to the default
+    pub fn parse(name: Option<&str>) -> Self {
+        match name {
+            Some("single") => Self::Single,
+            _ => Self::Multiple,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Single => "single",
+            Self::Multiple => "multiple",
+        }
+    }
+}
+
+impl Scoring {
+    /// Reads the name used in the JSON representation, falling back to the default
+    pub fn parse(name: Option<&str>) -> Self {
+        match name {
+            Some("partialCredit") => Self::PartialCredit,
+            Some("negativeMarking") => Self::NegativeMarking,
+            _ => Self::AllOrNothing,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::AllOrNothing => "allOrNothing",
+            Self::PartialCredit => "partialCredit",
+            Self::NegativeMarking => "negativeMarking",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Mark {
+    Gap {
+        solution: String,
+        answer: String,
+        matching: GapMatching,
+    },
+    /// A gap answered by picking one of the options, see [`dropdown_text`] for the marked text
+    Dropdown {
+        options: Vec<DropdownOption>,
+        answer: String,
+        shuffle: bool,
+    },
+    /// The solution is written as described in [`super::numeric`]
+    Numeric {
+        solution: String,
+        answer: String,
+    },
+    /// A formula checked for equivalence with the solution, see [`super::formula`]
+    Formula {
+        solution: String,
+        answer: String,
+    },
+    Latex {
+        source: String,
+    },
+    Bold,
+    Italic,
+    Code,
+    Strike,
+    Other(String),
+}
+
+/// How answers to a gap are compared with its solution, see [`super::grading::gap_credit`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct GapMatching {
+    /// Further accepted answers besides the solution
+    pub alternatives: Vec<Alternative>,
+    pub case_sensitive: bool,
+    /// Ignore surrounding and repeated whitespace as well as punctuation
+    pub normalize: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Alternative {
+    pub answer: String,
+    /// Fraction of the gap's point granted for this answer, between 0 and 1
+    pub credit: f64,
+    /// Whether `answer` is a regular expression which has to match the whole answer
+    pub regex: bool,
+}
+
+impl Default for GapMatching {
+    fn default() -> Self {
+        Self {
+            alternatives: Vec::new(),
+            case_sensitive: true,
+            normalize: false,
+        }
+    }
+}
+
+impl GapMatching {
+    fn from_json(value: &Value) -> Self {
+        Self {
+            alternatives: value
+                .get("alternatives")
+                .and_then(Value::as_array)
+                .map(|alternatives| alternatives.iter().map(Alternative::from_json).collect())
+                .unwrap_or_default(),
+            case_sensitive: value
+                .get("caseSensitive")
+                .and_then(Value::as_bool)
+                .unwrap_or(true),
+            normalize: bool_field(value, "normalize"),
+        }
+    }
+
+    fn write_json(&self, mark: &mut Map<String, Value>) {
+        mark.insert(
+            "alternatives".into(),
+            Value::Array(self.alternatives.iter().map(Alternative::to_json).collect()),
+        );
+        mark.insert("caseSensitive".into(), json!(self.case_sensitive));
+        mark.insert("normalize".into(), json!(self.normalize));
+    }
+}
+
+impl Alternative {
+    fn from_json(value: &Value) -> Self {
+        Self {
+            answer: str_field(value, "answer"),
+            credit: value
+                .get("credit")
+                .and_then(Value::as_f64)
+                .unwrap_or(1.0)
+                .clamp(0.0, 1.0),
+            regex: bool_field(value, "regex"),
+        }
+    }
+
+    fn to_json(&self) -> Value {
+        json!({"answer": self.answer, "credit": self.credit, "regex": self.regex})
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Variable {
+    pub name: String,
+    pub definition: String,
+    /// The value drawn for a solution
+    pub value: Option<f64>,
+}
+
+impl Variable {
+    fn from_json(value: &Value) -> Self {
+        Self {
+            name: str_field(value, "name"),
+            definition: str_field(value, "definition"),
+            value: value.get("value").and_then(Value::as_f64),
+        }
+    }
+
+    fn to_json(&self) -> Value {
+        let mut variable = Map::new();
+        variable.insert("name".into(), json!(self.name));
+        variable.insert("definition".into(), json!(self.definition));
+        if let Some(value) = self.value {
+            variable.insert("value".into(), json!(value));
+        }
+        Value::Object(variable)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InvalidTask {
+    /// A dropdown without options or without a correct one
+    Dropdown,
+    /// A gap with an alternative answer which is not a valid regular expression
+    GapAlternative,
+    /// A numeric task whose solution cannot be read
+    Numeric,
+    /// A formula whose solution cannot be read
+    Formula,
+    /// A single choice block without exactly one correct answer
+    SingleChoice,
+    /// A pool drawing none or more items than it has
+    Pool,
+    /// A variable with an invalid or duplicate name or a definition that cannot be read
+    Variable,
+    /// An essay worth no points or with a minimum above its maximum number of words
+    Essay,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DropdownOption {
+    pub text: String,
+    pub correct: bool,
+}
+
+impl DropdownOption {
+    fn from_json(value: &Value) -> Self {
+        Self {
+            text: str_field(value, "text"),
+            correct: bool_field(value, "correct"),
+        }
+    }
+
+    fn to_json(&self) -> Value {
+        json!({"text": self.text, "correct": self.correct})
+    }
+}
+
+/// The text marked as dropdown, which is the first correct option like the solution of a gap
+pub fn dropdown_text(options: &[DropdownOption]) -> String {
+    options
+        .iter()
+        .find(|option| option.correct)
+        .or_else(|| options.first())
+        .map(|option| option.text.clone())
+        .unwrap_or_default()
+}
+
+fn str_field(value: &Value, field: &str) -> String {
+    value
+        .get(field)
+        .and_then(Value::as_str)
+        .unwrap_or_default()
+        .to_string()
+}
+
+fn bool_field(value: &Value, field: &str) -> bool {
+    value.get(field).and_then(Value::as_bool).unwrap_or(false)
+}
+
+fn u32_field(value: &Value, field: &str) -> Option<u32> {
+    value
+        .get(field)
+        .and_then(Value::as_u64)
+        .map(|n| n.min(u32::MAX as u64) as u32)
+}
+
+impl Node {
+    pub fn new(kind: NodeKind, content: Vec<Node>) -> Self {
+        Self {
+            kind,
+            content,
+            marks: Vec::new(),
+        }
+    }
+
+    pub fn text<S: Into<String>>(text: S, marks: Vec<Mark>) -> Self {
+        Self {
+            kind: NodeKind::Text(text.into()),
+            content: Vec::new(),
+            marks,
+        }
+    }
+
+    pub fn from_json(value: &Value) -> Self {
+        let kind = match value
+            .get("type")
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+        {
+            "doc" => NodeKind::Doc,
+            "paragraph" => NodeKind::Paragraph,
+            "heading" => NodeKind::Heading {
+                level: value
+                    .get("level")
+                    .and_then(|level| {
+                        // Documents converted from tiptap store the level as a string
+                        level
+                            .as_u64()
+                            .or_else(|| level.as_str().and_then(|l| l.parse().ok()))
+                    })
+                    .unwrap_or(1)
+                    .clamp(1, 6) as u8,
+            },
+            "text" => NodeKind::Text(str_field(value, "text")),
+            "codeBlock" => NodeKind::CodeBlock {
+                language: str_field(value, "language"),
+            },
+            "audio" => NodeKind::Audio {
+                source: str_field(value, "source"),
+                mimetype: str_field(value, "mimetype"),
+            },
+            "multipleChoice" => NodeKind::MultipleChoice {
+                mode: ChoiceMode::parse(value.get("mode").and_then(Value::as_str)),
+                scoring: Scoring::parse(value.get("scoring").and_then(Value::as_str)),
+                shuffle: bool_field(value, "shuffle"),
+            },
+            "multipleChoiceAnswer" => NodeKind::MultipleChoiceAnswer {
+                solution: bool_field(value, "solution"),
+                answer: bool_field(value, "answer"),
+            },
+            "pool" => NodeKind::Pool {
+                draw: value.get("draw").and_then(Value::as_u64).unwrap_or(1) as usize,
+                drawn: value.get("drawn").and_then(Value::as_array).map(|drawn| {
+                    drawn
+                        .iter()
+                        .filter_map(Value::as_u64)
+                        .map(|i| i as usize)
+                        .collect()
+                }),
+            },
+            "poolItem" => NodeKind::PoolItem,
+            "variables" => NodeKind::Variables(
+                value
+                    .get("variables")
+                    .and_then(Value::as_array)
+                    .map(|variables| variables.iter().map(Variable::from_json).collect())
+                    .unwrap_or_default(),
+            ),
+            "essay" => NodeKind::Essay {
+                min_words: u32_field(value, "minWords"),
+                max_words: u32_field(value, "maxWords"),
+                points: u32_field(value, "points").unwrap_or(1),
+                model_answer: str_field(value, "modelAnswer"),
+                answer: str_field(value, "answer"),
+                awarded: value.get("awarded").and_then(Value::as_f64),
+            },
+            "bulletList" => NodeKind::BulletList,
+            "orderedList" => NodeKind::OrderedList,
+            "listItem" => NodeKind::ListItem,
+            "blockquote" => NodeKind::Blockquote,
+            "hardBreak" => NodeKind::HardBreak,
+            "horizontalRule" => NodeKind::HorizontalRule,
+            other => NodeKind::Other(other.to_string()),
+        };
+        let content = value
+            .get("content")
+            .and_then(Value::as_array)
+            .map(|children| children.iter().map(Node::from_json).collect())
+            .unwrap_or_default();
+        let marks = value
+            .get("marks")
+            .and_then(Value::as_array)
+            .map(|marks| marks.iter().map(Mark::from_json).collect())
+            .unwrap_or_default();
+        Self {
+            kind,
+            content,
+            marks,
+        }
+    }
+
+    pub fn to_json(&self) -> Value {
+        let mut node = Map::new();
+        let kind = match &self.kind {
+            NodeKind::Doc => "doc",
+            NodeKind::Paragraph => "paragraph",
+            NodeKind::Heading { level } => {
+                node.insert("level".into(), json!(level));
+                "heading"
+            }
+            NodeKind::Text(text) => {
+                node.insert("text".into(), json!(text));
+                "text"
+            }
+            NodeKind::CodeBlock { language } => {
+                node.insert("language".into(), json!(language));
+                "codeBlock"
+            }
+            NodeKind::Audio { source, mimetype } => {
+                node.insert("source".into(), json!(source));
+                node.insert("mimetype".into(), json!(mimetype));
+                "audio"
+            }
+            NodeKind::MultipleChoice {
+                mode,
+                scoring,
+                shuffle,
+            } => {
+                node.insert("mode".into(), json!(mode.as_str()));
+                node.insert("scoring".into(), json!(scoring.as_str()));
+                node.insert("shuffle".into(), json!(shuffle));
+                "multipleChoice"
+            }
+            NodeKind::MultipleChoiceAnswer { solution, answer } => {
+                node.insert("solution".into(), json!(solution));
+                node.insert("answer".into(), json!(answer));
+                "multipleChoiceAnswer"
+            }
+            NodeKind::Pool { draw, drawn } => {
+                node.insert("draw".into(), json!(draw));
+                if let Some(drawn) = drawn {
+                    node.insert("drawn".into(), json!(drawn));
+                }
+                "pool"
+            }
+            NodeKind::PoolItem => "poolItem",
+            NodeKind::Variables(variables) => {
+                node.insert(
+                    "variables".into(),
+                    Value::Array(variables.iter().map(Variable::to_json).collect()),
+                );
+                "variables"
+            }
+            NodeKind::Essay {
+                min_words,
+                max_words,
+                points,
+                model_answer,
+                answer,
+                awarded,
+            } => {
+                if let Some(min_words) = min_words {
+                    node.insert("minWords".into(), json!(min_words));
+                }
+                if let Some(max_words) = max_words {
+                    node.insert("maxWords".into(), json!(max_words));
+                }
+                node.insert("points".into(), json!(points));
+                node.insert("modelAnswer".into(), json!(model_answer));
+                node.insert("answer".into(), json!(answer));
+                if let Some(awarded) = awarded {
+                    node.insert("awarded".into(), json!(awarded));
+                }
+                "essay"
+            }
+            NodeKind::BulletList => "bulletList",
+            NodeKind::OrderedList => "orderedList",
+            NodeKind::ListItem => "listItem",
+            NodeKind::Blockquote => "blockquote",
+            NodeKind::HardBreak => "hardBreak",
+            NodeKind::HorizontalRule => "horizontalRule",
+            NodeKind::Other(kind) => kind,
+        };
+        node.insert("type".into(), json!(kind));
+        node.insert(
+            "content".into(),
+            Value::Array(self.content.iter().map(Node::to_json).collect()),
+        );
+        node.insert(
+            "marks".into(),
+            Value::Array(self.marks.iter().map(Mark::to_json).collect()),
+        );
+        Value::Object(node)
+    }
+
+    /// Checks that all tasks in the subtree can be solved
+    pub fn validate(&self) -> Result<(), InvalidTask> {
+        self.marks.iter().try_for_each(Mark::validate)?;
+        if let NodeKind::MultipleChoice {
+            mode: ChoiceMode::Single,
+            ..
+        } = self.kind
+        {
+            let correct = self
+                .content
+                .iter()
+                .filter(|answer| {
+                    matches!(
+                        answer.kind,
+                        NodeKind::MultipleChoiceAnswer { solution: true, .. }
+                    )
+                })
+                .count();
+            if correct != 1 {
+                return Err(InvalidTask::SingleChoice);
+            }
+        }
+        if let NodeKind::Pool { draw, .. } = self.kind {
+            if draw == 0 || draw > self.content.len() {
+                return Err(InvalidTask::Pool);
+            }
+        }
+        if let NodeKind::Variables(variables) = &self.kind {
+            for (i, variable) in variables.iter().enumerate() {
+                if !variables::is_valid_name(&variable.name)
+                    || variables[..i].iter().any(|v| v.name == variable.name)
+                    || Definition::parse(&variable.definition).is_none()
+                {
+                    return Err(InvalidTask::Variable);
+                }
+            }
+        }
+        if let NodeKind::Essay {
+            min_words,
+            max_words,
+            points,
+            ..
+        } = self.kind
+        {
+            if points == 0 || matches!((min_words, max_words), (Some(min), Some(max)) if min > max)
+            {
+                return Err(InvalidTask::Essay);
+            }
+        }
+        self.content.iter().try_for_each(Node::validate)
+    }
+
+    /// Concatenated text of all descendants, ignoring marks
+    pub fn text_content(&self) -> String {
+        match &self.kind {
+            NodeKind::Text(text) => text.clone(),
+            NodeKind::HardBreak => "\n".to_string(),
+            _ => self.content.iter().map(Node::text_content).collect(),
+        }
+    }
+}
+
+impl Mark {
+    pub fn from_json(value: &Value) -> Self {
+        match value
+            .get("type")
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+        {
+            "gap" => Mark::Gap {
+                solution: str_field(value, "solution"),
+                answer: str_field(value, "answer"),
+                matching: GapMatching::from_json(value),
+            },
+            "dropdown" => Mark::Dropdown {
+                options: value
+                    .get("options")
+                    .and_then(Value::as_array)
+                    .map(|options| options.iter().map(DropdownOption::from_json).collect())
+                    .unwrap_or_default(),
+                answer: str_field(value, "answer"),
+                shuffle: bool_field(value, "shuffle"),
+            },
+            "numeric" => Mark::Numeric {
+                solution: str_field(value, "solution"),
+                answer: str_field(value, "answer"),
+            },
+            "formula" => Mark::Formula {
+                solution: str_field(value, "solution"),
+                answer: str_field(value, "answer"),
+            },
+            "latex" => Mark::Latex {
+                source: str_field(value, "source"),
+            },
+            "bold" => Mark::Bold,
+            "italic" => Mark::Italic,
+            "code" => Mark::Code,
+            "strike" => Mark::Strike,
+            other => Mark::Other(other.to_string()),
+        }
+    }
+
+    /// Checks that the task can be solved, with sample values for the expressions of variables
+    pub fn validate(&self) -> Result<(), InvalidTask> {
+        let (valid, invalid) = match self {
+            Mark::Gap { matching, .. } => (
+                matching
+                    .alternatives
+                    .iter()
+                    .filter(|alternative| alternative.regex)
+                    .all(|alternative| {
+                        let pattern = variables::with_sample_values(&alternative.answer);
+                        grading::answer_regex(&pattern, matching.case_sensitive).is_ok()
+                    }),
+                InvalidTask::GapAlternative,
+            ),
+            Mark::Dropdown { options, .. } => (
+                options.iter().any(|option| option.correct)
+                    && options.iter().all(|option| !option.text.trim().is_empty()),
+                InvalidTask::Dropdown,
+            ),
+            Mark::Numeric { solution, .. } => (
+                NumericTask::parse(&variables::with_sample_values(solution)).is_some(),
+                InvalidTask::Numeric,
+            ),
+            Mark::Formula { solution, .. } => (
+                formula::parse(&variables::with_sample_values(solution)).is_some(),
+                InvalidTask::Formula,
+            ),
+            _ => return Ok(()),
+        };
+        if valid {
+            Ok(())
+        } else {
+            Err(invalid)
+        }
+    }
+
+    pub fn to_json(&self) -> Value {
+        match self {
+            Mark::Gap {
+                solution,
+                answer,
+                matching,
+            } => {
+                let mut mark = Map::new();
+                mark.insert("type".into(), json!("gap"));
+                mark.insert("solution".into(), json!(solution));
+                mark.insert("answer".into(), json!(answer));
+                matching.write_json(&mut mark);
+                Value::Object(mark)
+            }
+            Mark::Dropdown {
+                options,
+                answer,
+                shuffle,
+            } => json!({
+                "type": "dropdown",
+                "options": options.iter().map(DropdownOption::to_json).collect::<Vec<_>>(),
+                "answer": answer,
+                "shuffle": shuffle,
+            }),
+            Mark::Numeric { solution, answer } => {
+                json!({"type": "numeric", "solution": solution, "answer": answer})
+            }
+            Mark::Formula { solution, answer } => {
+                json!({"type": "formula", "solution": solution, "answer": answer})
+            }
+            Mark::Latex { source } => json!({"type": "latex", "source": source}),
+            Mark::Bold => json!({"type": "bold"}),
+            Mark::Italic => json!({"type": "italic"}),
+            Mark::Code => json!({"type": "code"}),
+            Mark::Strike => json!({"type": "strike"}),
+            Mark::Other(kind) => json!({ "type": kind }),
+        }
+    }
+}
+
+/// Hides which dropdown options are correct and the model answers of essays in sheet content
+/// shown before solving it
+pub fn redact_solutions(value: &mut Value) {
+    redact_dropdowns(value);
+    essay::hide_model_answers(value);
+}
+
+/// Hides which dropdown options are correct. The marked text is replaced as well, since it is the
+/// first correct option.
+pub fn redact_dropdowns(value: &mut Value) {
+    let mut options = None;
+    if let Some(marks) = value.get_mut("marks").and_then(Value::as_array_mut) {
+        for mark in marks
+            .iter_mut()
+            .filter(|mark| mark.get("type").and_then(Value::as_str) == Some("dropdown"))
+        {
+            if let Some(list) = mark.get_mut("options").and_then(Value::as_array_mut) {
+                for option in list.iter_mut().filter_map(Value::as_object_mut) {
+                    option.insert("correct".into(), json!(false));
+                }
+                options = Some(
+                    list.iter()
+                        .map(|option| str_field(option, "text"))
+                        .collect::<Vec<_>>()
+                        .join(" / "),
+                );
+            }
+        }
+    }
+    if let (Some(options), Some(node)) = (options, value.as_object_mut()) {
+        node.insert("text".into(), json!(options));
+    }
+    if let Some(content) = value.get_mut("content").and_then(Value::as_array_mut) {
+        content.iter_mut().for_each(redact_dropdowns);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn task(mark: Value) -> Node {
+        Node::from_json(&json!({"type": "doc", "marks": [], "content": [
+            {"type": "paragraph", "marks": [], "content": [
+                {"type": "text", "text": "Antwort", "marks": [mark], "content": []}
+            ]}
+        ]}))
+    }
+
+    fn gap(alternative: &str) -> Node {
+        task(
+            json!({"type": "gap", "solution": "Rom", "answer": "", "alternatives": [
+                {"answer": alternative, "credit": 0.5, "regex": true}
+            ]}),
+        )
+    }
+
+    #[test]
+    fn rejects_invalid_regular_expressions() {
+        assert_eq!(gap("R(om|oma)").validate(), Ok(()));
+        assert_eq!(gap("R(om|oma").validate(), Err(InvalidTask::GapAlternative));
+        assert_eq!(gap("[a-").validate(), Err(InvalidTask::GapAlternative));
+    }
+
+    #[test]
+    fn rejects_unreadable_solutions() {
+        let numeric =
+            |solution: &str| task(json!({"type": "numeric", "solution": solution, "answer": ""}));
+        assert_eq!(numeric("3,5 ± 0,1 cm").validate(), Ok(()));
+        assert_eq!(numeric("{{a * 2}} m").validate(), Ok(()));
+        assert_eq!(numeric("etwa drei").validate(), Err(InvalidTask::Numeric));
+        assert_eq!(numeric("").validate(), Err(InvalidTask::Numeric));
+        let formula =
+            |solution: &str| task(json!({"type": "formula", "solution": solution, "answer": ""}));
+        assert_eq!(formula("\\frac{1}{2}x^2 + x").validate(), Ok(()));
+        assert_eq!(formula("\\frac{{{a}}}{2}x").validate(), Ok(()));
+        assert_eq!(formula("x +").validate(), Err(InvalidTask::Formula));
+    }
+}
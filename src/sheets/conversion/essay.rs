@@ -0,0 +1,135 @@
+//! Free-text answers graded by teachers.
+//!
+//! An `essay` node asks for a written answer, optionally within word limits. It is worth `points`,
+//! which a teacher awards by hand, so [`super::grading`] counts it as ungraded until then. The
+//! model answer is meant for teachers and is hidden from students. Essays are identified by their
+//! position among the essays of a solution, which shuffling does not change.
+//!
+//! Like [`super::pool`] the functions changing solutions work on the JSON content.
+
+use serde_json::{json, Map, Value};
+
+use super::document::{Node, NodeKind};
+
+pub fn count_words(text: &str) -> usize {
+    text.split_whitespace().count()
+}
+
+/// The word limits as shown to students, empty without limits
+pub fn word_limits(min_words: Option<u32>, max_words: Option<u32>) -> String {
+    match (min_words, max_words) {
+        (Some(min), Some(max)) => format!("{} bis {} Wörter", min, max),
+        (Some(min), None) => format!("mindestens {} Wörter", min),
+        (None, Some(max)) => format!("höchstens {} Wörter", max),
+        (None, None) => String::new(),
+    }
+}
+
+/// All essays in document order
+pub fn essays(node: &Node) -> Vec<&Node> {
+    let mut essays = Vec::new();
+    collect(node, &mut essays);
+    essays
+}
+
+fn collect<'a>(node: &'a Node, essays: &mut Vec<&'a Node>) {
+    if let NodeKind::Essay { .. } = node.kind {
+        essays.push(node);
+    }
+    for child in &node.content {
+        collect(child, essays);
+    }
+}
+
+fn is_essay(value: &Value) -> bool {
+    value.get("type").and_then(Value::as_str) == Some("essay")
+}
+
+fn for_each_essay<F: FnMut(&mut Map<String, Value>)>(value: &mut Value, f: &mut F) {
+    if is_essay(value) {
+        if let Some(essay) = value.as_object_mut() {
+            f(essay);
+        }
+    }
+    if let Some(children) = value.get_mut("content").and_then(Value::as_array_mut) {
+        for child in children {
+            for_each_essay(child, f);
+        }
+    }
+}
+
+/// Removes the model answers from content shown to students
+pub fn hide_model_answers(content: &mut Value) {
+    for_each_essay(content, &mut |essay| {
+        essay.remove("modelAnswer");
+    });
+}
+
+/// Awards points to the essay at the given position, `false` if there is no such essay or it is
+/// not worth that many points
+pub fn award(content: &mut Value, index: usize, points: f64) -> bool {
+    let mut position = 0;
+    let mut awarded = false;
+    for_each_essay(content, &mut |essay| {
+        if position == index {
+            let worth = essay.get("points").and_then(Value::as_u64).unwrap_or(1);
+            if (0.0..=worth as f64).contains(&points) {
+                essay.insert("awarded".into(), json!(points));
+                awarded = true;
+            }
+        }
+        position += 1;
+    });
+    awarded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{document, render};
+    use super::*;
+
+    fn essay(answer: &str, awarded: Option<f64>) -> Value {
+        let mut essay = json!({
+            "type": "essay",
+            "points": 2,
+            "modelAnswer": "Weil die Erde sich dreht.",
+            "answer": answer,
+        });
+        if let Some(awarded) = awarded {
+            essay["awarded"] = json!(awarded);
+        }
+        essay
+    }
+
+    #[test]
+    fn awards_points_within_the_limit() {
+        let mut content = json!({"type": "doc", "content": [essay("a", None), essay("b", None)]});
+        assert!(award(&mut content, 1, 2.0));
+        assert!(!award(&mut content, 0, 2.5));
+        assert!(!award(&mut content, 0, -1.0));
+        assert!(!award(&mut content, 2, 1.0));
+        assert!(content["content"][0].get("awarded").is_none());
+        assert_eq!(content["content"][1]["awarded"], json!(2.0));
+        hide_model_answers(&mut content);
+        assert!(content["content"][1].get("modelAnswer").is_none());
+    }
+
+    #[test]
+    fn hides_model_answers_from_viewers() {
+        let mut content = json!({"type": "doc", "marks": [], "content": [
+            {"type": "essay", "points": 2, "modelAnswer": "Weil die Erde sich dreht.",
+                "marks": [], "content": []}
+        ]});
+        document::redact_solutions(&mut content);
+        let rendered = render::render_sheet(&Node::from_json(&content));
+        assert!(!content.to_string().contains("modelAnswer"));
+        assert!(!rendered.contains("data-model-answer"));
+        assert!(!rendered.contains("Weil die Erde"));
+    }
+
+    #[test]
+    fn counts_words() {
+        assert_eq!(count_words("  Die Erde\n dreht sich. "), 4);
+        assert_eq!(count_words(""), 0);
+    }
+}
@@ -0,0 +1,387 @@
+//! Arithmetic expressions like `2(a + b)^2 / sqrt(c)`.
+//!
+//! Numbers use a decimal point, function arguments are separated by commas and a factor may follow
+//! a number or a closing parenthesis without `*`. The constants `pi` and `e` and the usual
+//! functions are known, everything else is a variable.
+
+use std::f64::consts::{E, PI};
+
+/// Deeper nesting, counting each operator of a chain like `1 + 1 + 1` as a level, is rejected
+/// instead of overflowing the stack when parsing or evaluating
+pub const MAX_DEPTH: usize = 256;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expression {
+    Number(f64),
+    Variable(String),
+    Negate(Box<Expression>),
+    Binary(Operator, Box<Expression>, Box<Expression>),
+    Call(String, Vec<Expression>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Operator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Power,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Name(String),
+    Operator(Operator),
+    Open,
+    Close,
+    Comma,
+}
+
+impl Expression {
+    pub fn parse(text: &str) -> Option<Self> {
+        let tokens = tokenize(text)?;
+        let mut parser = Parser {
+            tokens,
+            next: 0,
+            depth: 0,
+        };
+        let expression = parser.sum()?;
+        if parser.next == parser.tokens.len() {
+            Some(expression)
+        } else {
+            None
+        }
+    }
+
+    /// The value for the given variable values, `None` if a variable is unknown or the result is
+    /// not a finite number
+    pub fn evaluate(&self, variable: &dyn Fn(&str) -> Option<f64>) -> Option<f64> {
+        let value = match self {
+            Self::Number(value) => *value,
+            Self::Variable(name) => variable(name)?,
+            Self::Negate(operand) => -operand.evaluate(variable)?,
+            Self::Binary(operator, left, right) => {
+                let (left, right) = (left.evaluate(variable)?, right.evaluate(variable)?);
+                match operator {
+                    Operator::Add => left + right,
+                    Operator::Subtract => left - right,
+                    Operator::Multiply => left * right,
+                    Operator::Divide => left / right,
+                    Operator::Power => left.powf(right),
+                }
+            }
+            Self::Call(function, arguments) => {
+                let arguments = arguments
+                    .iter()
+                    .map(|argument| argument.evaluate(variable))
+                    .collect::<Option<Vec<f64>>>()?;
+                call(function, &arguments)?
+            }
+        };
+        if value.is_finite() {
+            Some(value)
+        } else {
+            None
+        }
+    }
+
+    /// Names of all variables in the expression, in order of their first occurrence
+    pub fn variables(&self) -> Vec<&str> {
+        let mut names = Vec::new();
+        self.collect_variables(&mut names);
+        names
+    }
+
+    fn collect_variables<'a>(&'a self, names: &mut Vec<&'a str>) {
+        match self {
+            Self::Number(_) => {}
+            Self::Variable(name) => {
+                if !names.contains(&name.as_str()) {
+                    names.push(name);
+                }
+            }
+            Self::Negate(operand) => operand.collect_variables(names),
+            Self::Binary(_, left, right) => {
+                left.collect_variables(names);
+                right.collect_variables(names);
+            }
+            Self::Call(_, arguments) => {
+                for argument in arguments {
+                    argument.collect_variables(names);
+                }
+            }
+        }
+    }
+}
+
+fn call(function: &str, arguments: &[f64]) -> Option<f64> {
+    let value = match (function, arguments) {
+        ("sqrt", [x]) => x.sqrt(),
+        ("abs", [x]) => x.abs(),
+        ("round", [x]) => x.round(),
+        ("round", [x, digits]) => {
+            let factor = 10f64.powi(*digits as i32);
+            (x * factor).round() / factor
+        }
+        ("floor", [x]) => x.floor(),
+        ("ceil", [x]) => x.ceil(),
+        ("min", [first, rest @ ..]) => rest.iter().fold(*first, |a, &b| a.min(b)),
+        ("max", [first, rest @ ..]) => rest.iter().fold(*first, |a, &b| a.max(b)),
+        ("sin", [x]) => x.sin(),
+        ("cos", [x]) => x.cos(),
+        ("tan", [x]) => x.tan(),
+        ("asin", [x]) => x.asin(),
+        ("acos", [x]) => x.acos(),
+        ("atan", [x]) => x.atan(),
+        ("exp", [x]) => x.exp(),
+        ("ln", [x]) => x.ln(),
+        ("log", [x]) => x.log10(),
+        _ => return None,
+    };
+    Some(value)
+}
+
+pub fn is_function(name: &str) -> bool {
+    matches!(
+        name,
+        "sqrt"
+            | "abs"
+            | "round"
+            | "floor"
+            | "ceil"
+            | "min"
+            | "max"
+            | "sin"
+            | "cos"
+            | "tan"
+            | "asin"
+            | "acos"
+            | "atan"
+            | "exp"
+            | "ln"
+            | "log"
+    )
+}
+
+fn tokenize(text: &str) -> Option<Vec<Token>> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let token = match c {
+            c if c.is_whitespace() => {
+                i += 1;
+                continue;
+            }
+            '0'..='9' | '.' => {
+                let start = i;
+                while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                    i += 1;
+                }
+                let number: String = chars[start..i].iter().collect();
+                tokens.push(Token::Number(number.parse().ok()?));
+                continue;
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let start = i;
+                while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                    i += 1;
+                }
+                let name: String = chars[start..i].iter().collect();
+                tokens.push(match name.as_str() {
+                    "pi" | "π" => Token::Number(PI),
+                    "e" => Token::Number(E),
+                    _ => Token::Name(name),
+                });
+                continue;
+            }
+            '+' => Token::Operator(Operator::Add),
+            '-' | '−' => Token::Operator(Operator::Subtract),
+            '*' | '·' | '×' => Token::Operator(Operator::Multiply),
+            '/' | ':' | '÷' => Token::Operator(Operator::Divide),
+            '^' => Token::Operator(Operator::Power),
+            '(' | '[' | '{' => Token::Open,
+            ')' | ']' | '}' => Token::Close,
+            ',' | ';' => Token::Comma,
+            _ => return None,
+        };
+        tokens.push(token);
+        i += 1;
+    }
+    Some(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    next: usize,
+    depth: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.next)
+    }
+
+    fn eat(&mut self, token: &Token) -> bool {
+        if self.peek() == Some(token) {
+            self.next += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Enters the next level of nesting, `None` if it is too deep
+    fn descend(&mut self) -> Option<()> {
+        if self.depth == MAX_DEPTH {
+            return None;
+        }
+        self.depth += 1;
+        Some(())
+    }
+
+    fn sum(&mut self) -> Option<Expression> {
+        let depth = self.depth;
+        let mut left = self.product()?;
+        loop {
+            let operator = match self.peek() {
+                Some(&Token::Operator(o)) if matches!(o, Operator::Add | Operator::Subtract) => o,
+                _ => {
+                    self.depth = depth;
+                    return Some(left);
+                }
+            };
+            self.next += 1;
+            self.descend()?;
+            left = Expression::Binary(operator, Box::new(left), Box::new(self.product()?));
+        }
+    }
+
+    fn product(&mut self) -> Option<Expression> {
+        let depth = self.depth;
+        let mut left = self.unary()?;
+        loop {
+            let (operator, right) = match self.peek() {
+                Some(&Token::Operator(o)) if matches!(o, Operator::Multiply | Operator::Divide) => {
+                    self.next += 1;
+                    self.descend()?;
+                    (o, self.unary()?)
+                }
+                // Implicit multiplication like `2x` or `(a + 1)(a - 1)`
+                Some(Token::Number(_)) | Some(Token::Name(_)) | Some(Token::Open) => {
+                    self.descend()?;
+                    (Operator::Multiply, self.power()?)
+                }
+                _ => {
+                    self.depth = depth;
+                    return Some(left);
+                }
+            };
+            left = Expression::Binary(operator, Box::new(left), Box::new(right));
+        }
+    }
+
+    fn unary(&mut self) -> Option<Expression> {
+        self.descend()?;
+        let expression = if self.eat(&Token::Operator(Operator::Subtract)) {
+            Expression::Negate(Box::new(self.unary()?))
+        } else if self.eat(&Token::Operator(Operator::Add)) {
+            self.unary()?
+        } else {
+            self.power()?
+        };
+        self.depth -= 1;
+        Some(expression)
+    }
+
+    fn power(&mut self) -> Option<Expression> {
+        let base = self.primary()?;
+        if self.eat(&Token::Operator(Operator::Power)) {
+            // Right associative and binding tighter than a sign in front, `-2^2` is `-4`
+            let exponent = self.unary()?;
+            return Some(Expression::Binary(
+                Operator::Power,
+                Box::new(base),
+                Box::new(exponent),
+            ));
+        }
+        Some(base)
+    }
+
+    fn primary(&mut self) -> Option<Expression> {
+        self.descend()?;
+        let token = self.peek()?.clone();
+        self.next += 1;
+        let expression = match token {
+            Token::Number(value) => Expression::Number(value),
+            Token::Name(name) if is_function(&name) => {
+                let mut arguments = Vec::new();
+                if self.eat(&Token::Open) {
+                    loop {
+                        arguments.push(self.sum()?);
+                        if self.eat(&Token::Close) {
+                            break;
+                        }
+                        if !self.eat(&Token::Comma) {
+                            return None;
+                        }
+                    }
+                } else {
+                    // `sin x` takes the next factor as its argument
+                    arguments.push(self.power()?);
+                }
+                Expression::Call(name, arguments)
+            }
+            Token::Name(name) => Expression::Variable(name),
+            Token::Open => {
+                let inner = self.sum()?;
+                if !self.eat(&Token::Close) {
+                    return None;
+                }
+                inner
+            }
+            _ => return None,
+        };
+        self.depth -= 1;
+        Some(expression)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn evaluate(text: &str) -> Option<f64> {
+        let variables = |name: &str| match name {
+            "a" => Some(3.0),
+            "b" => Some(4.0),
+            _ => None,
+        };
+        Expression::parse(text)?.evaluate(&variables)
+    }
+
+    #[test]
+    fn evaluates_expressions() {
+        assert_eq!(evaluate("a + b * 2"), Some(11.0));
+        assert_eq!(evaluate("(a + b) * 2"), Some(14.0));
+        assert_eq!(evaluate("2(a + 1)"), Some(8.0));
+        assert_eq!(evaluate("2a^2"), Some(18.0));
+        assert_eq!(evaluate("-2^2"), Some(-4.0));
+        assert_eq!(evaluate("2^3^2"), Some(512.0));
+        assert_eq!(evaluate("sqrt(a^2 + b^2)"), Some(5.0));
+        assert_eq!(evaluate("round(b / a, 2)"), Some(1.33));
+        assert_eq!(evaluate("max(a, b, 1)"), Some(4.0));
+        assert_eq!(evaluate("a · b − 1"), Some(11.0));
+        assert_eq!(evaluate("a * -b"), Some(-12.0));
+        assert_eq!(evaluate("1 / (a - 3)"), None);
+        assert_eq!(evaluate("c + 1"), None);
+        assert_eq!(evaluate("a +"), None);
+        assert_eq!(evaluate("(a"), None);
+        assert_eq!(
+            Expression::parse("a * b + a").unwrap().variables(),
+            vec!["a", "b"]
+        );
+    }
+}
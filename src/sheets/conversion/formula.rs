@@ -0,0 +1,273 @@
+//! Formula tasks, whose answer is an expression like `2(x + 1)` checked for equivalence with the
+//! solution.
+//!
+//! Solutions and answers may be written in LaTeX or in the syntax of [`super::expression`]. For
+//! answers a comma between digits is a decimal comma and names of several letters are products
+//! of single letter variables, so `2xy` is `2 * x * y`. Both expressions are evaluated for the
+//! same values of their variables at a fixed set of points and are equivalent if they agree at
+//! every point where the solution is defined.
+
+use super::expression::{self, Expression};
+
+/// Values the variables take at the sample points, irregular enough not to hit special cases
+const SAMPLES: [f64; 12] = [
+    0.37, 1.21, -0.83, 2.54, -1.67, 0.58, 3.13, -2.29, 1.79, -0.41, 2.87, -3.05,
+];
+
+/// Sample points at which the solution has to be defined for a verdict
+const MIN_POINTS: usize = 4;
+
+pub fn parse(text: &str) -> Option<Expression> {
+    let plain = latex_to_plain(text)?;
+    Expression::parse(&split_names(&decimal_commas(&plain)))
+}
+
+/// Whether the answer is equivalent to the solution, answers to solutions which cannot be read
+/// are compared as text
+pub fn check(solution: &str, answer: &str) -> bool {
+    match (parse(solution), parse(answer)) {
+        (Some(solution), Some(answer)) => equivalent(&solution, &answer),
+        (None, _) => solution.trim() == answer.trim(),
+        (Some(_), None) => false,
+    }
+}
+
+pub fn equivalent(solution: &Expression, answer: &Expression) -> bool {
+    let mut names: Vec<&str> = solution.variables();
+    for name in answer.variables() {
+        if !names.contains(&name) {
+            names.push(name);
+        }
+    }
+    let mut defined = 0;
+    for point in 0..SAMPLES.len() {
+        let value = |name: &str| {
+            let k = names.iter().position(|&n| n == name)?;
+            Some(SAMPLES[(point * 5 + k * 7) % SAMPLES.len()])
+        };
+        let expected = match solution.evaluate(&value) {
+            Some(expected) => expected,
+            None => continue,
+        };
+        defined += 1;
+        match answer.evaluate(&value) {
+            Some(actual) if close(expected, actual) => {}
+            _ => return false,
+        }
+    }
+    defined >= MIN_POINTS.min(SAMPLES.len())
+}
+
+fn close(expected: f64, actual: f64) -> bool {
+    (expected - actual).abs() <= 1e-9 * expected.abs().max(actual.abs()).max(1.0)
+}
+
+/// Rewrites the LaTeX commands of school mathematics in plain syntax, `None` for unknown ones or
+/// groups nested too deeply
+pub fn latex_to_plain(latex: &str) -> Option<String> {
+    nested_latex_to_plain(latex, 0)
+}
+
+fn nested_latex_to_plain(latex: &str, depth: usize) -> Option<String> {
+    if depth == expression::MAX_DEPTH {
+        return None;
+    }
+    let inner = |latex: &str| nested_latex_to_plain(latex, depth + 1);
+    let chars: Vec<char> = latex.chars().collect();
+    let mut plain = String::new();
+    let mut i = 0;
+    while i < chars.len() {
+        if chars[i] != '\\' {
+            plain.push(chars[i]);
+            i += 1;
+            continue;
+        }
+        i += 1;
+        let start = i;
+        while i < chars.len() && chars[i].is_ascii_alphabetic() {
+            i += 1;
+        }
+        let command: String = chars[start..i].iter().collect();
+        match command.as_str() {
+            // `\,` and the like are spaces, `\{` is a brace
+            "" => {
+                let c = chars.get(i)?;
+                plain.push(match c {
+                    '{' | '}' | '|' => *c,
+                    _ => ' ',
+                });
+                i += 1;
+            }
+            "frac" | "dfrac" | "tfrac" => {
+                let numerator = group(&chars, &mut i)?;
+                let denominator = group(&chars, &mut i)?;
+                plain.push_str(&format!(
+                    "(({})/({}))",
+                    inner(&numerator)?,
+                    inner(&denominator)?
+                ));
+            }
+            "sqrt" => {
+                let index = if chars.get(i) == Some(&'[') {
+                    let end = i + chars[i..].iter().position(|&c| c == ']')?;
+                    let index: String = chars[i + 1..end].iter().collect();
+                    i = end + 1;
+                    Some(index)
+                } else {
+                    None
+                };
+                let radicand = inner(&group(&chars, &mut i)?)?;
+                match index {
+                    Some(index) => {
+                        plain.push_str(&format!("(({})^(1/({})))", radicand, inner(&index)?))
+                    }
+                    None => plain.push_str(&format!("sqrt({})", radicand)),
+                }
+            }
+            "cdot" | "times" | "ast" => plain.push('*'),
+            "div" => plain.push('/'),
+            "left" | "right" | "mathrm" | "text" | "operatorname" => {}
+            "pi" => plain.push_str(" pi "),
+            "arcsin" => plain.push_str(" asin "),
+            "arccos" => plain.push_str(" acos "),
+            "arctan" => plain.push_str(" atan "),
+            "lg" => plain.push_str(" log "),
+            name if expression::is_function(name) => plain.push_str(&format!(" {} ", name)),
+            _ => return None,
+        }
+    }
+    Some(plain)
+}
+
+/// The argument of a command, either in braces or a single character
+fn group(chars: &[char], i: &mut usize) -> Option<String> {
+    while chars.get(*i)?.is_whitespace() {
+        *i += 1;
+    }
+    if chars[*i] != '{' {
+        *i += 1;
+        return Some(chars[*i - 1].to_string());
+    }
+    let mut depth = 0;
+    for (j, &c) in chars.iter().enumerate().skip(*i) {
+        match c {
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    let inner = chars[*i + 1..j].iter().collect();
+                    *i = j + 1;
+                    return Some(inner);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+fn decimal_commas(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let digit = |i: Option<usize>| {
+        i.and_then(|i| chars.get(i))
+            .map_or(false, char::is_ascii_digit)
+    };
+    chars
+        .iter()
+        .enumerate()
+        .map(|(i, &c)| {
+            if c == ',' && digit(i.checked_sub(1)) && digit(Some(i + 1)) {
+                '.'
+            } else {
+                c
+            }
+        })
+        .collect()
+}
+
+/// Splits names which are neither functions nor constants into single letters
+fn split_names(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut name = String::new();
+    for c in text.chars().chain(std::iter::once(' ')) {
+        if c.is_alphabetic() {
+            name.push(c);
+            continue;
+        }
+        if !name.is_empty() {
+            result.push_str(&split_name(&name));
+            name.clear();
+        }
+        result.push(c);
+    }
+    result.pop();
+    result
+}
+
+fn split_name(name: &str) -> String {
+    if expression::is_function(name) || name == "pi" {
+        return format!(" {} ", name);
+    }
+    // `sinx` is the sine of x
+    let function = [
+        "sqrt", "asin", "acos", "atan", "sin", "cos", "tan", "exp", "abs", "ln",
+    ]
+    .iter()
+    .find(|function| name.len() > function.len() && name.starts_with(*function));
+    if let Some(function) = function {
+        return format!(" {} {}", function, split_name(&name[function.len()..]));
+    }
+    let letters: Vec<String> = name.chars().map(String::from).collect();
+    format!(" {} ", letters.join(" "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_equivalent_answers() {
+        assert!(check("2x+2", "2(x+1)"));
+        assert!(check("2(x+1)", "2 * x + 2"));
+        assert!(check("(a+b)^2", "a^2 + 2ab + b^2"));
+        assert!(check("\\frac{x^2-1}{x-1}", "x+1"));
+        assert!(check("\\sqrt{x^2}", "abs(x)"));
+        assert!(check("\\frac{1}{2}x", "0,5x"));
+        assert!(check("x^{\\frac12}", "\\sqrt[2]{x}"));
+        assert!(check("\\sin(x)^2 + \\cos(x)^2", "1"));
+        assert!(check("\\pi r^2", "r^2 \\cdot \\pi"));
+        assert!(check("2xy", "2 \\cdot y \\cdot x"));
+        assert!(check("\\left(x+1\\right)\\cdot 3", "3x+3"));
+    }
+
+    #[test]
+    fn rejects_different_answers() {
+        assert!(!check("2x+2", "2x+1"));
+        assert!(!check("2x+2", "2x"));
+        assert!(!check("(a+b)^2", "a^2 + b^2"));
+        assert!(!check("x", "y"));
+        assert!(!check("x", "x +"));
+        assert!(!check("x", "\\unknown{x}"));
+    }
+
+    #[test]
+    fn rejects_deeply_nested_answers() {
+        let signs = "-".repeat(100_000) + "x";
+        assert!(!check("x", &signs));
+        let parentheses = "(".repeat(100_000) + "x" + &")".repeat(100_000);
+        assert!(!check("x", &parentheses));
+        let sum = vec!["x"; 100_000].join("+");
+        assert!(!check("x", &sum));
+        let fractions = "\\frac{1}{".repeat(1_000) + "x" + &"}".repeat(1_000);
+        assert!(!check("x", &fractions));
+        // Nesting of usual depth is still read
+        let fractions = "\\frac{1}{".repeat(20) + "x" + &"}".repeat(20);
+        assert!(parse(&fractions).is_some());
+    }
+
+    #[test]
+    fn compares_unreadable_solutions_as_text() {
+        assert!(check("f'(x)", "f'(x)"));
+        assert!(!check("f'(x)", "f'(y)"));
+    }
+}
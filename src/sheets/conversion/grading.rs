@@ -0,0 +1,270 @@
+use regex::{Regex, RegexBuilder};
+
+use super::document::{ChoiceMode, GapMatching, Mark, Node, NodeKind, Scoring};
+use super::formula;
+use super::numeric::NumericTask;
+
+/// Upper bound for the compiled size of a teacher's regular expression
+const REGEX_SIZE_LIMIT: usize = 1 << 16;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Score {
+    /// Gaps with alternative answers may grant a fraction of their point
+    pub achieved: f64,
+    pub total: u32,
+    /// Essays which still need to be graded by a teacher, their points count towards the total
+    pub ungraded: u32,
+}
+
+impl Score {
+    fn add(&mut self, credit: f64) {
+        self.total += 1;
+        self.achieved += credit;
+    }
+}
+
+/// Grades a solution the same way the solution view does: every gap, every dropdown, every numeric
+/// task and every multiple choice block is worth one point. Essays are worth their points once a
+/// teacher graded them.
+pub fn grade(node: &Node) -> Score {
+    let mut score = Score::default();
+    grade_into(node, &mut score);
+    score
+}
+
+fn grade_into(node: &Node, score: &mut Score) {
+    for mark in &node.marks {
+        if let Some(credit) = mark_credit(mark) {
+            score.add(credit);
+        }
+    }
+    if let NodeKind::MultipleChoice { mode, scoring, .. } = node.kind {
+        score.add(choice_credit(mode, scoring, &node.content));
+    }
+    if let NodeKind::Essay {
+        points, awarded, ..
+    } = node.kind
+    {
+        score.total += points;
+        match awarded {
+            Some(awarded) => score.achieved += awarded,
+            None => score.ungraded += 1,
+        }
+    }
+    for child in &node.content {
+        grade_into(child, score);
+    }
+}
+
+pub fn answer_correct(node: &Node) -> bool {
+    match &node.kind {
+        NodeKind::MultipleChoiceAnswer { solution, answer } => solution == answer,
+        _ => true,
+    }
+}
+
+/// Fraction of the point granted for the ticked answers of a multiple choice block
+pub fn choice_credit(mode: ChoiceMode, scoring: Scoring, answers: &[Node]) -> f64 {
+    let scoring = match mode {
+        // Only one answer can be ticked, so there is nothing to split
+        ChoiceMode::Single => Scoring::AllOrNothing,
+        ChoiceMode::Multiple => scoring,
+    };
+    let (mut correct, mut wrong, mut ticked_correct, mut ticked_wrong) = (0, 0, 0, 0);
+    for answer in answers {
+        if let NodeKind::MultipleChoiceAnswer { solution, answer } = answer.kind {
+            match (solution, answer) {
+                (true, true) => ticked_correct += 1,
+                (false, true) => ticked_wrong += 1,
+                _ => {}
+            }
+            if solution {
+                correct += 1;
+            } else {
+                wrong += 1;
+            }
+        }
+    }
+    // Without correct answers the only right choice is to tick nothing
+    if scoring == Scoring::AllOrNothing || correct == 0 {
+        return credit(ticked_correct == correct && ticked_wrong == 0);
+    }
+    let achieved = ticked_correct as f64 / correct as f64;
+    match scoring {
+        Scoring::NegativeMarking if wrong > 0 => {
+            (achieved - ticked_wrong as f64 / wrong as f64).max(0.0)
+        }
+        _ => achieved,
+    }
+}
+
+/// Whether the answer to a gap, dropdown, numeric or formula task is fully correct, `None` for
+/// other marks
+pub fn mark_correct(mark: &Mark) -> Option<bool> {
+    mark_credit(mark).map(|credit| credit >= 1.0)
+}
+
+/// Fraction of the point granted for the answer to a gap, dropdown, numeric or formula task,
+/// `None` for other marks
+pub fn mark_credit(mark: &Mark) -> Option<f64> {
+    match mark {
+        Mark::Gap {
+            solution,
+            answer,
+            matching,
+        } => Some(gap_credit(solution, matching, answer)),
+        Mark::Dropdown {
+            options, answer, ..
+        } => Some(credit(
+            options
+                .iter()
+                .any(|option| option.correct && option.text == *answer),
+        )),
+        Mark::Numeric { solution, answer } => Some(credit(match NumericTask::parse(solution) {
+            Some(task) => task.check(answer),
+            // Solutions which cannot be parsed are compared like gaps
+            None => answer.trim() == solution.trim(),
+        })),
+        Mark::Formula { solution, answer } => Some(credit(formula::check(solution, answer))),
+        _ => None,
+    }
+}
+
+/// Best credit among the solution, which is worth the full point, and all matching alternatives
+pub fn gap_credit(solution: &str, matching: &GapMatching, answer: &str) -> f64 {
+    let prepare = |text: &str| {
+        let text = if matching.normalize {
+            normalize(text)
+        } else {
+            text.to_string()
+        };
+        if matching.case_sensitive {
+            text
+        } else {
+            text.to_lowercase()
+        }
+    };
+    let answer = prepare(answer);
+    let mut best = credit(prepare(solution) == answer);
+    for alternative in &matching.alternatives {
+        if alternative.credit <= best {
+            continue;
+        }
+        let matches = if alternative.regex {
+            answer_regex(&alternative.answer, matching.case_sensitive)
+                // Invalid expressions, saved before they were rejected, match nothing
+                .map_or(false, |regex| regex.is_match(&answer))
+        } else {
+            prepare(&alternative.answer) == answer
+        };
+        if matches {
+            best = alternative.credit.clamp(0.0, 1.0);
+        }
+    }
+    best
+}
+
+/// Compiles an alternative answer given as regular expression, which has to match the whole answer
+pub fn answer_regex(pattern: &str, case_sensitive: bool) -> Result<Regex, regex::Error> {
+    RegexBuilder::new(&format!("^(?:{})$", pattern))
+        .case_insensitive(!case_sensitive)
+        .size_limit(REGEX_SIZE_LIMIT)
+        .build()
+}
+
+/// Drops punctuation and collapses whitespace, so `z. B.` and ` z B ` are the same answer
+fn normalize(text: &str) -> String {
+    text.split(|c: char| c.is_whitespace() || is_punctuation(c))
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn is_punctuation(c: char) -> bool {
+    c.is_ascii_punctuation()
+        || matches!(
+            c,
+            '„' | '“' | '”' | '‚' | '‘' | '’' | '«' | '»' | '–' | '—' | '…'
+        )
+}
+
+fn credit(correct: bool) -> f64 {
+    if correct {
+        1.0
+    } else {
+        0.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::document::Alternative;
+    use super::*;
+
+    fn alternative(answer: &str, credit: f64, regex: bool) -> Alternative {
+        Alternative {
+            answer: answer.to_string(),
+            credit,
+            regex,
+        }
+    }
+
+    fn answers(ticks: &[(bool, bool)]) -> Vec<Node> {
+        ticks
+            .iter()
+            .map(|&(solution, answer)| {
+                Node::new(NodeKind::MultipleChoiceAnswer { solution, answer }, vec![])
+            })
+            .collect()
+    }
+
+    #[test]
+    fn applies_choice_scoring() {
+        let ticks = answers(&[(true, true), (true, false), (false, true), (false, false)]);
+        let multiple = ChoiceMode::Multiple;
+        assert_eq!(choice_credit(multiple, Scoring::AllOrNothing, &ticks), 0.0);
+        assert_eq!(choice_credit(multiple, Scoring::PartialCredit, &ticks), 0.5);
+        assert_eq!(
+            choice_credit(multiple, Scoring::NegativeMarking, &ticks),
+            0.0
+        );
+        let ticks = answers(&[(true, true), (true, true), (false, true), (false, false)]);
+        assert_eq!(
+            choice_credit(multiple, Scoring::NegativeMarking, &ticks),
+            0.5
+        );
+        assert_eq!(
+            choice_credit(ChoiceMode::Single, Scoring::PartialCredit, &ticks),
+            0.0
+        );
+        let ticks = answers(&[(false, false), (false, false)]);
+        assert_eq!(choice_credit(multiple, Scoring::PartialCredit, &ticks), 1.0);
+    }
+
+    #[test]
+    fn matches_gaps_exactly_by_default() {
+        let matching = GapMatching::default();
+        assert_eq!(gap_credit("Berlin", &matching, "Berlin"), 1.0);
+        assert_eq!(gap_credit("Berlin", &matching, "berlin"), 0.0);
+        assert_eq!(gap_credit("Berlin", &matching, "Berlin "), 0.0);
+    }
+
+    #[test]
+    fn applies_matching_rules() {
+        let matching = GapMatching {
+            alternatives: vec![
+                alternative("Bonn", 0.5, false),
+                alternative("(ost|west) ?berlin", 0.75, true),
+                alternative("[", 1.0, true),
+            ],
+            case_sensitive: false,
+            normalize: true,
+        };
+        assert_eq!(gap_credit("z. B. Berlin", &matching, "Z B  berlin!"), 1.0);
+        assert_eq!(gap_credit("Berlin", &matching, "bonn"), 0.5);
+        assert_eq!(gap_credit("Berlin", &matching, "Ost-Berlin"), 0.75);
+        assert_eq!(gap_credit("Berlin", &matching, "Westberlin"), 0.75);
+        assert_eq!(gap_credit("Berlin", &matching, "Berlin Mitte"), 0.0);
+        assert_eq!(gap_credit("Berlin", &matching, "["), 0.0);
+    }
+}
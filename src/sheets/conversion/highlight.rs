@@ -0,0 +1,533 @@
+//! Syntax highlighting of code blocks for the languages most used in class.
+//!
+//! Highlighting is purely lexical and uses the class names of highlight.js, so its themes apply.
+
+use super::html::escape;
+
+struct Language {
+    names: &'static [&'static str],
+    keywords: &'static [&'static str],
+    literals: &'static [&'static str],
+    line_comments: &'static [&'static str],
+    block_comment: Option<(&'static str, &'static str)>,
+    quotes: &'static [char],
+    triple_quotes: bool,
+    case_insensitive: bool,
+}
+
+static LANGUAGES: [Language; 9] = [
+    Language {
+        names: &["python", "py"],
+        keywords: &[
+            "and", "as", "assert", "async", "await", "break", "class", "continue", "def", "del",
+            "elif", "else", "except", "finally", "for", "from", "global", "if", "import", "in",
+            "is", "lambda", "nonlocal", "not", "or", "pass", "raise", "return", "try", "while",
+            "with", "yield",
+        ],
+        literals: &["True", "False", "None"],
+        line_comments: &["#"],
+        block_comment: None,
+        quotes: &['"', '\''],
+        triple_quotes: true,
+        case_insensitive: false,
+    },
+    Language {
+        names: &["java"],
+        keywords: &[
+            "abstract",
+            "boolean",
+            "break",
+            "byte",
+            "case",
+            "catch",
+            "char",
+            "class",
+            "continue",
+            "default",
+            "do",
+            "double",
+            "else",
+            "enum",
+            "extends",
+            "final",
+            "finally",
+            "float",
+            "for",
+            "if",
+            "implements",
+            "import",
+            "instanceof",
+            "int",
+            "interface",
+            "long",
+            "new",
+            "package",
+            "private",
+            "protected",
+            "public",
+            "return",
+            "short",
+            "static",
+            "super",
+            "switch",
+            "this",
+            "throw",
+            "throws",
+            "try",
+            "var",
+            "void",
+            "while",
+        ],
+        literals: &["true", "false", "null"],
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        quotes: &['"', '\''],
+        triple_quotes: false,
+        case_insensitive: false,
+    },
+    Language {
+        names: &["javascript", "js", "typescript", "ts"],
+        keywords: &[
+            "async",
+            "await",
+            "break",
+            "case",
+            "catch",
+            "class",
+            "const",
+            "continue",
+            "default",
+            "delete",
+            "do",
+            "else",
+            "export",
+            "extends",
+            "finally",
+            "for",
+            "from",
+            "function",
+            "if",
+            "import",
+            "in",
+            "instanceof",
+            "interface",
+            "let",
+            "new",
+            "of",
+            "return",
+            "switch",
+            "this",
+            "throw",
+            "try",
+            "type",
+            "typeof",
+            "var",
+            "void",
+            "while",
+            "yield",
+        ],
+        literals: &["true", "false", "null", "undefined", "NaN"],
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        quotes: &['"', '\'', '`'],
+        triple_quotes: false,
+        case_insensitive: false,
+    },
+    Language {
+        names: &["c", "cpp", "c++", "h", "hpp"],
+        keywords: &[
+            "auto",
+            "bool",
+            "break",
+            "case",
+            "char",
+            "class",
+            "const",
+            "continue",
+            "default",
+            "delete",
+            "do",
+            "double",
+            "else",
+            "enum",
+            "extern",
+            "float",
+            "for",
+            "if",
+            "include",
+            "int",
+            "long",
+            "namespace",
+            "new",
+            "private",
+            "protected",
+            "public",
+            "return",
+            "short",
+            "signed",
+            "sizeof",
+            "static",
+            "struct",
+            "switch",
+            "template",
+            "typedef",
+            "unsigned",
+            "using",
+            "void",
+            "while",
+        ],
+        literals: &["true", "false", "NULL", "nullptr"],
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        quotes: &['"', '\''],
+        triple_quotes: false,
+        case_insensitive: false,
+    },
+    Language {
+        names: &["csharp", "cs", "c#"],
+        keywords: &[
+            "abstract",
+            "bool",
+            "break",
+            "case",
+            "catch",
+            "class",
+            "const",
+            "continue",
+            "double",
+            "else",
+            "enum",
+            "for",
+            "foreach",
+            "if",
+            "in",
+            "int",
+            "interface",
+            "namespace",
+            "new",
+            "override",
+            "private",
+            "protected",
+            "public",
+            "return",
+            "static",
+            "string",
+            "struct",
+            "switch",
+            "this",
+            "throw",
+            "try",
+            "using",
+            "var",
+            "virtual",
+            "void",
+            "while",
+        ],
+        literals: &["true", "false", "null"],
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        quotes: &['"', '\''],
+        triple_quotes: false,
+        case_insensitive: false,
+    },
+    Language {
+        names: &["rust", "rs"],
+        keywords: &[
+            "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum",
+            "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub",
+            "ref", "return", "self", "Self", "static", "struct", "trait", "type", "unsafe", "use",
+            "where", "while",
+        ],
+        literals: &["true", "false", "None", "Some", "Ok", "Err"],
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        quotes: &['"'],
+        triple_quotes: false,
+        case_insensitive: false,
+    },
+    Language {
+        names: &["go", "golang"],
+        keywords: &[
+            "break",
+            "case",
+            "chan",
+            "const",
+            "continue",
+            "default",
+            "defer",
+            "else",
+            "for",
+            "func",
+            "go",
+            "if",
+            "import",
+            "interface",
+            "map",
+            "package",
+            "range",
+            "return",
+            "select",
+            "struct",
+            "switch",
+            "type",
+            "var",
+        ],
+        literals: &["true", "false", "nil", "iota"],
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        quotes: &['"', '\'', '`'],
+        triple_quotes: false,
+        case_insensitive: false,
+    },
+    Language {
+        names: &["sql"],
+        keywords: &[
+            "add",
+            "all",
+            "alter",
+            "and",
+            "as",
+            "asc",
+            "between",
+            "by",
+            "case",
+            "create",
+            "delete",
+            "desc",
+            "distinct",
+            "drop",
+            "else",
+            "end",
+            "exists",
+            "foreign",
+            "from",
+            "group",
+            "having",
+            "in",
+            "index",
+            "inner",
+            "insert",
+            "into",
+            "is",
+            "join",
+            "key",
+            "left",
+            "like",
+            "limit",
+            "not",
+            "on",
+            "or",
+            "order",
+            "outer",
+            "primary",
+            "references",
+            "right",
+            "select",
+            "set",
+            "table",
+            "then",
+            "union",
+            "update",
+            "values",
+            "when",
+            "where",
+        ],
+        literals: &["true", "false", "null"],
+        line_comments: &["--"],
+        block_comment: Some(("/*", "*/")),
+        quotes: &['\'', '"'],
+        triple_quotes: false,
+        case_insensitive: true,
+    },
+    Language {
+        names: &["bash", "sh", "shell", "zsh"],
+        keywords: &[
+            "case", "do", "done", "elif", "else", "esac", "export", "fi", "for", "function", "if",
+            "in", "local", "return", "then", "until", "while",
+        ],
+        literals: &["true", "false"],
+        line_comments: &["#"],
+        block_comment: None,
+        quotes: &['"', '\''],
+        triple_quotes: false,
+        case_insensitive: false,
+    },
+];
+
+fn language(name: &str) -> Option<&'static Language> {
+    let name = name.trim().to_lowercase();
+    LANGUAGES
+        .iter()
+        .find(|language| language.names.contains(&name.as_str()))
+}
+
+fn span(class: &str, text: &str) -> String {
+    format!("<span class=\"hljs-{}\">{}</span>", class, escape(text))
+}
+
+/// Returns the code as escaped HTML, with spans around the tokens of known languages
+pub fn highlight(code: &str, language_name: &str) -> String {
+    let language = match language(language_name) {
+        Some(language) => language,
+        None => return escape(code),
+    };
+
+    let mut html = String::new();
+    let mut rest = code;
+    while let Some(c) = rest.chars().next() {
+        let (class, length) = if let Some(length) = comment(language, rest) {
+            (Some("comment"), length)
+        } else if language.quotes.contains(&c) {
+            (Some("string"), string(language, rest, c))
+        } else if c.is_ascii_digit() {
+            (Some("number"), word(rest))
+        } else if c.is_alphabetic() || c == '_' {
+            let length = word(rest);
+            let text = &rest[..length];
+            let matches = |words: &[&str]| {
+                words.iter().any(|w| {
+                    if language.case_insensitive {
+                        w.eq_ignore_ascii_case(text)
+                    } else {
+                        *w == text
+                    }
+                })
+            };
+            if matches(language.keywords) {
+                (Some("keyword"), length)
+            } else if matches(language.literals) {
+                (Some("literal"), length)
+            } else {
+                (None, length)
+            }
+        } else {
+            (None, c.len_utf8())
+        };
+        let (token, remaining) = rest.split_at(length);
+        match class {
+            Some(class) => html.push_str(&span(class, token)),
+            None => html.push_str(&escape(token)),
+        }
+        rest = remaining;
+    }
+    html
+}
+
+fn comment(language: &Language, code: &str) -> Option<usize> {
+    if let Some((open, close)) = language.block_comment {
+        if let Some(rest) = code.strip_prefix(open) {
+            return Some(
+                rest.find(close)
+                    .map_or(code.len(), |end| open.len() + end + close.len()),
+            );
+        }
+    }
+    if language.line_comments.iter().any(|c| code.starts_with(c)) {
+        return Some(code.find('\n').unwrap_or(code.len()));
+    }
+    None
+}
+
+fn string(language: &Language, code: &str, quote: char) -> usize {
+    let triple: String = [quote; 3].iter().collect();
+    if language.triple_quotes && code.starts_with(&triple) {
+        return code[3..]
+            .find(&triple)
+            .map_or(code.len(), |end| 3 + end + 3);
+    }
+    let mut escaped = false;
+    for (i, c) in code.char_indices().skip(1) {
+        if escaped {
+            escaped = false;
+        } else if c == '\\' {
+            escaped = true;
+        } else if c == quote {
+            return i + c.len_utf8();
+        } else if c == '\n' && quote != '`' {
+            // Unterminated strings end with the line
+            return i;
+        }
+    }
+    code.len()
+}
+
+fn word(code: &str) -> usize {
+    let number = code.starts_with(|c: char| c.is_ascii_digit());
+    code.find(|c: char| !(c.is_alphanumeric() || c == '_' || number && c == '.'))
+        .unwrap_or(code.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn highlights_tokens() {
+        assert_eq!(
+            highlight("def f(x):\n    return x > 2.5  # groß\n", "Python"),
+            concat!(
+                "<span class=\"hljs-keyword\">def</span> f(x):\n    ",
+                "<span class=\"hljs-keyword\">return</span> x &gt; ",
+                "<span class=\"hljs-number\">2.5</span>  ",
+                "<span class=\"hljs-comment\"># groß</span>\n"
+            )
+        );
+        assert_eq!(
+            highlight("let s = \"a \\\" b\"; /* x */ true", "rs"),
+            concat!(
+                "<span class=\"hljs-keyword\">let</span> s = ",
+                "<span class=\"hljs-string\">&quot;a \\&quot; b&quot;</span>; ",
+                "<span class=\"hljs-comment\">/* x */</span> ",
+                "<span class=\"hljs-literal\">true</span>"
+            )
+        );
+    }
+
+    #[test]
+    fn matches_keywords_by_language() {
+        assert_eq!(
+            highlight("Select name FROM t -- alle", "sql"),
+            concat!(
+                "<span class=\"hljs-keyword\">Select</span> name ",
+                "<span class=\"hljs-keyword\">FROM</span> t ",
+                "<span class=\"hljs-comment\">-- alle</span>"
+            )
+        );
+        // Python keywords are case sensitive and identifiers are matched as a whole
+        assert_eq!(highlight("Def define", "py"), "Def define");
+        assert_eq!(
+            highlight("s = '''a\n'b'\n'''", "python"),
+            "s = <span class=\"hljs-string\">'''a\n'b'\n'''</span>"
+        );
+    }
+
+    #[test]
+    fn escapes_unknown_languages() {
+        assert_eq!(
+            highlight("<b>if</b> & 1", "brainfuck"),
+            "&lt;b&gt;if&lt;/b&gt; &amp; 1"
+        );
+        assert_eq!(highlight("if", ""), "if");
+    }
+
+    #[test]
+    fn unterminated_tokens_do_not_panic() {
+        assert_eq!(
+            highlight("x = \"offen\ny", "js"),
+            "x = <span class=\"hljs-string\">&quot;offen</span>\ny"
+        );
+        assert_eq!(
+            highlight("a /* offen", "java"),
+            "a <span class=\"hljs-comment\">/* offen</span>"
+        );
+        for language in ["python", "java", "js", "c", "cs", "rust", "go", "sql", "sh"] {
+            for code in [
+                "\"", "'", "`", "\"\"\"", "'''a", "/*", "/", "\\", "\"\\", "1.", "ä1ö_", "😀'",
+                "#", "--", "",
+            ] {
+                let html = highlight(code, language);
+                assert!(!html.contains('<') || html.contains("<span"), "{}", html);
+            }
+        }
+    }
+}
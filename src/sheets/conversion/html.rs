@@ -0,0 +1,1160 @@
+//! Conversion between sheet nodes and the HTML fragments other learning platforms use.
+//!
+//! Gaps and formulas are represented like the editor renders them, as `span` elements with a
+//! `data-type` attribute. Dropdowns are `select` elements whose correct options are marked with
+//! `data-correct="true"`. Multiple choice blocks keep their mode and scoring scheme in `data-mode`
+//! and `data-scoring`, blocks and dropdowns shuffled per student have `data-shuffle="true"`.
+//! Pools are `div` elements with `data-draw`, holding one `div` per item. Variables are listed in a
+//! `pre` element with one `name = definition` per line, QTI item bodies leave them out. Essays are
+//! empty `div` elements keeping their points, word limits and model answer in `data-` attributes.
+
+use super::document::{
+    dropdown_text, ChoiceMode, DropdownOption, GapMatching, Mark, Node, NodeKind, Scoring, Variable,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Content {
+    Element(Element),
+    Text(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Element {
+    pub name: String,
+    pub attributes: Vec<(String, String)>,
+    pub children: Vec<Content>,
+}
+
+impl Element {
+    pub fn new<S: Into<String>>(name: S) -> Self {
+        Self {
+            name: name.into(),
+            attributes: Vec::new(),
+            children: Vec::new(),
+        }
+    }
+
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn text_content(&self) -> String {
+        let mut text = String::new();
+        collect_text(&self.children, &mut text);
+        text
+    }
+}
+
+fn collect_text(content: &[Content], text: &mut String) {
+    for c in content {
+        match c {
+            Content::Text(t) => text.push_str(t),
+            Content::Element(e) => collect_text(&e.children, text),
+        }
+    }
+}
+
+// Writing
+
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Writes gaps, dropdowns, numeric and formula tasks as their solution, for content without
+/// interactions
+pub fn solution_text(mark: &Mark) -> String {
+    match mark {
+        Mark::Gap { solution, .. }
+        | Mark::Numeric { solution, .. }
+        | Mark::Formula { solution, .. } => escape(solution),
+        Mark::Dropdown { options, .. } => escape(&correct_options(options).join(" / ")),
+        _ => String::new(),
+    }
+}
+
+pub fn correct_options(options: &[DropdownOption]) -> Vec<&str> {
+    options
+        .iter()
+        .filter(|option| option.correct)
+        .map(|option| option.text.as_str())
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Dialect {
+    Html,
+    /// The XHTML subset allowed in QTI item bodies
+    Qti,
+}
+
+struct Writer<'a> {
+    dialect: Dialect,
+    gap: &'a mut dyn FnMut(&Mark) -> String,
+    html: String,
+}
+
+/// Writes the nodes as HTML, gaps, dropdowns, numeric and formula tasks are written by `gap`
+/// which receives their mark
+pub fn write_blocks(
+    nodes: &[Node],
+    dialect: Dialect,
+    gap: &mut dyn FnMut(&Mark) -> String,
+) -> String {
+    let mut writer = Writer {
+        dialect,
+        gap,
+        html: String::new(),
+    };
+    for node in nodes {
+        writer.block(node);
+    }
+    writer.html
+}
+
+impl Writer<'_> {
+    fn wrap(&mut self, tag: &str, attributes: &str, node: &Node, inline: bool) {
+        self.html.push_str(&format!("<{}{}>", tag, attributes));
+        if inline {
+            self.inline(&node.content);
+        } else {
+            for child in &node.content {
+                self.block(child);
+            }
+        }
+        self.html.push_str(&format!("</{}>", tag));
+    }
+
+    fn block(&mut self, node: &Node) {
+        let qti = self.dialect == Dialect::Qti;
+        match &node.kind {
+            NodeKind::Doc | NodeKind::Other(_) => self.wrap("div", "", node, false),
+            NodeKind::Paragraph => self.wrap("p", "", node, true),
+            NodeKind::Heading { level } => self.wrap(&format!("h{}", level), "", node, true),
+            NodeKind::Text(_) | NodeKind::HardBreak => self.inline(std::slice::from_ref(node)),
+            NodeKind::CodeBlock { language } => self.html.push_str(&format!(
+                "<pre><code class=\"language-{}\">{}</code></pre>",
+                escape(language),
+                escape(&node.text_content())
+            )),
+            NodeKind::Audio { source, mimetype } if qti => self.html.push_str(&format!(
+                "<object type=\"{}\" data=\"{}\"></object>",
+                escape(mimetype),
+                escape(source)
+            )),
+            NodeKind::Audio { source, mimetype } => self.html.push_str(&format!(
+                "<audio controls=\"controls\"><source src=\"{}\" type=\"{}\"/></audio>",
+                escape(source),
+                escape(mimetype)
+            )),
+            NodeKind::MultipleChoice { .. } if qti => self.wrap("ul", "", node, false),
+            NodeKind::MultipleChoice {
+                mode,
+                scoring,
+                shuffle,
+            } => self.wrap(
+                "ul",
+                &format!(
+                    " data-type=\"multipleChoice\" data-mode=\"{}\" data-scoring=\"{}\" data-shuffle=\"{}\"",
+                    mode.as_str(),
+                    scoring.as_str(),
+                    shuffle
+                ),
+                node,
+                false,
+            ),
+            NodeKind::MultipleChoiceAnswer { .. } if qti => self.wrap("li", "", node, false),
+            NodeKind::MultipleChoiceAnswer { solution, .. } => self.wrap(
+                "li",
+                &format!(" data-solution=\"{}\"", solution),
+                node,
+                false,
+            ),
+            NodeKind::BulletList => self.wrap("ul", "", node, false),
+            NodeKind::OrderedList => self.wrap("ol", "", node, false),
+            NodeKind::ListItem => self.wrap("li", "", node, false),
+            NodeKind::Blockquote => self.wrap("blockquote", "", node, false),
+            NodeKind::HorizontalRule => self.html.push_str("<hr/>"),
+            NodeKind::Pool { .. } | NodeKind::PoolItem if qti => self.wrap("div", "", node, false),
+            NodeKind::Pool { draw, .. } => self.wrap(
+                "div",
+                &format!(" data-type=\"pool\" data-draw=\"{}\"", draw),
+                node,
+                false,
+            ),
+            NodeKind::PoolItem => self.wrap("div", " data-type=\"poolItem\"", node, false),
+            NodeKind::Variables(_) if qti => {}
+            NodeKind::Variables(variables) => self.html.push_str(&format!(
+                "<pre data-type=\"variables\">{}</pre>",
+                escape(
+                    &variables
+                        .iter()
+                        .map(|v| format!("{} = {}", v.name, v.definition))
+                        .collect::<Vec<_>>()
+                        .join("\n")
+                )
+            )),
+            // QTI has interactions for essays, which end up in items of their own
+            NodeKind::Essay { .. } if qti => {}
+            NodeKind::Essay {
+                min_words,
+                max_words,
+                points,
+                model_answer,
+                ..
+            } => {
+                self.html
+                    .push_str(&format!("<div data-type=\"essay\" data-points=\"{}\"", points));
+                if let Some(min_words) = min_words {
+                    self.html
+                        .push_str(&format!(" data-min-words=\"{}\"", min_words));
+                }
+                if let Some(max_words) = max_words {
+                    self.html
+                        .push_str(&format!(" data-max-words=\"{}\"", max_words));
+                }
+                self.html.push_str(&format!(
+                    " data-model-answer=\"{}\"></div>",
+                    escape(model_answer)
+                ));
+            }
+        }
+    }
+
+    fn inline(&mut self, nodes: &[Node]) {
+        // QTI item bodies have no element for struck through text
+        let strike = match self.dialect {
+            Dialect::Html => ("<s>", "</s>"),
+            Dialect::Qti => ("<span class=\"strike\">", "</span>"),
+        };
+        for node in nodes {
+            match &node.kind {
+                NodeKind::Text(text) => {
+                    let mut closing = Vec::new();
+                    for (mark, (open, close)) in [
+                        (Mark::Bold, ("<strong>", "</strong>")),
+                        (Mark::Italic, ("<em>", "</em>")),
+                        (Mark::Strike, strike),
+                        (Mark::Code, ("<code>", "</code>")),
+                    ] {
+                        if node.marks.contains(&mark) {
+                            self.html.push_str(open);
+                            closing.push(close);
+                        }
+                    }
+                    let gap = &mut self.gap;
+                    let special = node.marks.iter().find_map(|mark| match mark {
+                        Mark::Gap { .. }
+                        | Mark::Dropdown { .. }
+                        | Mark::Numeric { .. }
+                        | Mark::Formula { .. } => Some(gap(mark)),
+                        Mark::Latex { source } => Some(format!("\\({}\\)", escape(source))),
+                        _ => None,
+                    });
+                    self.html.push_str(&special.unwrap_or_else(|| escape(text)));
+                    for close in closing.iter().rev() {
+                        self.html.push_str(close);
+                    }
+                }
+                NodeKind::HardBreak => self.html.push_str("<br/>"),
+                _ => self.inline(&node.content),
+            }
+        }
+    }
+}
+
+// Parsing
+
+const VOID_ELEMENTS: [&str; 14] = [
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "param", "source",
+    "track", "wbr",
+];
+
+const BLOCK_ELEMENTS: [&str; 19] = [
+    "address",
+    "article",
+    "aside",
+    "blockquote",
+    "div",
+    "figure",
+    "footer",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "header",
+    "ol",
+    "p",
+    "pre",
+    "table",
+    "ul",
+];
+
+/// Parses an HTML fragment leniently, unclosed elements are closed implicitly
+pub fn parse(html: &str) -> Vec<Content> {
+    let mut stack: Vec<Element> = vec![Element::new("#root")];
+    let mut rest = html;
+    while !rest.is_empty() {
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            rest = comment.find("-->").map_or("", |end| &comment[end + 3..]);
+        } else if let Some(cdata) = rest.strip_prefix("<![CDATA[") {
+            let end = cdata.find("]]>").unwrap_or(cdata.len());
+            push_text(&mut stack, cdata[..end].to_string());
+            rest = cdata.get(end + 3..).unwrap_or("");
+        } else if rest.starts_with("<!") || rest.starts_with("<?") {
+            rest = rest.find('>').map_or("", |end| &rest[end + 1..]);
+        } else if let Some(closing) = rest.strip_prefix("</") {
+            let end = closing.find('>').unwrap_or(closing.len());
+            let name = closing[..end].trim().to_ascii_lowercase();
+            close(&mut stack, &name);
+            rest = closing.get(end + 1..).unwrap_or("");
+        } else if rest.starts_with('<') && rest[1..].starts_with(|c: char| c.is_ascii_alphabetic())
+        {
+            let (element, self_closing, remaining) = parse_tag(&rest[1..]);
+            rest = remaining;
+            let name = element.name.clone();
+            if name == "script" || name == "style" {
+                let end = rest
+                    .to_ascii_lowercase()
+                    .find(&format!("</{}", name))
+                    .unwrap_or(rest.len());
+                rest = &rest[end..];
+                continue;
+            }
+            open(&mut stack, element);
+            if self_closing || VOID_ELEMENTS.contains(&name.as_str()) {
+                close(&mut stack, &name);
+            }
+        } else {
+            // Text starts with a literal `<` if it is not followed by a tag
+            let first = rest.chars().next().map_or(1, char::len_utf8);
+            let end = rest[first..]
+                .find('<')
+                .map_or(rest.len(), |end| end + first);
+            push_text(&mut stack, decode_entities(&rest[..end]));
+            rest = &rest[end..];
+        }
+    }
+    while stack.len() > 1 {
+        let element = stack.pop().expect("stack is not empty");
+        push_content(&mut stack, Content::Element(element));
+    }
+    stack.pop().expect("root is always on the stack").children
+}
+
+fn parse_tag(tag: &str) -> (Element, bool, &str) {
+    let name_end = tag
+        .find(|c: char| c.is_whitespace() || c == '>' || c == '/')
+        .unwrap_or(tag.len());
+    let mut element = Element::new(tag[..name_end].to_ascii_lowercase());
+    let mut rest = &tag[name_end..];
+    loop {
+        rest = rest.trim_start();
+        if let Some(remaining) = rest.strip_prefix("/>") {
+            return (element, true, remaining);
+        }
+        if let Some(remaining) = rest.strip_prefix('>') {
+            return (element, false, remaining);
+        }
+        if rest.is_empty() {
+            return (element, false, rest);
+        }
+        if let Some(remaining) = rest.strip_prefix('/') {
+            rest = remaining;
+            continue;
+        }
+        let name_end = rest
+            .find(|c: char| c.is_whitespace() || c == '=' || c == '>' || c == '/')
+            .unwrap_or(rest.len())
+            .max(1);
+        let name = rest[..name_end].to_ascii_lowercase();
+        rest = rest[name_end..].trim_start();
+        let value = if let Some(value) = rest.strip_prefix('=') {
+            let value = value.trim_start();
+            let (raw, remaining) = match value.chars().next() {
+                Some(quote @ ('"' | '\'')) => {
+                    let end = value[1..].find(quote).map_or(value.len(), |end| end + 1);
+                    (&value[1..end], value.get(end + 1..).unwrap_or(""))
+                }
+                _ => {
+                    let end = value
+                        .find(|c: char| c.is_whitespace() || c == '>')
+                        .unwrap_or(value.len());
+                    (&value[..end], &value[end..])
+                }
+            };
+            rest = remaining;
+            decode_entities(raw)
+        } else {
+            String::new()
+        };
+        element.attributes.push((name, value));
+    }
+}
+
+fn open(stack: &mut Vec<Element>, element: Element) {
+    let name = element.name.as_str();
+    // Paragraphs and list items end implicitly
+    if BLOCK_ELEMENTS.contains(&name) && stack.iter().any(|e| e.name == "p") {
+        close(stack, "p");
+    }
+    if name == "li" {
+        let list = stack
+            .iter()
+            .rposition(|e| e.name == "ul" || e.name == "ol")
+            .unwrap_or(0);
+        if stack[list..].iter().any(|e| e.name == "li") {
+            close(stack, "li");
+        }
+    }
+    stack.push(element);
+}
+
+fn close(stack: &mut Vec<Element>, name: &str) {
+    if let Some(position) = stack.iter().rposition(|e| e.name == name) {
+        if position == 0 {
+            return;
+        }
+        while stack.len() > position {
+            let element = stack.pop().expect("stack is not empty");
+            push_content(stack, Content::Element(element));
+        }
+    }
+}
+
+fn push_content(stack: &mut [Element], content: Content) {
+    stack
+        .last_mut()
+        .expect("root is always on the stack")
+        .children
+        .push(content);
+}
+
+fn push_text(stack: &mut [Element], text: String) {
+    let parent = stack.last_mut().expect("root is always on the stack");
+    if let Some(Content::Text(previous)) = parent.children.last_mut() {
+        previous.push_str(&text);
+    } else {
+        parent.children.push(Content::Text(text));
+    }
+}
+
+pub fn decode_entities(text: &str) -> String {
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let end = match rest.find(';') {
+            Some(end) if end <= 10 => end,
+            _ => {
+                decoded.push('&');
+                rest = &rest[1..];
+                continue;
+            }
+        };
+        let entity = &rest[1..end];
+        let c = if let Some(hex) = entity
+            .strip_prefix("#x")
+            .or_else(|| entity.strip_prefix("#X"))
+        {
+            u32::from_str_radix(hex, 16).ok().and_then(char::from_u32)
+        } else if let Some(decimal) = entity.strip_prefix('#') {
+            decimal.parse().ok().and_then(char::from_u32)
+        } else {
+            named_entity(entity)
+        };
+        match c {
+            Some(c) => {
+                decoded.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+fn named_entity(name: &str) -> Option<char> {
+    Some(match name {
+        "amp" => '&',
+        "lt" => '<',
+        "gt" => '>',
+        "quot" => '"',
+        "apos" => '\'',
+        "nbsp" => ' ',
+        "auml" => 'ä',
+        "ouml" => 'ö',
+        "uuml" => 'ü',
+        "Auml" => 'Ä',
+        "Ouml" => 'Ö',
+        "Uuml" => 'Ü',
+        "szlig" => 'ß',
+        "euro" => '€',
+        "ndash" => '–',
+        "mdash" => '—',
+        "hellip" => '…',
+        "laquo" => '«',
+        "raquo" => '»',
+        "bdquo" => '„',
+        "ldquo" => '“',
+        "rdquo" => '”',
+        "lsquo" => '‘',
+        "rsquo" => '’',
+        "times" => '×',
+        "divide" => '÷',
+        "middot" => '·',
+        "deg" => '°',
+        "copy" => '©',
+        _ => return None,
+    })
+}
+
+// Conversion to nodes
+
+/// Converts parsed HTML to sheet nodes, content without a counterpart is skipped and reported.
+#[derive(Default)]
+pub struct Reader {
+    pub skipped: Vec<String>,
+}
+
+impl Reader {
+    fn skip(&mut self, what: &str) {
+        if !self.skipped.iter().any(|s| s == what) {
+            self.skipped.push(what.to_string());
+        }
+    }
+
+    pub fn blocks(&mut self, content: &[Content]) -> Vec<Node> {
+        let mut blocks = Vec::new();
+        let mut inline: Vec<&Content> = Vec::new();
+        for c in content {
+            match c {
+                Content::Element(e) if self.is_block(e) => {
+                    self.flush_paragraph(&mut inline, &mut blocks);
+                    self.block(e, &mut blocks);
+                }
+                _ => inline.push(c),
+            }
+        }
+        self.flush_paragraph(&mut inline, &mut blocks);
+        blocks
+    }
+
+    fn is_block(&self, element: &Element) -> bool {
+        BLOCK_ELEMENTS.contains(&element.name.as_str())
+            || matches!(
+                element.name.as_str(),
+                "hr" | "audio" | "li" | "section" | "main" | "body" | "html" | "tr"
+            )
+            || (element.name == "object" && is_audio(element))
+    }
+
+    fn flush_paragraph(&mut self, inline: &mut Vec<&Content>, blocks: &mut Vec<Node>) {
+        let content: Vec<Content> = inline.drain(..).cloned().collect();
+        let nodes = self.inline(&content);
+        if !nodes.is_empty() {
+            blocks.push(Node::new(NodeKind::Paragraph, nodes));
+        }
+    }
+
+    fn block(&mut self, element: &Element, blocks: &mut Vec<Node>) {
+        match element.name.as_str() {
+            "p" => blocks.push(Node::new(
+                NodeKind::Paragraph,
+                self.inline(&element.children),
+            )),
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                let level = element.name[1..].parse().unwrap_or(1);
+                blocks.push(Node::new(
+                    NodeKind::Heading { level },
+                    self.inline(&element.children),
+                ));
+            }
+            "pre" if element.attribute("data-type") == Some("variables") => {
+                let variables = element
+                    .text_content()
+                    .lines()
+                    .filter_map(|line| line.split_once('='))
+                    .map(|(name, definition)| Variable {
+                        name: name.trim().to_string(),
+                        definition: definition.trim().to_string(),
+                        value: None,
+                    })
+                    .collect();
+                blocks.push(Node::new(NodeKind::Variables(variables), Vec::new()));
+            }
+            "pre" => {
+                let language = element
+                    .children
+                    .iter()
+                    .find_map(|c| match c {
+                        Content::Element(code) if code.name == "code" => code.attribute("class"),
+                        _ => None,
+                    })
+                    .and_then(|class| {
+                        class
+                            .split_whitespace()
+                            .find_map(|c| c.strip_prefix("language-"))
+                    })
+                    .unwrap_or("plain");
+                let code = element.text_content();
+                let code = code.strip_suffix('\n').unwrap_or(&code);
+                let content = if code.is_empty() {
+                    Vec::new()
+                } else {
+                    vec![Node::text(code, Vec::new())]
+                };
+                blocks.push(Node::new(
+                    NodeKind::CodeBlock {
+                        language: language.to_string(),
+                    },
+                    content,
+                ));
+            }
+            "ul" if element.attribute("data-type") == Some("multipleChoice") => {
+                let answers = self.list_items(element, |e| NodeKind::MultipleChoiceAnswer {
+                    solution: e.attribute("data-solution") == Some("true"),
+                    answer: false,
+                });
+                let kind = NodeKind::MultipleChoice {
+                    mode: ChoiceMode::parse(element.attribute("data-mode")),
+                    scoring: Scoring::parse(element.attribute("data-scoring")),
+                    shuffle: element.attribute("data-shuffle") == Some("true"),
+                };
+                blocks.push(Node::new(kind, answers));
+            }
+            "div" if element.attribute("data-type") == Some("pool") => {
+                let items: Vec<Node> = element
+                    .children
+                    .iter()
+                    .filter_map(|c| match c {
+                        Content::Element(item)
+                            if item.attribute("data-type") == Some("poolItem") =>
+                        {
+                            Some(Node::new(NodeKind::PoolItem, self.blocks(&item.children)))
+                        }
+                        _ => None,
+                    })
+                    .filter(|item| !item.content.is_empty())
+                    .collect();
+                if !items.is_empty() {
+                    let draw = element
+                        .attribute("data-draw")
+                        .and_then(|draw| draw.parse().ok())
+                        .unwrap_or(1)
+                        .clamp(1, items.len());
+                    blocks.push(Node::new(NodeKind::Pool { draw, drawn: None }, items));
+                }
+            }
+            "div" if element.attribute("data-type") == Some("essay") => {
+                let number = |name| element.attribute(name).and_then(|n| n.parse().ok());
+                blocks.push(Node::new(
+                    NodeKind::Essay {
+                        min_words: number("data-min-words"),
+                        max_words: number("data-max-words"),
+                        points: number("data-points").unwrap_or(1).max(1),
+                        model_answer: element
+                            .attribute("data-model-answer")
+                            .unwrap_or_default()
+                            .to_string(),
+                        answer: String::new(),
+                        awarded: None,
+                    },
+                    Vec::new(),
+                ));
+            }
+            "ul" | "ol" => {
+                let kind = if element.name == "ul" {
+                    NodeKind::BulletList
+                } else {
+                    NodeKind::OrderedList
+                };
+                let items = self.list_items(element, |_| NodeKind::ListItem);
+                if !items.is_empty() {
+                    blocks.push(Node::new(kind, items));
+                }
+            }
+            "li" => blocks.extend(self.blocks(&element.children)),
+            "blockquote" => {
+                let content = self.blocks(&element.children);
+                if !content.is_empty() {
+                    blocks.push(Node::new(NodeKind::Blockquote, content));
+                }
+            }
+            "hr" => blocks.push(Node::new(NodeKind::HorizontalRule, Vec::new())),
+            "object" => match element.attribute("data") {
+                Some(source) => blocks.push(Node::new(
+                    NodeKind::Audio {
+                        source: source.to_string(),
+                        mimetype: element.attribute("type").unwrap_or_default().to_string(),
+                    },
+                    Vec::new(),
+                )),
+                None => self.skip("Audiodateien ohne Quelle"),
+            },
+            "audio" => {
+                let source = element.attribute("src").map(str::to_string).or_else(|| {
+                    element.children.iter().find_map(|c| match c {
+                        Content::Element(s) if s.name == "source" => {
+                            s.attribute("src").map(str::to_string)
+                        }
+                        _ => None,
+                    })
+                });
+                let mimetype = element.children.iter().find_map(|c| match c {
+                    Content::Element(s) if s.name == "source" => {
+                        s.attribute("type").map(str::to_string)
+                    }
+                    _ => None,
+                });
+                match source {
+                    Some(source) => blocks.push(Node::new(
+                        NodeKind::Audio {
+                            source,
+                            mimetype: mimetype.unwrap_or_default(),
+                        },
+                        Vec::new(),
+                    )),
+                    None => self.skip("Audiodateien ohne Quelle"),
+                }
+            }
+            "table" => {
+                self.table_rows(&element.children, blocks);
+            }
+            "tr" => self.table_rows(
+                std::slice::from_ref(&Content::Element(element.clone())),
+                blocks,
+            ),
+            _ => blocks.extend(self.blocks(&element.children)),
+        }
+    }
+
+    fn table_rows(&mut self, content: &[Content], blocks: &mut Vec<Node>) {
+        for c in content {
+            if let Content::Element(e) = c {
+                if e.name == "tr" {
+                    let mut row = Vec::new();
+                    for cell in &e.children {
+                        if let Content::Element(cell) = cell {
+                            if !row.is_empty() {
+                                row.push(Node::text(" | ", Vec::new()));
+                            }
+                            row.extend(self.inline(&cell.children));
+                        }
+                    }
+                    normalize_whitespace(&mut row);
+                    blocks.push(Node::new(NodeKind::Paragraph, row));
+                } else {
+                    self.table_rows(&e.children, blocks);
+                }
+            }
+        }
+    }
+
+    fn list_items(&mut self, list: &Element, kind: impl Fn(&Element) -> NodeKind) -> Vec<Node> {
+        let mut items = Vec::new();
+        for c in &list.children {
+            if let Content::Element(item) = c {
+                if item.name == "li" {
+                    let mut content = self.blocks(&item.children);
+                    if content.is_empty() {
+                        content.push(Node::new(NodeKind::Paragraph, Vec::new()));
+                    }
+                    items.push(Node::new(kind(item), content));
+                }
+            }
+        }
+        items
+    }
+
+    pub fn inline(&mut self, content: &[Content]) -> Vec<Node> {
+        let mut nodes = Vec::new();
+        self.inline_into(content, &mut Vec::new(), &mut nodes);
+        normalize_whitespace(&mut nodes);
+        nodes
+    }
+
+    fn inline_into(&mut self, content: &[Content], marks: &mut Vec<Mark>, nodes: &mut Vec<Node>) {
+        for c in content {
+            match c {
+                Content::Text(text) => push_formulas(text, marks, nodes),
+                Content::Element(e) => {
+                    let mark = match e.name.as_str() {
+                        "br" => {
+                            nodes.push(Node::new(NodeKind::HardBreak, Vec::new()));
+                            continue;
+                        }
+                        "img" => {
+                            self.skip("Bilder");
+                            continue;
+                        }
+                        "object" => {
+                            self.skip("Eingebettete Objekte");
+                            continue;
+                        }
+                        "math" => {
+                            match tex_annotation(e) {
+                                Some(source) => {
+                                    let mut latex_marks = marks.clone();
+                                    latex_marks.push(Mark::Latex {
+                                        source: source.clone(),
+                                    });
+                                    nodes.push(Node::text(source, latex_marks));
+                                }
+                                None => {
+                                    self.skip("MathML-Formeln ohne TeX-Quelltext");
+                                    push_formulas(&e.text_content(), marks, nodes);
+                                }
+                            }
+                            continue;
+                        }
+                        "span" if e.attribute("data-type") == Some("gap") => {
+                            let solution = e.attribute("data-solution").unwrap_or_default();
+                            let mut gap_marks = marks.clone();
+                            gap_marks.push(Mark::Gap {
+                                solution: solution.to_string(),
+                                answer: String::new(),
+                                matching: GapMatching::default(),
+                            });
+                            nodes.push(Node::text(solution, gap_marks));
+                            continue;
+                        }
+                        "select" => {
+                            let options: Vec<DropdownOption> = e
+                                .children
+                                .iter()
+                                .filter_map(|c| match c {
+                                    Content::Element(option) if option.name == "option" => {
+                                        Some(DropdownOption {
+                                            text: option.text_content().trim().to_string(),
+                                            correct: option.attribute("data-correct")
+                                                == Some("true"),
+                                        })
+                                    }
+                                    _ => None,
+                                })
+                                .filter(|option| !option.text.is_empty())
+                                .collect();
+                            let text = dropdown_text(&options);
+                            let mut dropdown_marks = marks.clone();
+                            dropdown_marks.push(Mark::Dropdown {
+                                options,
+                                answer: String::new(),
+                                shuffle: e.attribute("data-shuffle") == Some("true"),
+                            });
+                            nodes.push(Node::text(text, dropdown_marks));
+                            continue;
+                        }
+                        "span" if e.attribute("data-type") == Some("numeric") => {
+                            let solution = e.attribute("data-solution").unwrap_or_default();
+                            let mut numeric_marks = marks.clone();
+                            numeric_marks.push(Mark::Numeric {
+                                solution: solution.to_string(),
+                                answer: String::new(),
+                            });
+                            nodes.push(Node::text(solution, numeric_marks));
+                            continue;
+                        }
+                        "span" if e.attribute("data-type") == Some("latex") => {
+                            let source = e.text_content();
+                            let mut latex_marks = marks.clone();
+                            latex_marks.push(Mark::Latex {
+                                source: source.clone(),
+                            });
+                            nodes.push(Node::text(source, latex_marks));
+                            continue;
+                        }
+                        "strong" | "b" => Some(Mark::Bold),
+                        "em" | "i" => Some(Mark::Italic),
+                        "code" | "tt" | "kbd" => Some(Mark::Code),
+                        "s" | "strike" | "del" => Some(Mark::Strike),
+                        "span" if e.attribute("class") == Some("strike") => Some(Mark::Strike),
+                        _ => None,
+                    };
+                    let added = match mark {
+                        Some(mark) if !marks.contains(&mark) => {
+                            marks.push(mark);
+                            true
+                        }
+                        _ => false,
+                    };
+                    self.inline_into(&e.children, marks, nodes);
+                    if added {
+                        marks.pop();
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn is_audio(element: &Element) -> bool {
+    element
+        .attribute("type")
+        .map_or(false, |mimetype| mimetype.starts_with("audio/"))
+}
+
+/// The TeX source MathML formulas converted from TeX usually carry
+fn tex_annotation(math: &Element) -> Option<String> {
+    math.children.iter().find_map(|c| match c {
+        Content::Element(e) if e.name == "annotation" => match e.attribute("encoding") {
+            Some("application/x-tex") | Some("TeX") => Some(e.text_content().trim().to_string()),
+            _ => None,
+        },
+        Content::Element(e) => tex_annotation(e),
+        Content::Text(_) => None,
+    })
+}
+
+/// Splits `\(...\)` and `$$...$$` formulas off the text, as rendered by MathJax
+fn push_formulas(text: &str, marks: &[Mark], nodes: &mut Vec<Node>) {
+    let mut rest = text;
+    loop {
+        let next = [("\\(", "\\)"), ("$$", "$$"), ("\\[", "\\]")]
+            .iter()
+            .filter_map(|(open, close)| {
+                let start = rest.find(open)?;
+                let end = rest[start + open.len()..].find(close)? + start + open.len();
+                Some((start, end, open.len(), close.len()))
+            })
+            .min_by_key(|(start, ..)| *start);
+        match next {
+            Some((start, end, open, close)) => {
+                if start > 0 {
+                    nodes.push(Node::text(&rest[..start], marks.to_vec()));
+                }
+                let source = rest[start + open..end].trim().to_string();
+                let mut latex_marks = marks.to_vec();
+                latex_marks.push(Mark::Latex {
+                    source: source.clone(),
+                });
+                nodes.push(Node::text(source, latex_marks));
+                rest = &rest[end + close..];
+            }
+            None => {
+                if !rest.is_empty() {
+                    nodes.push(Node::text(rest, marks.to_vec()));
+                }
+                return;
+            }
+        }
+    }
+}
+
+/// Collapses whitespace like a browser would and merges text nodes with equal marks
+fn normalize_whitespace(nodes: &mut Vec<Node>) {
+    let mut normalized: Vec<Node> = Vec::with_capacity(nodes.len());
+    let mut previous_space = true;
+    for mut node in nodes.drain(..) {
+        if let NodeKind::Text(text) = &node.kind {
+            let atomic = node.marks.iter().any(|m| {
+                matches!(
+                    m,
+                    Mark::Gap { .. }
+                        | Mark::Dropdown { .. }
+                        | Mark::Numeric { .. }
+                        | Mark::Formula { .. }
+                        | Mark::Latex { .. }
+                )
+            });
+            if !atomic {
+                let mut collapsed = String::with_capacity(text.len());
+                for c in text.chars() {
+                    if c.is_whitespace() {
+                        if !previous_space {
+                            collapsed.push(' ');
+                        }
+                        previous_space = true;
+                    } else {
+                        collapsed.push(c);
+                        previous_space = false;
+                    }
+                }
+                if collapsed.is_empty() {
+                    continue;
+                }
+                if let Some(Node {
+                    kind: NodeKind::Text(previous),
+                    marks,
+                    ..
+                }) = normalized.last_mut()
+                {
+                    if *marks == node.marks {
+                        previous.push_str(&collapsed);
+                        continue;
+                    }
+                }
+                node.kind = NodeKind::Text(collapsed);
+            } else {
+                previous_space = false;
+            }
+        } else if node.kind == NodeKind::HardBreak {
+            previous_space = true;
+            if let Some(Node {
+                kind: NodeKind::Text(previous),
+                ..
+            }) = normalized.last_mut()
+            {
+                if previous.ends_with(' ') {
+                    previous.pop();
+                }
+            }
+        }
+        normalized.push(node);
+    }
+    // Trailing whitespace is not rendered either
+    if let Some(Node {
+        kind: NodeKind::Text(last),
+        ..
+    }) = normalized.last_mut()
+    {
+        if last.ends_with(' ') {
+            last.pop();
+        }
+    }
+    normalized.retain(|node| node.kind != NodeKind::Text(String::new()));
+    *nodes = normalized;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::markdown::from_markdown;
+    use super::*;
+
+    /// Writes tasks like the editor renders them
+    fn editor_gap(mark: &Mark) -> String {
+        match mark {
+            Mark::Gap { solution, .. } => format!(
+                "<span data-type=\"gap\" data-solution=\"{}\"></span>",
+                escape(solution)
+            ),
+            Mark::Numeric { solution, .. } => format!(
+                "<span data-type=\"numeric\" data-solution=\"{}\"></span>",
+                escape(solution)
+            ),
+            Mark::Dropdown {
+                options, shuffle, ..
+            } => {
+                let mut select = format!("<select data-shuffle=\"{}\">", shuffle);
+                for option in options {
+                    select.push_str(&format!(
+                        "<option data-correct=\"{}\">{}</option>",
+                        option.correct,
+                        escape(&option.text)
+                    ));
+                }
+                select.push_str("</select>");
+                select
+            }
+            _ => String::new(),
+        }
+    }
+
+    fn read(html: &str) -> (Node, Vec<String>) {
+        let mut reader = Reader::default();
+        let blocks = reader.blocks(&parse(html));
+        (Node::new(NodeKind::Doc, blocks), reader.skipped)
+    }
+
+    fn round_trip(markdown: &str) {
+        let content = from_markdown(markdown).content;
+        let html = write_blocks(&content.content, Dialect::Html, &mut editor_gap);
+        let (imported, skipped) = read(&html);
+        assert_eq!(imported, content, "{}", html);
+        assert!(skipped.is_empty(), "{:?}", skipped);
+    }
+
+    #[test]
+    fn round_trip_formatting() {
+        round_trip("# Aufgabe\n\nText mit **fett**, *kursiv*, ~~durch~~ und `code` sowie $x^2$ & <mehr>.\\\nNeue Zeile\n\n> Zitat\n\n---\n\n```rust\nfn main() {}\n```\n\n- Eins\n- Zwei\n\n1. Erstens\n\n<audio src=\"https://example.org/a.ogg\" type=\"audio/ogg\"></audio>\n");
+    }
+
+    #[test]
+    fn round_trip_tasks() {
+        round_trip("Paris ist [[Hauptstadt]], Berlin liegt an der [[?Elbe|*Spree]], Bonn am [[??*Rhein|Main]] und es sind [[=3,5 ± 0,1]] Grad.\n\n- ( ) Bonn\n- (x) Berlin\n\n<!-- scoring: partialCredit, shuffle -->\n- [x] 2\n- [ ] 3\n- [x] 4\n");
+    }
+
+    #[test]
+    fn round_trip_pools_variables_and_essays() {
+        round_trip("<!-- variables\na = zufall(1, 9)\n-->\n\n<!-- pool: draw 1 -->\n\nErste [[1]]\n\n<!-- item -->\n\nZweite [[2]]\n\n<!-- end pool -->\n\n<!-- essay: points 3, words 50..200\nDie Erde dreht sich.\n-->\n");
+    }
+
+    #[test]
+    fn qti_leaves_out_what_item_bodies_cannot_hold() {
+        let content =
+            from_markdown("Erst ~~durch~~.\n\n<!-- variables\na = 1\n-->\n\n<!-- essay: points 2\n-->\n\n- [x] Ja\n")
+                .content;
+        let html = write_blocks(&content.content, Dialect::Qti, &mut solution_text);
+        assert_eq!(
+            html,
+            "<p>Erst <span class=\"strike\">durch</span>.</p><ul><li><p>Ja</p></li></ul>"
+        );
+    }
+
+    #[test]
+    fn reads_html_of_other_platforms() {
+        let (imported, skipped) = read(concat!(
+            "<h2>Titel</h2><p>Erster &amp; <b>fetter</b>&nbsp;Absatz\n  mit   Leerraum",
+            "<p>Zweiter mit \\(\\frac{1}{2}\\) und <img src=\"a.png\"> Bild</p>",
+            "<table><tr><td>A</td><td>B</td></tr></table>",
+            "<math><semantics><mi>x</mi><annotation encoding=\"application/x-tex\">x</annotation></semantics></math>",
+            "<script>alert(1)</script><object data=\"a.swf\"></object>",
+        ));
+        assert_eq!(
+            imported,
+            from_markdown("## Titel\n\nErster & **fetter** Absatz mit Leerraum\n\nZweiter mit $\\frac{1}{2}$ und Bild\n\nA | B\n\n$x$\n").content
+        );
+        assert_eq!(skipped, vec!["Bilder", "Eingebettete Objekte"]);
+    }
+
+    #[test]
+    fn malformed_html_does_not_panic() {
+        for html in [
+            "<p>offen <b>fett",
+            "</p></div>Text</span>",
+            "<p a=\"unterminated>Text",
+            "<",
+            "<<>>",
+            "&#xffffffff; &#99999999999; &; &amp",
+            "<!-- Kommentar",
+            "<![CDATA[ohne Ende",
+            "<ul><li>a<li>b</ol>",
+            "<select><option>a",
+            "<span data-type=\"gap\">",
+            "<div data-type=\"pool\" data-draw=\"x\"><div data-type=\"poolItem\"></div></div>",
+            "<div data-type=\"essay\" data-points=\"-3\"></div>",
+            "ä<ö>ü</ö>ß",
+        ] {
+            read(html);
+        }
+        let (imported, _) = read("<p>offen <b>fett");
+        assert_eq!(imported, from_markdown("offen **fett**\n").content);
+    }
+
+    #[test]
+    fn decodes_entities() {
+        assert_eq!(
+            decode_entities("&lt;a&gt; &quot;b&quot; &#228; &#x1F600; &auml; &unknown; & x"),
+            "<a> \"b\" ä 😀 ä &unknown; & x"
+        );
+    }
+}
@@ -0,0 +1,1550 @@
+//! Conversion between sheets and Markdown.
+//!
+//! Besides CommonMark headings, paragraphs, emphasis, lists, quotes, rules and fenced code blocks
+//! the following conventions are used:
+//!
+//! - `[[solution]]` is a gap, `[[=solution]]` a numeric task, `[[$solution]]` a formula task and
+//!   `[[?*right|wrong]]` a dropdown whose correct options are prefixed with `*`
+//! - `$source$` is a LaTeX formula
+//! - `- [x] answer` and `- [ ] answer` are the answers of a multiple choice block, `- (x) answer`
+//!   and `- ( ) answer` those of a single choice block
+//! - `<!-- scoring: partialCredit, shuffle -->` right before a multiple choice block sets its
+//!   scoring scheme and shuffles its answers per student, either part may be left out
+//! - `[[??*right|wrong]]` is a dropdown whose options are shuffled per student
+//! - `<audio src="..." type="..."></audio>` on its own line is an audio node
+//! - `<!-- pool: draw 2 -->` starts a pool of which every student gets two items, the items are
+//!   separated by `<!-- item -->` and the pool ends with `<!-- end pool -->`
+//! - A comment starting with `<!-- variables` lists variables, one `name = definition` per line
+//! - A comment starting with `<!-- essay: points 2, words 50..200` is an essay worth two points,
+//!   either part may be left out and the comment holds the model answer
+//! - The sheet title is stored as `title` in a front matter block
+
+use super::document::{
+    dropdown_text, ChoiceMode, DropdownOption, GapMatching, Mark, Node, NodeKind, Scoring, Variable,
+};
+
+pub struct MarkdownDocument {
+    pub title: Option<String>,
+    pub content: Node,
+}
+
+pub fn to_markdown(title: &str, content: &Node) -> String {
+    let mut markdown = format!(
+        "---\ntitle: {}\n---\n\n",
+        serde_json::to_string(title).expect("strings are always serializable")
+    );
+    let body = match content.kind {
+        NodeKind::Doc => write_blocks(&content.content),
+        _ => write_blocks(std::slice::from_ref(content)),
+    };
+    markdown.push_str(&body);
+    markdown.push('\n');
+    markdown
+}
+
+pub fn from_markdown(markdown: &str) -> MarkdownDocument {
+    let markdown = markdown.replace("\r\n", "\n");
+    let lines: Vec<&str> = markdown.lines().collect();
+    let (title, body) = split_front_matter(&lines);
+    let mut content = parse_blocks(body);
+    if content.is_empty() {
+        content.push(Node::new(NodeKind::Paragraph, Vec::new()));
+    }
+    MarkdownDocument {
+        title,
+        content: Node::new(NodeKind::Doc, content),
+    }
+}
+
+// Writing
+
+fn write_blocks(nodes: &[Node]) -> String {
+    let mut blocks: Vec<String> = Vec::new();
+    let mut previous: Option<&NodeKind> = None;
+    for node in nodes {
+        let block = write_block(node);
+        if block.is_empty() {
+            continue;
+        }
+        // Consecutive lists of the same kind would be read back as a single list
+        if previous.map(|p| list_kind(p).is_some() && list_kind(p) == list_kind(&node.kind))
+            == Some(true)
+            && !block.starts_with("<!--")
+        {
+            blocks.push("<!-- -->".to_string());
+        }
+        blocks.push(block);
+        previous = Some(&node.kind);
+    }
+    blocks.join("\n\n")
+}
+
+fn list_kind(kind: &NodeKind) -> Option<ItemKind> {
+    match kind {
+        NodeKind::BulletList => Some(ItemKind::Bullet),
+        NodeKind::OrderedList => Some(ItemKind::Ordered),
+        NodeKind::MultipleChoice {
+            mode: ChoiceMode::Single,
+            ..
+        } => Some(ItemKind::Choice),
+        NodeKind::MultipleChoice { .. } => Some(ItemKind::Task),
+        _ => None,
+    }
+}
+
+fn write_block(node: &Node) -> String {
+    match &node.kind {
+        NodeKind::Doc | NodeKind::Other(_) => write_blocks(&node.content),
+        NodeKind::Paragraph => write_paragraph(&node.content),
+        NodeKind::Heading { level } => format!(
+            "{} {}",
+            "#".repeat(*level as usize),
+            write_paragraph(&node.content)
+        ),
+        NodeKind::Text(_) | NodeKind::HardBreak => write_paragraph(std::slice::from_ref(node)),
+        NodeKind::CodeBlock { language } => {
+            let code = node.text_content();
+            let fence = "`".repeat((longest_run(&code, '`') + 1).max(3));
+            let language = if language == "plain" { "" } else { language };
+            if code.is_empty() {
+                format!("{}{}\n{}", fence, language, fence)
+            } else {
+                format!("{}{}\n{}\n{}", fence, language, code, fence)
+            }
+        }
+        NodeKind::Audio { source, mimetype } => format!(
+            "<audio src=\"{}\" type=\"{}\"></audio>",
+            escape_attribute(source),
+            escape_attribute(mimetype)
+        ),
+        NodeKind::MultipleChoice {
+            mode,
+            scoring,
+            shuffle,
+        } => {
+            let (checked, unchecked) = match mode {
+                ChoiceMode::Single => ("- (x) ", "- ( ) "),
+                ChoiceMode::Multiple => ("- [x] ", "- [ ] "),
+            };
+            let answers = node
+                .content
+                .iter()
+                .map(|answer| {
+                    let solution = matches!(
+                        answer.kind,
+                        NodeKind::MultipleChoiceAnswer { solution: true, .. }
+                    );
+                    let marker = if solution { checked } else { unchecked };
+                    write_item(marker, 2, &answer.content)
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+            let mut settings = Vec::new();
+            if *scoring != Scoring::AllOrNothing {
+                settings.push(format!("scoring: {}", scoring.as_str()));
+            }
+            if *shuffle {
+                settings.push("shuffle".to_string());
+            }
+            if settings.is_empty() {
+                answers
+            } else {
+                format!("<!-- {} -->\n{}", settings.join(", "), answers)
+            }
+        }
+        NodeKind::BulletList | NodeKind::OrderedList => node
+            .content
+            .iter()
+            .enumerate()
+            .map(|(i, item)| {
+                let marker = if node.kind == NodeKind::OrderedList {
+                    format!("{}. ", i + 1)
+                } else {
+                    "- ".to_string()
+                };
+                write_item(&marker, marker.len(), &item.content)
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+        NodeKind::ListItem | NodeKind::MultipleChoiceAnswer { .. } => {
+            write_item("- ", 2, &node.content)
+        }
+        NodeKind::Blockquote => write_blocks(&node.content)
+            .lines()
+            .map(|line| {
+                if line.is_empty() {
+                    ">".to_string()
+                } else {
+                    format!("> {}", line)
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+        NodeKind::HorizontalRule => "---".to_string(),
+        NodeKind::Pool { draw, .. } => {
+            let items = node
+                .content
+                .iter()
+                .map(|item| write_blocks(&item.content))
+                .collect::<Vec<_>>()
+                .join("\n\n<!-- item -->\n\n");
+            format!(
+                "<!-- pool: draw {} -->\n\n{}\n\n<!-- end pool -->",
+                draw, items
+            )
+        }
+        NodeKind::PoolItem => write_blocks(&node.content),
+        NodeKind::Variables(variables) => {
+            let mut comment = "<!-- variables".to_string();
+            for variable in variables {
+                comment.push_str(&format!("\n{} = {}", variable.name, variable.definition));
+            }
+            comment.push_str("\n-->");
+            comment
+        }
+        NodeKind::Essay {
+            min_words,
+            max_words,
+            points,
+            model_answer,
+            ..
+        } => {
+            let mut comment = format!("<!-- essay: points {}", points);
+            if min_words.is_some() || max_words.is_some() {
+                let bound = |words: &Option<u32>| words.map(|w| w.to_string()).unwrap_or_default();
+                comment.push_str(&format!(
+                    ", words {}..{}",
+                    bound(min_words),
+                    bound(max_words)
+                ));
+            }
+            if !model_answer.is_empty() {
+                comment.push('\n');
+                comment.push_str(model_answer);
+            }
+            comment.push_str("\n-->");
+            comment
+        }
+    }
+}
+
+/// Continuation lines are indented by `indent`, which is the width of the list marker
+fn write_item(marker: &str, indent: usize, content: &[Node]) -> String {
+    let indent = " ".repeat(indent);
+    let body = write_blocks(content);
+    let mut item = String::from(marker.trim_end());
+    for (i, line) in body.lines().enumerate() {
+        if i == 0 {
+            item.push(' ');
+        } else {
+            item.push('\n');
+            if !line.is_empty() {
+                item.push_str(&indent);
+            }
+        }
+        item.push_str(line);
+    }
+    item
+}
+
+fn write_paragraph(nodes: &[Node]) -> String {
+    let mut text = String::new();
+    write_inline(nodes, &mut text);
+    text.split('\n')
+        .map(escape_line_start)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+const MARK_ORDER: [&str; 9] = [
+    "bold", "code", "italic", "strike", "gap", "dropdown", "numeric", "formula", "latex",
+];
+
+fn mark_rank(mark: &Mark) -> usize {
+    let name = match mark {
+        Mark::Bold => "bold",
+        Mark::Code => "code",
+        Mark::Italic => "italic",
+        Mark::Strike => "strike",
+        Mark::Gap { .. } => "gap",
+        Mark::Dropdown { .. } => "dropdown",
+        Mark::Numeric { .. } => "numeric",
+        Mark::Formula { .. } => "formula",
+        Mark::Latex { .. } => "latex",
+        Mark::Other(_) => return MARK_ORDER.len(),
+    };
+    MARK_ORDER
+        .iter()
+        .position(|m| *m == name)
+        .unwrap_or(MARK_ORDER.len())
+}
+
+fn write_inline(nodes: &[Node], out: &mut String) {
+    for node in nodes {
+        match &node.kind {
+            NodeKind::Text(text) => write_text(text, &node.marks, out),
+            NodeKind::HardBreak => out.push_str("\\\n"),
+            _ => write_inline(&node.content, out),
+        }
+    }
+}
+
+fn write_text(text: &str, marks: &[Mark], out: &mut String) {
+    let has = |mark: &Mark| marks.contains(mark);
+    let mut delimiters = String::new();
+    if has(&Mark::Bold) {
+        delimiters.push_str("**");
+    }
+    if has(&Mark::Italic) {
+        delimiters.push('*');
+    }
+    if has(&Mark::Strike) {
+        delimiters.push_str("~~");
+    }
+    out.push_str(&delimiters);
+    let gap = marks.iter().find_map(|mark| match mark {
+        Mark::Gap { solution, .. } => Some(("", solution)),
+        Mark::Numeric { solution, .. } => Some(("=", solution)),
+        Mark::Formula { solution, .. } => Some(("$", solution)),
+        _ => None,
+    });
+    let dropdown = marks.iter().find_map(|mark| match mark {
+        Mark::Dropdown {
+            options, shuffle, ..
+        } => Some((options, shuffle)),
+        _ => None,
+    });
+    let latex = marks.iter().find_map(|mark| match mark {
+        Mark::Latex { source } => Some(source),
+        _ => None,
+    });
+    if let Some((options, shuffle)) = dropdown {
+        out.push_str(if *shuffle { "[[??" } else { "[[?" });
+        for (i, option) in options.iter().enumerate() {
+            if i > 0 {
+                out.push('|');
+            }
+            if option.correct {
+                out.push('*');
+            }
+            for (j, c) in option.text.chars().enumerate() {
+                // A leading `?` would shuffle the options
+                let leading = i == 0 && j == 0 && !option.correct && c == '?';
+                if matches!(c, '\\' | ']' | '|' | '*') || leading {
+                    out.push('\\');
+                }
+                out.push(c);
+            }
+        }
+        out.push_str("]]");
+    } else if let Some((prefix, solution)) = gap {
+        out.push_str("[[");
+        out.push_str(prefix);
+        for (i, c) in solution.chars().enumerate() {
+            // A leading `=`, `$` or `?` would turn a gap into a numeric or formula task or dropdown
+            let leading = i == 0 && prefix.is_empty() && matches!(c, '=' | '$' | '?');
+            if c == '\\' || c == ']' || leading {
+                out.push('\\');
+            }
+            out.push(c);
+        }
+        out.push_str("]]");
+    } else if let Some(source) = latex {
+        out.push('$');
+        out.push_str(&source.replace('$', "\\$"));
+        out.push('$');
+    } else if has(&Mark::Code) {
+        let fence = "`".repeat(longest_run(text, '`') + 1);
+        let padding = if text.starts_with('`') || text.ends_with('`') {
+            " "
+        } else {
+            ""
+        };
+        out.push_str(&fence);
+        out.push_str(padding);
+        out.push_str(text);
+        out.push_str(padding);
+        out.push_str(&fence);
+    } else {
+        for c in text.chars() {
+            if matches!(c, '\\' | '*' | '`' | '~' | '$' | '[' | ']' | '<') {
+                out.push('\\');
+            }
+            out.push(c);
+        }
+    }
+    out.push_str(&delimiters.chars().rev().collect::<String>());
+}
+
+// Characters that would start a block at the beginning of a line have to be escaped
+fn escape_line_start(line: &str) -> String {
+    if line.starts_with(['#', '>', '-', '+']) {
+        return format!("\\{}", line);
+    }
+    let digits = line.chars().take_while(char::is_ascii_digit).count();
+    if digits > 0 && line[digits..].starts_with(['.', ')']) {
+        return format!("{}\\{}", &line[..digits], &line[digits..]);
+    }
+    line.to_string()
+}
+
+fn escape_attribute(value: &str) -> String {
+    value.replace('&', "&amp;").replace('"', "&quot;")
+}
+
+fn unescape_attribute(value: &str) -> String {
+    value.replace("&quot;", "\"").replace("&amp;", "&")
+}
+
+fn longest_run(text: &str, c: char) -> usize {
+    let mut longest = 0;
+    let mut current = 0;
+    for t in text.chars() {
+        if t == c {
+            current += 1;
+            longest = longest.max(current);
+        } else {
+            current = 0;
+        }
+    }
+    longest
+}
+
+// Reading
+
+fn split_front_matter<'a, 'b>(lines: &'b [&'a str]) -> (Option<String>, &'b [&'a str]) {
+    if lines.first().map(|l| l.trim_end()) != Some("---") {
+        return (None, lines);
+    }
+    let end = match lines.iter().skip(1).position(|l| l.trim_end() == "---") {
+        Some(end) => end + 1,
+        None => return (None, lines),
+    };
+    let fields: Option<Vec<(&str, &str)>> = lines[1..end]
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.split_once(':'))
+        .collect();
+    let fields = match fields {
+        Some(fields) => fields,
+        // Not a front matter block but a document starting with a rule
+        None => return (None, lines),
+    };
+    let title = fields
+        .into_iter()
+        .find(|(key, _)| key.trim() == "title")
+        .map(|(_, value)| {
+            let value = value.trim();
+            serde_json::from_str::<String>(value)
+                .unwrap_or_else(|_| value.trim_matches(|c| c == '\'' || c == '"').to_string())
+        });
+    (title, &lines[end + 1..])
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ItemKind {
+    Bullet,
+    Ordered,
+    Task,
+    Choice,
+}
+
+struct ListItem<'a> {
+    kind: ItemKind,
+    checked: bool,
+    /// Width of the marker including the following space, continuation lines are indented by it
+    width: usize,
+    content: &'a str,
+}
+
+fn indentation(line: &str) -> usize {
+    line.len() - line.trim_start_matches(' ').len()
+}
+
+fn parse_list_item(line: &str) -> Option<ListItem<'_>> {
+    let indent = indentation(line);
+    if indent > 3 {
+        return None;
+    }
+    let rest = &line[indent..];
+    let (kind, marker_len) = if rest.starts_with(['-', '*', '+']) {
+        (ItemKind::Bullet, 1)
+    } else {
+        let digits = rest.chars().take_while(char::is_ascii_digit).count();
+        if digits == 0 || digits > 9 || !rest[digits..].starts_with(['.', ')']) {
+            return None;
+        }
+        (ItemKind::Ordered, digits + 1)
+    };
+    let after_marker = &rest[marker_len..];
+    if !after_marker.is_empty() && !after_marker.starts_with(' ') {
+        return None;
+    }
+    let content = after_marker.strip_prefix(' ').unwrap_or(after_marker);
+    let mut item = ListItem {
+        kind,
+        checked: false,
+        width: indent + marker_len + 1,
+        content,
+    };
+    if kind == ItemKind::Bullet {
+        for (task, kind, checked) in [
+            ("[ ]", ItemKind::Task, false),
+            ("[x]", ItemKind::Task, true),
+            ("[X]", ItemKind::Task, true),
+            ("( )", ItemKind::Choice, false),
+            ("(x)", ItemKind::Choice, true),
+            ("(X)", ItemKind::Choice, true),
+        ] {
+            if let Some(rest) = content.strip_prefix(task) {
+                if rest.is_empty() || rest.starts_with(' ') {
+                    item.kind = kind;
+                    item.checked = checked;
+                    item.content = rest.strip_prefix(' ').unwrap_or(rest);
+                }
+            }
+        }
+    }
+    Some(item)
+}
+
+fn fence(line: &str) -> Option<(char, usize, &str)> {
+    let trimmed = line.trim_start();
+    let c = trimmed.chars().next().filter(|c| *c == '`' || *c == '~')?;
+    let length = trimmed.chars().take_while(|t| *t == c).count();
+    if length < 3 {
+        return None;
+    }
+    Some((c, length, trimmed[length..].trim()))
+}
+
+fn heading(line: &str) -> Option<(u8, &str)> {
+    let trimmed = line.trim_start();
+    let level = trimmed.chars().take_while(|c| *c == '#').count();
+    let rest = &trimmed[level..];
+    if (1..=6).contains(&level) && (rest.is_empty() || rest.starts_with(' ')) {
+        Some((level as u8, rest.trim()))
+    } else {
+        None
+    }
+}
+
+fn is_rule(line: &str) -> bool {
+    let chars: Vec<char> = line.chars().filter(|c| !c.is_whitespace()).collect();
+    chars.len() >= 3
+        && ['-', '*', '_']
+            .iter()
+            .any(|marker| chars.iter().all(|c| c == marker))
+}
+
+fn is_comment(line: &str) -> bool {
+    let trimmed = line.trim();
+    trimmed.starts_with("<!--") && trimmed.ends_with("-->")
+}
+
+/// Reads the scoring scheme and whether to shuffle from a comment before a multiple choice block
+fn choice_comment(line: &str) -> Option<(Scoring, bool)> {
+    let comment = line.trim().strip_prefix("<!--")?.strip_suffix("-->")?;
+    let mut scoring = None;
+    let mut shuffle = false;
+    for setting in comment.split(',').map(str::trim) {
+        if let Some(name) = setting.strip_prefix("scoring:") {
+            scoring = Some(Scoring::parse(Some(name.trim())));
+        } else if setting == "shuffle" {
+            shuffle = true;
+        }
+    }
+    if scoring.is_none() && !shuffle {
+        return None;
+    }
+    Some((scoring.unwrap_or(Scoring::AllOrNothing), shuffle))
+}
+
+fn comment(line: &str) -> Option<&str> {
+    let comment = line.trim().strip_prefix("<!--")?.strip_suffix("-->")?;
+    Some(comment.trim())
+}
+
+fn pool_start(line: &str) -> Option<usize> {
+    let draw = comment(line)?.strip_prefix("pool:")?.trim();
+    draw.strip_prefix("draw")?.trim().parse().ok()
+}
+
+/// Reads a pool starting at `start`, returns it and the index of the line after its end
+fn parse_pool(lines: &[&str], start: usize, draw: usize) -> (Node, usize) {
+    let mut items: Vec<Vec<&str>> = vec![Vec::new()];
+    let mut depth = 0;
+    let mut i = start + 1;
+    while i < lines.len() {
+        let line = lines[i];
+        i += 1;
+        if pool_start(line).is_some() {
+            depth += 1;
+        } else if comment(line) == Some("end pool") {
+            if depth == 0 {
+                break;
+            }
+            depth -= 1;
+        } else if comment(line) == Some("item") && depth == 0 {
+            items.push(Vec::new());
+            continue;
+        }
+        items
+            .last_mut()
+            .expect("there is always an item")
+            .push(line);
+    }
+    let items: Vec<Node> = items
+        .iter()
+        .map(|item| parse_blocks(item))
+        .filter(|content| !content.is_empty())
+        .map(|content| Node::new(NodeKind::PoolItem, content))
+        .collect();
+    let draw = draw.clamp(1, items.len().max(1));
+    (Node::new(NodeKind::Pool { draw, drawn: None }, items), i)
+}
+
+/// Reads a variables comment starting at `start`, returns it and the index of the line after it
+fn parse_variables(lines: &[&str], start: usize) -> (Node, usize) {
+    let mut variables = Vec::new();
+    let mut i = start + 1;
+    while i < lines.len() {
+        let line = lines[i].trim();
+        i += 1;
+        if line == "-->" {
+            break;
+        }
+        if let Some((name, definition)) = line.split_once('=') {
+            variables.push(Variable {
+                name: name.trim().to_string(),
+                definition: definition.trim().to_string(),
+                value: None,
+            });
+        }
+    }
+    (Node::new(NodeKind::Variables(variables), Vec::new()), i)
+}
+
+fn essay_start(line: &str) -> Option<&str> {
+    line.trim().strip_prefix("<!-- essay:")
+}
+
+/// Reads an essay comment starting at `start`, returns it and the index of the line after it
+fn parse_essay(lines: &[&str], start: usize, header: &str) -> (Node, usize) {
+    let (mut min_words, mut max_words, mut points) = (None, None, 1);
+    for setting in header.split(',').map(str::trim) {
+        if let Some(value) = setting.strip_prefix("points") {
+            points = value.trim().parse().unwrap_or(1).max(1);
+        } else if let Some((min, max)) = setting
+            .strip_prefix("words")
+            .and_then(|words| words.split_once(".."))
+        {
+            min_words = min.trim().parse().ok();
+            max_words = max.trim().parse().ok();
+        }
+    }
+    let mut model_answer = Vec::new();
+    let mut i = start + 1;
+    while i < lines.len() {
+        let line = lines[i];
+        i += 1;
+        if line.trim() == "-->" {
+            break;
+        }
+        model_answer.push(line);
+    }
+    let essay = NodeKind::Essay {
+        min_words,
+        max_words,
+        points,
+        model_answer: model_answer.join("\n"),
+        answer: String::new(),
+        awarded: None,
+    };
+    (Node::new(essay, Vec::new()), i)
+}
+
+fn audio(line: &str) -> Option<Node> {
+    let trimmed = line.trim();
+    if !trimmed.starts_with("<audio") {
+        return None;
+    }
+    let attribute = |name: &str| {
+        let start = trimmed.find(&format!(" {}=\"", name))? + name.len() + 3;
+        let end = trimmed[start..].find('"')?;
+        Some(unescape_attribute(&trimmed[start..start + end]))
+    };
+    Some(Node::new(
+        NodeKind::Audio {
+            source: attribute("src")?,
+            mimetype: attribute("type").unwrap_or_default(),
+        },
+        Vec::new(),
+    ))
+}
+
+fn starts_block(line: &str) -> bool {
+    let trimmed = line.trim_start();
+    fence(line).is_some()
+        || heading(line).is_some()
+        || trimmed.starts_with('>')
+        || is_rule(line)
+        || parse_list_item(line).is_some()
+        || is_comment(line)
+        || line.trim() == "<!-- variables"
+        || essay_start(line).is_some()
+        || audio(line).is_some()
+}
+
+fn parse_blocks(lines: &[&str]) -> Vec<Node> {
+    let mut nodes = Vec::new();
+    let mut settings = None;
+    let mut i = 0;
+    while i < lines.len() {
+        let line = lines[i];
+        if line.trim().is_empty() {
+            i += 1;
+            continue;
+        }
+        if line.trim() == "<!-- variables" {
+            let (variables, next) = parse_variables(lines, i);
+            nodes.push(variables);
+            settings = None;
+            i = next;
+            continue;
+        }
+        if let Some(header) = essay_start(line) {
+            let (essay, next) = parse_essay(lines, i, header);
+            nodes.push(essay);
+            settings = None;
+            i = next;
+            continue;
+        }
+        if let Some(draw) = pool_start(line) {
+            let (pool, next) = parse_pool(lines, i, draw);
+            if !pool.content.is_empty() {
+                nodes.push(pool);
+            }
+            settings = None;
+            i = next;
+            continue;
+        }
+        if is_comment(line) {
+            settings = choice_comment(line);
+            i += 1;
+            continue;
+        }
+        // Settings only apply to the block right after their comment
+        let settings = settings.take();
+        if let Some((c, length, language)) = fence(line) {
+            let indent = indentation(line);
+            let mut code = Vec::new();
+            i += 1;
+            while i < lines.len() {
+                if let Some((closing, closing_length, "")) = fence(lines[i]) {
+                    if closing == c && closing_length >= length {
+                        i += 1;
+                        break;
+                    }
+                }
+                let line = lines[i];
+                code.push(&line[indentation(line).min(indent)..]);
+                i += 1;
+            }
+            let code = code.join("\n");
+            let language = if language.is_empty() {
+                "plain"
+            } else {
+                language
+            };
+            let content = if code.is_empty() {
+                Vec::new()
+            } else {
+                vec![Node::text(code, Vec::new())]
+            };
+            nodes.push(Node::new(
+                NodeKind::CodeBlock {
+                    language: language.to_string(),
+                },
+                content,
+            ));
+        } else if let Some((level, text)) = heading(line) {
+            nodes.push(Node::new(NodeKind::Heading { level }, parse_inline(text)));
+            i += 1;
+        } else if is_rule(line) {
+            nodes.push(Node::new(NodeKind::HorizontalRule, Vec::new()));
+            i += 1;
+        } else if let Some(node) = audio(line) {
+            nodes.push(node);
+            i += 1;
+        } else if line.trim_start().starts_with('>') {
+            let mut quoted = Vec::new();
+            while i < lines.len() && lines[i].trim_start().starts_with('>') {
+                let line = &lines[i].trim_start()[1..];
+                quoted.push(line.strip_prefix(' ').unwrap_or(line));
+                i += 1;
+            }
+            nodes.push(Node::new(NodeKind::Blockquote, parse_blocks(&quoted)));
+        } else if let Some(first) = parse_list_item(line) {
+            let (mut node, next) = parse_list(lines, i, first.kind);
+            if let (
+                NodeKind::MultipleChoice {
+                    scoring, shuffle, ..
+                },
+                Some(settings),
+            ) = (&mut node.kind, settings)
+            {
+                *scoring = settings.0;
+                *shuffle = settings.1;
+            }
+            nodes.push(node);
+            i = next;
+        } else {
+            let mut paragraph = vec![line];
+            i += 1;
+            while i < lines.len() && !lines[i].trim().is_empty() && !starts_block(lines[i]) {
+                paragraph.push(lines[i]);
+                i += 1;
+            }
+            nodes.push(Node::new(
+                NodeKind::Paragraph,
+                parse_inline(&join_paragraph(&paragraph)),
+            ));
+        }
+    }
+    nodes
+}
+
+fn join_paragraph(lines: &[&str]) -> String {
+    lines
+        .iter()
+        .map(|line| {
+            let line = line.trim_start();
+            // Two trailing spaces are a hard break as well
+            if line.ends_with("  ") {
+                format!("{}\\", line.trim_end())
+            } else {
+                line.trim_end().to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn parse_list(lines: &[&str], start: usize, kind: ItemKind) -> (Node, usize) {
+    let mut items = Vec::new();
+    let mut i = start;
+    while i < lines.len() {
+        let item = match parse_list_item(lines[i]) {
+            Some(item) if item.kind == kind => item,
+            _ => break,
+        };
+        let mut content = vec![item.content.to_string()];
+        i += 1;
+        while i < lines.len() {
+            let line = lines[i];
+            if line.trim().is_empty() {
+                // Blank lines only continue the item if indented content follows
+                let next = lines[i..].iter().position(|l| !l.trim().is_empty());
+                match next {
+                    Some(next) if indentation(lines[i + next]) >= item.width => {
+                        content.resize(content.len() + next, String::new());
+                        i += next;
+                    }
+                    _ => break,
+                }
+            } else if indentation(line) >= item.width {
+                content.push(line[item.width..].to_string());
+                i += 1;
+            } else if !starts_block(line) && content.last().map(String::is_empty) == Some(false) {
+                content.push(line.to_string());
+                i += 1;
+            } else {
+                break;
+            }
+        }
+        let content: Vec<&str> = content.iter().map(String::as_str).collect();
+        let mut blocks = parse_blocks(&content);
+        if blocks.is_empty() {
+            blocks.push(Node::new(NodeKind::Paragraph, Vec::new()));
+        }
+        let item_kind = match kind {
+            ItemKind::Task | ItemKind::Choice => NodeKind::MultipleChoiceAnswer {
+                solution: item.checked,
+                answer: false,
+            },
+            _ => NodeKind::ListItem,
+        };
+        items.push(Node::new(item_kind, blocks));
+        // Items of a loose list are separated by blank lines
+        let next = lines[i..].iter().position(|l| !l.trim().is_empty());
+        match next {
+            Some(next) if parse_list_item(lines[i + next]).map(|item| item.kind) == Some(kind) => {
+                i += next
+            }
+            _ => break,
+        }
+    }
+    let list_kind = match kind {
+        ItemKind::Bullet => NodeKind::BulletList,
+        ItemKind::Ordered => NodeKind::OrderedList,
+        ItemKind::Task => NodeKind::MultipleChoice {
+            mode: ChoiceMode::Multiple,
+            scoring: Scoring::AllOrNothing,
+            shuffle: false,
+        },
+        ItemKind::Choice => NodeKind::MultipleChoice {
+            mode: ChoiceMode::Single,
+            scoring: Scoring::AllOrNothing,
+            shuffle: false,
+        },
+    };
+    (Node::new(list_kind, items), i)
+}
+
+#[derive(Debug)]
+enum Token {
+    Text(String),
+    Delimiter(Mark, &'static str),
+    Atom(String, Mark),
+    HardBreak,
+}
+
+fn parse_inline(text: &str) -> Vec<Node> {
+    let tokens = pair_delimiters(tokenize(text));
+    let mut nodes: Vec<Node> = Vec::new();
+    let mut active: Vec<Mark> = Vec::new();
+    let marks_with = |active: &[Mark], mark: Option<Mark>| {
+        let mut marks: Vec<Mark> = active.iter().cloned().chain(mark).collect();
+        marks.sort_by_key(mark_rank);
+        marks
+    };
+    for token in tokens {
+        match token {
+            Token::Text(text) => {
+                let marks = marks_with(&active, None);
+                match nodes.last_mut() {
+                    Some(Node {
+                        kind: NodeKind::Text(previous),
+                        marks: previous_marks,
+                        ..
+                    }) if *previous_marks == marks && !marks.iter().any(is_atomic) => {
+                        previous.push_str(&text)
+                    }
+                    _ => nodes.push(Node::text(text, marks)),
+                }
+            }
+            Token::Delimiter(mark, _) => {
+                if let Some(position) = active.iter().position(|m| *m == mark) {
+                    active.remove(position);
+                } else {
+                    active.push(mark);
+                }
+            }
+            Token::Atom(text, mark) => {
+                nodes.push(Node::text(text, marks_with(&active, Some(mark))))
+            }
+            Token::HardBreak => nodes.push(Node::new(NodeKind::HardBreak, Vec::new())),
+        }
+    }
+    nodes.retain(|node| node.kind != NodeKind::Text(String::new()));
+    nodes
+}
+
+fn is_atomic(mark: &Mark) -> bool {
+    matches!(
+        mark,
+        Mark::Gap { .. }
+            | Mark::Dropdown { .. }
+            | Mark::Numeric { .. }
+            | Mark::Formula { .. }
+            | Mark::Latex { .. }
+            | Mark::Code
+    )
+}
+
+// Delimiters without a partner are plain text
+fn pair_delimiters(mut tokens: Vec<Token>) -> Vec<Token> {
+    for mark in [Mark::Bold, Mark::Italic, Mark::Strike] {
+        let positions: Vec<usize> = tokens
+            .iter()
+            .enumerate()
+            .filter(|(_, token)| matches!(token, Token::Delimiter(m, _) if *m == mark))
+            .map(|(i, _)| i)
+            .collect();
+        if positions.len() % 2 == 1 {
+            let last = positions[positions.len() - 1];
+            if let Token::Delimiter(_, literal) = tokens[last] {
+                tokens[last] = Token::Text(literal.to_string());
+            }
+        }
+    }
+    tokens
+}
+
+fn tokenize(text: &str) -> Vec<Token> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens = Vec::new();
+    let mut plain = String::new();
+    let mut i = 0;
+    let flush = |plain: &mut String, tokens: &mut Vec<Token>| {
+        if !plain.is_empty() {
+            tokens.push(Token::Text(std::mem::take(plain)));
+        }
+    };
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+        match c {
+            '\\' if next == Some('\n') => {
+                flush(&mut plain, &mut tokens);
+                tokens.push(Token::HardBreak);
+                i += 2;
+            }
+            '\\' if matches!(next, Some(n) if n.is_ascii_punctuation()) => {
+                plain.push(chars[i + 1]);
+                i += 2;
+            }
+            '\n' => {
+                plain.push(' ');
+                i += 1;
+            }
+            '`' => {
+                let length = run_length(&chars, i, '`');
+                match find_run(&chars, i + length, '`', length) {
+                    Some(end) => {
+                        let mut code: String = chars[i + length..end].iter().collect();
+                        if code.len() > 2
+                            && code.starts_with(' ')
+                            && code.ends_with(' ')
+                            && !code.trim().is_empty()
+                        {
+                            code = code[1..code.len() - 1].to_string();
+                        }
+                        flush(&mut plain, &mut tokens);
+                        tokens.push(Token::Atom(code, Mark::Code));
+                        i = end + length;
+                    }
+                    None => {
+                        plain.extend(&chars[i..i + length]);
+                        i += length;
+                    }
+                }
+            }
+            '[' if next == Some('[') => {
+                let atom = match chars.get(i + 2) {
+                    Some('?') => {
+                        let shuffle = chars.get(i + 3) == Some(&'?');
+                        let start = if shuffle { i + 4 } else { i + 3 };
+                        dropdown_options(&chars, start).map(|(options, end)| {
+                            let text = dropdown_text(&options);
+                            let mark = Mark::Dropdown {
+                                options,
+                                answer: String::new(),
+                                shuffle,
+                            };
+                            (text, mark, end)
+                        })
+                    }
+                    Some('=') => {
+                        closing(&chars, i + 3, &[']', ']'], true).map(|(solution, end)| {
+                            let mark = Mark::Numeric {
+                                solution: solution.clone(),
+                                answer: String::new(),
+                            };
+                            (solution, mark, end)
+                        })
+                    }
+                    Some('$') => {
+                        closing(&chars, i + 3, &[']', ']'], true).map(|(solution, end)| {
+                            let mark = Mark::Formula {
+                                solution: solution.clone(),
+                                answer: String::new(),
+                            };
+                            (solution, mark, end)
+                        })
+                    }
+                    _ => closing(&chars, i + 2, &[']', ']'], true).map(|(solution, end)| {
+                        let mark = Mark::Gap {
+                            solution: solution.clone(),
+                            answer: String::new(),
+                            matching: GapMatching::default(),
+                        };
+                        (solution, mark, end)
+                    }),
+                };
+                match atom {
+                    Some((text, mark, end)) => {
+                        flush(&mut plain, &mut tokens);
+                        tokens.push(Token::Atom(text, mark));
+                        i = end;
+                    }
+                    None => {
+                        plain.push_str("[[");
+                        i += 2;
+                    }
+                }
+            }
+            '$' => {
+                let delimiter: &[char] = if next == Some('$') {
+                    &['$', '$']
+                } else {
+                    &['$']
+                };
+                match closing(&chars, i + delimiter.len(), delimiter, false) {
+                    Some((source, end)) if !source.is_empty() => {
+                        flush(&mut plain, &mut tokens);
+                        tokens.push(Token::Atom(source.clone(), Mark::Latex { source }));
+                        i = end;
+                    }
+                    _ => {
+                        plain.push('$');
+                        i += 1;
+                    }
+                }
+            }
+            '*' if next == Some('*') => {
+                flush(&mut plain, &mut tokens);
+                tokens.push(Token::Delimiter(Mark::Bold, "**"));
+                i += 2;
+            }
+            '*' => {
+                flush(&mut plain, &mut tokens);
+                tokens.push(Token::Delimiter(Mark::Italic, "*"));
+                i += 1;
+            }
+            '~' if next == Some('~') => {
+                flush(&mut plain, &mut tokens);
+                tokens.push(Token::Delimiter(Mark::Strike, "~~"));
+                i += 2;
+            }
+            _ => {
+                plain.push(c);
+                i += 1;
+            }
+        }
+    }
+    flush(&mut plain, &mut tokens);
+    tokens
+}
+
+fn run_length(chars: &[char], start: usize, c: char) -> usize {
+    chars[start..].iter().take_while(|t| **t == c).count()
+}
+
+fn find_run(chars: &[char], start: usize, c: char, length: usize) -> Option<usize> {
+    let mut i = start;
+    while i < chars.len() {
+        let run = run_length(chars, i, c);
+        if run == length {
+            return Some(i);
+        }
+        i += run.max(1);
+    }
+    None
+}
+
+/// Reads the options of a dropdown up to the closing `]]`, returning them and the position after it
+fn dropdown_options(chars: &[char], start: usize) -> Option<(Vec<DropdownOption>, usize)> {
+    let empty = || DropdownOption {
+        text: String::new(),
+        correct: false,
+    };
+    let mut options = Vec::new();
+    let mut option = empty();
+    let mut i = start;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' if i + 1 < chars.len() => {
+                option.text.push(chars[i + 1]);
+                i += 1;
+            }
+            '*' if option.text.is_empty() && !option.correct => option.correct = true,
+            '|' => options.push(std::mem::replace(&mut option, empty())),
+            ']' if chars.get(i + 1) == Some(&']') => {
+                options.push(option);
+                return Some((options, i + 2));
+            }
+            c => option.text.push(c),
+        }
+        i += 1;
+    }
+    None
+}
+
+/// Finds the closing delimiter, returning the unescaped content and the position after it.
+///
+/// Gaps escape every character with a backslash, formulas only the dollar sign since LaTeX
+/// itself is full of backslashes.
+fn closing(
+    chars: &[char],
+    start: usize,
+    delimiter: &[char],
+    escape_all: bool,
+) -> Option<(String, usize)> {
+    let mut content = String::new();
+    let mut i = start;
+    while i < chars.len() {
+        if chars[i] == '\\' {
+            if let Some(&next) = chars.get(i + 1) {
+                if escape_all || next == '$' {
+                    content.push(next);
+                    i += 2;
+                    continue;
+                }
+            }
+        }
+        if chars[i..].starts_with(delimiter) {
+            return Some((content, i + delimiter.len()));
+        }
+        if chars[i] == '\n' && !escape_all {
+            content.push(' ');
+        } else {
+            content.push(chars[i]);
+        }
+        i += 1;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn round_trip(content: serde_json::Value) {
+        let node = Node::from_json(&content);
+        let markdown = to_markdown("Titel", &node);
+        let document = from_markdown(&markdown);
+        assert_eq!(document.title.as_deref(), Some("Titel"));
+        assert_eq!(document.content, node, "markdown was:\n{}", markdown);
+        assert_eq!(to_markdown("Titel", &document.content), markdown);
+    }
+
+    fn doc(content: serde_json::Value) -> serde_json::Value {
+        json!({"type": "doc", "content": content})
+    }
+
+    fn paragraph(content: serde_json::Value) -> serde_json::Value {
+        json!({"type": "paragraph", "content": content})
+    }
+
+    fn text(text: &str) -> serde_json::Value {
+        json!({"type": "text", "text": text})
+    }
+
+    fn marked(text: &str, marks: serde_json::Value) -> serde_json::Value {
+        json!({"type": "text", "text": text, "marks": marks})
+    }
+
+    #[test]
+    fn round_trip_headings_and_paragraphs() {
+        round_trip(doc(json!([
+            {"type": "heading", "level": 1, "content": [text("Aufgabe 1")]},
+            paragraph(json!([text("Ein ganz normaler Absatz.")])),
+            {"type": "heading", "level": 3, "content": [text("Unteraufgabe")]},
+            paragraph(json!([text("Noch ein Absatz.")])),
+        ])));
+    }
+
+    #[test]
+    fn round_trip_marks() {
+        round_trip(doc(json!([paragraph(json!([
+            text("Text mit "),
+            marked("fett", json!([{"type": "bold"}])),
+            text(", "),
+            marked("kursiv", json!([{"type": "italic"}])),
+            text(", "),
+            marked("beidem", json!([{"type": "bold"}, {"type": "italic"}])),
+            text(", "),
+            marked("durchgestrichen", json!([{"type": "strike"}])),
+            text(" und "),
+            marked("let x = `a`;", json!([{"type": "code"}])),
+            text("."),
+        ]))])));
+    }
+
+    #[test]
+    fn round_trip_gaps_and_latex() {
+        round_trip(doc(json!([paragraph(json!([
+            text("Die Hauptstadt von Deutschland ist "),
+            marked(
+                "Berlin",
+                json!([{"type": "gap", "solution": "Berlin", "answer": ""}])
+            ),
+            text(", "),
+            marked(
+                "a]b\\c",
+                json!([{"type": "gap", "solution": "a]b\\c", "answer": ""}])
+            ),
+            text(" und "),
+            marked(
+                "\\frac{1}{2} \\$",
+                json!([{"type": "latex", "source": "\\frac{1}{2} \\$"}])
+            ),
+            text(" ist ein Bruch."),
+        ]))])));
+    }
+
+    #[test]
+    fn round_trip_numeric_tasks() {
+        round_trip(doc(json!([paragraph(json!([
+            text("Das Pendel ist "),
+            marked(
+                "3,5 ± 0,1 cm",
+                json!([{"type": "numeric", "solution": "3,5 ± 0,1 cm", "answer": ""}])
+            ),
+            text(" lang."),
+        ]))])));
+    }
+
+    #[test]
+    fn round_trip_formula_tasks() {
+        round_trip(doc(json!([paragraph(json!([
+            text("Ausmultipliziert ist das "),
+            marked(
+                "\\frac{1}{2}x^2 + x",
+                json!([{"type": "formula", "solution": "\\frac{1}{2}x^2 + x", "answer": ""}])
+            ),
+            text(", aber "),
+            marked(
+                "$5",
+                json!([{"type": "gap", "solution": "$5", "answer": ""}])
+            ),
+            text(" ist eine Lücke."),
+        ]))])));
+    }
+
+    #[test]
+    fn round_trip_dropdowns() {
+        round_trip(doc(json!([paragraph(json!([
+            text("Berlin liegt an der "),
+            marked(
+                "Spree",
+                json!([{"type": "dropdown", "options": [
+                    {"text": "Elbe", "correct": false},
+                    {"text": "Spree", "correct": true},
+                    {"text": "*|]\\", "correct": true},
+                ], "answer": ""}])
+            ),
+            text(" und "),
+            marked("?", json!([{"type": "gap", "solution": "?", "answer": ""}])),
+            text(" oder "),
+            marked(
+                "ja",
+                json!([{"type": "dropdown", "shuffle": true, "options": [
+                    {"text": "?", "correct": false},
+                    {"text": "ja", "correct": true},
+                ], "answer": ""}])
+            ),
+        ]))])));
+    }
+
+    #[test]
+    fn round_trip_escaping() {
+        round_trip(doc(json!([
+            paragraph(json!([text(
+                "# Keine Überschrift, *kein* Fett, [[keine Lücke]] und $5"
+            )])),
+            paragraph(json!([text("- kein Listenpunkt")])),
+            paragraph(json!([text("1. auch kein Listenpunkt")])),
+            paragraph(json!([text("> kein Zitat <audio> \\ ~~")])),
+        ])));
+    }
+
+    #[test]
+    fn round_trip_hard_break() {
+        round_trip(doc(json!([paragraph(json!([
+            text("Erste Zeile"),
+            {"type": "hardBreak"},
+            text("- zweite Zeile"),
+        ]))])));
+    }
+
+    #[test]
+    fn round_trip_code_block() {
+        round_trip(doc(json!([
+            {"type": "codeBlock", "language": "python", "content": [text("def f(x):\n    return x * 2\n\n```\nprint(f(21))")]},
+            {"type": "codeBlock", "language": "plain", "content": [text("# kein Titel")]},
+            {"type": "codeBlock", "language": "rust", "content": []},
+        ])));
+    }
+
+    #[test]
+    fn round_trip_lists() {
+        round_trip(doc(json!([
+            {"type": "bulletList", "content": [
+                {"type": "listItem", "content": [paragraph(json!([text("Erster Punkt")]))]},
+                {"type": "listItem", "content": [
+                    paragraph(json!([text("Zweiter Punkt")])),
+                    {"type": "orderedList", "content": [
+                        {"type": "listItem", "content": [paragraph(json!([text("Eins")]))]},
+                        {"type": "listItem", "content": [
+                            paragraph(json!([text("Zwei")])),
+                            paragraph(json!([text("Mit zweitem Absatz")])),
+                        ]},
+                    ]},
+                ]},
+            ]},
+            {"type": "bulletList", "content": [
+                {"type": "listItem", "content": [paragraph(json!([text("Neue Liste")]))]},
+            ]},
+        ])));
+    }
+
+    #[test]
+    fn round_trip_multiple_choice() {
+        round_trip(doc(json!([
+            paragraph(json!([text("Welche Zahlen sind gerade?")])),
+            {"type": "multipleChoice", "content": [
+                {"type": "multipleChoiceAnswer", "solution": true, "answer": false, "content": [paragraph(json!([text("2")]))]},
+                {"type": "multipleChoiceAnswer", "solution": false, "answer": false, "content": [paragraph(json!([text("3")]))]},
+                {"type": "multipleChoiceAnswer", "solution": true, "answer": false, "content": [paragraph(json!([
+                    marked("x^2", json!([{"type": "latex", "source": "x^2"}])),
+                    text(" für gerades x"),
+                ]))]},
+            ]},
+            {"type": "multipleChoice", "content": [
+                {"type": "multipleChoiceAnswer", "solution": false, "answer": false, "content": [paragraph(json!([text("Zweite Frage")]))]},
+            ]},
+            {"type": "bulletList", "content": [
+                {"type": "listItem", "content": [paragraph(json!([text("[ ] keine Antwort")]))]},
+            ]},
+        ])));
+    }
+
+    #[test]
+    fn round_trip_choice_modes_and_scoring() {
+        let answer = |solution: bool, answer: &str| {
+            json!(
+                {"type": "multipleChoiceAnswer", "solution": solution, "answer": false, "content": [paragraph(json!([text(answer)]))]}
+            )
+        };
+        round_trip(doc(json!([
+            {"type": "multipleChoice", "mode": "single", "content": [answer(false, "Bonn"), answer(true, "Berlin")]},
+            {"type": "multipleChoice", "scoring": "partialCredit", "content": [answer(true, "2"), answer(true, "4")]},
+            {"type": "multipleChoice", "scoring": "negativeMarking", "shuffle": true, "content": [answer(true, "2"), answer(false, "3")]},
+            {"type": "multipleChoice", "mode": "single", "shuffle": true, "content": [answer(true, "2"), answer(false, "3")]},
+            {"type": "multipleChoice", "content": [answer(false, "5")]},
+        ])));
+        let document = from_markdown("<!-- scoring: partialCredit -->\n\nText\n\n- [x] 2");
+        assert_eq!(
+            document.content.content[1].kind,
+            NodeKind::MultipleChoice {
+                mode: ChoiceMode::Multiple,
+                scoring: Scoring::AllOrNothing,
+                shuffle: false,
+            }
+        );
+    }
+
+    #[test]
+    fn round_trip_pools() {
+        let item = |content: serde_json::Value| json!({"type": "poolItem", "content": content});
+        round_trip(doc(json!([
+            paragraph(json!([text("Vorher")])),
+            {"type": "pool", "draw": 2, "content": [
+                item(json!([paragraph(json!([text("Erste Aufgabe")]))])),
+                item(json!([
+                    paragraph(json!([text("Zweite Aufgabe")])),
+                    {"type": "pool", "draw": 1, "content": [
+                        item(json!([paragraph(json!([text("a")]))])),
+                        item(json!([paragraph(json!([text("b")]))])),
+                    ]},
+                ])),
+                item(json!([{"type": "bulletList", "content": [
+                    {"type": "listItem", "content": [paragraph(json!([text("Liste")]))]},
+                ]}])),
+            ]},
+            {"type": "bulletList", "content": [
+                {"type": "listItem", "content": [paragraph(json!([text("Danach")]))]},
+            ]},
+        ])));
+    }
+
+    #[test]
+    fn round_trip_variables() {
+        round_trip(doc(json!([
+            {"type": "variables", "variables": [
+                {"name": "a", "definition": "1..10"},
+                {"name": "b", "definition": "[2, 3.5]"},
+                {"name": "c", "definition": "a * b"},
+            ]},
+            paragraph(json!([
+                text("{{a}} · {{b}} = "),
+                marked("{{c}}", json!([{"type": "numeric", "solution": "{{c}}", "answer": ""}])),
+            ])),
+        ])));
+    }
+
+    #[test]
+    fn round_trip_essays() {
+        round_trip(doc(json!([
+            paragraph(json!([text("Warum gibt es Tag und Nacht?")])),
+            {"type": "essay", "points": 2, "minWords": 20, "maxWords": 80,
+                "modelAnswer": "Die Erde dreht sich.\n\nDadurch ist immer eine Seite der Sonne zugewandt.",
+                "answer": ""},
+            {"type": "essay", "points": 1, "maxWords": 30, "modelAnswer": "", "answer": ""},
+        ])));
+    }
+
+    #[test]
+    fn round_trip_quote_rule_and_audio() {
+        round_trip(doc(json!([
+            {"type": "blockquote", "content": [
+                paragraph(json!([text("Ein Zitat.")])),
+                paragraph(json!([text("Mit zwei Absätzen.")])),
+            ]},
+            {"type": "horizontalRule"},
+            {"type": "audio", "source": "/media/abc\"def&.mp3", "mimetype": "audio/mpeg"},
+        ])));
+    }
+
+    #[test]
+    fn read_common_markdown() {
+        let document = from_markdown(
+            "# Aufgabe\n\nEin Absatz\nüber zwei Zeilen mit __Unterstrichen__ und 2 * 3.\n\n* eins\n* zwei\n\n1) erstens\n2) zweitens\n\n- [X] richtig\n- [ ] falsch\n\n```\ncode\n```\n",
+        );
+        assert_eq!(document.title, None);
+        let expected = Node::from_json(&doc(json!([
+            {"type": "heading", "level": 1, "content": [text("Aufgabe")]},
+            paragraph(json!([text("Ein Absatz über zwei Zeilen mit __Unterstrichen__ und 2 * 3.")])),
+            {"type": "bulletList", "content": [
+                {"type": "listItem", "content": [paragraph(json!([text("eins")]))]},
+                {"type": "listItem", "content": [paragraph(json!([text("zwei")]))]},
+            ]},
+            {"type": "orderedList", "content": [
+                {"type": "listItem", "content": [paragraph(json!([text("erstens")]))]},
+                {"type": "listItem", "content": [paragraph(json!([text("zweitens")]))]},
+            ]},
+            {"type": "multipleChoice", "content": [
+                {"type": "multipleChoiceAnswer", "solution": true, "answer": false, "content": [paragraph(json!([text("richtig")]))]},
+                {"type": "multipleChoiceAnswer", "solution": false, "answer": false, "content": [paragraph(json!([text("falsch")]))]},
+            ]},
+            {"type": "codeBlock", "language": "plain", "content": [text("code")]},
+        ])));
+        assert_eq!(document.content, expected);
+    }
+
+    #[test]
+    fn read_front_matter() {
+        let document = from_markdown("---\ntitle: Brüche\nauthor: someone\n---\n\nText\n");
+        assert_eq!(document.title.as_deref(), Some("Brüche"));
+        let document = from_markdown("---\n\nText\n\n---\n");
+        assert_eq!(document.title, None);
+        assert_eq!(document.content.content.len(), 3);
+    }
+
+    #[test]
+    fn read_empty_document() {
+        let document = from_markdown("");
+        assert_eq!(
+            document.content,
+            Node::new(
+                NodeKind::Doc,
+                vec![Node::new(NodeKind::Paragraph, Vec::new())]
+            )
+        );
+    }
+}
@@ -0,0 +1,845 @@
+//! Conversion of LaTeX formulas to MathML, shaped like the MathML-only output of KaTeX.
+//!
+//! Only the commonly used subset of LaTeX is supported. Like KaTeX with `throwOnError: false`,
+//! formulas that cannot be parsed are shown as red source text.
+
+use std::fmt::{self, Display};
+
+use super::html::escape;
+
+const MATH_NAMESPACE: &str = "http://www.w3.org/1998/Math/MathML";
+
+/// Deeper nesting is rejected instead of overflowing the stack
+const MAX_DEPTH: usize = 64;
+
+/// Renders an inline formula
+pub fn render(source: &str) -> String {
+    match Parser::new(source).formula() {
+        Ok(rows) => format!(
+            "<span class=\"katex\"><math xmlns=\"{}\"><semantics>{}<annotation encoding=\"application/x-tex\">{}</annotation></semantics></math></span>",
+            MATH_NAMESPACE,
+            row(rows),
+            escape(source)
+        ),
+        Err(message) => format!(
+            "<span class=\"katex-error\" title=\"KaTeX parse error: {}\" style=\"color:#cc0000\">{}</span>",
+            escape(&message),
+            escape(source)
+        ),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Command(String),
+    Char(char),
+    Space,
+    Open,
+    Close,
+    Superscript,
+    Subscript,
+    Prime,
+    Align,
+}
+
+impl Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Command(name) => write!(f, "'\\{}'", name),
+            Self::Char(c) => write!(f, "'{}'", c),
+            Self::Space => write!(f, "' '"),
+            Self::Open => write!(f, "'{{'"),
+            Self::Close => write!(f, "'}}'"),
+            Self::Superscript => write!(f, "'^'"),
+            Self::Subscript => write!(f, "'_'"),
+            Self::Prime => write!(f, "'\''"),
+            Self::Align => write!(f, "'&'"),
+        }
+    }
+}
+
+fn tokenize(source: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut chars = source.chars().peekable();
+    while let Some(c) = chars.next() {
+        tokens.push(match c {
+            '\\' => {
+                let mut name = String::new();
+                while let Some(&next) = chars.peek() {
+                    if !next.is_ascii_alphabetic() {
+                        break;
+                    }
+                    name.push(next);
+                    chars.next();
+                }
+                if name.is_empty() {
+                    if let Some(next) = chars.next() {
+                        name.push(next);
+                    }
+                }
+                Token::Command(name)
+            }
+            '{' => Token::Open,
+            '}' => Token::Close,
+            '^' => Token::Superscript,
+            '_' => Token::Subscript,
+            '\'' => Token::Prime,
+            '&' => Token::Align,
+            c if c.is_whitespace() => Token::Space,
+            c => Token::Char(c),
+        });
+    }
+    tokens
+}
+
+fn row(mut items: Vec<String>) -> String {
+    if items.len() == 1 {
+        items.remove(0)
+    } else {
+        format!("<mrow>{}</mrow>", items.concat())
+    }
+}
+
+fn operator(symbol: &str) -> String {
+    format!("<mo>{}</mo>", escape(symbol))
+}
+
+fn fence(symbol: &str) -> String {
+    if symbol.is_empty() {
+        String::new()
+    } else {
+        format!("<mo fence=\"true\">{}</mo>", escape(symbol))
+    }
+}
+
+fn space(width: &str) -> String {
+    format!("<mspace width=\"{}\"></mspace>", width)
+}
+
+enum Symbol {
+    Identifier(&'static str),
+    /// Identifiers which are upright although they are single letters
+    Upright(&'static str),
+    Operator(&'static str),
+}
+
+fn symbol(name: &str) -> Option<Symbol> {
+    use Symbol::*;
+    Some(match name {
+        "alpha" => Identifier("α"),
+        "beta" => Identifier("β"),
+        "gamma" => Identifier("γ"),
+        "delta" => Identifier("δ"),
+        "epsilon" => Identifier("ϵ"),
+        "varepsilon" => Identifier("ε"),
+        "zeta" => Identifier("ζ"),
+        "eta" => Identifier("η"),
+        "theta" => Identifier("θ"),
+        "vartheta" => Identifier("ϑ"),
+        "iota" => Identifier("ι"),
+        "kappa" => Identifier("κ"),
+        "lambda" => Identifier("λ"),
+        "mu" => Identifier("μ"),
+        "nu" => Identifier("ν"),
+        "xi" => Identifier("ξ"),
+        "pi" => Identifier("π"),
+        "varpi" => Identifier("ϖ"),
+        "rho" => Identifier("ρ"),
+        "varrho" => Identifier("ϱ"),
+        "sigma" => Identifier("σ"),
+        "varsigma" => Identifier("ς"),
+        "tau" => Identifier("τ"),
+        "upsilon" => Identifier("υ"),
+        "phi" => Identifier("ϕ"),
+        "varphi" => Identifier("φ"),
+        "chi" => Identifier("χ"),
+        "psi" => Identifier("ψ"),
+        "omega" => Identifier("ω"),
+        "Gamma" => Upright("Γ"),
+        "Delta" => Upright("Δ"),
+        "Theta" => Upright("Θ"),
+        "Lambda" => Upright("Λ"),
+        "Xi" => Upright("Ξ"),
+        "Pi" => Upright("Π"),
+        "Sigma" => Upright("Σ"),
+        "Upsilon" => Upright("Υ"),
+        "Phi" => Upright("Φ"),
+        "Psi" => Upright("Ψ"),
+        "Omega" => Upright("Ω"),
+        "infty" => Identifier("∞"),
+        "partial" => Identifier("∂"),
+        "nabla" => Upright("∇"),
+        "emptyset" | "varnothing" => Upright("∅"),
+        "hbar" => Identifier("ℏ"),
+        "ell" => Identifier("ℓ"),
+        "%" => Upright("%"),
+        "$" => Upright("$"),
+        "#" => Upright("#"),
+        "_" => Upright("_"),
+        "cdot" => Operator("⋅"),
+        "times" => Operator("×"),
+        "div" => Operator("÷"),
+        "pm" => Operator("±"),
+        "mp" => Operator("∓"),
+        "ast" => Operator("∗"),
+        "star" => Operator("⋆"),
+        "circ" => Operator("∘"),
+        "bullet" => Operator("∙"),
+        "leq" | "le" => Operator("≤"),
+        "geq" | "ge" => Operator("≥"),
+        "neq" | "ne" => Operator("≠"),
+        "ll" => Operator("≪"),
+        "gg" => Operator("≫"),
+        "approx" => Operator("≈"),
+        "equiv" => Operator("≡"),
+        "sim" => Operator("∼"),
+        "simeq" => Operator("≃"),
+        "cong" => Operator("≅"),
+        "propto" => Operator("∝"),
+        "to" | "rightarrow" => Operator("→"),
+        "leftarrow" | "gets" => Operator("←"),
+        "leftrightarrow" => Operator("↔"),
+        "Rightarrow" | "implies" => Operator("⇒"),
+        "Leftarrow" => Operator("⇐"),
+        "Leftrightarrow" | "iff" => Operator("⇔"),
+        "mapsto" => Operator("↦"),
+        "in" => Operator("∈"),
+        "notin" => Operator("∉"),
+        "ni" => Operator("∋"),
+        "subset" => Operator("⊂"),
+        "subseteq" => Operator("⊆"),
+        "supset" => Operator("⊃"),
+        "supseteq" => Operator("⊇"),
+        "cup" => Operator("∪"),
+        "cap" => Operator("∩"),
+        "setminus" => Operator("∖"),
+        "forall" => Operator("∀"),
+        "exists" => Operator("∃"),
+        "neg" | "lnot" => Operator("¬"),
+        "land" | "wedge" => Operator("∧"),
+        "lor" | "vee" => Operator("∨"),
+        "oplus" => Operator("⊕"),
+        "otimes" => Operator("⊗"),
+        "perp" => Operator("⊥"),
+        "parallel" => Operator("∥"),
+        "angle" => Operator("∠"),
+        "mid" => Operator("∣"),
+        "ldots" | "dots" => Operator("…"),
+        "cdots" => Operator("⋯"),
+        "vdots" => Operator("⋮"),
+        "ddots" => Operator("⋱"),
+        "sum" => Operator("∑"),
+        "prod" => Operator("∏"),
+        "coprod" => Operator("∐"),
+        "int" => Operator("∫"),
+        "iint" => Operator("∬"),
+        "oint" => Operator("∮"),
+        "bigcup" => Operator("⋃"),
+        "bigcap" => Operator("⋂"),
+        "{" | "lbrace" => Operator("{"),
+        "}" | "rbrace" => Operator("}"),
+        "|" | "Vert" => Operator("∥"),
+        "vert" | "lvert" | "rvert" => Operator("∣"),
+        "langle" => Operator("⟨"),
+        "rangle" => Operator("⟩"),
+        "lfloor" => Operator("⌊"),
+        "rfloor" => Operator("⌋"),
+        "lceil" => Operator("⌈"),
+        "rceil" => Operator("⌉"),
+        _ => return None,
+    })
+}
+
+const FUNCTIONS: [&str; 30] = [
+    "arccos", "arcsin", "arctan", "arg", "cos", "cosh", "cot", "coth", "csc", "deg", "det", "dim",
+    "exp", "gcd", "inf", "ker", "lg", "lim", "liminf", "limsup", "ln", "log", "max", "min", "Pr",
+    "sec", "sin", "sinh", "sup", "tan",
+];
+
+fn delimiter(token: &Token) -> Option<&'static str> {
+    Some(match token {
+        Token::Char('.') => "",
+        Token::Char('(') => "(",
+        Token::Char(')') => ")",
+        Token::Char('[') => "[",
+        Token::Char(']') => "]",
+        Token::Char('|') => "∣",
+        Token::Char('/') => "/",
+        Token::Char('<') => "⟨",
+        Token::Char('>') => "⟩",
+        Token::Command(name) => match symbol(name) {
+            Some(Symbol::Operator(op)) if "{}∥∣⟨⟩⌊⌋⌈⌉".contains(op) => op,
+            _ => return None,
+        },
+        _ => return None,
+    })
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+    variant: Option<&'static str>,
+    depth: usize,
+}
+
+type ParseResult<T> = Result<T, String>;
+
+impl Parser {
+    fn new(source: &str) -> Self {
+        Self {
+            tokens: tokenize(source),
+            position: 0,
+            variant: None,
+            depth: 0,
+        }
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn skip_spaces(&mut self) {
+        while self.peek() == Some(&Token::Space) {
+            self.position += 1;
+        }
+    }
+
+    fn expect(&mut self, expected: Token) -> ParseResult<()> {
+        self.skip_spaces();
+        match self.next() {
+            Some(token) if token == expected => Ok(()),
+            Some(token) => Err(format!("Expected {}, got {}", expected, token)),
+            None => Err(format!("Expected {}, got end of input", expected)),
+        }
+    }
+
+    fn formula(&mut self) -> ParseResult<Vec<String>> {
+        let items = self.expression()?;
+        match self.next() {
+            None => Ok(items),
+            Some(token) => Err(format!("Unexpected {}", token)),
+        }
+    }
+
+    /// Parses atoms until the end of the current group, row or cell
+    fn expression(&mut self) -> ParseResult<Vec<String>> {
+        let mut items = Vec::new();
+        loop {
+            self.skip_spaces();
+            match self.peek() {
+                None | Some(Token::Close) | Some(Token::Align) => break,
+                Some(Token::Command(name)) if name == "right" || name == "end" || name == "\\" => {
+                    break
+                }
+                _ => {}
+            }
+            if let Some(item) = self.scripted()? {
+                items.push(item);
+            }
+        }
+        Ok(items)
+    }
+
+    fn scripted(&mut self) -> ParseResult<Option<String>> {
+        let base = match self.peek() {
+            Some(Token::Superscript) | Some(Token::Subscript) | Some(Token::Prime) => {
+                Some("<mrow></mrow>".to_string())
+            }
+            _ => self.atom(false)?,
+        };
+        let base = match base {
+            Some(base) => base,
+            None => return Ok(None),
+        };
+
+        let mut superscript: Option<String> = None;
+        let mut subscript: Option<String> = None;
+        let mut primes = String::new();
+        loop {
+            self.skip_spaces();
+            match self.peek() {
+                Some(Token::Superscript) => {
+                    self.position += 1;
+                    if superscript.is_some() {
+                        return Err("Double superscript".to_string());
+                    }
+                    superscript = Some(self.argument()?);
+                }
+                Some(Token::Subscript) => {
+                    self.position += 1;
+                    if subscript.is_some() {
+                        return Err("Double subscript".to_string());
+                    }
+                    subscript = Some(self.argument()?);
+                }
+                Some(Token::Prime) if superscript.is_none() => {
+                    self.position += 1;
+                    primes.push('′');
+                }
+                _ => break,
+            }
+        }
+        if !primes.is_empty() {
+            let primes = operator(&primes);
+            superscript = Some(match superscript {
+                Some(superscript) => format!("<mrow>{}{}</mrow>", primes, superscript),
+                None => primes,
+            });
+        }
+        Ok(Some(match (subscript, superscript) {
+            (None, None) => base,
+            (Some(sub), None) => format!("<msub>{}{}</msub>", base, sub),
+            (None, Some(sup)) => format!("<msup>{}{}</msup>", base, sup),
+            (Some(sub), Some(sup)) => format!("<msubsup>{}{}{}</msubsup>", base, sub, sup),
+        }))
+    }
+
+    /// Parses the argument of a command or script, which is a group or a single token
+    fn argument(&mut self) -> ParseResult<String> {
+        self.skip_spaces();
+        match self.atom(true)? {
+            Some(argument) => Ok(argument),
+            None => Err("Expected an argument".to_string()),
+        }
+    }
+
+    fn group(&mut self) -> ParseResult<String> {
+        let items = self.expression()?;
+        self.expect(Token::Close)?;
+        Ok(row(items))
+    }
+
+    fn identifier(&self, text: &str) -> String {
+        match self.variant {
+            Some(variant) => format!("<mi mathvariant=\"{}\">{}</mi>", variant, escape(text)),
+            None => format!("<mi>{}</mi>", escape(text)),
+        }
+    }
+
+    fn number(&self, text: &str) -> String {
+        match self.variant {
+            Some(variant) if variant != "normal" => {
+                format!("<mn mathvariant=\"{}\">{}</mn>", variant, escape(text))
+            }
+            _ => format!("<mn>{}</mn>", escape(text)),
+        }
+    }
+
+    /// Parses a single atom, `single` limits numbers to one digit like in `x^23`
+    fn atom(&mut self, single: bool) -> ParseResult<Option<String>> {
+        if self.depth == MAX_DEPTH {
+            return Err("Formula is nested too deeply".to_string());
+        }
+        self.depth += 1;
+        let atom = self.nested_atom(single);
+        self.depth -= 1;
+        atom
+    }
+
+    /// The next character if it continues a number, which a decimal point only does before a digit
+    fn number_char(&self) -> Option<char> {
+        match (self.peek(), self.tokens.get(self.position + 1)) {
+            (Some(Token::Char(d)), _) if d.is_ascii_digit() => Some(*d),
+            (Some(Token::Char('.')), Some(Token::Char(d))) if d.is_ascii_digit() => Some('.'),
+            _ => None,
+        }
+    }
+
+    fn nested_atom(&mut self, single: bool) -> ParseResult<Option<String>> {
+        let token = match self.next() {
+            Some(token) => token,
+            None => return Err("Unexpected end of input".to_string()),
+        };
+        Ok(Some(match token {
+            Token::Open => self.group()?,
+            Token::Char(c) if c.is_alphabetic() => self.identifier(&c.to_string()),
+            Token::Char(c) if c.is_ascii_digit() => {
+                let mut number = c.to_string();
+                if !single {
+                    while let Some(d) = self.number_char() {
+                        number.push(d);
+                        self.position += 1;
+                    }
+                }
+                self.number(&number)
+            }
+            Token::Char('-') => operator("−"),
+            Token::Char('*') => operator("∗"),
+            Token::Char('~') => "<mtext>\u{a0}</mtext>".to_string(),
+            Token::Char(c) => operator(&c.to_string()),
+            Token::Prime => operator("′"),
+            Token::Command(name) => return self.command(&name),
+            token => return Err(format!("Unexpected {}", token)),
+        }))
+    }
+
+    fn command(&mut self, name: &str) -> ParseResult<Option<String>> {
+        if let Some(symbol) = symbol(name) {
+            return Ok(Some(match symbol {
+                Symbol::Identifier(text) => self.identifier(text),
+                Symbol::Upright(text) => format!("<mi mathvariant=\"normal\">{}</mi>", text),
+                Symbol::Operator(text) => operator(text),
+            }));
+        }
+        if FUNCTIONS.contains(&name) {
+            return Ok(Some(format!("<mi>{}</mi><mo>\u{2061}</mo>", name)));
+        }
+        Ok(Some(match name {
+            "," | "thinspace" => space("0.1667em"),
+            ":" | ">" | "medspace" => space("0.2222em"),
+            ";" | "thickspace" => space("0.2778em"),
+            "!" => space("-0.1667em"),
+            "quad" => space("1em"),
+            "qquad" => space("2em"),
+            " " => "<mtext>\u{a0}</mtext>".to_string(),
+            "frac" | "dfrac" | "tfrac" => {
+                let numerator = self.argument()?;
+                let denominator = self.argument()?;
+                format!("<mfrac>{}{}</mfrac>", numerator, denominator)
+            }
+            "binom" => {
+                let top = self.argument()?;
+                let bottom = self.argument()?;
+                format!(
+                    "<mrow>{}<mfrac linethickness=\"0px\">{}{}</mfrac>{}</mrow>",
+                    fence("("),
+                    top,
+                    bottom,
+                    fence(")")
+                )
+            }
+            "sqrt" => {
+                self.skip_spaces();
+                if self.peek() == Some(&Token::Char('[')) {
+                    self.position += 1;
+                    let mut index = Vec::new();
+                    loop {
+                        self.skip_spaces();
+                        match self.peek() {
+                            Some(Token::Char(']')) => break,
+                            None => return Err("Expected ']'".to_string()),
+                            _ => {}
+                        }
+                        if let Some(item) = self.scripted()? {
+                            index.push(item);
+                        }
+                    }
+                    self.position += 1;
+                    let radicand = self.argument()?;
+                    format!("<mroot>{}{}</mroot>", radicand, row(index))
+                } else {
+                    format!("<msqrt>{}</msqrt>", self.argument()?)
+                }
+            }
+            "left" => {
+                let open = self.delimiter()?;
+                let items = self.expression()?;
+                match self.next() {
+                    Some(Token::Command(name)) if name == "right" => {}
+                    _ => return Err("Expected \\right".to_string()),
+                }
+                let close = self.delimiter()?;
+                format!(
+                    "<mrow>{}{}{}</mrow>",
+                    fence(open),
+                    items.concat(),
+                    fence(close)
+                )
+            }
+            "right" => return Err("Unexpected \\right".to_string()),
+            "big" | "Big" | "bigg" | "Bigg" | "bigl" | "bigr" | "Bigl" | "Bigr" | "biggl"
+            | "biggr" | "Biggl" | "Biggr" => {
+                let size = match name.trim_end_matches(|c| c == 'l' || c == 'r') {
+                    "big" => "1.2em",
+                    "Big" => "1.8em",
+                    "bigg" => "2.4em",
+                    _ => "3em",
+                };
+                format!(
+                    "<mo fence=\"false\" stretchy=\"true\" minsize=\"{0}\" maxsize=\"{0}\">{1}</mo>",
+                    size,
+                    escape(self.delimiter()?)
+                )
+            }
+            "text" | "textrm" | "textnormal" | "mbox" => {
+                format!("<mtext>{}</mtext>", escape(&self.text()?))
+            }
+            "textbf" => format!(
+                "<mtext mathvariant=\"bold\">{}</mtext>",
+                escape(&self.text()?)
+            ),
+            "textit" => format!(
+                "<mtext mathvariant=\"italic\">{}</mtext>",
+                escape(&self.text()?)
+            ),
+            "operatorname" => format!("<mi>{}</mi><mo>\u{2061}</mo>", escape(self.text()?.trim())),
+            "mathrm" | "rm" => self.styled("normal")?,
+            "mathbf" | "bf" | "boldsymbol" => self.styled("bold")?,
+            "mathit" => self.styled("italic")?,
+            "mathbb" => self.styled("double-struck")?,
+            "mathcal" => self.styled("script")?,
+            "mathfrak" => self.styled("fraktur")?,
+            "mathsf" => self.styled("sans-serif")?,
+            "mathtt" => self.styled("monospace")?,
+            "vec" => self.accent("⃗")?,
+            "hat" => self.accent("^")?,
+            "widehat" => self.accent("^")?,
+            "bar" => self.accent("ˉ")?,
+            "dot" => self.accent("˙")?,
+            "ddot" => self.accent("¨")?,
+            "tilde" | "widetilde" => self.accent("~")?,
+            "overline" => format!(
+                "<mover accent=\"true\">{}<mo stretchy=\"true\">‾</mo></mover>",
+                self.argument()?
+            ),
+            "underline" => format!(
+                "<munder accentunder=\"true\">{}<mo stretchy=\"true\">‾</mo></munder>",
+                self.argument()?
+            ),
+            "overrightarrow" => self.accent("→")?,
+            "begin" => self.environment()?,
+            "displaystyle" | "textstyle" | "limits" | "nolimits" => return Ok(None),
+            _ => return Err(format!("Undefined control sequence: \\{}", name)),
+        }))
+    }
+
+    fn delimiter(&mut self) -> ParseResult<&'static str> {
+        self.skip_spaces();
+        match self.next() {
+            Some(token) => delimiter(&token).ok_or_else(|| format!("Invalid delimiter {}", token)),
+            None => Err("Expected a delimiter".to_string()),
+        }
+    }
+
+    fn styled(&mut self, variant: &'static str) -> ParseResult<String> {
+        let outer = self.variant.replace(variant);
+        let argument = self.argument();
+        self.variant = outer;
+        argument
+    }
+
+    fn accent(&mut self, accent: &str) -> ParseResult<String> {
+        Ok(format!(
+            "<mover accent=\"true\">{}<mo>{}</mo></mover>",
+            self.argument()?,
+            accent
+        ))
+    }
+
+    /// Reads the raw text of a group argument, used for text and environment names
+    fn text(&mut self) -> ParseResult<String> {
+        self.expect(Token::Open)?;
+        let mut text = String::new();
+        let mut depth = 0;
+        loop {
+            match self.next() {
+                None => return Err("Expected '}'".to_string()),
+                Some(Token::Close) if depth == 0 => break,
+                Some(Token::Close) => depth -= 1,
+                Some(Token::Open) => depth += 1,
+                Some(Token::Char(c)) => text.push(c),
+                Some(Token::Space) => text.push('\u{a0}'),
+                Some(Token::Superscript) => text.push('^'),
+                Some(Token::Subscript) => text.push('_'),
+                Some(Token::Prime) => text.push('\''),
+                Some(Token::Align) => text.push('&'),
+                Some(Token::Command(name)) => match name.as_str() {
+                    " " | "," | ";" | ":" | "quad" => text.push('\u{a0}'),
+                    name if name.chars().all(|c| !c.is_alphabetic()) => text.push_str(name),
+                    name => return Err(format!("Undefined control sequence: \\{}", name)),
+                },
+            }
+        }
+        Ok(text)
+    }
+
+    fn environment(&mut self) -> ParseResult<String> {
+        let name = self.text()?.replace('\u{a0}', "");
+        let (open, close, align) = match name.as_str() {
+            "matrix" | "smallmatrix" => ("", "", "center"),
+            "pmatrix" => ("(", ")", "center"),
+            "bmatrix" => ("[", "]", "center"),
+            "Bmatrix" => ("{", "}", "center"),
+            "vmatrix" => ("∣", "∣", "center"),
+            "Vmatrix" => ("∥", "∥", "center"),
+            "cases" => ("{", "", "left"),
+            "aligned" | "align" | "align*" | "gathered" => ("", "", "right left"),
+            _ => return Err(format!("No such environment: {}", name)),
+        };
+
+        let mut rows: Vec<Vec<String>> = Vec::new();
+        loop {
+            let mut cells = Vec::new();
+            let end = loop {
+                cells.push(row(self.expression()?));
+                match self.next() {
+                    Some(Token::Align) => continue,
+                    Some(Token::Command(command)) if command == "\\" => break false,
+                    Some(Token::Command(command)) if command == "end" => break true,
+                    _ => return Err(format!("Expected \\end{{{}}}", name)),
+                }
+            };
+            // A trailing line break does not start another row
+            if !(end && cells.len() == 1 && cells[0] == "<mrow></mrow>") {
+                rows.push(cells);
+            }
+            if end {
+                break;
+            }
+        }
+        if self.text()?.replace('\u{a0}', "") != name {
+            return Err(format!("Mismatched \\end for {}", name));
+        }
+
+        let table = rows
+            .into_iter()
+            .map(|cells| {
+                let cells: Vec<String> = cells
+                    .into_iter()
+                    .map(|cell| format!("<mtd>{}</mtd>", cell))
+                    .collect();
+                format!("<mtr>{}</mtr>", cells.concat())
+            })
+            .collect::<Vec<_>>()
+            .concat();
+        Ok(format!(
+            "<mrow>{}<mtable rowspacing=\"0.16em\" columnalign=\"{}\">{}</mtable>{}</mrow>",
+            fence(open),
+            align,
+            table,
+            fence(close)
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The MathML of a formula without the surrounding elements
+    fn mathml(source: &str) -> String {
+        let rendered = render(source);
+        let start = rendered.find("<semantics>").expect("formula is valid") + "<semantics>".len();
+        let end = rendered.find("<annotation").expect("formula is valid");
+        rendered[start..end].to_string()
+    }
+
+    fn is_error(source: &str) -> bool {
+        render(source).starts_with("<span class=\"katex-error\"")
+    }
+
+    #[test]
+    fn renders_common_formulas() {
+        assert_eq!(mathml("x"), "<mi>x</mi>");
+        assert_eq!(
+            mathml("x^2 + 1.5"),
+            "<mrow><msup><mi>x</mi><mn>2</mn></msup><mo>+</mo><mn>1.5</mn></mrow>"
+        );
+        assert_eq!(
+            mathml("x^23"),
+            "<mrow><msup><mi>x</mi><mn>2</mn></msup><mn>3</mn></mrow>"
+        );
+        assert_eq!(
+            mathml("\\frac{1}{2}"),
+            "<mfrac><mn>1</mn><mn>2</mn></mfrac>"
+        );
+        assert_eq!(
+            mathml("\\sqrt[3]{a_i}"),
+            "<mroot><msub><mi>a</mi><mi>i</mi></msub><mn>3</mn></mroot>"
+        );
+        assert_eq!(
+            mathml("f'(x)"),
+            "<mrow><msup><mi>f</mi><mo>′</mo></msup><mo>(</mo><mi>x</mi><mo>)</mo></mrow>"
+        );
+        assert_eq!(
+            mathml("\\sin \\alpha \\leq \\text{ein Wert}"),
+            "<mrow><mi>sin</mi><mo>\u{2061}</mo><mi>α</mi><mo>≤</mo><mtext>ein\u{a0}Wert</mtext></mrow>"
+        );
+        assert_eq!(
+            mathml("\\mathbb{R} \\mathbf{v}"),
+            "<mrow><mi mathvariant=\"double-struck\">R</mi><mi mathvariant=\"bold\">v</mi></mrow>"
+        );
+        assert_eq!(
+            mathml("\\left( a \\right."),
+            "<mrow><mo fence=\"true\">(</mo><mi>a</mi></mrow>"
+        );
+    }
+
+    #[test]
+    fn renders_environments() {
+        assert_eq!(
+            mathml("\\begin{pmatrix} 1 & 0 \\\\ 0 & 1 \\\\ \\end{pmatrix}"),
+            concat!(
+                "<mrow><mo fence=\"true\">(</mo><mtable rowspacing=\"0.16em\" columnalign=\"center\">",
+                "<mtr><mtd><mn>1</mn></mtd><mtd><mn>0</mn></mtd></mtr>",
+                "<mtr><mtd><mn>0</mn></mtd><mtd><mn>1</mn></mtd></mtr>",
+                "</mtable><mo fence=\"true\">)</mo></mrow>"
+            )
+        );
+        assert!(!is_error(
+            "f(x) = \\begin{cases} 1 & x > 0 \\\\ 0 & \\text{sonst} \\end{cases}"
+        ));
+    }
+
+    #[test]
+    fn escapes_source_and_text() {
+        let rendered = render("a < b \\text{<b>}");
+        assert!(rendered.contains("<mo>&lt;</mo>"));
+        assert!(rendered.contains("<mtext>&lt;b&gt;</mtext>"));
+        assert!(rendered.contains(
+            "<annotation encoding=\"application/x-tex\">a &lt; b \\text{&lt;b&gt;}</annotation>"
+        ));
+    }
+
+    #[test]
+    fn shows_malformed_formulas_as_source() {
+        assert_eq!(
+            render("\\frac{1}{<2"),
+            "<span class=\"katex-error\" title=\"KaTeX parse error: Expected '}', got end of input\" style=\"color:#cc0000\">\\frac{1}{&lt;2</span>"
+        );
+        for source in [
+            "{",
+            "}",
+            "x^",
+            "x^2^3",
+            "x_1_2",
+            "\\",
+            "\\foo",
+            "\\frac{1}",
+            "\\sqrt[3",
+            "\\left( x",
+            "\\left\\foo x \\right)",
+            "x \\right)",
+            "\\begin{pmatrix} 1 \\end{bmatrix}",
+            "\\begin{unknown} \\end{unknown}",
+            "\\begin{matrix} 1",
+            "\\text{\\foo}",
+            "a & b",
+            "\\big",
+            "\\ä",
+        ] {
+            assert!(is_error(source), "{}", source);
+        }
+        for source in ["", " ", "\\displaystyle", "ä_ö", "''"] {
+            assert!(!is_error(source), "{}", source);
+        }
+    }
+
+    #[test]
+    fn rejects_deep_nesting() {
+        assert!(!is_error(&format!("{}x{}", "{".repeat(50), "}".repeat(50))));
+        assert!(is_error(&format!(
+            "{}x{}",
+            "{".repeat(10_000),
+            "}".repeat(10_000)
+        )));
+        assert!(is_error(&"\\sqrt".repeat(10_000)));
+        assert!(is_error(&"x^".repeat(10_000)));
+    }
+}
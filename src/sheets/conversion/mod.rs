@@ -0,0 +1,50 @@
+use std::fmt::{self, Display};
+
+pub mod answers;
+pub mod document;
+pub mod essay;
+pub mod expression;
+pub mod formula;
+pub mod grading;
+pub mod highlight;
+pub mod html;
+pub mod markdown;
+pub mod mathml;
+pub mod moodle;
+pub mod numeric;
+pub mod pdf;
+pub mod pool;
+pub mod qti;
+pub mod render;
+pub mod scorm;
+pub mod shuffle;
+pub mod variables;
+
+#[derive(Debug)]
+pub enum Error {
+    Pdf(lopdf::Error),
+    Xml(roxmltree::Error),
+    Malformed(&'static str),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Pdf(e) => write!(f, "Error writing PDF: {}", e),
+            Self::Xml(e) => write!(f, "Invalid XML: {}", e),
+            Self::Malformed(msg) => write!(f, "Malformed document: {}", msg),
+        }
+    }
+}
+
+impl From<lopdf::Error> for Error {
+    fn from(e: lopdf::Error) -> Self {
+        Self::Pdf(e)
+    }
+}
+
+impl From<roxmltree::Error> for Error {
+    fn from(e: roxmltree::Error) -> Self {
+        Self::Xml(e)
+    }
+}
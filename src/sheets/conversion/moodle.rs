@@ -0,0 +1,906 @@
+//! Conversion between sheets and Moodle XML question banks.
+//!
+//! A sheet is split into questions at its multiple choice blocks and essays: every
+//! `multipleChoice` node becomes a multichoice question and every `essay` node an essay question
+//! with the model answer as information for graders. The content in between becomes a cloze
+//! question whose gaps are short answer subquestions, or a description if it has no gaps. Formulas
+//! are short answers too, which only accept their solution as written.
+//!
+//! Pools become subcategories named like `Pool 1 (2 aus 5)` holding the questions of their items,
+//! from which a quiz can draw random questions. Moodle draws whole questions, so items should be a
+//! single question each, items split into several questions are named `Aufgabe 1, Teil 2` and so on
+//! and grouped into one item again on import.
+//!
+//! Variables are left out, Moodle has calculated questions of its own, so the expressions using
+//! them are exported as they are.
+//!
+//! Moodle sums the fractions of all ticked answers and never grants less than nothing, which is
+//! exactly partial credit or negative marking. All-or-nothing blocks deduct for wrong answers as
+//! well, which comes closest without a plugin question type.
+
+use roxmltree::Document;
+
+use super::document::{ChoiceMode, GapMatching, Mark, Node, NodeKind, Scoring};
+use super::html::{self, Dialect, Reader};
+use super::markdown;
+use super::numeric::NumericTask;
+use super::Error;
+
+#[derive(Debug)]
+pub struct MoodleImport {
+    pub title: Option<String>,
+    pub content: Node,
+    /// Questions and content that could not be converted
+    pub unsupported: Vec<String>,
+}
+
+struct PoolImport {
+    draw: usize,
+    items: Vec<(String, Vec<Node>)>,
+}
+
+impl PoolImport {
+    fn add(&mut self, name: &str, content: Vec<Node>) {
+        // Parts of one item share the name up to the part number
+        let item = name.split(", Teil ").next().unwrap_or(name);
+        match self.items.last_mut() {
+            Some((last, blocks)) if last == item => blocks.extend(content),
+            _ => self.items.push((item.to_string(), content)),
+        }
+    }
+
+    fn into_node(self) -> Option<Node> {
+        let items: Vec<Node> = self
+            .items
+            .into_iter()
+            .filter(|(_, content)| !content.is_empty())
+            .map(|(_, content)| Node::new(NodeKind::PoolItem, content))
+            .collect();
+        if items.is_empty() {
+            return None;
+        }
+        let draw = self.draw.clamp(1, items.len());
+        Some(Node::new(NodeKind::Pool { draw, drawn: None }, items))
+    }
+}
+
+enum Question<'a> {
+    Description(Vec<&'a Node>),
+    Cloze(Vec<&'a Node>),
+    MultipleChoice {
+        text: Vec<&'a Node>,
+        answers: &'a Node,
+    },
+    Essay {
+        text: Vec<&'a Node>,
+        essay: &'a Node,
+    },
+}
+
+// Export
+
+pub fn to_moodle_xml(title: &str, content: &Node) -> String {
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<quiz>\n");
+    let category = format!("$course$/top/{}", title.replace('/', "//"));
+    write_category(&category, &mut xml);
+    let mut number = 0;
+    let mut pools = 0;
+    let mut blocks: Vec<&Node> = Vec::new();
+    let mut write_questions = |blocks: &[&Node], xml: &mut String| {
+        for question in split_questions(blocks) {
+            number += 1;
+            write_question(&format!("{} - Frage {}", title, number), &question, xml);
+        }
+    };
+    for block in &content.content {
+        let draw = match block.kind {
+            NodeKind::Pool { draw, .. } => draw,
+            _ => {
+                blocks.push(block);
+                continue;
+            }
+        };
+        write_questions(&blocks, &mut xml);
+        blocks.clear();
+        pools += 1;
+        let pool = format!("Pool {} ({} aus {})", pools, draw, block.content.len());
+        write_category(&format!("{}/{}", category, pool), &mut xml);
+        for (i, item) in block.content.iter().enumerate() {
+            let questions = split_questions(&item.content.iter().collect::<Vec<_>>());
+            for (part, question) in questions.iter().enumerate() {
+                let mut name = format!("{} - {}, Aufgabe {}", title, pool, i + 1);
+                if questions.len() > 1 {
+                    name.push_str(&format!(", Teil {}", part + 1));
+                }
+                write_question(&name, question, &mut xml);
+            }
+        }
+        write_category(&category, &mut xml);
+    }
+    write_questions(&blocks, &mut xml);
+    xml.push_str("</quiz>\n");
+    xml
+}
+
+fn write_category(category: &str, xml: &mut String) {
+    xml.push_str(&format!(
+        "  <question type=\"category\">\n    <category>\n      <text>{}</text>\n    </category>\n  </question>\n",
+        html::escape(category)
+    ));
+}
+
+fn split_questions<'a>(blocks: &[&'a Node]) -> Vec<Question<'a>> {
+    let mut questions = Vec::new();
+    let mut pending: Vec<&Node> = Vec::new();
+    for &block in blocks {
+        if let NodeKind::Variables(_) = block.kind {
+            continue;
+        }
+        if let NodeKind::MultipleChoice { .. } | NodeKind::Essay { .. } = block.kind {
+            // Content after the last gap introduces the multiple choice or essay question
+            let split = pending
+                .iter()
+                .rposition(|node| has_gaps(node))
+                .map_or(0, |last| last + 1);
+            let text = pending.split_off(split);
+            if !pending.is_empty() {
+                questions.push(Question::Cloze(std::mem::take(&mut pending)));
+            }
+            questions.push(match block.kind {
+                NodeKind::Essay { .. } => Question::Essay { text, essay: block },
+                _ => Question::MultipleChoice {
+                    text,
+                    answers: block,
+                },
+            });
+        } else {
+            pending.push(block);
+        }
+    }
+    if pending.iter().any(|node| has_gaps(node)) {
+        questions.push(Question::Cloze(pending));
+    } else if !pending.is_empty() {
+        questions.push(Question::Description(pending));
+    }
+    questions
+}
+
+fn has_gaps(node: &Node) -> bool {
+    node.marks.iter().any(|m| {
+        matches!(
+            m,
+            Mark::Gap { .. } | Mark::Dropdown { .. } | Mark::Numeric { .. } | Mark::Formula { .. }
+        )
+    }) || node.content.iter().any(has_gaps)
+}
+
+fn write_question(name: &str, question: &Question, xml: &mut String) {
+    let (kind, text) = match question {
+        Question::Description(text) => ("description", write_html(text, &mut html::solution_text)),
+        Question::Cloze(text) => ("cloze", write_html(text, &mut cloze_gap)),
+        Question::MultipleChoice { text, .. } => {
+            ("multichoice", write_html(text, &mut html::solution_text))
+        }
+        Question::Essay { text, .. } => ("essay", write_html(text, &mut html::solution_text)),
+    };
+    xml.push_str(&format!("  <question type=\"{}\">\n", kind));
+    xml.push_str(&format!(
+        "    <name>\n      <text>{}</text>\n    </name>\n",
+        html::escape(name)
+    ));
+    xml.push_str(&format!(
+        "    <questiontext format=\"html\">\n      <text>{}</text>\n    </questiontext>\n",
+        cdata(&text)
+    ));
+    xml.push_str(
+        "    <generalfeedback format=\"html\">\n      <text></text>\n    </generalfeedback>\n",
+    );
+    if let Question::MultipleChoice { answers, .. } = question {
+        let correct = answers.content.iter().filter(|a| solution(a)).count();
+        let wrong = answers.content.len() - correct;
+        let (single, penalty) = match answers.kind {
+            NodeKind::MultipleChoice {
+                mode: ChoiceMode::Single,
+                ..
+            } => (true, false),
+            NodeKind::MultipleChoice {
+                scoring: Scoring::PartialCredit,
+                ..
+            } => (false, false),
+            _ => (false, true),
+        };
+        xml.push_str("    <defaultgrade>1</defaultgrade>\n");
+        xml.push_str(&format!("    <single>{}</single>\n", single));
+        let shuffle = matches!(answers.kind, NodeKind::MultipleChoice { shuffle: true, .. });
+        xml.push_str(&format!(
+            "    <shuffleanswers>{}</shuffleanswers>\n",
+            shuffle
+        ));
+        xml.push_str("    <answernumbering>none</answernumbering>\n");
+        for answer in &answers.content {
+            let fraction = match (solution(answer), penalty) {
+                (true, _) => fraction(100.0 / correct as f64),
+                (false, false) => "0".to_string(),
+                (false, true) => fraction(-100.0 / wrong as f64),
+            };
+            let text = write_html(
+                &answer.content.iter().collect::<Vec<_>>(),
+                &mut html::solution_text,
+            );
+            xml.push_str(&format!(
+                "    <answer fraction=\"{}\" format=\"html\">\n      <text>{}</text>\n      <feedback format=\"html\">\n        <text></text>\n      </feedback>\n    </answer>\n",
+                fraction,
+                cdata(&text)
+            ));
+        }
+    }
+    if let Question::Essay { essay, .. } = question {
+        if let NodeKind::Essay {
+            min_words,
+            max_words,
+            points,
+            model_answer,
+            ..
+        } = &essay.kind
+        {
+            xml.push_str(&format!("    <defaultgrade>{}</defaultgrade>\n", points));
+            xml.push_str("    <responseformat>editor</responseformat>\n");
+            xml.push_str("    <responserequired>1</responserequired>\n");
+            xml.push_str("    <responsefieldlines>15</responsefieldlines>\n");
+            xml.push_str(&format!(
+                "    <minwordlimit>{}</minwordlimit>\n",
+                min_words.unwrap_or(0)
+            ));
+            xml.push_str(&format!(
+                "    <maxwordlimit>{}</maxwordlimit>\n",
+                max_words.unwrap_or(0)
+            ));
+            xml.push_str("    <attachments>0</attachments>\n");
+            xml.push_str(&format!(
+                "    <graderinfo format=\"plain_text\">\n      <text>{}</text>\n    </graderinfo>\n",
+                cdata(model_answer)
+            ));
+        }
+    }
+    xml.push_str("  </question>\n");
+}
+
+fn solution(answer: &Node) -> bool {
+    matches!(
+        answer.kind,
+        NodeKind::MultipleChoiceAnswer { solution: true, .. }
+    )
+}
+
+fn write_html(nodes: &[&Node], gap: &mut dyn FnMut(&Mark) -> String) -> String {
+    let nodes: Vec<Node> = nodes.iter().map(|&node| node.clone()).collect();
+    html::write_blocks(&nodes, Dialect::Html, gap)
+}
+
+/// Moodle only accepts fractions from a fixed list, which are rounded to five decimals
+fn fraction(value: f64) -> String {
+    let formatted = format!("{:.5}", value);
+    formatted
+        .trim_end_matches('0')
+        .trim_end_matches('.')
+        .to_string()
+}
+
+fn cloze_gap(mark: &Mark) -> String {
+    let solution = match mark {
+        Mark::Gap {
+            solution, matching, ..
+        } => {
+            // Moodle only knows wildcards, so regular expressions are left out
+            let mut answers = format!("={}", cloze_escape(solution));
+            for alternative in matching
+                .alternatives
+                .iter()
+                .filter(|a| !a.regex && a.credit > 0.0)
+            {
+                answers.push_str(&format!(
+                    "~%{}%{}",
+                    fraction(alternative.credit * 100.0),
+                    cloze_escape(&alternative.answer)
+                ));
+            }
+            let kind = if matching.case_sensitive {
+                "SHORTANSWER_C"
+            } else {
+                "SHORTANSWER"
+            };
+            return format!("{{1:{}:{}}}", kind, answers);
+        }
+        Mark::Dropdown {
+            options, shuffle, ..
+        } => {
+            let answers: Vec<String> = options
+                .iter()
+                .map(|option| {
+                    let prefix = if option.correct { "%100%" } else { "" };
+                    format!("{}{}", prefix, cloze_escape(&option.text))
+                })
+                .collect();
+            let kind = if *shuffle {
+                "MULTICHOICE_S"
+            } else {
+                "MULTICHOICE"
+            };
+            return format!("{{1:{}:{}}}", kind, answers.join("~"));
+        }
+        Mark::Numeric { solution, .. } => match NumericTask::parse(solution) {
+            // Moodle has no units in cloze questions, so the value is expected without one
+            Some(task) => {
+                return format!(
+                    "{{1:NUMERICAL:={}:{}}}",
+                    task.value,
+                    task.absolute_tolerance()
+                )
+            }
+            None => solution,
+        },
+        // Cloze questions cannot check formulas for equivalence, only the solution as written
+        // is accepted
+        Mark::Formula { solution, .. } => solution,
+        _ => return String::new(),
+    };
+    format!("{{1:SHORTANSWER:={}}}", cloze_escape(solution))
+}
+
+fn cloze_escape(answer: &str) -> String {
+    let mut escaped = String::with_capacity(answer.len());
+    for c in answer.chars() {
+        if matches!(c, '}' | '#' | '~' | '/' | '"' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    html::escape(&escaped)
+}
+
+fn cdata(text: &str) -> String {
+    format!("<![CDATA[{}]]>", text.replace("]]>", "]]]]><![CDATA[>"))
+}
+
+// Import
+
+pub fn from_moodle_xml(xml: &str) -> Result<MoodleImport, Error> {
+    let document = Document::parse(xml)?;
+    let quiz = document.root_element();
+    if quiz.tag_name().name() != "quiz" {
+        return Err(Error::Malformed("missing quiz element"));
+    }
+    let mut title = None;
+    let mut blocks = Vec::new();
+    let mut reader = Reader::default();
+    let mut unsupported = Vec::new();
+    let mut pool: Option<PoolImport> = None;
+    for (i, question) in quiz
+        .children()
+        .filter(|n| n.has_tag_name("question"))
+        .enumerate()
+    {
+        let name = child_text(question, &["name", "text"])
+            .filter(|name| !name.trim().is_empty())
+            .unwrap_or_else(|| format!("Frage {}", i + 1));
+        let kind = question.attribute("type").unwrap_or_default();
+        let mut content = Vec::new();
+        match kind {
+            "category" => {
+                blocks.extend(pool.take().and_then(PoolImport::into_node));
+                let category = child_text(question, &["category", "text"]);
+                match category.as_deref().and_then(pool_draw) {
+                    Some(draw) => {
+                        pool = Some(PoolImport {
+                            draw,
+                            items: Vec::new(),
+                        })
+                    }
+                    None => title = category.and_then(|c| category_name(&c)).or(title),
+                }
+            }
+            "description" => content.extend(question_text(question, &mut reader)),
+            "cloze" | "multianswer" => {
+                let text = child_text(question, &["questiontext", "text"]).unwrap_or_default();
+                let text = replace_subquestions(&text);
+                content.extend(reader.blocks(&html::parse(&text)));
+            }
+            "multichoice" | "truefalse" => {
+                content.extend(question_text(question, &mut reader));
+                let single = kind == "truefalse"
+                    || child_text(question, &["single"])
+                        .map_or(false, |s| matches!(s.trim(), "true" | "1"));
+                let shuffle = child_text(question, &["shuffleanswers"])
+                    .map_or(false, |s| matches!(s.trim(), "true" | "1"));
+                let mut penalty = false;
+                let answers = question
+                    .children()
+                    .filter(|n| n.has_tag_name("answer"))
+                    .map(|answer| {
+                        let correct = answer
+                            .attribute("fraction")
+                            .and_then(|f| f.parse::<f64>().ok())
+                            .map_or(false, |f| f > 0.0);
+                        penalty |= answer
+                            .attribute("fraction")
+                            .and_then(|f| f.parse::<f64>().ok())
+                            .map_or(false, |f| f < 0.0);
+                        let text = child_text(answer, &["text"]).unwrap_or_default();
+                        let mut content = if kind == "truefalse" {
+                            let text = match text.trim() {
+                                "true" => "Wahr",
+                                "false" => "Falsch",
+                                other => other,
+                            };
+                            vec![Node::new(
+                                NodeKind::Paragraph,
+                                vec![Node::text(text, Vec::new())],
+                            )]
+                        } else {
+                            formatted_text(answer, &text, &mut reader)
+                        };
+                        if content.is_empty() {
+                            content.push(Node::new(NodeKind::Paragraph, Vec::new()));
+                        }
+                        Node::new(
+                            NodeKind::MultipleChoiceAnswer {
+                                solution: correct,
+                                answer: false,
+                            },
+                            content,
+                        )
+                    })
+                    .collect::<Vec<_>>();
+                // Single choice blocks need exactly one correct answer, unlike Moodle
+                let kind = if single && answers.iter().filter(|a| solution(a)).count() == 1 {
+                    NodeKind::MultipleChoice {
+                        mode: ChoiceMode::Single,
+                        scoring: Scoring::AllOrNothing,
+                        shuffle,
+                    }
+                } else {
+                    NodeKind::MultipleChoice {
+                        mode: ChoiceMode::Multiple,
+                        scoring: if penalty {
+                            Scoring::NegativeMarking
+                        } else {
+                            Scoring::PartialCredit
+                        },
+                        shuffle,
+                    }
+                };
+                content.push(Node::new(kind, answers));
+            }
+            "shortanswer" | "numerical" => {
+                content.extend(question_text(question, &mut reader));
+                let solution = question
+                    .children()
+                    .filter(|n| n.has_tag_name("answer"))
+                    .filter(|answer| {
+                        answer
+                            .attribute("fraction")
+                            .and_then(|f| f.parse::<f64>().ok())
+                            .map_or(false, |f| f >= 100.0)
+                    })
+                    .find_map(|answer| child_text(answer, &["text"]))
+                    .unwrap_or_default();
+                let solution = html::decode_entities(solution.trim());
+                content.push(Node::new(
+                    NodeKind::Paragraph,
+                    vec![Node::text(
+                        solution.clone(),
+                        vec![Mark::Gap {
+                            solution,
+                            answer: String::new(),
+                            matching: GapMatching::default(),
+                        }],
+                    )],
+                ));
+            }
+            "essay" => {
+                content.extend(question_text(question, &mut reader));
+                // Moodle writes 0 for no limit
+                let limit = |name| {
+                    child_text(question, &[name])
+                        .and_then(|limit| limit.trim().parse().ok())
+                        .filter(|&limit| limit > 0)
+                };
+                let points = child_text(question, &["defaultgrade"])
+                    .and_then(|grade| grade.trim().parse::<f64>().ok())
+                    .map_or(1, |grade| grade.round().max(1.0) as u32);
+                let model_answer = match child(question, &["graderinfo"]) {
+                    Some(info) => {
+                        let text = child_text(info, &["text"]).unwrap_or_default();
+                        formatted_text(info, &text, &mut reader)
+                            .iter()
+                            .map(Node::text_content)
+                            .collect::<Vec<_>>()
+                            .join("\n\n")
+                    }
+                    None => String::new(),
+                };
+                content.push(Node::new(
+                    NodeKind::Essay {
+                        min_words: limit("minwordlimit"),
+                        max_words: limit("maxwordlimit"),
+                        points,
+                        model_answer,
+                        answer: String::new(),
+                        awarded: None,
+                    },
+                    Vec::new(),
+                ));
+            }
+            other => unsupported.push(format!("{} (Fragetyp {})", name, other)),
+        }
+        match &mut pool {
+            Some(pool) => pool.add(&name, content),
+            None => blocks.extend(content),
+        }
+    }
+    blocks.extend(pool.and_then(PoolImport::into_node));
+    unsupported.extend(
+        reader
+            .skipped
+            .iter()
+            .map(|skipped| format!("{} wurden nicht übernommen", skipped)),
+    );
+    if blocks.is_empty() {
+        blocks.push(Node::new(NodeKind::Paragraph, Vec::new()));
+    }
+    Ok(MoodleImport {
+        title,
+        content: Node::new(NodeKind::Doc, blocks),
+        unsupported,
+    })
+}
+
+fn child<'a, 'input>(
+    node: roxmltree::Node<'a, 'input>,
+    path: &[&str],
+) -> Option<roxmltree::Node<'a, 'input>> {
+    path.iter().try_fold(node, |node, name| {
+        node.children().find(|n| n.has_tag_name(*name))
+    })
+}
+
+fn child_text(node: roxmltree::Node, path: &[&str]) -> Option<String> {
+    child(node, path).map(|n| {
+        n.children()
+            .filter_map(|c| c.text())
+            .collect::<Vec<_>>()
+            .concat()
+    })
+}
+
+/// The last segment of a category path like `$course$/top/Bruchrechnung`
+fn category_name(category: &str) -> Option<String> {
+    category
+        .split('/')
+        .map(str::trim)
+        .filter(|segment| !segment.is_empty() && !segment.starts_with('$') && *segment != "top")
+        .last()
+        .map(str::to_string)
+}
+
+/// The number of questions to draw from a pool category like `$course$/top/Brüche/Pool 1 (2 aus 5)`
+fn pool_draw(category: &str) -> Option<usize> {
+    let name = category_name(category)?;
+    let counts = name.strip_prefix("Pool ")?.split_once('(')?.1;
+    counts.split_once(" aus ")?.0.trim().parse().ok()
+}
+
+fn question_text(question: roxmltree::Node, reader: &mut Reader) -> Vec<Node> {
+    match child(question, &["questiontext"]) {
+        Some(text_node) => {
+            let text = child_text(text_node, &["text"]).unwrap_or_default();
+            formatted_text(text_node, &text, reader)
+        }
+        None => Vec::new(),
+    }
+}
+
+/// Converts text according to the `format` attribute of its element
+fn formatted_text(node: roxmltree::Node, text: &str, reader: &mut Reader) -> Vec<Node> {
+    match node.attribute("format").unwrap_or("html") {
+        "markdown" => markdown::from_markdown(text).content.content,
+        "plain_text" | "moodle_auto_format" => text
+            .split("\n\n")
+            .filter(|paragraph| !paragraph.trim().is_empty())
+            .map(|paragraph| {
+                Node::new(
+                    NodeKind::Paragraph,
+                    vec![Node::text(paragraph.trim(), Vec::new())],
+                )
+            })
+            .collect(),
+        _ => reader.blocks(&html::parse(text)),
+    }
+}
+
+/// Replaces embedded cloze subquestions like `{1:SHORTANSWER:=Berlin}` with gap, numeric and
+/// dropdown elements
+fn replace_subquestions(text: &str) -> String {
+    let mut replaced = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('{') {
+        replaced.push_str(&rest[..start]);
+        rest = &rest[start..];
+        match parse_subquestion(rest) {
+            Some((subquestion, length)) => {
+                match subquestion {
+                    Subquestion::Text(solution) => replaced.push_str(&span("gap", &solution)),
+                    Subquestion::Numerical(solution) => {
+                        replaced.push_str(&span("numeric", &solution))
+                    }
+                    Subquestion::Selection(options, shuffle) => {
+                        replaced.push_str(&format!(
+                            "<select data-type=\"dropdown\" data-shuffle=\"{}\">",
+                            shuffle
+                        ));
+                        for (option, correct) in options {
+                            replaced.push_str(&format!(
+                                "<option data-correct=\"{}\">{}</option>",
+                                correct,
+                                html::escape(&html::decode_entities(&option))
+                            ));
+                        }
+                        replaced.push_str("</select>");
+                    }
+                }
+                rest = &rest[length..];
+            }
+            None => {
+                replaced.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+    replaced.push_str(rest);
+    replaced
+}
+
+fn span(data_type: &str, solution: &str) -> String {
+    format!(
+        "<span data-type=\"{}\" data-solution=\"{}\"></span>",
+        data_type,
+        html::escape(&html::decode_entities(solution))
+    )
+}
+
+enum Subquestion {
+    Text(String),
+    Numerical(String),
+    /// The options with whether they are correct, and whether they are shuffled
+    Selection(Vec<(String, bool)>, bool),
+}
+
+/// Parses a subquestion at the start of `text`, returns it with the length of its markup
+fn parse_subquestion(text: &str) -> Option<(Subquestion, usize)> {
+    let mut end = None;
+    let mut escaped = false;
+    for (i, c) in text.char_indices().skip(1) {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '}' => {
+                end = Some(i);
+                break;
+            }
+            '{' => return None,
+            _ => {}
+        }
+    }
+    let end = end?;
+    let mut parts = text[1..end].splitn(3, ':');
+    let weight = parts.next()?.trim();
+    if !weight.is_empty() && weight.parse::<u32>().is_err() {
+        return None;
+    }
+    let kind = parts.next()?.trim().to_string();
+    let answers = split_unescaped(parts.next()?, '~');
+    // Answers start with `=` or a percentage of the grade like `%50%`, followed by feedback after `#`
+    let answers = answers.iter().filter_map(|answer| {
+        let answer = answer.trim();
+        let (fraction, answer) = match answer.strip_prefix('=') {
+            Some(answer) => (100.0, answer),
+            None => match answer.strip_prefix('%').and_then(|a| a.split_once('%')) {
+                Some((fraction, answer)) => (fraction.trim().parse::<f64>().ok()?, answer),
+                None => (0.0, answer),
+            },
+        };
+        let answer = split_unescaped(answer, '#').into_iter().next()?;
+        Some((fraction, answer))
+    });
+    let subquestion = match kind.as_str() {
+        "SHORTANSWER" | "SA" | "MW" | "SHORTANSWER_C" | "SAC" | "MWC" => Subquestion::Text(
+            answers
+                .filter(|(fraction, _)| *fraction >= 100.0)
+                .map(|(_, answer)| unescape(&answer))
+                .next()
+                .unwrap_or_default(),
+        ),
+        "NUMERICAL" | "NM" => Subquestion::Numerical(
+            answers
+                .filter(|(fraction, _)| *fraction >= 100.0)
+                .find_map(|(_, answer)| {
+                    // Numerical answers are written as `value:tolerance`
+                    let mut parts = split_unescaped(&answer, ':').into_iter();
+                    let value = parts.next()?;
+                    Some(match parts.next() {
+                        Some(tolerance) if tolerance.trim().parse::<f64>().ok() != Some(0.0) => {
+                            format!("{} ± {}", value.trim(), tolerance.trim())
+                        }
+                        _ => value.trim().to_string(),
+                    })
+                })
+                .unwrap_or_default(),
+        ),
+        kind if kind.starts_with("MULTICHOICE")
+            || kind.starts_with("MC")
+            || kind.starts_with("MULTIRESPONSE")
+            || kind.starts_with("MR") =>
+        {
+            Subquestion::Selection(
+                answers
+                    .map(|(fraction, answer)| {
+                        (unescape(&answer).trim().to_string(), fraction > 0.0)
+                    })
+                    .collect(),
+                // Shuffled variants end in `_S` or `S`, like `MULTICHOICE_VS` or `MCS`
+                kind.ends_with('S'),
+            )
+        }
+        _ => return None,
+    };
+    Some((subquestion, end + 1))
+}
+
+fn split_unescaped(text: &str, separator: char) -> Vec<String> {
+    let mut parts = vec![String::new()];
+    let mut escaped = false;
+    for c in text.chars() {
+        let current = parts.last_mut().expect("parts is not empty");
+        if escaped {
+            current.push('\\');
+            current.push(c);
+            escaped = false;
+        } else if c == '\\' {
+            escaped = true;
+        } else if c == separator {
+            parts.push(String::new());
+        } else {
+            current.push(c);
+        }
+    }
+    parts
+}
+
+fn unescape(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut escaped = false;
+    for c in text.chars() {
+        if c == '\\' && !escaped {
+            escaped = true;
+        } else {
+            unescaped.push(c);
+            escaped = false;
+        }
+    }
+    unescaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::markdown::from_markdown;
+    use super::*;
+
+    fn round_trip(markdown: &str) {
+        let content = from_markdown(markdown).content;
+        let imported = from_moodle_xml(&to_moodle_xml("Titel", &content)).unwrap();
+        assert_eq!(imported.title.as_deref(), Some("Titel"));
+        assert_eq!(imported.content, from_markdown(markdown).content);
+        assert!(
+            imported.unsupported.is_empty(),
+            "{:?}",
+            imported.unsupported
+        );
+    }
+
+    fn quiz(questions: &str) -> String {
+        format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<quiz>\n{}</quiz>\n",
+            questions
+        )
+    }
+
+    #[test]
+    fn round_trip_descriptions() {
+        round_trip(
+            "# Aufgabe\n\nText mit **fett**, *kursiv* und `code`.\n\n> Zitat\n\n- Eins\n- Zwei\n",
+        );
+    }
+
+    #[test]
+    fn round_trip_cloze_gaps() {
+        round_trip("Die Hauptstadt von Frankreich ist [[Paris]], Berlin liegt an der [[?Elbe|*Spree]] und Bonn am [[??*Rhein|Main]].\n");
+    }
+
+    #[test]
+    fn round_trip_multiple_choice() {
+        round_trip("Frage?\n\n- ( ) Bonn\n- (x) Berlin\n\n<!-- scoring: partialCredit, shuffle -->\n- [x] 2\n- [ ] 3\n- [x] 4\n\n<!-- scoring: negativeMarking -->\n- [x] 2\n- [ ] 3\n");
+    }
+
+    #[test]
+    fn round_trip_essays() {
+        round_trip("Warum gibt es Tag und Nacht? [[Drehung]]\n\nErkläre es.\n\n<!-- essay: points 3, words 50..200\nDie Erde dreht sich.\n-->\n\nNachwort\n");
+    }
+
+    #[test]
+    fn round_trip_pools() {
+        round_trip("Vorher [[a]]\n\n<!-- pool: draw 2 -->\n\nErste [[1]]\n\n<!-- item -->\n\nZweite\n\n<!-- scoring: partialCredit -->\n- [x] ja\n- [ ] nein\n\nDanach [[2]]\n\n<!-- item -->\n\nDritte [[3]]\n\n<!-- end pool -->\n\nNachher [[b]]\n");
+    }
+
+    #[test]
+    fn numeric_and_formula_tasks_become_subquestions() {
+        let content = from_markdown("Das Ergebnis ist [[=3,5 ± 0,1]] bzw. [[$x^2]].\n").content;
+        let xml = to_moodle_xml("Titel", &content);
+        assert!(xml.contains("{1:NUMERICAL:=3.5:0.1}"));
+        assert!(xml.contains("{1:SHORTANSWER:=x^2}"));
+        // Formulas can only be checked as written, so they come back as gaps
+        let imported = from_moodle_xml(&xml).unwrap();
+        assert_eq!(
+            imported.content,
+            from_markdown("Das Ergebnis ist [[=3.5 ± 0.1]] bzw. [[x^2]].\n").content
+        );
+    }
+
+    #[test]
+    fn imports_short_answer_true_false_and_numerical_questions() {
+        let xml = quiz(concat!(
+            "<question type=\"category\"><category><text>$course$/top/Erdkunde</text></category></question>\n",
+            "<question type=\"shortanswer\"><name><text>Stadt</text></name>",
+            "<questiontext format=\"html\"><text><![CDATA[<p>Hauptstadt von Italien?</p>]]></text></questiontext>",
+            "<answer fraction=\"50\"><text>Mailand</text></answer><answer fraction=\"100\"><text>Rom</text></answer></question>\n",
+            "<question type=\"truefalse\"><name><text>Wahr</text></name>",
+            "<questiontext format=\"plain_text\"><text>Rom liegt am Tiber.</text></questiontext>",
+            "<answer fraction=\"100\"><text>true</text></answer><answer fraction=\"0\"><text>false</text></answer></question>\n",
+        ));
+        let imported = from_moodle_xml(&xml).unwrap();
+        assert_eq!(imported.title.as_deref(), Some("Erdkunde"));
+        assert_eq!(
+            imported.content,
+            from_markdown("Hauptstadt von Italien?\n\n[[Rom]]\n\nRom liegt am Tiber.\n\n- (x) Wahr\n- ( ) Falsch\n").content
+        );
+        assert!(imported.unsupported.is_empty());
+    }
+
+    #[test]
+    fn reports_unsupported_questions() {
+        let xml = quiz(concat!(
+            "<question type=\"matching\"><name><text>Zuordnung</text></name></question>\n",
+            "<question type=\"description\"><name><text>Bild</text></name>",
+            "<questiontext format=\"html\"><text><![CDATA[<p>Siehe <img src=\"a.png\"/> oben</p>]]></text></questiontext></question>\n",
+            "<question type=\"ddwtos\"></question>\n",
+        ));
+        let imported = from_moodle_xml(&xml).unwrap();
+        assert_eq!(
+            imported.unsupported,
+            vec![
+                "Zuordnung (Fragetyp matching)",
+                "Frage 3 (Fragetyp ddwtos)",
+                "Bilder wurden nicht übernommen",
+            ]
+        );
+        assert_eq!(imported.content, from_markdown("Siehe oben\n").content);
+    }
+
+    #[test]
+    fn rejects_other_documents() {
+        assert!(matches!(
+            from_moodle_xml("<questions/>"),
+            Err(Error::Malformed(_))
+        ));
+        assert!(from_moodle_xml("kein XML").is_err());
+    }
+}
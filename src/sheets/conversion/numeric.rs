@@ -0,0 +1,499 @@
+//! Numeric tasks, whose solution is written like `3,5 ± 0,1 cm`, `9,81 m/s² ± 2 %` or
+//! `3,50 cm (3 sig)`.
+//!
+//! Numbers may use a decimal point or a decimal comma and be written in scientific notation,
+//! answers with units are converted to the unit of the solution.
+
+use std::f64::consts::PI;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Tolerance {
+    Absolute(f64),
+    /// Fraction of the solution
+    Relative(f64),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NumericTask {
+    pub value: f64,
+    pub tolerance: Tolerance,
+    pub unit: Option<String>,
+    pub significant_figures: Option<u32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Number {
+    value: f64,
+    /// Trailing zeros of integers may or may not be significant
+    significant_figures: (u32, u32),
+}
+
+impl NumericTask {
+    pub fn parse(spec: &str) -> Option<Self> {
+        let (spec, significant_figures) = split_significant_figures(spec)?;
+        let (quantity, tolerance) = match split_tolerance(spec) {
+            Some((quantity, tolerance)) => (quantity, Some(tolerance)),
+            None => (spec, None),
+        };
+        let (number, mut unit) = parse_quantity(quantity)?;
+        let tolerance = match tolerance {
+            None => Tolerance::Absolute(0.0),
+            Some(tolerance) => {
+                let (amount, tolerance_unit) = parse_quantity(tolerance)?;
+                match tolerance_unit {
+                    None => Tolerance::Absolute(amount.value),
+                    Some("%") => Tolerance::Relative(amount.value / 100.0),
+                    // The unit may be given once after the tolerance
+                    Some(tolerance_unit) if unit.is_none() => {
+                        unit = Some(tolerance_unit);
+                        Tolerance::Absolute(amount.value)
+                    }
+                    Some(tolerance_unit) => {
+                        Tolerance::Absolute(convert(amount.value, tolerance_unit, unit?)?)
+                    }
+                }
+            }
+        };
+        Some(Self {
+            value: number.value,
+            tolerance,
+            unit: unit.map(str::to_string),
+            significant_figures,
+        })
+    }
+
+    /// The largest allowed deviation from the value
+    pub fn absolute_tolerance(&self) -> f64 {
+        match self.tolerance {
+            Tolerance::Absolute(tolerance) => tolerance.abs(),
+            Tolerance::Relative(tolerance) => (tolerance * self.value).abs(),
+        }
+    }
+
+    pub fn check(&self, answer: &str) -> bool {
+        let (number, unit) = match parse_quantity(answer) {
+            Some(quantity) => quantity,
+            None => return false,
+        };
+        let value = match (&self.unit, unit) {
+            (None, None) => number.value,
+            (Some(expected), Some(unit)) => match convert(number.value, unit, expected) {
+                Some(value) => value,
+                None => return false,
+            },
+            _ => return false,
+        };
+        if let Some(figures) = self.significant_figures {
+            let (min, max) = number.significant_figures;
+            if figures < min || figures > max {
+                return false;
+            }
+        }
+        // Allow for the rounding errors of unit conversions
+        let epsilon = 1e-9 * self.value.abs().max(f64::MIN_POSITIVE);
+        (value - self.value).abs() <= self.absolute_tolerance() + epsilon
+    }
+}
+
+fn split_significant_figures(spec: &str) -> Option<(&str, Option<u32>)> {
+    let spec = spec.trim();
+    if let Some(rest) = spec.strip_suffix(')') {
+        if let Some(start) = rest.rfind('(') {
+            let inner = rest[start + 1..].trim();
+            let digits: String = inner.chars().take_while(char::is_ascii_digit).collect();
+            if inner[digits.len()..].trim_start().starts_with("sig") {
+                return Some((rest[..start].trim_end(), Some(digits.parse().ok()?)));
+            }
+        }
+    }
+    Some((spec, None))
+}
+
+fn split_tolerance(spec: &str) -> Option<(&str, &str)> {
+    ["±", "+/-", "+-"].iter().find_map(|separator| {
+        spec.find(separator)
+            .map(|i| (&spec[..i], &spec[i + separator.len()..]))
+    })
+}
+
+/// Splits the text into a number and the unit following it
+fn parse_quantity(text: &str) -> Option<(Number, Option<&str>)> {
+    let text = text.trim();
+    let (number, rest) = parse_number(text)?;
+    let unit = rest.trim();
+    Some((number, if unit.is_empty() { None } else { Some(unit) }))
+}
+
+fn parse_number(text: &str) -> Option<(Number, &str)> {
+    let (negative, unsigned) = match text.chars().next()? {
+        '-' | '−' => (true, text[text.chars().next()?.len_utf8()..].trim_start()),
+        '+' => (false, text[1..].trim_start()),
+        _ => (false, text),
+    };
+    let length = unsigned
+        .find(|c: char| !(c.is_ascii_digit() || c == '.' || c == ','))
+        .unwrap_or(unsigned.len());
+    let mantissa = normalize_separators(&unsigned[..length])?;
+    let (exponent, rest) = parse_exponent(&unsigned[length..]);
+    let value: f64 = mantissa.parse().ok()?;
+    let value = value * 10f64.powi(exponent);
+    Some((
+        Number {
+            value: if negative { -value } else { value },
+            significant_figures: significant_figures(&mantissa),
+        },
+        rest,
+    ))
+}
+
+/// Converts the digits to the format of Rust. The last separator is the decimal separator if it
+/// is the only one or both kinds are used, repeated separators group thousands.
+fn normalize_separators(digits: &str) -> Option<String> {
+    if !digits.chars().any(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let points = digits.matches('.').count();
+    let commas = digits.matches(',').count();
+    let decimal = match (points, commas) {
+        (0, 0) => None,
+        (_, 0) if points > 1 => None,
+        (0, _) if commas > 1 => None,
+        _ => digits.rfind(|c| c == '.' || c == ','),
+    };
+    let mut normalized = String::with_capacity(digits.len());
+    for (i, c) in digits.char_indices() {
+        match c {
+            _ if Some(i) == decimal => normalized.push('.'),
+            '.' | ',' if decimal.map_or(false, |decimal| i > decimal) => return None,
+            '.' | ',' => {}
+            c => normalized.push(c),
+        }
+    }
+    Some(normalized)
+}
+
+/// Parses exponents like `e-3`, `·10^5` or `× 10⁻³`
+fn parse_exponent(text: &str) -> (i32, &str) {
+    let parsed = text
+        .strip_prefix(|c| c == 'e' || c == 'E')
+        .or_else(|| {
+            text.trim_start()
+                .strip_prefix(|c| matches!(c, '·' | '*' | '×' | 'x' | '⋅'))
+                .and_then(|rest| rest.trim_start().strip_prefix("10"))
+                .map(|rest| rest.strip_prefix('^').unwrap_or(rest))
+        })
+        .and_then(|rest| {
+            let rest = rest.strip_prefix(|c| c == '{').unwrap_or(rest);
+            let mut exponent = String::new();
+            let mut length = 0;
+            for c in rest.chars() {
+                let digit = match c {
+                    '-' | '−' | '⁻' if exponent.is_empty() => '-',
+                    '+' | '⁺' if exponent.is_empty() => '+',
+                    '0'..='9' => c,
+                    _ => match "⁰¹²³⁴⁵⁶⁷⁸⁹".chars().position(|s| s == c) {
+                        Some(digit) => (b'0' + digit as u8) as char,
+                        None => break,
+                    },
+                };
+                exponent.push(digit);
+                length += c.len_utf8();
+            }
+            let rest = &rest[length..];
+            let rest = rest.strip_prefix(|c| c == '}').unwrap_or(rest);
+            exponent.parse().ok().map(|exponent| (exponent, rest))
+        });
+    parsed.unwrap_or((0, text))
+}
+
+fn significant_figures(mantissa: &str) -> (u32, u32) {
+    let digits = mantissa.trim_start_matches(|c| c == '0' || c == '.');
+    let count = digits.chars().filter(char::is_ascii_digit).count() as u32;
+    if count == 0 {
+        // Zero has as many significant figures as it has decimals
+        let decimals = mantissa.split('.').nth(1).map_or(0, str::len) as u32;
+        let figures = decimals.max(1);
+        (figures, figures)
+    } else if mantissa.contains('.') {
+        (count, count)
+    } else {
+        let trailing = digits.len() - digits.trim_end_matches('0').len();
+        (count - trailing as u32, count)
+    }
+}
+
+/// Exponents of metre, kilogram, second, ampere, kelvin, degree Celsius and radian
+type Dimension = [i8; 7];
+
+const METRE: Dimension = [1, 0, 0, 0, 0, 0, 0];
+const KILOGRAM: Dimension = [0, 1, 0, 0, 0, 0, 0];
+const SECOND: Dimension = [0, 0, 1, 0, 0, 0, 0];
+const AMPERE: Dimension = [0, 0, 0, 1, 0, 0, 0];
+const KELVIN: Dimension = [0, 0, 0, 0, 1, 0, 0];
+const CELSIUS: Dimension = [0, 0, 0, 0, 0, 1, 0];
+const RADIAN: Dimension = [0, 0, 0, 0, 0, 0, 1];
+const NONE: Dimension = [0; 7];
+const NEWTON: Dimension = [1, 1, -2, 0, 0, 0, 0];
+const JOULE: Dimension = [2, 1, -2, 0, 0, 0, 0];
+const WATT: Dimension = [2, 1, -3, 0, 0, 0, 0];
+const PASCAL: Dimension = [-1, 1, -2, 0, 0, 0, 0];
+const COULOMB: Dimension = [0, 0, 1, 1, 0, 0, 0];
+const VOLT: Dimension = [2, 1, -3, -1, 0, 0, 0];
+const OHM: Dimension = [2, 1, -3, -2, 0, 0, 0];
+const HERTZ: Dimension = [0, 0, -1, 0, 0, 0, 0];
+const LITRE: Dimension = [3, 0, 0, 0, 0, 0, 0];
+
+/// Units with the factor to convert them to SI units and whether they take SI prefixes
+static UNITS: &[(&str, f64, Dimension, bool)] = &[
+    ("m", 1.0, METRE, true),
+    ("g", 1e-3, KILOGRAM, true),
+    ("t", 1e3, KILOGRAM, false),
+    ("s", 1.0, SECOND, true),
+    ("min", 60.0, SECOND, false),
+    ("h", 3600.0, SECOND, false),
+    ("d", 86400.0, SECOND, false),
+    ("A", 1.0, AMPERE, true),
+    ("K", 1.0, KELVIN, true),
+    ("°C", 1.0, CELSIUS, false),
+    ("rad", 1.0, RADIAN, true),
+    ("°", PI / 180.0, RADIAN, false),
+    ("%", 0.01, NONE, false),
+    ("N", 1.0, NEWTON, true),
+    ("J", 1.0, JOULE, true),
+    ("Wh", 3600.0, JOULE, true),
+    ("eV", 1.602_176_634e-19, JOULE, true),
+    ("W", 1.0, WATT, true),
+    ("Pa", 1.0, PASCAL, true),
+    ("bar", 1e5, PASCAL, true),
+    ("C", 1.0, COULOMB, true),
+    ("V", 1.0, VOLT, true),
+    ("Ω", 1.0, OHM, true),
+    ("Ohm", 1.0, OHM, true),
+    ("Hz", 1.0, HERTZ, true),
+    ("l", 1e-3, LITRE, true),
+    ("L", 1e-3, LITRE, true),
+    ("a", 100.0, [2, 0, 0, 0, 0, 0, 0], true),
+];
+
+static PREFIXES: &[(&str, f64)] = &[
+    ("da", 1e1),
+    ("T", 1e12),
+    ("G", 1e9),
+    ("M", 1e6),
+    ("k", 1e3),
+    ("h", 1e2),
+    ("d", 1e-1),
+    ("c", 1e-2),
+    ("m", 1e-3),
+    ("µ", 1e-6),
+    ("μ", 1e-6),
+    ("u", 1e-6),
+    ("n", 1e-9),
+    ("p", 1e-12),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Unit {
+    factor: f64,
+    dimension: Dimension,
+}
+
+impl Unit {
+    /// `None` if an exponent of the dimension gets out of range
+    fn pow(self, exponent: i8) -> Option<Self> {
+        let mut dimension = self.dimension;
+        for d in &mut dimension {
+            *d = d.checked_mul(exponent)?;
+        }
+        Some(Self {
+            factor: self.factor.powi(exponent.into()),
+            dimension,
+        })
+    }
+
+    /// `None` if an exponent of the dimension gets out of range
+    fn mul(self, other: Self) -> Option<Self> {
+        let mut dimension = self.dimension;
+        for (d, o) in dimension.iter_mut().zip(&other.dimension) {
+            *d = d.checked_add(*o)?;
+        }
+        Some(Self {
+            factor: self.factor * other.factor,
+            dimension,
+        })
+    }
+}
+
+fn base_unit(symbol: &str) -> Option<Unit> {
+    let unit =
+        |&(_, factor, dimension, _): &(&str, f64, Dimension, bool)| Unit { factor, dimension };
+    if let Some(base) = UNITS.iter().find(|(name, ..)| *name == symbol) {
+        return Some(unit(base));
+    }
+    PREFIXES.iter().find_map(|(prefix, scale)| {
+        let rest = symbol.strip_prefix(prefix)?;
+        UNITS
+            .iter()
+            .find(|(name, _, _, prefixable)| *prefixable && *name == rest)
+            .map(|base| {
+                let base = unit(base);
+                Unit {
+                    factor: base.factor * scale,
+                    ..base
+                }
+            })
+    })
+}
+
+/// Parses a factor like `m`, `cm²`, `s^-2` or `m2`
+fn parse_factor(factor: &str) -> Option<Unit> {
+    let split = factor
+        .find(|c: char| c == '^' || c.is_ascii_digit() || "⁻²³".contains(c))
+        .unwrap_or(factor.len());
+    let (symbol, exponent) = factor.split_at(split);
+    let exponent = match exponent.trim_start_matches('^') {
+        "" => 1,
+        "²" => 2,
+        "³" => 3,
+        "⁻¹" => -1,
+        "⁻²" => -2,
+        "⁻³" => -3,
+        exponent => exponent.replace('−', "-").parse().ok()?,
+    };
+    base_unit(symbol)?.pow(exponent)
+}
+
+fn parse_product(text: &str) -> Option<Unit> {
+    text.split(|c: char| c == '·' || c == '*' || c == '⋅' || c.is_whitespace())
+        .filter(|factor| !factor.is_empty())
+        .map(parse_factor)
+        .try_fold(
+            Unit {
+                factor: 1.0,
+                dimension: NONE,
+            },
+            |product, factor| product.mul(factor?),
+        )
+}
+
+fn parse_unit(text: &str) -> Option<Unit> {
+    let mut parts = text.splitn(2, '/');
+    let numerator = parts.next()?.trim();
+    let mut unit = if numerator == "1" {
+        parse_product("")?
+    } else {
+        parse_product(numerator)?
+    };
+    if let Some(denominator) = parts.next() {
+        let denominator = denominator
+            .trim()
+            .trim_start_matches('(')
+            .trim_end_matches(')');
+        unit = unit.mul(parse_product(denominator)?.pow(-1)?)?;
+    }
+    Some(unit)
+}
+
+/// Converts a value between units of the same dimension. Units which are not known only match
+/// themselves.
+fn convert(value: f64, from: &str, to: &str) -> Option<f64> {
+    let strip = |unit: &str| unit.split_whitespace().collect::<String>();
+    if strip(from) == strip(to) {
+        return Some(value);
+    }
+    let (from, to) = (parse_unit(from)?, parse_unit(to)?);
+    if from.dimension == to.dimension {
+        Some(value * from.factor / to.factor)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(spec: &str, answer: &str) -> bool {
+        NumericTask::parse(spec).unwrap().check(answer)
+    }
+
+    #[test]
+    fn parses_specifications() {
+        assert_eq!(
+            NumericTask::parse("3,5 ± 0,1 cm"),
+            Some(NumericTask {
+                value: 3.5,
+                tolerance: Tolerance::Absolute(0.1),
+                unit: Some("cm".to_string()),
+                significant_figures: None,
+            })
+        );
+        assert_eq!(
+            NumericTask::parse("9.81 m/s² +- 2 % (3 sig)"),
+            Some(NumericTask {
+                value: 9.81,
+                tolerance: Tolerance::Relative(0.02),
+                unit: Some("m/s²".to_string()),
+                significant_figures: Some(3),
+            })
+        );
+        assert_eq!(NumericTask::parse("drei"), None);
+    }
+
+    #[test]
+    fn accepts_both_decimal_separators() {
+        assert!(check("3,5", "3.5"));
+        assert!(check("3.5", "3,50"));
+        assert!(check("1234,5", "1.234,5"));
+        assert!(check("1234.5", "1,234.5"));
+        assert!(!check("3,5", "35"));
+    }
+
+    #[test]
+    fn accepts_scientific_notation() {
+        assert!(check("0,0035", "3,5e-3"));
+        assert!(check("0,0035", "3,5 · 10^-3"));
+        assert!(check("3500", "3,5×10³"));
+    }
+
+    #[test]
+    fn applies_tolerance() {
+        assert!(check("3,5 ± 0,1", "3,58"));
+        assert!(!check("3,5 ± 0,1", "3,65"));
+        assert!(check("200 ± 5 %", "209"));
+        assert!(!check("200 ± 5 %", "211"));
+        assert!(check("-2", "−2"));
+    }
+
+    #[test]
+    fn converts_units() {
+        assert!(check("3,5 cm", "0,035 m"));
+        assert!(check("3,5 cm", "35mm"));
+        assert!(check("36 km/h", "10 m/s"));
+        assert!(check("1 kWh", "3,6 MJ"));
+        assert!(check("3,5 m ± 2 cm", "3,52 m"));
+        assert!(!check("3,5 cm", "3,5"));
+        assert!(!check("3,5 cm", "3,5 g"));
+        assert!(!check("3,5", "3,5 cm"));
+        assert!(check("4 Äpfel", "4 Äpfel"));
+    }
+
+    #[test]
+    fn rejects_units_with_exponents_out_of_range() {
+        assert_eq!(parse_unit("a^100"), None);
+        assert_eq!(parse_unit("m^127 m"), None);
+        assert_eq!(parse_unit("1/m^-128"), None);
+        assert!(!check("5 m", "5 a^100"));
+        // Wrapping around would leave no dimension at all
+        assert!(!check("5", "5 m^127 m^127 m^2"));
+    }
+
+    #[test]
+    fn checks_significant_figures() {
+        assert!(check("3,50 (3 sig)", "3,50"));
+        assert!(!check("3,50 (3 sig)", "3,5"));
+        assert!(check("1200 (2 sig)", "1200"));
+        assert!(check("1200 (4 sig)", "1200"));
+        assert!(check("0,0012 (2 sig)", "0,0012"));
+    }
+}
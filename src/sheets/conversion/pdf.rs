@@ -0,0 +1,1298 @@
+use lopdf::content::{Content, Operation};
+use lopdf::{dictionary, Document, Object, ObjectId, Stream, StringFormat};
+
+use super::document::{ChoiceMode, Mark, Node, NodeKind};
+use super::essay;
+use super::grading::{self, Score};
+use super::variables::format_number;
+use super::Error;
+
+const PAGE_WIDTH: f64 = 595.0;
+const PAGE_HEIGHT: f64 = 842.0;
+const MARGIN: f64 = 56.0;
+const CONTENT_WIDTH: f64 = PAGE_WIDTH - 2.0 * MARGIN;
+const LIST_INDENT: f64 = 18.0;
+const BODY_SIZE: f64 = 11.0;
+const CODE_SIZE: f64 = 9.5;
+const MIN_GAP_WIDTH: f64 = 50.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Variant {
+    /// Gaps are left blank and answer boxes unticked
+    Worksheet,
+    /// Gaps and answer boxes are filled in with the solution
+    AnswerKey,
+    /// Gaps and answer boxes are filled in with a student's answers and marked as correct or not
+    Solution,
+    /// Like `Solution`, but for the students themselves, who are not told the solutions to
+    /// incorrect answers
+    OwnSolution,
+}
+
+impl Variant {
+    fn graded(self) -> bool {
+        matches!(self, Variant::Solution | Variant::OwnSolution)
+    }
+}
+
+pub struct SolutionDocument {
+    pub student: String,
+    pub changed: String,
+    pub content: Node,
+    /// Written by a teacher, empty if there is none
+    pub feedback: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Font {
+    Regular,
+    Bold,
+    Oblique,
+    BoldOblique,
+    Mono,
+}
+
+impl Font {
+    const ALL: [Font; 5] = [
+        Font::Regular,
+        Font::Bold,
+        Font::Oblique,
+        Font::BoldOblique,
+        Font::Mono,
+    ];
+
+    fn resource(self) -> &'static str {
+        match self {
+            Font::Regular => "F1",
+            Font::Bold => "F2",
+            Font::Oblique => "F3",
+            Font::BoldOblique => "F4",
+            Font::Mono => "F5",
+        }
+    }
+
+    fn base_font(self) -> &'static str {
+        match self {
+            Font::Regular => "Helvetica",
+            Font::Bold => "Helvetica-Bold",
+            Font::Oblique => "Helvetica-Oblique",
+            Font::BoldOblique => "Helvetica-BoldOblique",
+            Font::Mono => "Courier",
+        }
+    }
+
+    fn with_bold(self) -> Font {
+        match self {
+            Font::Regular => Font::Bold,
+            Font::Oblique => Font::BoldOblique,
+            other => other,
+        }
+    }
+
+    fn with_oblique(self) -> Font {
+        match self {
+            Font::Regular => Font::Oblique,
+            Font::Bold => Font::BoldOblique,
+            other => other,
+        }
+    }
+
+    /// Glyph width in thousandths of the font size, taken from the AFM files of the standard fonts
+    fn glyph_width(self, c: char) -> u16 {
+        if self == Font::Mono {
+            return 600;
+        }
+        let bold = matches!(self, Font::Bold | Font::BoldOblique);
+        match (bold, c) {
+            (false, ' '..='~') => HELVETICA_WIDTHS[c as usize - ' ' as usize],
+            (true, ' '..='~') => HELVETICA_BOLD_WIDTHS[c as usize - ' ' as usize],
+            (false, 'Ä') => 667,
+            (true, 'Ä') => 722,
+            (_, 'Ö') => 778,
+            (_, 'Ü') => 722,
+            (true, 'ö' | 'ü') => 611,
+            (_, 'ä' | 'ö' | 'ü') => 556,
+            (_, 'ß') => 611,
+            (_, '•') => 350,
+            (_, '—' | '…') => 1000,
+            _ => 556,
+        }
+    }
+
+    fn text_width(self, text: &str, size: f64) -> f64 {
+        text.chars()
+            .map(|c| f64::from(self.glyph_width(c)))
+            .sum::<f64>()
+            * size
+            / 1000.0
+    }
+}
+
+#[rustfmt::skip]
+const HELVETICA_WIDTHS: [u16; 95] = [
+    278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278, 278,
+    556, 556, 556, 556, 556, 556, 556, 556, 556, 556, 278, 278, 584, 584, 584, 556,
+    1015, 667, 667, 722, 722, 667, 611, 778, 722, 278, 500, 667, 556, 833, 722, 778,
+    667, 778, 722, 667, 611, 722, 667, 944, 667, 667, 611, 278, 278, 278, 469, 556,
+    333, 556, 556, 500, 556, 556, 278, 556, 556, 222, 222, 500, 222, 833, 556, 556,
+    556, 556, 333, 500, 278, 556, 500, 722, 500, 500, 500, 334, 260, 334, 584,
+];
+
+#[rustfmt::skip]
+const HELVETICA_BOLD_WIDTHS: [u16; 95] = [
+    278, 333, 474, 556, 556, 889, 722, 238, 333, 333, 389, 584, 278, 333, 278, 278,
+    556, 556, 556, 556, 556, 556, 556, 556, 556, 556, 333, 333, 584, 584, 584, 611,
+    975, 722, 722, 722, 722, 667, 611, 778, 722, 278, 556, 722, 611, 833, 722, 778,
+    667, 778, 722, 667, 611, 722, 667, 944, 667, 667, 611, 333, 278, 333, 584, 556,
+    333, 556, 611, 556, 611, 556, 333, 611, 611, 278, 278, 556, 278, 889, 611, 611,
+    611, 611, 389, 556, 333, 611, 556, 778, 556, 556, 500, 389, 280, 389, 584,
+];
+
+// The standard fonts are used with WinAnsiEncoding, characters outside of it are replaced
+fn encode(text: &str) -> Vec<u8> {
+    text.chars()
+        .map(|c| match c {
+            ' '..='~' | '\u{a0}'..='\u{ff}' => c as u8,
+            '€' => 0x80,
+            '‚' => 0x82,
+            '„' => 0x84,
+            '…' => 0x85,
+            '‘' => 0x91,
+            '’' => 0x92,
+            '“' => 0x93,
+            '”' => 0x94,
+            '•' => 0x95,
+            '–' => 0x96,
+            '—' => 0x97,
+            _ => b'?',
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Color(f64, f64, f64);
+
+const BLACK: Color = Color(0.0, 0.0, 0.0);
+const GREY: Color = Color(0.45, 0.45, 0.45);
+const SOLUTION: Color = Color(0.13, 0.32, 0.75);
+const CORRECT: Color = Color(0.16, 0.55, 0.25);
+const INCORRECT: Color = Color(0.8, 0.15, 0.15);
+
+#[derive(Debug, Clone, Copy)]
+struct Style {
+    font: Font,
+    size: f64,
+    color: Color,
+    underline: bool,
+}
+
+impl Style {
+    fn body() -> Self {
+        Self {
+            font: Font::Regular,
+            size: BODY_SIZE,
+            color: BLACK,
+            underline: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum PieceKind {
+    Word(String),
+    Space,
+    Blank(f64),
+    CheckBox(bool),
+    Verdict(bool),
+    Break,
+}
+
+#[derive(Debug, Clone)]
+struct Piece {
+    kind: PieceKind,
+    style: Style,
+}
+
+impl Piece {
+    fn width(&self) -> f64 {
+        match &self.kind {
+            PieceKind::Word(word) => self.style.font.text_width(word, self.style.size),
+            PieceKind::Space => self.style.font.text_width(" ", self.style.size),
+            PieceKind::Blank(width) => *width,
+            PieceKind::CheckBox(_) | PieceKind::Verdict(_) => self.style.size,
+            PieceKind::Break => 0.0,
+        }
+    }
+}
+
+fn push_text(pieces: &mut Vec<Piece>, text: &str, style: Style) {
+    for (i, line) in text.split('\n').enumerate() {
+        if i > 0 {
+            pieces.push(Piece {
+                kind: PieceKind::Break,
+                style,
+            });
+        }
+        for (j, word) in line.split(' ').enumerate() {
+            if j > 0 {
+                pieces.push(Piece {
+                    kind: PieceKind::Space,
+                    style,
+                });
+            }
+            if !word.is_empty() {
+                pieces.push(Piece {
+                    kind: PieceKind::Word(word.to_string()),
+                    style,
+                });
+            }
+        }
+    }
+}
+
+/// Renders the most common LaTeX commands as plain text, the standard fonts cannot typeset formulas
+fn latex_to_text(source: &str) -> String {
+    const REPLACEMENTS: [(&str, &str); 14] = [
+        ("\\cdot", "·"),
+        ("\\times", "×"),
+        ("\\div", "÷"),
+        ("\\pm", "±"),
+        ("\\mu", "µ"),
+        ("\\circ", "°"),
+        ("\\,", " "),
+        ("\\;", " "),
+        ("\\left", ""),
+        ("\\right", ""),
+        ("^{2}", "²"),
+        ("^{3}", "³"),
+        ("^2", "²"),
+        ("^3", "³"),
+    ];
+    let mut text = source.to_string();
+    for (command, replacement) in REPLACEMENTS.iter() {
+        text = text.replace(command, replacement);
+    }
+    while let Some(start) = text.find("\\frac{") {
+        let rest = &text[start + "\\frac{".len()..];
+        let numerator_end = match rest.find("}{") {
+            Some(end) => end,
+            None => break,
+        };
+        let denominator = &rest[numerator_end + 2..];
+        let denominator_end = match denominator.find('}') {
+            Some(end) => end,
+            None => break,
+        };
+        let fraction = format!(
+            "({})/({})",
+            &rest[..numerator_end],
+            &denominator[..denominator_end]
+        );
+        let end = start + "\\frac{".len() + numerator_end + 2 + denominator_end + 1;
+        text.replace_range(start..end, &fraction);
+    }
+    text.replace(['{', '}', '\\'], "")
+}
+
+struct Renderer {
+    variant: Variant,
+    pages: Vec<Vec<Operation>>,
+    page: usize,
+    y: f64,
+}
+
+impl Renderer {
+    fn new(variant: Variant) -> Self {
+        Self {
+            variant,
+            pages: vec![Vec::new()],
+            page: 0,
+            y: PAGE_HEIGHT - MARGIN,
+        }
+    }
+
+    fn ops(&mut self) -> &mut Vec<Operation> {
+        &mut self.pages[self.page]
+    }
+
+    fn ensure_space(&mut self, height: f64) {
+        if self.y - height < MARGIN {
+            self.new_page();
+        }
+    }
+
+    fn new_page(&mut self) {
+        self.pages.push(Vec::new());
+        self.page = self.pages.len() - 1;
+        self.y = PAGE_HEIGHT - MARGIN;
+    }
+
+    fn skip(&mut self, height: f64) {
+        self.y -= height;
+    }
+
+    fn text_at(&mut self, x: f64, y: f64, text: &str, style: Style) {
+        let Color(r, g, b) = style.color;
+        let ops = self.ops();
+        ops.push(Operation::new("rg", vec![r.into(), g.into(), b.into()]));
+        ops.push(Operation::new("BT", vec![]));
+        ops.push(Operation::new(
+            "Tf",
+            vec![style.font.resource().into(), style.size.into()],
+        ));
+        ops.push(Operation::new("Td", vec![x.into(), y.into()]));
+        ops.push(Operation::new(
+            "Tj",
+            vec![Object::String(encode(text), StringFormat::Literal)],
+        ));
+        ops.push(Operation::new("ET", vec![]));
+    }
+
+    fn line(&mut self, x1: f64, y1: f64, x2: f64, y2: f64, width: f64, color: Color) {
+        let Color(r, g, b) = color;
+        let ops = self.ops();
+        ops.push(Operation::new("RG", vec![r.into(), g.into(), b.into()]));
+        ops.push(Operation::new("w", vec![width.into()]));
+        ops.push(Operation::new("m", vec![x1.into(), y1.into()]));
+        ops.push(Operation::new("l", vec![x2.into(), y2.into()]));
+        ops.push(Operation::new("S", vec![]));
+    }
+
+    fn check_box(&mut self, x: f64, y: f64, size: f64, checked: bool, color: Color) {
+        let ops = self.ops();
+        ops.push(Operation::new("RG", vec![0.into(), 0.into(), 0.into()]));
+        ops.push(Operation::new("w", vec![0.8.into()]));
+        ops.push(Operation::new(
+            "re",
+            vec![x.into(), y.into(), size.into(), size.into()],
+        ));
+        ops.push(Operation::new("S", vec![]));
+        if checked {
+            let inset = size * 0.2;
+            self.line(
+                x + inset,
+                y + inset,
+                x + size - inset,
+                y + size - inset,
+                1.2,
+                color,
+            );
+            self.line(
+                x + inset,
+                y + size - inset,
+                x + size - inset,
+                y + inset,
+                1.2,
+                color,
+            );
+        }
+    }
+
+    /// Draws a check mark for correct and a cross for incorrect answers
+    fn verdict(&mut self, x: f64, y: f64, size: f64, correct: bool) {
+        if correct {
+            self.line(
+                x,
+                y + size * 0.45,
+                x + size * 0.35,
+                y + size * 0.1,
+                1.4,
+                CORRECT,
+            );
+            self.line(
+                x + size * 0.35,
+                y + size * 0.1,
+                x + size * 0.9,
+                y + size * 0.85,
+                1.4,
+                CORRECT,
+            );
+        } else {
+            let inset = size * 0.15;
+            self.line(
+                x + inset,
+                y + inset,
+                x + size - inset,
+                y + size - inset,
+                1.4,
+                INCORRECT,
+            );
+            self.line(
+                x + inset,
+                y + size - inset,
+                x + size - inset,
+                y + inset,
+                1.4,
+                INCORRECT,
+            );
+        }
+    }
+
+    fn filled_rect(&mut self, x: f64, y: f64, width: f64, height: f64, color: Color) {
+        let Color(r, g, b) = color;
+        let ops = self.ops();
+        ops.push(Operation::new("rg", vec![r.into(), g.into(), b.into()]));
+        ops.push(Operation::new(
+            "re",
+            vec![x.into(), y.into(), width.into(), height.into()],
+        ));
+        ops.push(Operation::new("f", vec![]));
+    }
+
+    /// Breaks the pieces into lines and places them, starting with `marker` left of the first line
+    fn flow(&mut self, pieces: Vec<Piece>, indent: f64, leading: f64, marker: Option<&str>) {
+        let max_width = CONTENT_WIDTH - indent;
+        let mut lines: Vec<Vec<(f64, Piece)>> = vec![Vec::new()];
+        let mut x = 0.0;
+        let mut pending_space = 0.0;
+        for piece in pieces {
+            match piece.kind {
+                PieceKind::Space => {
+                    if x > 0.0 {
+                        pending_space += piece.width();
+                    }
+                    continue;
+                }
+                PieceKind::Break => {
+                    lines.push(Vec::new());
+                    x = 0.0;
+                    pending_space = 0.0;
+                    continue;
+                }
+                _ => {}
+            }
+            let width = piece.width();
+            if x > 0.0 && x + pending_space + width > max_width {
+                lines.push(Vec::new());
+                x = 0.0;
+                pending_space = 0.0;
+            }
+            let current = lines.last_mut().expect("no current line");
+            current.push((x + pending_space, piece));
+            x += pending_space + width;
+            pending_space = 0.0;
+        }
+        for (i, line) in lines.into_iter().enumerate() {
+            self.ensure_space(leading);
+            let baseline = self.y - leading * 0.8;
+            let left = MARGIN + indent;
+            if i == 0 {
+                if let Some(marker) = marker {
+                    self.text_at(left - LIST_INDENT + 4.0, baseline, marker, Style::body());
+                }
+            }
+            for (x, piece) in line {
+                let width = piece.width();
+                let style = piece.style;
+                match piece.kind {
+                    PieceKind::Word(word) => self.text_at(left + x, baseline, &word, style),
+                    PieceKind::Blank(_) => {}
+                    PieceKind::CheckBox(checked) => self.check_box(
+                        left + x,
+                        baseline - 1.5,
+                        style.size * 0.85,
+                        checked,
+                        style.color,
+                    ),
+                    PieceKind::Verdict(correct) => {
+                        self.verdict(left + x, baseline - 1.5, style.size * 0.85, correct)
+                    }
+                    PieceKind::Space | PieceKind::Break => {}
+                }
+                if style.underline {
+                    self.line(
+                        left + x,
+                        baseline - 2.0,
+                        left + x + width,
+                        baseline - 2.0,
+                        0.6,
+                        style.color,
+                    );
+                }
+            }
+            self.skip(leading);
+        }
+    }
+
+    fn inline_pieces(&self, nodes: &[Node], pieces: &mut Vec<Piece>) {
+        for node in nodes {
+            match &node.kind {
+                NodeKind::Text(text) => self.text_pieces(text, &node.marks, pieces),
+                NodeKind::HardBreak => pieces.push(Piece {
+                    kind: PieceKind::Break,
+                    style: Style::body(),
+                }),
+                _ => {
+                    if !pieces.is_empty() {
+                        pieces.push(Piece {
+                            kind: PieceKind::Break,
+                            style: Style::body(),
+                        });
+                    }
+                    self.inline_pieces(&node.content, pieces);
+                }
+            }
+        }
+    }
+
+    fn text_pieces(&self, text: &str, marks: &[Mark], pieces: &mut Vec<Piece>) {
+        let mut style = Style::body();
+        for mark in marks {
+            match mark {
+                Mark::Bold => style.font = style.font.with_bold(),
+                Mark::Italic => style.font = style.font.with_oblique(),
+                Mark::Code => style.font = Font::Mono,
+                _ => {}
+            }
+        }
+        for mark in marks {
+            match mark {
+                Mark::Gap {
+                    solution, answer, ..
+                }
+                | Mark::Numeric { solution, answer }
+                | Mark::Formula { solution, answer } => {
+                    self.answer_pieces(mark, solution, answer, style, pieces);
+                    return;
+                }
+                Mark::Dropdown {
+                    options, answer, ..
+                } => {
+                    if matches!(self.variant, Variant::Worksheet) {
+                        let choices: Vec<&str> =
+                            options.iter().map(|option| option.text.as_str()).collect();
+                        push_text(pieces, &format!("[{}]", choices.join(" / ")), style);
+                    } else {
+                        let correct: Vec<&str> = options
+                            .iter()
+                            .filter(|option| option.correct)
+                            .map(|option| option.text.as_str())
+                            .collect();
+                        self.answer_pieces(mark, &correct.join(" / "), answer, style, pieces);
+                    }
+                    return;
+                }
+                Mark::Latex { source } => {
+                    style.font = style.font.with_oblique();
+                    push_text(pieces, &latex_to_text(source), style);
+                    return;
+                }
+                _ => {}
+            }
+        }
+        push_text(pieces, text, style);
+    }
+
+    fn answer_pieces(
+        &self,
+        mark: &Mark,
+        solution: &str,
+        answer: &str,
+        mut style: Style,
+        pieces: &mut Vec<Piece>,
+    ) {
+        style.underline = true;
+        match self.variant {
+            Variant::Worksheet => {
+                let width = (style.font.text_width(solution, style.size) * 1.5)
+                    .clamp(MIN_GAP_WIDTH, CONTENT_WIDTH / 2.0);
+                pieces.push(Piece {
+                    kind: PieceKind::Blank(width),
+                    style,
+                });
+            }
+            Variant::AnswerKey => {
+                style.color = SOLUTION;
+                style.font = style.font.with_bold();
+                push_text(pieces, solution, style);
+            }
+            Variant::Solution | Variant::OwnSolution => {
+                let correct = grading::mark_correct(mark).unwrap_or(false);
+                style.color = if correct { CORRECT } else { INCORRECT };
+                if answer.is_empty() {
+                    pieces.push(Piece {
+                        kind: PieceKind::Blank(MIN_GAP_WIDTH),
+                        style,
+                    });
+                } else {
+                    push_text(pieces, answer, style);
+                }
+                style.underline = false;
+                pieces.push(Piece {
+                    kind: PieceKind::Verdict(correct),
+                    style,
+                });
+                if !correct && self.variant == Variant::Solution {
+                    push_text(
+                        pieces,
+                        &format!(" ({})", solution),
+                        Style {
+                            color: SOLUTION,
+                            ..style
+                        },
+                    );
+                }
+            }
+        }
+    }
+
+    fn block(&mut self, node: &Node, indent: f64, marker: Option<&str>) {
+        match &node.kind {
+            NodeKind::Doc => self.blocks(&node.content, indent),
+            NodeKind::Heading { level } => {
+                let size = match level {
+                    1 => 18.0,
+                    2 => 15.0,
+                    3 => 13.0,
+                    _ => BODY_SIZE + 1.0,
+                };
+                let mut pieces = Vec::new();
+                self.inline_pieces(&node.content, &mut pieces);
+                for piece in pieces.iter_mut() {
+                    piece.style.font = piece.style.font.with_bold();
+                    piece.style.size = size;
+                }
+                self.skip(size * 0.5);
+                self.ensure_space(size * 3.0);
+                self.flow(pieces, indent, size * 1.3, marker);
+                self.skip(4.0);
+            }
+            NodeKind::Paragraph => {
+                let mut pieces = Vec::new();
+                self.inline_pieces(&node.content, &mut pieces);
+                self.flow(pieces, indent, BODY_SIZE * 1.45, marker);
+                self.skip(5.0);
+            }
+            NodeKind::CodeBlock { .. } => {
+                let leading = CODE_SIZE * 1.35;
+                let style = Style {
+                    font: Font::Mono,
+                    size: CODE_SIZE,
+                    ..Style::body()
+                };
+                let columns = ((CONTENT_WIDTH - indent - 8.0) / (CODE_SIZE * 0.6)) as usize;
+                let columns = columns.max(1);
+                for line in node.text_content().split('\n') {
+                    let chars: Vec<char> = line.chars().collect();
+                    let chunks: Vec<String> = if chars.is_empty() {
+                        vec![String::new()]
+                    } else {
+                        chars.chunks(columns).map(|c| c.iter().collect()).collect()
+                    };
+                    for chunk in chunks {
+                        self.ensure_space(leading);
+                        self.filled_rect(
+                            MARGIN + indent,
+                            self.y - leading,
+                            CONTENT_WIDTH - indent,
+                            leading,
+                            Color(0.95, 0.95, 0.95),
+                        );
+                        self.text_at(
+                            MARGIN + indent + 4.0,
+                            self.y - leading * 0.78,
+                            &chunk,
+                            style,
+                        );
+                        self.skip(leading);
+                    }
+                }
+                self.skip(6.0);
+            }
+            NodeKind::Audio { .. } => {
+                let mut pieces = Vec::new();
+                push_text(
+                    &mut pieces,
+                    "[Audiodatei, nur in der digitalen Fassung verfügbar]",
+                    Style {
+                        font: Font::Oblique,
+                        color: GREY,
+                        ..Style::body()
+                    },
+                );
+                self.flow(pieces, indent, BODY_SIZE * 1.45, marker);
+                self.skip(5.0);
+            }
+            NodeKind::MultipleChoice { mode, .. } => {
+                if *mode == ChoiceMode::Single {
+                    let mut pieces = Vec::new();
+                    push_text(
+                        &mut pieces,
+                        "Genau eine Antwort ist richtig.",
+                        Style {
+                            font: Font::Oblique,
+                            color: GREY,
+                            ..Style::body()
+                        },
+                    );
+                    self.flow(pieces, indent, BODY_SIZE * 1.45, None);
+                }
+                for answer in &node.content {
+                    self.block(answer, indent, None);
+                }
+                self.skip(4.0);
+            }
+            NodeKind::MultipleChoiceAnswer { solution, answer } => {
+                let (checked, color) = match self.variant {
+                    Variant::Worksheet => (false, BLACK),
+                    Variant::AnswerKey => (*solution, SOLUTION),
+                    Variant::Solution | Variant::OwnSolution => (*answer, BLACK),
+                };
+                let mut pieces = vec![
+                    Piece {
+                        kind: PieceKind::CheckBox(checked),
+                        style: Style {
+                            color,
+                            ..Style::body()
+                        },
+                    },
+                    Piece {
+                        kind: PieceKind::Blank(6.0),
+                        style: Style::body(),
+                    },
+                ];
+                self.inline_pieces(&node.content, &mut pieces);
+                if self.variant.graded() {
+                    pieces.push(Piece {
+                        kind: PieceKind::Blank(6.0),
+                        style: Style::body(),
+                    });
+                    pieces.push(Piece {
+                        kind: PieceKind::Verdict(grading::answer_correct(node)),
+                        style: Style::body(),
+                    });
+                }
+                self.flow(pieces, indent, BODY_SIZE * 1.6, None);
+            }
+            NodeKind::BulletList | NodeKind::OrderedList => {
+                let ordered = node.kind == NodeKind::OrderedList;
+                for (i, item) in node.content.iter().enumerate() {
+                    let marker = if ordered {
+                        format!("{}.", i + 1)
+                    } else {
+                        "•".to_string()
+                    };
+                    self.list_item(item, indent + LIST_INDENT, &marker);
+                }
+            }
+            NodeKind::ListItem => self.list_item(node, indent + LIST_INDENT, "•"),
+            NodeKind::Blockquote => {
+                let top = self.y;
+                let page = self.pages.len();
+                self.blocks(&node.content, indent + LIST_INDENT);
+                if page == self.pages.len() {
+                    let x = MARGIN + indent + 4.0;
+                    self.line(x, top, x, self.y + 5.0, 2.0, GREY);
+                }
+            }
+            NodeKind::HorizontalRule => {
+                self.ensure_space(12.0);
+                self.skip(6.0);
+                self.line(
+                    MARGIN + indent,
+                    self.y,
+                    MARGIN + CONTENT_WIDTH,
+                    self.y,
+                    0.5,
+                    GREY,
+                );
+                self.skip(6.0);
+            }
+            NodeKind::Text(_) | NodeKind::HardBreak => {
+                let mut pieces = Vec::new();
+                self.inline_pieces(std::slice::from_ref(node), &mut pieces);
+                self.flow(pieces, indent, BODY_SIZE * 1.45, marker);
+            }
+            NodeKind::Pool { draw, drawn } => {
+                let note = match drawn {
+                    Some(drawn) => format!(
+                        "Zufällig gezogene Aufgaben: {}",
+                        drawn
+                            .iter()
+                            .map(|i| (i + 1).to_string())
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    ),
+                    None => format!(
+                        "Zufällige Auswahl: {} von {} Aufgaben",
+                        draw,
+                        node.content.len()
+                    ),
+                };
+                let mut pieces = Vec::new();
+                push_text(
+                    &mut pieces,
+                    &note,
+                    Style {
+                        font: Font::Oblique,
+                        color: GREY,
+                        ..Style::body()
+                    },
+                );
+                self.flow(pieces, indent, BODY_SIZE * 1.45, marker);
+                self.skip(5.0);
+                self.blocks(&node.content, indent);
+            }
+            NodeKind::Variables(variables) => {
+                let listed: Vec<String> = match self.variant {
+                    Variant::Worksheet => return,
+                    Variant::AnswerKey => variables
+                        .iter()
+                        .map(|v| format!("{} = {}", v.name, v.definition))
+                        .collect(),
+                    Variant::Solution | Variant::OwnSolution => variables
+                        .iter()
+                        .filter_map(|v| {
+                            v.value
+                                .map(|value| format!("{} = {}", v.name, format_number(value)))
+                        })
+                        .collect(),
+                };
+                if listed.is_empty() {
+                    return;
+                }
+                let label = if self.variant.graded() {
+                    "Werte"
+                } else {
+                    "Variablen"
+                };
+                let mut pieces = Vec::new();
+                push_text(
+                    &mut pieces,
+                    &format!("{}: {}", label, listed.join("; ")),
+                    Style {
+                        font: Font::Oblique,
+                        color: GREY,
+                        ..Style::body()
+                    },
+                );
+                self.flow(pieces, indent, BODY_SIZE * 1.45, marker);
+                self.skip(5.0);
+            }
+            NodeKind::Essay {
+                min_words,
+                max_words,
+                points,
+                model_answer,
+                answer,
+                awarded,
+            } => {
+                let limits = essay::word_limits(*min_words, *max_words);
+                let mut note = format!(
+                    "Freitext{}, {}",
+                    if limits.is_empty() {
+                        String::new()
+                    } else {
+                        format!(" ({})", limits)
+                    },
+                    if *points == 1 {
+                        "1 Punkt".to_string()
+                    } else {
+                        format!("{} Punkte", points)
+                    }
+                );
+                if self.variant.graded() {
+                    note.push_str(&match awarded {
+                        Some(awarded) => format!(", bewertet mit {}", format_points(*awarded)),
+                        None => ", noch nicht bewertet".to_string(),
+                    });
+                }
+                let grey = Style {
+                    font: Font::Oblique,
+                    color: GREY,
+                    ..Style::body()
+                };
+                let mut pieces = Vec::new();
+                push_text(&mut pieces, &note, grey);
+                self.flow(pieces, indent, BODY_SIZE * 1.45, marker);
+                match self.variant {
+                    Variant::Worksheet => {
+                        // Ruled lines to write on, a few more for long answers
+                        let lines = max_words.or(*min_words).map_or(6, |words| words / 12 + 2);
+                        for _ in 0..lines.clamp(3, 20) {
+                            self.ensure_space(BODY_SIZE * 2.0);
+                            self.skip(BODY_SIZE * 2.0);
+                            self.line(
+                                MARGIN + indent,
+                                self.y,
+                                MARGIN + CONTENT_WIDTH,
+                                self.y,
+                                0.5,
+                                GREY,
+                            );
+                        }
+                    }
+                    Variant::AnswerKey => {
+                        let mut pieces = Vec::new();
+                        push_text(
+                            &mut pieces,
+                            model_answer,
+                            Style {
+                                color: SOLUTION,
+                                ..Style::body()
+                            },
+                        );
+                        self.flow(pieces, indent, BODY_SIZE * 1.45, None);
+                    }
+                    Variant::Solution | Variant::OwnSolution => {
+                        let mut pieces = Vec::new();
+                        push_text(&mut pieces, answer, Style::body());
+                        self.flow(pieces, indent, BODY_SIZE * 1.45, None);
+                    }
+                }
+                self.skip(5.0);
+            }
+            NodeKind::PoolItem => {
+                self.blocks(&node.content, indent);
+                self.skip(4.0);
+            }
+            NodeKind::Other(_) => self.blocks(&node.content, indent),
+        }
+    }
+
+    fn list_item(&mut self, item: &Node, indent: f64, marker: &str) {
+        let mut marker = Some(marker);
+        for child in &item.content {
+            self.block(child, indent, marker.take());
+        }
+    }
+
+    fn blocks(&mut self, nodes: &[Node], indent: f64) {
+        for node in nodes {
+            self.block(node, indent, None);
+        }
+    }
+
+    fn header(&mut self, title: &str, subtitles: &[String]) {
+        let title_style = Style {
+            font: Font::Bold,
+            size: 20.0,
+            ..Style::body()
+        };
+        let mut pieces = Vec::new();
+        push_text(&mut pieces, title, title_style);
+        self.flow(pieces, 0.0, 26.0, None);
+        for subtitle in subtitles {
+            let mut pieces = Vec::new();
+            push_text(
+                &mut pieces,
+                subtitle,
+                Style {
+                    color: GREY,
+                    ..Style::body()
+                },
+            );
+            self.flow(pieces, 0.0, 16.0, None);
+        }
+        if self.variant == Variant::Worksheet {
+            self.skip(10.0);
+            let baseline = self.y - 12.0;
+            self.text_at(MARGIN, baseline, "Name:", Style::body());
+            self.line(
+                MARGIN + 36.0,
+                baseline - 2.0,
+                MARGIN + 260.0,
+                baseline - 2.0,
+                0.6,
+                BLACK,
+            );
+            self.text_at(MARGIN + 290.0, baseline, "Datum:", Style::body());
+            self.line(
+                MARGIN + 330.0,
+                baseline - 2.0,
+                MARGIN + CONTENT_WIDTH,
+                baseline - 2.0,
+                0.6,
+                BLACK,
+            );
+            self.skip(16.0);
+        }
+        self.skip(8.0);
+        self.line(MARGIN, self.y, MARGIN + CONTENT_WIDTH, self.y, 0.8, GREY);
+        self.skip(14.0);
+    }
+
+    fn feedback(&mut self, feedback: &str) {
+        self.skip(12.0);
+        let mut pieces = Vec::new();
+        push_text(
+            &mut pieces,
+            "Rückmeldung der Lehrkraft",
+            Style {
+                font: Font::Bold,
+                ..Style::body()
+            },
+        );
+        self.flow(pieces, 0.0, 16.0, None);
+        let mut pieces = Vec::new();
+        push_text(&mut pieces, feedback, Style::body());
+        self.flow(pieces, 0.0, 16.0, None);
+    }
+
+    fn footers(&mut self) {
+        let total = self.pages.len();
+        let style = Style {
+            size: 9.0,
+            color: GREY,
+            ..Style::body()
+        };
+        for page in 0..total {
+            self.page = page;
+            let text = format!("Seite {} von {}", page + 1, total);
+            let width = style.font.text_width(&text, style.size);
+            self.text_at(PAGE_WIDTH - MARGIN - width, MARGIN / 2.0, &text, style);
+        }
+    }
+
+    fn finish(mut self, title: &str) -> Result<Vec<u8>, Error> {
+        self.footers();
+        let mut doc = Document::with_version("1.5");
+        let pages_id = doc.new_object_id();
+        let mut fonts = lopdf::Dictionary::new();
+        for font in Font::ALL.iter() {
+            let id = doc.add_object(dictionary! {
+                "Type" => "Font",
+                "Subtype" => "Type1",
+                "BaseFont" => font.base_font(),
+                "Encoding" => "WinAnsiEncoding",
+            });
+            fonts.set(font.resource(), id);
+        }
+        let resources_id = doc.add_object(dictionary! {
+            "Font" => fonts,
+        });
+        let mut kids: Vec<Object> = Vec::new();
+        for operations in self.pages {
+            let content = Content { operations };
+            let content_id = doc.add_object(Stream::new(dictionary! {}, content.encode()?));
+            let page_id: ObjectId = doc.add_object(dictionary! {
+                "Type" => "Page",
+                "Parent" => pages_id,
+                "Contents" => content_id,
+            });
+            kids.push(page_id.into());
+        }
+        let count = kids.len() as i64;
+        doc.objects.insert(
+            pages_id,
+            Object::Dictionary(dictionary! {
+                "Type" => "Pages",
+                "Kids" => kids,
+                "Count" => count,
+                "Resources" => resources_id,
+                "MediaBox" => vec![0.into(), 0.into(), PAGE_WIDTH.into(), PAGE_HEIGHT.into()],
+            }),
+        );
+        let catalog_id = doc.add_object(dictionary! {
+            "Type" => "Catalog",
+            "Pages" => pages_id,
+        });
+        let info_id = doc.add_object(dictionary! {
+            "Title" => Object::String(encode(title), StringFormat::Literal),
+            "Producer" => Object::string_literal("hci-bildung"),
+        });
+        doc.trailer.set("Root", catalog_id);
+        doc.trailer.set("Info", info_id);
+        doc.compress();
+        let mut pdf = Vec::new();
+        doc.save_to(&mut pdf).map_err(lopdf::Error::IO)?;
+        Ok(pdf)
+    }
+}
+
+/// Renders a sheet as printable PDF, either as blank worksheet or with the solutions filled in
+pub fn render_sheet(title: &str, content: &Node, variant: Variant) -> Result<Vec<u8>, Error> {
+    let mut renderer = Renderer::new(variant);
+    let subtitles = match variant {
+        Variant::AnswerKey => vec!["Lösungsschlüssel".to_string()],
+        Variant::Worksheet | Variant::Solution | Variant::OwnSolution => Vec::new(),
+    };
+    renderer.header(title, &subtitles);
+    renderer.block(content, 0.0, None);
+    renderer.finish(title)
+}
+
+/// Partial credit is shown with up to two decimals and a decimal comma
+fn format_points(points: f64) -> String {
+    let formatted = format!("{:.2}", points);
+    formatted
+        .trim_end_matches('0')
+        .trim_end_matches('.')
+        .replace('.', ",")
+}
+
+/// Renders graded solutions of a sheet, each one starting on a new page
+pub fn render_solutions(
+    title: &str,
+    solutions: &[SolutionDocument],
+    variant: Variant,
+) -> Result<Vec<u8>, Error> {
+    let mut renderer = Renderer::new(variant);
+    for (i, solution) in solutions.iter().enumerate() {
+        if i > 0 {
+            renderer.new_page();
+        }
+        let Score {
+            achieved,
+            total,
+            ungraded,
+        } = grading::grade(&solution.content);
+        let mut points = format!("Punkte: {} von {}", format_points(achieved), total);
+        if ungraded > 0 {
+            points.push_str(&format!(" ({} noch nicht bewertet)", ungraded));
+        }
+        renderer.header(
+            title,
+            &[
+                format!(
+                    "Lösung von {}, bearbeitet am {}",
+                    solution.student, solution.changed
+                ),
+                points,
+            ],
+        );
+        renderer.block(&solution.content, 0.0, None);
+        if !solution.feedback.is_empty() {
+            renderer.feedback(&solution.feedback);
+        }
+    }
+    renderer.finish(title)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::*;
+
+    fn sheet(blocks: Vec<Value>) -> Node {
+        Node::from_json(&json!({"type": "doc", "marks": [], "content": blocks}))
+    }
+
+    fn paragraph(content: Vec<Value>) -> Value {
+        json!({"type": "paragraph", "marks": [], "content": content})
+    }
+
+    fn text(text: &str, marks: Vec<Value>) -> Value {
+        json!({"type": "text", "text": text, "marks": marks, "content": []})
+    }
+
+    /// The text shown on each page, without the footers
+    fn pages(content: &Node, variant: Variant) -> Vec<String> {
+        let mut renderer = Renderer::new(variant);
+        renderer.header("Hauptstädte", &[]);
+        renderer.block(content, 0.0, None);
+        texts(&renderer)
+    }
+
+    fn texts(renderer: &Renderer) -> Vec<String> {
+        renderer
+            .pages
+            .iter()
+            .map(|operations| {
+                operations
+                    .iter()
+                    .filter(|op| op.operator == "Tj")
+                    .filter_map(|op| match &op.operands[0] {
+                        Object::String(bytes, _) => {
+                            Some(bytes.iter().map(|&b| b as char).collect())
+                        }
+                        _ => None,
+                    })
+                    .collect::<Vec<String>>()
+                    .join(" ")
+            })
+            .collect()
+    }
+
+    fn tasks() -> Node {
+        sheet(vec![
+            paragraph(vec![
+                text("Die Hauptstadt von Frankreich ist ", vec![]),
+                text(
+                    "Paris",
+                    vec![json!({"type": "gap", "solution": "Paris", "answer": ""})],
+                ),
+            ]),
+            paragraph(vec![text(
+                "Rom",
+                vec![json!({"type": "dropdown", "answer": "", "options": [
+                    {"text": "Rom", "correct": true},
+                    {"text": "Mailand", "correct": false},
+                ]})],
+            )]),
+            json!({"type": "essay", "points": 2, "modelAnswer": "Wegen der Lage am Tiber",
+                "marks": [], "content": []}),
+        ])
+    }
+
+    #[test]
+    fn worksheets_hide_solutions() {
+        let worksheet = pages(&tasks(), Variant::Worksheet).join(" ");
+        assert!(worksheet.contains("Frankreich"));
+        assert!(!worksheet.contains("Paris"));
+        // All options are offered, without telling which one is correct
+        assert!(worksheet.contains("[Rom / Mailand]"));
+        assert!(!worksheet.contains("Tiber"));
+    }
+
+    #[test]
+    fn answer_keys_show_solutions() {
+        let answer_key = pages(&tasks(), Variant::AnswerKey).join(" ");
+        assert!(answer_key.contains("Paris"));
+        assert!(!answer_key.contains("Mailand"));
+        assert!(answer_key.contains("Tiber"));
+    }
+
+    #[test]
+    fn long_content_continues_on_new_pages() {
+        let blocks = (1..=120)
+            .map(|i| paragraph(vec![text(&format!("Absatz{}", i), vec![])]))
+            .collect();
+        let pages = pages(&sheet(blocks), Variant::Worksheet);
+        assert!(pages.len() > 2);
+        // Every paragraph is shown exactly once, in order
+        let words: Vec<&str> = pages.iter().flat_map(|page| page.split(' ')).collect();
+        let paragraphs: Vec<&str> = words
+            .into_iter()
+            .filter(|word| word.starts_with("Absatz"))
+            .collect();
+        let expected: Vec<String> = (1..=120).map(|i| format!("Absatz{}", i)).collect();
+        assert_eq!(paragraphs, expected);
+    }
+
+    #[test]
+    fn own_solutions_hide_the_solutions_to_incorrect_answers() {
+        let solution = pages(&tasks(), Variant::Solution).join(" ");
+        assert!(solution.contains("(Paris)"));
+        assert!(solution.contains("(Rom)"));
+        let own_solution = pages(&tasks(), Variant::OwnSolution).join(" ");
+        assert!(own_solution.contains("Frankreich"));
+        assert!(!own_solution.contains("Paris"));
+        assert!(!own_solution.contains("(Rom)"));
+        assert!(!own_solution.contains("Tiber"));
+    }
+
+    #[test]
+    fn solutions_show_feedback() {
+        let mut renderer = Renderer::new(Variant::Solution);
+        renderer.block(&tasks(), 0.0, None);
+        renderer.feedback("Gut gemacht,\nnur Rom fehlt");
+        let solution = texts(&renderer).join(" ");
+        assert!(solution.contains("Gut gemacht,"));
+        assert!(solution.contains("nur Rom fehlt"));
+    }
+
+    #[test]
+    fn renders_every_page() {
+        let blocks = (1..=120)
+            .map(|i| paragraph(vec![text(&format!("Absatz {}", i), vec![])]))
+            .collect();
+        let pdf = render_sheet("Lang", &sheet(blocks), Variant::Worksheet).unwrap();
+        let pdf = String::from_utf8_lossy(&pdf);
+        assert!(pdf.starts_with("%PDF-1.5"));
+        let pages = pdf.matches("/Type/Page/").count();
+        assert!(pages > 2);
+        assert!(pdf.contains(&format!("/Count {}/", pages)));
+    }
+}
@@ -0,0 +1,103 @@
+//! Random draws from pools of exercises.
+//!
+//! A `pool` node holds `poolItem` nodes, each of them an exercise. When a student starts solving,
+//! the solution gets a random selection of `draw` items in their order on the sheet. Everything
+//! else is left out of the solution, so grading only sees the drawn items, and the pool records
+//! their positions in `drawn`.
+//!
+//! Like [`super::shuffle`] this works on the JSON content.
+
+use rand::seq::index;
+use rand::Rng;
+use serde_json::{json, Value};
+
+/// Replaces the items of every pool not drawn from yet by a random selection
+pub fn draw_pools<R: Rng>(content: &mut Value, rng: &mut R) {
+    if content.get("type").and_then(Value::as_str) == Some("pool") && content.get("drawn").is_none()
+    {
+        draw(content, rng);
+    }
+    if let Some(children) = content.get_mut("content").and_then(Value::as_array_mut) {
+        for child in children {
+            draw_pools(child, rng);
+        }
+    }
+}
+
+fn draw<R: Rng>(pool: &mut Value, rng: &mut R) {
+    let items = match pool.get_mut("content").and_then(Value::as_array_mut) {
+        Some(items) => std::mem::take(items),
+        None => Vec::new(),
+    };
+    let amount = pool
+        .get("draw")
+        .and_then(Value::as_u64)
+        .unwrap_or(1)
+        .min(items.len() as u64) as usize;
+    let mut drawn = index::sample(rng, items.len(), amount).into_vec();
+    drawn.sort_unstable();
+    let mut items: Vec<Option<Value>> = items.into_iter().map(Some).collect();
+    let content: Vec<Value> = drawn.iter().filter_map(|&i| items[i].take()).collect();
+    if let Some(pool) = pool.as_object_mut() {
+        pool.insert("content".into(), Value::Array(content));
+        pool.insert("drawn".into(), json!(drawn));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+
+    fn item(text: &str) -> Value {
+        json!({"type": "poolItem", "content": [
+            {"type": "paragraph", "content": [{"type": "text", "text": text}]},
+        ]})
+    }
+
+    fn texts(pool: &Value) -> Vec<&str> {
+        pool["content"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|item| item["content"][0]["content"][0]["text"].as_str().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn draws_items_in_sheet_order() {
+        let items: Vec<String> = (0..20).map(|i| i.to_string()).collect();
+        let sheet = json!({"type": "doc", "content": [
+            {"type": "pool", "draw": 5, "content": items.iter().map(|i| item(i)).collect::<Vec<_>>()},
+        ]});
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut solution = sheet.clone();
+        draw_pools(&mut solution, &mut rng);
+        let pool = &solution["content"][0];
+        let drawn: Vec<usize> = pool["drawn"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|i| i.as_u64().unwrap() as usize)
+            .collect();
+        assert_eq!(drawn.len(), 5);
+        assert!(drawn.windows(2).all(|pair| pair[0] < pair[1]));
+        let expected: Vec<String> = drawn.iter().map(|i| i.to_string()).collect();
+        assert_eq!(texts(pool), expected);
+
+        // Pools of a solution are not drawn from again
+        let before = solution.clone();
+        draw_pools(&mut solution, &mut rng);
+        assert_eq!(solution, before);
+    }
+
+    #[test]
+    fn draws_at_most_all_items() {
+        let mut pool = json!({"type": "pool", "draw": 3, "content": [item("a"), item("b")]});
+        draw_pools(&mut pool, &mut StdRng::seed_from_u64(1));
+        assert_eq!(texts(&pool), vec!["a", "b"]);
+        assert_eq!(pool["drawn"], json!([0, 1]));
+    }
+}
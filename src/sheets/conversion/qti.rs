@@ -0,0 +1,1083 @@
+//! Conversion between sheets and IMS QTI content packages.
+//!
+//! Sheets are exported as QTI 2.1 packages. Like for Moodle, a sheet is split into one item per
+//! multiple choice block or essay: the item body holds the content before the block with a
+//! `textEntryInteraction` for every gap and an `inlineChoiceInteraction` for every dropdown,
+//! followed by a `choiceInteraction` or an `extendedTextInteraction`. Partial credit and negative
+//! marking are expressed as a response mapping bounded by zero and one point. Essays are left out
+//! of the response processing, their model answer is the correct response and their points count
+//! towards the maximum score of the item. QTI has no word limits, so these are left out. Pools
+//! become sections that select `draw` of their subsections, one per item holding its items.
+//! Packages in QTI 2.x and 3.0 can be imported.
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+use roxmltree::Document;
+
+use super::document::{ChoiceMode, Mark, Node, NodeKind, Scoring};
+use super::html::{self, Content, Dialect, Element, Reader};
+use super::numeric::NumericTask;
+use super::Error;
+
+pub const MANIFEST: &str = "imsmanifest.xml";
+
+const TEST_FILE: &str = "assessment.xml";
+const QTI_NAMESPACE: &str = "http://www.imsglobal.org/xsd/imsqti_v2p1";
+const QTI_SCHEMA: &str = "http://www.imsglobal.org/xsd/qti/qtiv2p1/imsqti_v2p1p2.xsd";
+const CP_NAMESPACE: &str = "http://www.imsglobal.org/xsd/imscp_v1p1";
+
+#[derive(Debug)]
+pub struct QtiImport {
+    pub title: Option<String>,
+    pub content: Node,
+    /// Items and content that could not be converted
+    pub unsupported: Vec<String>,
+}
+
+enum Response {
+    Text(String),
+    Numeric {
+        value: f64,
+        tolerance: f64,
+    },
+    Choice {
+        correct: Vec<String>,
+        single: bool,
+        /// Points for ticking each choice if the block grants partial credit
+        mapping: Option<Vec<(String, f64)>>,
+    },
+    /// The identifiers of the correct options of a dropdown
+    InlineChoice(Vec<String>),
+    Essay {
+        model_answer: String,
+        points: u32,
+    },
+}
+
+// Export
+
+/// Returns the files of the package with their paths
+pub fn to_qti_package(title: &str, content: &Node) -> Vec<(String, String)> {
+    let mut items = Vec::new();
+    let mut section = String::new();
+    let mut pending = Vec::new();
+    let mut pools = 0;
+    for block in &content.content {
+        let draw = match block.kind {
+            NodeKind::Pool { draw, .. } => draw,
+            _ => {
+                pending.push(block.clone());
+                continue;
+            }
+        };
+        for identifier in add_items(title, &pending, &mut items) {
+            section.push_str(&item_ref(&identifier, 6));
+        }
+        pending.clear();
+        pools += 1;
+        section.push_str(&format!(
+            "      <assessmentSection identifier=\"pool-{0}\" title=\"Pool {0}\" visible=\"true\">\n        <selection select=\"{1}\"/>\n",
+            pools, draw
+        ));
+        for (i, item) in block.content.iter().enumerate() {
+            section.push_str(&format!(
+                "        <assessmentSection identifier=\"pool-{}-{}\" title=\"Aufgabe {}\" visible=\"false\" keepTogether=\"true\">\n",
+                pools,
+                i + 1,
+                i + 1
+            ));
+            for identifier in add_items(title, &item.content, &mut items) {
+                section.push_str(&item_ref(&identifier, 10));
+            }
+            section.push_str("        </assessmentSection>\n");
+        }
+        section.push_str("      </assessmentSection>\n");
+    }
+    for identifier in add_items(title, &pending, &mut items) {
+        section.push_str(&item_ref(&identifier, 6));
+    }
+
+    let mut manifest = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<manifest xmlns=\"{}\" identifier=\"manifest\">\n  <metadata>\n    <schema>QTIv2.1 Package</schema>\n    <schemaversion>1.0.0</schemaversion>\n  </metadata>\n  <organizations/>\n  <resources>\n",
+        CP_NAMESPACE
+    );
+    manifest.push_str(&format!(
+        "    <resource identifier=\"test\" type=\"imsqti_test_xmlv2p1\" href=\"{0}\">\n      <file href=\"{0}\"/>\n",
+        TEST_FILE
+    ));
+    for (identifier, _) in &items {
+        manifest.push_str(&format!(
+            "      <dependency identifierref=\"{}\"/>\n",
+            identifier
+        ));
+    }
+    manifest.push_str("    </resource>\n");
+    for (identifier, _) in &items {
+        manifest.push_str(&format!(
+            "    <resource identifier=\"{0}\" type=\"imsqti_item_xmlv2p1\" href=\"items/{0}.xml\">\n      <file href=\"items/{0}.xml\"/>\n    </resource>\n",
+            identifier
+        ));
+    }
+    manifest.push_str("  </resources>\n</manifest>\n");
+
+    let mut test = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<assessmentTest xmlns=\"{}\" xmlns:xsi=\"http://www.w3.org/2001/XMLSchema-instance\" xsi:schemaLocation=\"{} {}\" identifier=\"test\" title=\"{}\">\n",
+        QTI_NAMESPACE,
+        QTI_NAMESPACE,
+        QTI_SCHEMA,
+        html::escape(title)
+    );
+    test.push_str(&outcome_declaration("SCORE", 0));
+    test.push_str("  <testPart identifier=\"part\" navigationMode=\"nonlinear\" submissionMode=\"simultaneous\">\n");
+    test.push_str(&format!(
+        "    <assessmentSection identifier=\"section\" title=\"{}\" visible=\"true\">\n",
+        html::escape(title)
+    ));
+    test.push_str(&section);
+    test.push_str("    </assessmentSection>\n  </testPart>\n");
+    test.push_str("  <outcomeProcessing>\n    <setOutcomeValue identifier=\"SCORE\">\n      <sum>\n        <testVariables variableIdentifier=\"SCORE\"/>\n      </sum>\n    </setOutcomeValue>\n  </outcomeProcessing>\n");
+    test.push_str("</assessmentTest>\n");
+
+    let mut files = vec![
+        (MANIFEST.to_string(), manifest),
+        (TEST_FILE.to_string(), test),
+    ];
+    files.extend(
+        items
+            .into_iter()
+            .map(|(identifier, xml)| (format!("items/{}.xml", identifier), xml)),
+    );
+    files
+}
+
+/// Writes the items of the blocks, numbered on from the items written so far, and returns their
+/// identifiers
+fn add_items(title: &str, blocks: &[Node], items: &mut Vec<(String, String)>) -> Vec<String> {
+    split_items(blocks)
+        .iter()
+        .map(|(text, interaction)| {
+            let number = items.len() + 1;
+            let identifier = format!("item-{}", number);
+            let item_title = format!("{} - Frage {}", title, number);
+            let xml = write_item(&identifier, &item_title, text, *interaction);
+            items.push((identifier.clone(), xml));
+            identifier
+        })
+        .collect()
+}
+
+fn item_ref(identifier: &str, indent: usize) -> String {
+    format!(
+        "{}<assessmentItemRef identifier=\"{1}\" href=\"items/{1}.xml\"/>\n",
+        " ".repeat(indent),
+        identifier
+    )
+}
+
+/// Splits the blocks into item bodies, each ending with a multiple choice block or an essay except
+/// for the last one
+fn split_items(blocks: &[Node]) -> Vec<(Vec<Node>, Option<&Node>)> {
+    let mut items = Vec::new();
+    let mut pending = Vec::new();
+    for block in blocks {
+        if let NodeKind::MultipleChoice { .. } | NodeKind::Essay { .. } = block.kind {
+            items.push((std::mem::take(&mut pending), Some(block)));
+        } else {
+            pending.push(block.clone());
+        }
+    }
+    if !pending.is_empty() {
+        items.push((pending, None));
+    }
+    items
+}
+
+fn write_item(identifier: &str, title: &str, text: &[Node], interaction: Option<&Node>) -> String {
+    let mut responses = Vec::new();
+    let mut body = html::write_blocks(text, Dialect::Qti, &mut |mark| {
+        if let Mark::Dropdown {
+            options, shuffle, ..
+        } = mark
+        {
+            let response = format!("RESPONSE_{}", responses.len() + 1);
+            let mut correct = Vec::new();
+            let mut choices = String::new();
+            for (i, option) in options.iter().enumerate() {
+                let identifier = format!("{}_CHOICE_{}", response, i + 1);
+                if option.correct {
+                    correct.push(identifier.clone());
+                }
+                choices.push_str(&format!(
+                    "<inlineChoice identifier=\"{}\">{}</inlineChoice>",
+                    identifier,
+                    html::escape(&option.text)
+                ));
+            }
+            responses.push(Response::InlineChoice(correct));
+            return format!(
+                "<inlineChoiceInteraction responseIdentifier=\"{}\" shuffle=\"{}\">{}</inlineChoiceInteraction>",
+                response, shuffle, choices
+            );
+        }
+        let task = match mark {
+            Mark::Numeric { solution, .. } => NumericTask::parse(solution),
+            _ => None,
+        };
+        responses.push(match (task, mark) {
+            // QTI has no units, so the value is expected without one
+            (Some(task), _) => Response::Numeric {
+                value: task.value,
+                tolerance: task.absolute_tolerance(),
+            },
+            (
+                None,
+                Mark::Gap { solution, .. }
+                | Mark::Numeric { solution, .. }
+                | Mark::Formula { solution, .. },
+            ) => Response::Text(solution.to_string()),
+            _ => Response::Text(String::new()),
+        });
+        format!(
+            "<textEntryInteraction responseIdentifier=\"RESPONSE_{}\"/>",
+            responses.len()
+        )
+    });
+    if let Some(NodeKind::Essay {
+        points,
+        model_answer,
+        ..
+    }) = interaction.map(|essay| &essay.kind)
+    {
+        responses.push(Response::Essay {
+            model_answer: model_answer.clone(),
+            points: *points,
+        });
+        body.push_str(&format!(
+            "<extendedTextInteraction responseIdentifier=\"RESPONSE_{}\"/>",
+            responses.len()
+        ));
+    } else if let Some(choice) = interaction {
+        let mut correct = Vec::new();
+        let mut wrong = Vec::new();
+        let mut choices = String::new();
+        for (i, answer) in choice.content.iter().enumerate() {
+            let identifier = format!("CHOICE_{}", i + 1);
+            if let NodeKind::MultipleChoiceAnswer { solution: true, .. } = answer.kind {
+                correct.push(identifier.clone());
+            } else {
+                wrong.push(identifier.clone());
+            }
+            choices.push_str(&format!(
+                "<simpleChoice identifier=\"{}\">{}</simpleChoice>",
+                identifier,
+                html::write_blocks(&answer.content, Dialect::Qti, &mut html::solution_text)
+            ));
+        }
+        let (single, scoring, shuffle) = match choice.kind {
+            NodeKind::MultipleChoice {
+                mode,
+                scoring,
+                shuffle,
+            } => (mode == ChoiceMode::Single, scoring, shuffle),
+            _ => (false, Scoring::AllOrNothing, false),
+        };
+        let mapping = if single || correct.is_empty() || scoring == Scoring::AllOrNothing {
+            None
+        } else {
+            let credit = 1.0 / correct.len() as f64;
+            let penalty = if scoring == Scoring::NegativeMarking && !wrong.is_empty() {
+                -1.0 / wrong.len() as f64
+            } else {
+                0.0
+            };
+            Some(
+                correct
+                    .iter()
+                    .map(|identifier| (identifier.clone(), credit))
+                    .chain(wrong.iter().map(|identifier| (identifier.clone(), penalty)))
+                    .collect(),
+            )
+        };
+        responses.push(Response::Choice {
+            correct,
+            single,
+            mapping,
+        });
+        body.push_str(&format!(
+            "<choiceInteraction responseIdentifier=\"RESPONSE_{}\" shuffle=\"{}\" maxChoices=\"{}\">{}</choiceInteraction>",
+            responses.len(),
+            shuffle,
+            if single { 1 } else { 0 },
+            choices
+        ));
+    }
+
+    let mut xml = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<assessmentItem xmlns=\"{}\" xmlns:xsi=\"http://www.w3.org/2001/XMLSchema-instance\" xsi:schemaLocation=\"{} {}\" identifier=\"{}\" title=\"{}\" adaptive=\"false\" timeDependent=\"false\">\n",
+        QTI_NAMESPACE,
+        QTI_NAMESPACE,
+        QTI_SCHEMA,
+        identifier,
+        html::escape(title)
+    );
+    for (i, response) in responses.iter().enumerate() {
+        let numeric;
+        let (cardinality, base_type, values) = match response {
+            Response::Text(solution) => ("single", "string", std::slice::from_ref(solution)),
+            Response::Numeric { value, .. } => {
+                numeric = value.to_string();
+                ("single", "float", std::slice::from_ref(&numeric))
+            }
+            Response::Choice {
+                correct,
+                single: true,
+                ..
+            } => ("single", "identifier", correct.as_slice()),
+            Response::Choice { correct, .. } => ("multiple", "identifier", correct.as_slice()),
+            // A single cardinality allows only one correct value, the others are matched below
+            Response::InlineChoice(correct) => {
+                ("single", "identifier", &correct[..correct.len().min(1)])
+            }
+            Response::Essay { model_answer, .. } if model_answer.is_empty() => {
+                ("single", "string", &[][..])
+            }
+            Response::Essay { model_answer, .. } => {
+                ("single", "string", std::slice::from_ref(model_answer))
+            }
+        };
+        xml.push_str(&format!(
+            "  <responseDeclaration identifier=\"RESPONSE_{}\" cardinality=\"{}\" baseType=\"{}\">\n",
+            i + 1,
+            cardinality,
+            base_type
+        ));
+        if !values.is_empty() {
+            xml.push_str("    <correctResponse>\n");
+            for value in values {
+                xml.push_str(&format!("      <value>{}</value>\n", html::escape(value)));
+            }
+            xml.push_str("    </correctResponse>\n");
+        }
+        if let Response::Choice {
+            mapping: Some(mapping),
+            ..
+        } = response
+        {
+            xml.push_str("    <mapping lowerBound=\"0\" upperBound=\"1\" defaultValue=\"0\">\n");
+            for (key, value) in mapping {
+                xml.push_str(&format!(
+                    "      <mapEntry mapKey=\"{}\" mappedValue=\"{}\"/>\n",
+                    key, value
+                ));
+            }
+            xml.push_str("    </mapping>\n");
+        }
+        xml.push_str("  </responseDeclaration>\n");
+    }
+    xml.push_str(&outcome_declaration("SCORE", 0));
+    let max_score = responses
+        .iter()
+        .map(|response| match response {
+            Response::Essay { points, .. } => *points as usize,
+            _ => 1,
+        })
+        .sum();
+    xml.push_str(&outcome_declaration("MAXSCORE", max_score));
+    xml.push_str(&format!("  <itemBody>{}</itemBody>\n", body));
+    // Every interaction is worth one point, like gaps and multiple choice blocks on a sheet
+    xml.push_str("  <responseProcessing>\n");
+    for (i, response) in responses.iter().enumerate() {
+        // Essays are scored by hand
+        if let Response::Essay { .. } = response {
+            continue;
+        }
+        if let Response::Choice {
+            mapping: Some(_), ..
+        } = response
+        {
+            xml.push_str(&format!(
+                "    <setOutcomeValue identifier=\"SCORE\">\n      <sum>\n        <variable identifier=\"SCORE\"/>\n        <mapResponse identifier=\"RESPONSE_{}\"/>\n      </sum>\n    </setOutcomeValue>\n",
+                i + 1
+            ));
+            continue;
+        }
+        let variable = format!("<variable identifier=\"RESPONSE_{}\"/>", i + 1);
+        let condition = match response {
+            Response::Choice { correct, .. } if correct.is_empty() => {
+                format!("<isNull>{}</isNull>", variable)
+            }
+            Response::InlineChoice(correct) if correct.len() > 1 => format!(
+                "<or>{}</or>",
+                correct
+                    .iter()
+                    .map(|identifier| format!(
+                        "<match>{}<baseValue baseType=\"identifier\">{}</baseValue></match>",
+                        variable, identifier
+                    ))
+                    .collect::<Vec<_>>()
+                    .concat()
+            ),
+            Response::Numeric { tolerance, .. } => format!(
+                "<equal toleranceMode=\"absolute\" tolerance=\"{} {}\">{}<correct identifier=\"RESPONSE_{}\"/></equal>",
+                tolerance,
+                tolerance,
+                variable,
+                i + 1
+            ),
+            _ => format!(
+                "<match>{}<correct identifier=\"RESPONSE_{}\"/></match>",
+                variable,
+                i + 1
+            ),
+        };
+        xml.push_str(&format!(
+            "    <responseCondition>\n      <responseIf>\n        {}\n        <setOutcomeValue identifier=\"SCORE\">\n          <sum>\n            <variable identifier=\"SCORE\"/>\n            <baseValue baseType=\"float\">1</baseValue>\n          </sum>\n        </setOutcomeValue>\n      </responseIf>\n    </responseCondition>\n",
+            condition
+        ));
+    }
+    xml.push_str("  </responseProcessing>\n</assessmentItem>\n");
+    xml
+}
+
+fn outcome_declaration(identifier: &str, default: usize) -> String {
+    format!(
+        "  <outcomeDeclaration identifier=\"{}\" cardinality=\"single\" baseType=\"float\">\n    <defaultValue>\n      <value>{}</value>\n    </defaultValue>\n  </outcomeDeclaration>\n",
+        identifier, default
+    )
+}
+
+// Import
+
+/// Imports a package given as its files by path, items are read in the order of the test if
+/// the package has one
+pub fn from_qti_package(files: &HashMap<String, String>) -> Result<QtiImport, Error> {
+    let manifest = files
+        .get(MANIFEST)
+        .ok_or(Error::Malformed("missing imsmanifest.xml"))?;
+    let manifest = Document::parse(manifest)?;
+    let resources: Vec<(&str, &str)> = manifest
+        .descendants()
+        .filter(|n| n.tag_name().name() == "resource")
+        .filter_map(|n| Some((n.attribute("type")?, n.attribute("href")?)))
+        .collect();
+
+    let mut title = None;
+    let mut unsupported = Vec::new();
+    let test = resources
+        .iter()
+        .find(|(kind, _)| kind.starts_with("imsqti_test"))
+        .and_then(|(_, href)| Some((*href, files.get(*href)?)));
+    let entries: Vec<Entry> = match test {
+        Some((href, test)) => {
+            let test = Document::parse(test)?;
+            title = test.root_element().attribute("title").map(str::to_string);
+            read_entries(test.root_element(), href)
+        }
+        None => resources
+            .iter()
+            .filter(|(kind, _)| kind.starts_with("imsqti_item"))
+            .map(|(_, href)| Entry::Item(resolve("", href)))
+            .collect(),
+    };
+    if entries.is_empty() {
+        return Err(Error::Malformed("package contains no items"));
+    }
+
+    let mut reader = Reader::default();
+    let mut blocks = Vec::new();
+    let mut item_titles = Vec::new();
+    let mut import_item = |path: &str| -> Vec<Node> {
+        let item = match files.get(path) {
+            Some(item) => item,
+            None => {
+                unsupported.push(format!("{} (Datei fehlt)", path));
+                return Vec::new();
+            }
+        };
+        match Document::parse(item) {
+            Ok(item) => {
+                let root = item.root_element();
+                let name = root.attribute("title").unwrap_or(path).to_string();
+                if normalize_name(root.tag_name().name()) != "assessmentItem" {
+                    unsupported.push(format!("{} (keine Aufgabe)", name));
+                    return Vec::new();
+                }
+                let mut converter = ItemConverter {
+                    name: &name,
+                    responses: read_responses(root),
+                    scorings: read_scorings(root),
+                    essay_points: essay_points(root),
+                    unsupported: &mut unsupported,
+                };
+                let body = root
+                    .children()
+                    .find(|n| normalize_name(n.tag_name().name()) == "itemBody")
+                    .map(|body| converter.children(body))
+                    .unwrap_or_default();
+                item_titles.push(name);
+                reader.blocks(&body)
+            }
+            Err(e) => {
+                unsupported.push(format!("{} ({})", path, e));
+                Vec::new()
+            }
+        }
+    };
+    for entry in &entries {
+        match entry {
+            Entry::Item(path) => blocks.extend(import_item(path)),
+            Entry::Pool { draw, items } => {
+                let items: Vec<Node> = items
+                    .iter()
+                    .map(|paths| paths.iter().flat_map(|path| import_item(path)).collect())
+                    .filter(|content: &Vec<Node>| !content.is_empty())
+                    .map(|content| Node::new(NodeKind::PoolItem, content))
+                    .collect();
+                if !items.is_empty() {
+                    let draw = (*draw).clamp(1, items.len());
+                    blocks.push(Node::new(NodeKind::Pool { draw, drawn: None }, items));
+                }
+            }
+        }
+    }
+    unsupported.extend(
+        reader
+            .skipped
+            .iter()
+            .map(|skipped| format!("{} wurden nicht übernommen", skipped)),
+    );
+    if title.is_none() && item_titles.len() == 1 {
+        title = item_titles.pop();
+    }
+    if blocks.is_empty() {
+        blocks.push(Node::new(NodeKind::Paragraph, Vec::new()));
+    }
+    Ok(QtiImport {
+        title,
+        content: Node::new(NodeKind::Doc, blocks),
+        unsupported,
+    })
+}
+
+enum Entry {
+    Item(String),
+    /// A section selecting `draw` of its children, each of them an item
+    Pool {
+        draw: usize,
+        items: Vec<Vec<String>>,
+    },
+}
+
+/// The items of a test in order, sections with a selection are read as pools
+fn read_entries(node: roxmltree::Node, href: &str) -> Vec<Entry> {
+    let mut entries = Vec::new();
+    for child in node.children().filter(roxmltree::Node::is_element) {
+        match normalize_name(child.tag_name().name()).as_str() {
+            "assessmentItemRef" => entries.extend(
+                child
+                    .attribute("href")
+                    .map(|item| Entry::Item(resolve(href, item))),
+            ),
+            "assessmentSection" => {
+                let draw = child
+                    .children()
+                    .find(|n| normalize_name(n.tag_name().name()) == "selection")
+                    .and_then(|selection| selection.attribute("select"))
+                    .and_then(|select| select.parse().ok());
+                match draw {
+                    Some(draw) => {
+                        let items = child
+                            .children()
+                            .filter(roxmltree::Node::is_element)
+                            .map(|item| item_refs(item, href))
+                            .filter(|paths| !paths.is_empty())
+                            .collect();
+                        entries.push(Entry::Pool { draw, items });
+                    }
+                    None => entries.extend(read_entries(child, href)),
+                }
+            }
+            "testPart" => entries.extend(read_entries(child, href)),
+            _ => {}
+        }
+    }
+    entries
+}
+
+/// All items below a node, nested selections are not kept
+fn item_refs(node: roxmltree::Node, href: &str) -> Vec<String> {
+    node.descendants()
+        .filter(|n| normalize_name(n.tag_name().name()) == "assessmentItemRef")
+        .filter_map(|n| n.attribute("href"))
+        .map(|item| resolve(href, item))
+        .collect()
+}
+
+/// QTI 3.0 prefixes its elements with `qti-` and writes names in kebab case
+fn normalize_name(name: &str) -> String {
+    match name.strip_prefix("qti-") {
+        Some(name) => kebab_to_camel(name),
+        None => name.to_string(),
+    }
+}
+
+fn normalize_attribute(name: &str) -> String {
+    if name.starts_with("data-") {
+        name.to_string()
+    } else {
+        kebab_to_camel(name)
+    }
+}
+
+fn kebab_to_camel(name: &str) -> String {
+    let mut camel = String::with_capacity(name.len());
+    let mut upper = false;
+    for c in name.chars() {
+        if c == '-' {
+            upper = true;
+        } else if upper {
+            camel.extend(c.to_uppercase());
+            upper = false;
+        } else {
+            camel.push(c);
+        }
+    }
+    camel
+}
+
+/// Resolves a reference relative to the directory of the referencing file
+fn resolve(base: &str, href: &str) -> String {
+    let mut segments: Vec<&str> = base.split('/').collect();
+    segments.pop();
+    for segment in href.split('/') {
+        match segment {
+            "." | "" => {}
+            ".." => {
+                segments.pop();
+            }
+            segment => segments.push(segment),
+        }
+    }
+    segments.join("/")
+}
+
+fn read_responses(item: roxmltree::Node) -> HashMap<String, Vec<String>> {
+    item.children()
+        .filter(|n| normalize_name(n.tag_name().name()) == "responseDeclaration")
+        .filter_map(|declaration| {
+            let identifier = declaration.attribute("identifier")?;
+            let child = |name: &str| {
+                declaration
+                    .children()
+                    .find(|n| normalize_name(n.tag_name().name()) == name)
+            };
+            let mut values: Vec<String> = child("correctResponse")
+                .map(|correct| {
+                    correct
+                        .children()
+                        .filter(|n| normalize_name(n.tag_name().name()) == "value")
+                        .map(|value| value.text().unwrap_or_default().trim().to_string())
+                        .collect()
+                })
+                .unwrap_or_default();
+            // Without a correct response the best scored mapping is taken as the solution
+            if values.is_empty() {
+                let best = child("mapping").and_then(|mapping| {
+                    mapping
+                        .children()
+                        .filter(|n| normalize_name(n.tag_name().name()) == "mapEntry")
+                        .filter_map(|entry| {
+                            let value = attribute(entry, "mappedValue")?.parse::<f64>().ok()?;
+                            Some((value, attribute(entry, "mapKey")?))
+                        })
+                        .filter(|(value, _)| *value > 0.0)
+                        .max_by(|(a, _), (b, _)| a.partial_cmp(b).unwrap_or(Ordering::Equal))
+                });
+                values.extend(best.map(|(_, key)| key.to_string()));
+            }
+            Some((identifier.to_string(), values))
+        })
+        .collect()
+}
+
+/// Choice responses scored by a mapping grant partial credit, with negative marking if ticking a
+/// choice can cost points
+fn read_scorings(item: roxmltree::Node) -> HashMap<String, Scoring> {
+    item.children()
+        .filter(|n| normalize_name(n.tag_name().name()) == "responseDeclaration")
+        .filter_map(|declaration| {
+            let values: Vec<f64> = declaration
+                .children()
+                .find(|n| normalize_name(n.tag_name().name()) == "mapping")?
+                .children()
+                .filter(|n| normalize_name(n.tag_name().name()) == "mapEntry")
+                .filter_map(|entry| attribute(entry, "mappedValue")?.parse::<f64>().ok())
+                .collect();
+            if values.is_empty() {
+                return None;
+            }
+            let scoring = if values.iter().any(|value| *value < 0.0) {
+                Scoring::NegativeMarking
+            } else {
+                Scoring::PartialCredit
+            };
+            Some((attribute(declaration, "identifier")?.to_string(), scoring))
+        })
+        .collect()
+}
+
+/// Points of the essay in an item, which is the maximum score less one point for every other
+/// interaction. Essays in items with several of them or without a maximum score get one point.
+fn essay_points(item: roxmltree::Node) -> u32 {
+    let named =
+        |name: &'static str| move |n: &roxmltree::Node| normalize_name(n.tag_name().name()) == name;
+    let essays = item
+        .descendants()
+        .filter(named("extendedTextInteraction"))
+        .count();
+    let responses = item.children().filter(named("responseDeclaration")).count();
+    let max_score = item
+        .children()
+        .filter(named("outcomeDeclaration"))
+        .find(|n| attribute(*n, "identifier") == Some("MAXSCORE"))
+        .and_then(|outcome| outcome.descendants().find(named("value")))
+        .and_then(|value| value.text())
+        .and_then(|value| value.trim().parse::<f64>().ok());
+    match max_score {
+        Some(max_score) if essays == 1 => (max_score - responses.saturating_sub(1) as f64)
+            .round()
+            .max(1.0) as u32,
+        _ => 1,
+    }
+}
+
+/// Reads an attribute by its QTI 2.x name, also accepting the kebab case of QTI 3.0
+fn attribute<'a>(node: roxmltree::Node<'a, '_>, name: &str) -> Option<&'a str> {
+    node.attributes()
+        .iter()
+        .find(|a| normalize_attribute(a.name()) == name)
+        .map(|a| a.value())
+}
+
+struct ItemConverter<'a> {
+    name: &'a str,
+    responses: HashMap<String, Vec<String>>,
+    scorings: HashMap<String, Scoring>,
+    essay_points: u32,
+    unsupported: &'a mut Vec<String>,
+}
+
+impl ItemConverter<'_> {
+    fn report(&mut self, what: String) {
+        let message = format!("{}: {}", self.name, what);
+        if !self.unsupported.contains(&message) {
+            self.unsupported.push(message);
+        }
+    }
+
+    fn solutions(&self, interaction: roxmltree::Node) -> &[String] {
+        attribute(interaction, "responseIdentifier")
+            .and_then(|identifier| self.responses.get(identifier))
+            .map_or(&[], Vec::as_slice)
+    }
+
+    fn children(&mut self, node: roxmltree::Node) -> Vec<Content> {
+        node.children().flat_map(|c| self.convert(c)).collect()
+    }
+
+    fn convert(&mut self, node: roxmltree::Node) -> Vec<Content> {
+        if node.is_text() {
+            return vec![Content::Text(node.text().unwrap_or_default().to_string())];
+        }
+        if !node.is_element() {
+            return Vec::new();
+        }
+        let name = normalize_name(node.tag_name().name());
+        match name.as_str() {
+            "textEntryInteraction" => {
+                let solution = self.solutions(node).first().cloned().unwrap_or_default();
+                vec![gap(&solution)]
+            }
+            "inlineChoiceInteraction" => {
+                let correct = self.solutions(node).to_vec();
+                let mut select = Element::new("select");
+                if attribute(node, "shuffle") == Some("true") {
+                    select
+                        .attributes
+                        .push(("data-shuffle".to_string(), "true".to_string()));
+                }
+                for choice in node
+                    .children()
+                    .filter(|c| normalize_name(c.tag_name().name()) == "inlineChoice")
+                {
+                    let solution = choice
+                        .attribute("identifier")
+                        .map_or(false, |id| correct.iter().any(|s| s == id));
+                    let text: String = choice
+                        .descendants()
+                        .filter(|t| t.is_text())
+                        .filter_map(|t| t.text())
+                        .collect();
+                    let mut option = Element::new("option");
+                    option
+                        .attributes
+                        .push(("data-correct".to_string(), solution.to_string()));
+                    option.children.push(Content::Text(text.trim().to_string()));
+                    select.children.push(Content::Element(option));
+                }
+                vec![Content::Element(select)]
+            }
+            "choiceInteraction" => {
+                let correct = self.solutions(node).to_vec();
+                let mut converted = Vec::new();
+                let mut list = Element::new("ul");
+                list.attributes
+                    .push(("data-type".to_string(), "multipleChoice".to_string()));
+                if attribute(node, "shuffle") == Some("true") {
+                    list.attributes
+                        .push(("data-shuffle".to_string(), "true".to_string()));
+                }
+                if attribute(node, "maxChoices") == Some("1") && correct.len() == 1 {
+                    list.attributes.push((
+                        "data-mode".to_string(),
+                        ChoiceMode::Single.as_str().to_string(),
+                    ));
+                }
+                let scoring = attribute(node, "responseIdentifier")
+                    .and_then(|identifier| self.scorings.get(identifier));
+                if let Some(scoring) = scoring {
+                    list.attributes
+                        .push(("data-scoring".to_string(), scoring.as_str().to_string()));
+                }
+                for child in node.children().filter(|c| c.is_element()) {
+                    match normalize_name(child.tag_name().name()).as_str() {
+                        "prompt" => {
+                            let mut prompt = Element::new("p");
+                            prompt.children = self.children(child);
+                            converted.push(Content::Element(prompt));
+                        }
+                        "simpleChoice" => {
+                            let solution = child
+                                .attribute("identifier")
+                                .map_or(false, |id| correct.iter().any(|s| s == id));
+                            let mut item = Element::new("li");
+                            item.attributes
+                                .push(("data-solution".to_string(), solution.to_string()));
+                            item.children = self.children(child);
+                            list.children.push(Content::Element(item));
+                        }
+                        _ => {}
+                    }
+                }
+                converted.push(Content::Element(list));
+                converted
+            }
+            "extendedTextInteraction" => {
+                let mut converted = Vec::new();
+                for prompt in node
+                    .children()
+                    .filter(|c| normalize_name(c.tag_name().name()) == "prompt")
+                {
+                    let mut paragraph = Element::new("p");
+                    paragraph.children = self.children(prompt);
+                    converted.push(Content::Element(paragraph));
+                }
+                let model_answer = self.solutions(node).first().cloned().unwrap_or_default();
+                let mut essay = Element::new("div");
+                essay.attributes = vec![
+                    ("data-type".to_string(), "essay".to_string()),
+                    ("data-points".to_string(), self.essay_points.to_string()),
+                    ("data-model-answer".to_string(), model_answer),
+                ];
+                converted.push(Content::Element(essay));
+                converted
+            }
+            "feedbackBlock" | "feedbackInline" | "modalFeedback" | "rubricBlock"
+            | "templateBlock" | "templateInline" => {
+                self.report("Rückmeldungen und Hinweise wurden nicht übernommen".to_string());
+                Vec::new()
+            }
+            "object" if !is_external(node.attribute("data").unwrap_or_default()) => {
+                self.report("Mediendateien aus dem Paket wurden nicht übernommen".to_string());
+                Vec::new()
+            }
+            name if name.ends_with("Interaction") => {
+                self.report(format!("{} wird nicht unterstützt", name));
+                Vec::new()
+            }
+            _ => {
+                let mut element = Element::new(name);
+                element.attributes = node
+                    .attributes()
+                    .iter()
+                    .map(|a| (normalize_attribute(a.name()), a.value().to_string()))
+                    .collect();
+                element.children = self.children(node);
+                vec![Content::Element(element)]
+            }
+        }
+    }
+}
+
+fn gap(solution: &str) -> Content {
+    let mut gap = Element::new("span");
+    gap.attributes = vec![
+        ("data-type".to_string(), "gap".to_string()),
+        ("data-solution".to_string(), solution.to_string()),
+    ];
+    Content::Element(gap)
+}
+
+fn is_external(source: &str) -> bool {
+    ["http://", "https://", "data:"]
+        .iter()
+        .any(|scheme| source.starts_with(scheme))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::markdown::from_markdown;
+    use super::*;
+
+    fn package(files: &[(&str, &str)]) -> HashMap<String, String> {
+        files
+            .iter()
+            .map(|(path, content)| (path.to_string(), content.to_string()))
+            .collect()
+    }
+
+    fn round_trip(markdown: &str) {
+        let content = from_markdown(markdown).content;
+        let files: HashMap<String, String> =
+            to_qti_package("Titel", &content).into_iter().collect();
+        let imported = from_qti_package(&files).unwrap();
+        assert_eq!(imported.title.as_deref(), Some("Titel"));
+        assert_eq!(imported.content, content);
+        assert!(
+            imported.unsupported.is_empty(),
+            "{:?}",
+            imported.unsupported
+        );
+    }
+
+    #[test]
+    fn import_qti_2_1_package() {
+        let files = package(&[
+            (
+                "imsmanifest.xml",
+                include_str!("fixtures/qti/v2p1/imsmanifest.xml"),
+            ),
+            (
+                "tests/test.xml",
+                include_str!("fixtures/qti/v2p1/tests/test.xml"),
+            ),
+            (
+                "items/choice.xml",
+                include_str!("fixtures/qti/v2p1/items/choice.xml"),
+            ),
+            (
+                "items/text_entry.xml",
+                include_str!("fixtures/qti/v2p1/items/text_entry.xml"),
+            ),
+        ]);
+        let imported = from_qti_package(&files).unwrap();
+        let expected = from_markdown(
+            "## Aufgabe 1\n\nDie Zahl unter dem Bruchstrich heißt [[Nenner]].\n\nDer Bruch $\\frac{3}{4}$ wird *gelesen* als [[3/4]].\n\nWelche Brüche sind gleich **einhalb**?\n\n<!-- shuffle -->\n- [x] 2/4\n- [ ] 2/3\n- [x] 4/8\n",
+        );
+        assert_eq!(imported.title.as_deref(), Some("Bruchrechnung"));
+        assert_eq!(imported.content, expected.content);
+        assert_eq!(
+            imported.unsupported,
+            vec!["Lückentext: Rückmeldungen und Hinweise wurden nicht übernommen"]
+        );
+    }
+
+    #[test]
+    fn import_qti_3_0_package() {
+        let files = package(&[
+            (
+                "imsmanifest.xml",
+                include_str!("fixtures/qti/v3p0/imsmanifest.xml"),
+            ),
+            ("item.xml", include_str!("fixtures/qti/v3p0/item.xml")),
+        ]);
+        let imported = from_qti_package(&files).unwrap();
+        let expected = from_markdown(
+            "Die Hauptstadt von Frankreich ist [[Paris]].\n\nBerlin liegt an der [[?Elbe|*Spree]].\n\nWas ist die Hauptstadt von Deutschland?\n\n- ( ) Bonn\n- (x) Berlin\n\nBeschreibe deine Lieblingsstadt.\n\n<!-- essay: points 1\n-->\n",
+        );
+        assert_eq!(imported.title.as_deref(), Some("Hauptstädte"));
+        assert_eq!(imported.content, expected.content);
+        assert!(imported.unsupported.is_empty());
+    }
+
+    #[test]
+    fn import_without_manifest_fails() {
+        let files = package(&[("item.xml", include_str!("fixtures/qti/v3p0/item.xml"))]);
+        assert!(matches!(from_qti_package(&files), Err(Error::Malformed(_))));
+    }
+
+    #[test]
+    fn export_writes_interactions_and_response_processing() {
+        let content = from_markdown(
+            "Ein Viertel ist [[1/4]] & mehr.\n\n- [x] Richtig\n- [ ] Falsch\n- [x] Auch richtig\n\nZum Schluss [[Ende]].\n",
+        )
+        .content;
+        let files = to_qti_package("Brüche", &content);
+        let paths: Vec<&str> = files.iter().map(|(path, _)| path.as_str()).collect();
+        assert_eq!(
+            paths,
+            vec![
+                "imsmanifest.xml",
+                "assessment.xml",
+                "items/item-1.xml",
+                "items/item-2.xml"
+            ]
+        );
+        let first = &files[2].1;
+        assert!(first.contains("<textEntryInteraction responseIdentifier=\"RESPONSE_1\"/>"));
+        assert!(first.contains("<value>1/4</value>"));
+        assert!(first.contains("<choiceInteraction responseIdentifier=\"RESPONSE_2\""));
+        assert!(first.contains("<value>CHOICE_1</value>\n      <value>CHOICE_3</value>"));
+        assert!(first.contains("<match><variable identifier=\"RESPONSE_2\"/><correct identifier=\"RESPONSE_2\"/></match>"));
+        assert!(first.contains("<value>2</value>"));
+        for (_, xml) in &files {
+            Document::parse(xml).unwrap();
+        }
+    }
+
+    #[test]
+    fn round_trip_formatting() {
+        round_trip("# Aufgabe\n\nText mit **fett**, *kursiv*, ~~durch~~ und `code` sowie $x^2$.\\\nNeue Zeile [[Lücke]].\n\n> Zitat\n\n---\n\n```rust\nfn main() {}\n```\n\n- Eins\n- Zwei\n\n1. Erstens\n");
+    }
+
+    #[test]
+    fn round_trip_multiple_choice() {
+        round_trip("Frage?\n\n- [ ] Nein\n- [x] Ja\n\n- [ ] Keine richtig\n\nNachwort\n");
+    }
+
+    #[test]
+    fn round_trip_choice_modes_and_scoring() {
+        round_trip("- ( ) Bonn\n- (x) Berlin\n\n<!-- scoring: partialCredit, shuffle -->\n- [x] 2\n- [ ] 3\n- [x] 4\n\n<!-- scoring: negativeMarking -->\n- [x] 2\n- [ ] 3\n");
+    }
+
+    #[test]
+    fn round_trip_dropdowns() {
+        round_trip("Berlin liegt an der [[?Elbe|*Spree]], Bonn am [[??*Rhein|Main]].\n");
+    }
+
+    #[test]
+    fn round_trip_pools() {
+        round_trip("Vorher [[a]]\n\n<!-- pool: draw 2 -->\n\nErste [[1]]\n\n<!-- item -->\n\nZweite\n\n- [x] ja\n- [ ] nein\n\nDanach [[2]]\n\n<!-- item -->\n\nDritte [[3]]\n\n<!-- end pool -->\n\nNachher");
+    }
+
+    #[test]
+    fn round_trip_essays() {
+        round_trip("Warum gibt es Tag und Nacht? [[Drehung]]\n\n<!-- essay: points 3\nDie Erde dreht sich.\n-->\n\n<!-- essay: points 1\n-->\n\nNachwort\n");
+    }
+
+    #[test]
+    fn round_trip_audio() {
+        round_trip("<audio src=\"https://example.org/a.ogg\" type=\"audio/ogg\"></audio>\n");
+    }
+}
@@ -0,0 +1,188 @@
+//! Static HTML rendering of sheets for pages and exports without the Vue app.
+//!
+//! The markup follows the Vue components, so the stylesheet of the site applies to both.
+
+use super::document::{ChoiceMode, DropdownOption, Mark, Node, NodeKind};
+use super::essay;
+use super::highlight::highlight;
+use super::html::escape;
+use super::mathml;
+
+/// Renders the content of a sheet including the answers given so far
+pub fn render_sheet(content: &Node) -> String {
+    let mut html = String::new();
+    block(content, &mut html);
+    html
+}
+
+fn wrap(tag: &str, attributes: &str, node: &Node, html: &mut String) {
+    html.push_str(&format!("<{}{}>", tag, attributes));
+    for child in &node.content {
+        block(child, html);
+    }
+    html.push_str(&format!("</{}>", tag));
+}
+
+fn block(node: &Node, html: &mut String) {
+    match &node.kind {
+        NodeKind::Doc => wrap("div", " class=\"content\"", node, html),
+        NodeKind::Other(_) => wrap("div", "", node, html),
+        NodeKind::Paragraph => {
+            html.push_str("<p>");
+            inline(&node.content, html);
+            html.push_str("</p>");
+        }
+        NodeKind::Heading { level } => {
+            html.push_str(&format!("<h{}>", level));
+            inline(&node.content, html);
+            html.push_str(&format!("</h{}>", level));
+        }
+        NodeKind::Text(_) | NodeKind::HardBreak => inline(std::slice::from_ref(node), html),
+        NodeKind::CodeBlock { language } => html.push_str(&format!(
+            "<pre><code class=\"hljs language-{}\">{}</code></pre>",
+            escape(language),
+            highlight(&node.text_content(), language)
+        )),
+        NodeKind::Audio { source, mimetype } => html.push_str(&format!(
+            "<audio controls=\"true\"><source src=\"{}\" type=\"{}\"></audio>",
+            escape(source),
+            escape(mimetype)
+        )),
+        NodeKind::MultipleChoice { mode, .. } => {
+            html.push_str("<ul data-type=\"multipleChoice\">");
+            for child in &node.content {
+                choice_answer(child, *mode, html);
+            }
+            html.push_str("</ul>");
+        }
+        NodeKind::MultipleChoiceAnswer { .. } => choice_answer(node, ChoiceMode::Multiple, html),
+        NodeKind::BulletList => wrap("ul", "", node, html),
+        NodeKind::OrderedList => wrap("ol", "", node, html),
+        NodeKind::ListItem => wrap("li", "", node, html),
+        NodeKind::Blockquote => wrap("blockquote", "", node, html),
+        NodeKind::HorizontalRule => html.push_str("<hr>"),
+        NodeKind::Pool { draw, drawn } => {
+            let note = match drawn {
+                Some(_) => "Zufällig gezogene Aufgaben".to_string(),
+                None => format!(
+                    "Zufällige Auswahl: {} von {} Aufgaben",
+                    draw,
+                    node.content.len()
+                ),
+            };
+            html.push_str(&format!(
+                "<div data-type=\"pool\"><p class=\"pool-note\">{}</p>",
+                note
+            ));
+            for child in &node.content {
+                block(child, html);
+            }
+            html.push_str("</div>");
+        }
+        NodeKind::PoolItem => wrap("div", " data-type=\"poolItem\"", node, html),
+        // Values are only known in solutions and may give away the results
+        NodeKind::Variables(_) => {}
+        NodeKind::Essay {
+            min_words,
+            max_words,
+            answer,
+            ..
+        } => {
+            html.push_str("<div data-type=\"essay\">");
+            let limits = essay::word_limits(*min_words, *max_words);
+            if !limits.is_empty() {
+                html.push_str(&format!("<p class=\"essay-note\">{}</p>", limits));
+            }
+            html.push_str(&format!(
+                "<textarea class=\"textarea\" disabled>{}</textarea></div>",
+                escape(answer)
+            ));
+        }
+    }
+}
+
+fn choice_answer(node: &Node, mode: ChoiceMode, html: &mut String) {
+    let checked = match node.kind {
+        NodeKind::MultipleChoiceAnswer { answer, .. } => answer,
+        _ => return block(node, html),
+    };
+    html.push_str(&format!(
+        "<li><label><input type=\"{}\" disabled{}><div>",
+        match mode {
+            ChoiceMode::Single => "radio",
+            ChoiceMode::Multiple => "checkbox",
+        },
+        if checked { " checked" } else { "" }
+    ));
+    for child in &node.content {
+        block(child, html);
+    }
+    html.push_str("</div></label></li>");
+}
+
+fn inline(nodes: &[Node], html: &mut String) {
+    for node in nodes {
+        match &node.kind {
+            NodeKind::Text(text) => {
+                let mut closing = Vec::new();
+                for (mark, (open, close)) in [
+                    (Mark::Bold, ("<strong>", "</strong>")),
+                    (Mark::Italic, ("<em>", "</em>")),
+                    (Mark::Strike, ("<s>", "</s>")),
+                    (Mark::Code, ("<code>", "</code>")),
+                ] {
+                    if node.marks.contains(&mark) {
+                        html.push_str(open);
+                        closing.push(close);
+                    }
+                }
+                let special = node.marks.iter().find_map(|mark| match mark {
+                    Mark::Gap {
+                        solution, answer, ..
+                    }
+                    | Mark::Numeric { solution, answer }
+                    | Mark::Formula { solution, answer } => Some(gap(solution, answer)),
+                    Mark::Dropdown {
+                        options, answer, ..
+                    } => Some(dropdown(options, answer)),
+                    Mark::Latex { source } => Some(mathml::render(source)),
+                    _ => None,
+                });
+                html.push_str(&special.unwrap_or_else(|| escape(text)));
+                for close in closing.iter().rev() {
+                    html.push_str(close);
+                }
+            }
+            NodeKind::HardBreak => html.push_str("<br>"),
+            _ => inline(&node.content, html),
+        }
+    }
+}
+
+fn gap(solution: &str, answer: &str) -> String {
+    // Same width as in the Vue app, which does not reveal the exact solution length
+    let width = (solution.chars().count() + 4) / 5 * 5;
+    format!(
+        "<input class=\"input gap\" type=\"text\" value=\"{}\" style=\"width: {}rem\" disabled>",
+        escape(answer),
+        width
+    )
+}
+
+fn dropdown(options: &[DropdownOption], answer: &str) -> String {
+    let mut html =
+        String::from("<span class=\"select is-small\"><select disabled><option></option>");
+    for option in options {
+        html.push_str(&format!(
+            "<option{}>{}</option>",
+            if option.text == answer {
+                " selected"
+            } else {
+                ""
+            },
+            escape(&option.text)
+        ));
+    }
+    html.push_str("</select></span>");
+    html
+}
@@ -0,0 +1,106 @@
+//! Files of SCORM packages, which deliver a sheet through a learning management system.
+//!
+//! The package page renders the sheet statically and mounts the bundled Vue app on top of it,
+//! which reports score and completion through the runtime API of the LMS.
+
+use serde_json::Value;
+
+use super::document::Node;
+use super::html;
+use super::render;
+
+pub const INDEX: &str = "index.html";
+pub const MANIFEST: &str = "imsmanifest.xml";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Version {
+    Scorm12,
+    Scorm2004,
+}
+
+// Bulma is loaded from a CDN on the site, the package must work offline
+const STYLE: &str = "body { font-family: BlinkMacSystemFont, -apple-system, \"Segoe UI\", Roboto, Helvetica, Arial, sans-serif; color: #4a4a4a; line-height: 1.5; margin: 0; }
+#app { max-width: 60rem; margin: 0 auto; padding: 1.5rem; }
+h1, h2, h3, h4, h5, h6 { color: #363636; line-height: 1.125; }
+.button { cursor: pointer; border: 1px solid #dbdbdb; border-radius: 4px; padding: 0.25em 0.75em; background: #fff; display: inline-flex; align-items: center; }
+.button.is-success { background: #48c774; border-color: transparent; color: #fff; }
+.button[disabled] { opacity: 0.5; cursor: not-allowed; }
+input[type=text] { border: 1px solid #dbdbdb; border-radius: 4px; padding: 0.125em 0.5em; font: inherit; }
+pre { overflow-x: auto; }";
+const SHEET_STYLE: &str = include_str!("../../../assets/css/sheet.css");
+
+pub fn index_html(title: &str, content: &Value, scripts: &[String], styles: &[String]) -> String {
+    let body = render::render_sheet(&Node::from_json(content));
+    let mut page = format!(
+        "<!DOCTYPE html>\n<html lang=\"de\">\n<head>\n  <meta charset=\"utf-8\">\n  <meta name=\"viewport\" content=\"width=device-width,initial-scale=1.0\">\n  <title>{}</title>\n  <style>\n{}\n{}\n  </style>\n",
+        html::escape(title),
+        STYLE,
+        SHEET_STYLE
+    );
+    for style in styles {
+        page.push_str(&format!(
+            "  <link rel=\"stylesheet\" href=\"{}\">\n",
+            html::escape(style)
+        ));
+    }
+    page.push_str("</head>\n<body>\n");
+    page.push_str(&format!(
+        "  <div id=\"app\" data-mode='\"scorm\"' data-sheettitle='{}' data-content='{}'>\n",
+        attribute_json(&Value::String(title.to_string())),
+        attribute_json(content)
+    ));
+    page.push_str(&format!(
+        "    <h1>{}</h1>\n    <noscript><p>Zum Überprüfen der Antworten wird JavaScript benötigt.</p></noscript>\n    {}\n  </div>\n",
+        html::escape(title),
+        body
+    ));
+    for script in scripts {
+        page.push_str(&format!(
+            "  <script defer src=\"{}\"></script>\n",
+            html::escape(script)
+        ));
+    }
+    page.push_str("</body>\n</html>\n");
+    page
+}
+
+// The Vue app parses its data attributes as JSON
+fn attribute_json(value: &Value) -> String {
+    html::escape(&value.to_string()).replace('\'', "&#39;")
+}
+
+/// Lists all files of the package in a single shareable content object launched by the index
+pub fn manifest(version: Version, identifier: &str, title: &str, files: &[String]) -> String {
+    let (namespaces, schema_version, scorm_type) = match version {
+        Version::Scorm12 => (
+            "xmlns=\"http://www.imsproject.org/xsd/imscp_rootv1p1p2\" xmlns:adlcp=\"http://www.adlnet.org/xsd/adlcp_rootv1p2\" xmlns:xsi=\"http://www.w3.org/2001/XMLSchema-instance\" xsi:schemaLocation=\"http://www.imsproject.org/xsd/imscp_rootv1p1p2 imscp_rootv1p1p2.xsd http://www.adlnet.org/xsd/adlcp_rootv1p2 adlcp_rootv1p2.xsd\"",
+            "1.2",
+            "adlcp:scormtype",
+        ),
+        Version::Scorm2004 => (
+            "xmlns=\"http://www.imsglobal.org/xsd/imscp_v1p1\" xmlns:adlcp=\"http://www.adlnet.org/xsd/adlcp_v1p3\" xmlns:xsi=\"http://www.w3.org/2001/XMLSchema-instance\" xsi:schemaLocation=\"http://www.imsglobal.org/xsd/imscp_v1p1 imscp_v1p1.xsd http://www.adlnet.org/xsd/adlcp_v1p3 adlcp_v1p3.xsd\"",
+            "2004 4th Edition",
+            "adlcp:scormType",
+        ),
+    };
+    let title = html::escape(title);
+    let mut manifest = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<manifest identifier=\"{}\" version=\"1\" {}>\n  <metadata>\n    <schema>ADL SCORM</schema>\n    <schemaversion>{}</schemaversion>\n  </metadata>\n",
+        html::escape(identifier),
+        namespaces,
+        schema_version
+    );
+    manifest.push_str(&format!(
+        "  <organizations default=\"organization\">\n    <organization identifier=\"organization\">\n      <title>{0}</title>\n      <item identifier=\"item\" identifierref=\"sheet\">\n        <title>{0}</title>\n      </item>\n    </organization>\n  </organizations>\n",
+        title
+    ));
+    manifest.push_str(&format!(
+        "  <resources>\n    <resource identifier=\"sheet\" type=\"webcontent\" {}=\"sco\" href=\"{}\">\n",
+        scorm_type, INDEX
+    ));
+    for file in files {
+        manifest.push_str(&format!("      <file href=\"{}\"/>\n", html::escape(file)));
+    }
+    manifest.push_str("    </resource>\n  </resources>\n</manifest>\n");
+    manifest
+}
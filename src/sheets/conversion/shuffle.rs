@@ -0,0 +1,162 @@
+//! Per-student order of multiple choice answers and dropdown options.
+//!
+//! Tasks with `shuffle` set are shuffled when a solution is created, seeded by the solution ID.
+//! The order is not stored: it is derived again from the seed and the position of the task in the
+//! canonical document, so only the shuffled solution needs to be kept. Tasks are numbered in
+//! document order of the canonical sheet, which shuffling answers would change for the tasks
+//! inside them, so [`unshuffle`] restores the children of a node before it descends into them.
+//!
+//! Like [`super::document::redact_solutions`] this works on the JSON content, so attributes the
+//! document model does not know survive.
+
+use serde_json::Value;
+
+/// Brings shuffled tasks into the order of the student with the given seed
+pub fn shuffle(content: &mut Value, seed: u64) {
+    walk(content, seed, &mut 0, Direction::Shuffle);
+}
+
+/// Brings the tasks of a solution shuffled with the given seed back into the order of the sheet
+pub fn unshuffle(content: &mut Value, seed: u64) {
+    walk(content, seed, &mut 0, Direction::Unshuffle);
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Direction {
+    Shuffle,
+    Unshuffle,
+}
+
+fn walk(value: &mut Value, seed: u64, task: &mut u64, direction: Direction) {
+    if let Some(marks) = value.get_mut("marks").and_then(Value::as_array_mut) {
+        for mark in marks
+            .iter_mut()
+            .filter(|mark| mark.get("type").and_then(Value::as_str) == Some("dropdown"))
+            .filter(|mark| shuffled(mark))
+        {
+            *task += 1;
+            if let Some(options) = mark.get_mut("options").and_then(Value::as_array_mut) {
+                reorder(options, seed, *task, direction);
+            }
+        }
+    }
+    // Answers are reordered as a whole around the walk through them, so the tasks inside them are
+    // numbered in canonical order both ways
+    let mut choice = None;
+    if value.get("type").and_then(Value::as_str) == Some("multipleChoice") && shuffled(value) {
+        *task += 1;
+        choice = Some(*task);
+    }
+    if let Some(content) = value.get_mut("content").and_then(Value::as_array_mut) {
+        if let (Some(choice), Direction::Unshuffle) = (choice, direction) {
+            reorder(content, seed, choice, direction);
+        }
+        for child in content.iter_mut() {
+            walk(child, seed, task, direction);
+        }
+        if let (Some(choice), Direction::Shuffle) = (choice, direction) {
+            reorder(content, seed, choice, direction);
+        }
+    }
+}
+
+fn shuffled(value: &Value) -> bool {
+    value.get("shuffle").and_then(Value::as_bool) == Some(true)
+}
+
+fn reorder(items: &mut Vec<Value>, seed: u64, task: u64, direction: Direction) {
+    let order = permutation(items.len(), seed, task);
+    let mut slots: Vec<Option<Value>> = items.drain(..).map(Some).collect();
+    // The item at position `i` of the shuffled list is the item at `order[i]` of the canonical one
+    match direction {
+        Direction::Shuffle => {
+            items.extend(order.iter().filter_map(|&from| slots[from].take()));
+        }
+        Direction::Unshuffle => {
+            let mut canonical: Vec<Option<Value>> = vec![None; slots.len()];
+            for (i, &to) in order.iter().enumerate() {
+                canonical[to] = slots[i].take();
+            }
+            items.extend(canonical.into_iter().flatten());
+        }
+    }
+}
+
+/// Fisher-Yates shuffle of the positions with a generator of its own, so the order never changes
+/// with library updates
+fn permutation(len: usize, seed: u64, task: u64) -> Vec<usize> {
+    let mut state = seed ^ task.wrapping_mul(0x9e37_79b9_7f4a_7c15);
+    let mut order: Vec<usize> = (0..len).collect();
+    for i in (1..len).rev() {
+        let j = (splitmix64(&mut state) % (i as u64 + 1)) as usize;
+        order.swap(i, j);
+    }
+    order
+}
+
+fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn answer(text: &str) -> Value {
+        json!({"type": "multipleChoiceAnswer", "solution": false, "answer": false, "content": [
+            {"type": "paragraph", "content": [{"type": "text", "text": text, "marks": [
+                {"type": "dropdown", "shuffle": true, "answer": "", "options": [
+                    {"text": format!("{}1", text), "correct": true},
+                    {"text": format!("{}2", text), "correct": false},
+                    {"text": format!("{}3", text), "correct": false},
+                ]},
+            ]}]},
+        ]})
+    }
+
+    fn sheet(shuffle: bool) -> Value {
+        json!({"type": "doc", "content": [
+            {"type": "multipleChoice", "shuffle": shuffle, "content": [
+                answer("a"), answer("b"), answer("c"), answer("d"), answer("e"),
+            ]},
+        ]})
+    }
+
+    #[test]
+    fn unshuffle_restores_the_sheet() {
+        let canonical = sheet(true);
+        let orders: Vec<Value> = (0..8)
+            .map(|seed| {
+                let mut content = canonical.clone();
+                shuffle(&mut content, seed);
+                let mut again = canonical.clone();
+                shuffle(&mut again, seed);
+                assert_eq!(content, again);
+                let shuffled = content.clone();
+                unshuffle(&mut content, seed);
+                assert_eq!(content, canonical);
+                shuffled
+            })
+            .collect();
+        assert!(orders.iter().any(|order| *order != canonical));
+        assert!(orders.iter().any(|order| *order != orders[0]));
+    }
+
+    #[test]
+    fn keeps_tasks_without_shuffle() {
+        let mut content = sheet(false);
+        shuffle(&mut content, 3);
+        let answers = content["content"][0]["content"].as_array().unwrap();
+        let texts: Vec<&str> = answers
+            .iter()
+            .map(|a| a["content"][0]["content"][0]["text"].as_str().unwrap())
+            .collect();
+        assert_eq!(texts, vec!["a", "b", "c", "d", "e"]);
+    }
+}
@@ -0,0 +1,287 @@
+//! Exercises with values generated for every student.
+//!
+//! A `variables` node defines variables, each of them as one of
+//!
+//! - `1..10`, an integer from 1 to 10
+//! - `0.5..2.5 step 0.5`, a number from the range in the given steps
+//! - `[2, 3, 5, 7]`, one of the listed numbers
+//! - a formula like `a * b` in terms of the variables defined before, see [`super::expression`]
+//!
+//! Text, gap, numeric and formula solutions, dropdown options and LaTeX may contain expressions in
+//! double braces like `{{a + b}}`. When a student starts solving, values are drawn for all
+//! variables, stored with their definitions and every expression is replaced by its value, so
+//! grading and teachers see the numbers of the student. Expressions which cannot be evaluated are
+//! left as they are.
+//!
+//! Like [`super::pool`] this works on the JSON content.
+
+use rand::Rng;
+use serde_json::{json, Value};
+
+use super::expression::Expression;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Definition {
+    Range { min: f64, max: f64, step: f64 },
+    List(Vec<f64>),
+    Formula(Expression),
+}
+
+impl Definition {
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = text.trim();
+        if let Some(list) = text.strip_prefix('[').and_then(|t| t.strip_suffix(']')) {
+            let values = list
+                .split(',')
+                .map(|value| value.trim().parse().ok())
+                .collect::<Option<Vec<f64>>>()?;
+            return Some(Self::List(values));
+        }
+        if let Some((min, rest)) = text.split_once("..") {
+            let (max, step) = match rest.split_once("step") {
+                Some((max, step)) => (max, step.trim().parse().ok()?),
+                None => (rest, 1.0),
+            };
+            let (min, max): (f64, f64) = (min.trim().parse().ok()?, max.trim().parse().ok()?);
+            if min > max || step <= 0.0 || !(min.is_finite() && max.is_finite()) {
+                return None;
+            }
+            return Some(Self::Range { min, max, step });
+        }
+        Expression::parse(text).map(Self::Formula)
+    }
+
+    /// Draws a value, formulas are evaluated with the values drawn so far
+    pub fn draw<R: Rng>(&self, rng: &mut R, values: &[(String, f64)]) -> Option<f64> {
+        match self {
+            Self::Range { min, max, step } => {
+                let steps = ((max - min) / step + 1e-9).floor() as u64;
+                let value = min + rng.gen_range(0..=steps) as f64 * step;
+                // Steps like 0.1 are not exact in binary
+                Some((value * 1e9).round() / 1e9)
+            }
+            Self::List(list) if list.is_empty() => None,
+            Self::List(list) => Some(list[rng.gen_range(0..list.len())]),
+            Self::Formula(expression) => expression.evaluate(&|name| {
+                values
+                    .iter()
+                    .find(|(defined, _)| defined == name)
+                    .map(|(_, value)| *value)
+            }),
+        }
+    }
+}
+
+pub fn is_valid_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some(c) if c.is_alphabetic() || c == '_')
+        && chars.all(|c| c.is_alphanumeric() || c == '_')
+        && Expression::parse(name) == Some(Expression::Variable(name.to_string()))
+}
+
+/// Draws values for the variables of the sheet and replaces all expressions by their values
+pub fn instantiate<R: Rng>(content: &mut Value, rng: &mut R) {
+    let mut values = Vec::new();
+    draw_values(content, rng, &mut values);
+    if !values.is_empty() {
+        substitute(content, &values);
+    }
+}
+
+fn draw_values<R: Rng>(value: &mut Value, rng: &mut R, values: &mut Vec<(String, f64)>) {
+    if value.get("type").and_then(Value::as_str) == Some("variables") {
+        if let Some(variables) = value.get_mut("variables").and_then(Value::as_array_mut) {
+            for variable in variables.iter_mut().filter_map(Value::as_object_mut) {
+                let name = variable.get("name").and_then(Value::as_str).unwrap_or("");
+                let drawn = variable
+                    .get("definition")
+                    .and_then(Value::as_str)
+                    .and_then(Definition::parse)
+                    .and_then(|definition| definition.draw(rng, values));
+                if let Some(drawn) = drawn {
+                    values.push((name.to_string(), drawn));
+                    variable.insert("value".into(), json!(drawn));
+                }
+            }
+        }
+    }
+    if let Some(children) = value.get_mut("content").and_then(Value::as_array_mut) {
+        for child in children {
+            draw_values(child, rng, values);
+        }
+    }
+}
+
+fn substitute(value: &mut Value, values: &[(String, f64)]) {
+    let replace = |text: &mut Value| {
+        if let Some(replaced) = text.as_str().map(|t| replace_expressions(t, values)) {
+            *text = Value::String(replaced);
+        }
+    };
+    if let Some(text) = value.get_mut("text") {
+        replace(text);
+    }
+    if let Some(marks) = value.get_mut("marks").and_then(Value::as_array_mut) {
+        for mark in marks.iter_mut() {
+            for field in ["solution", "source"] {
+                if let Some(text) = mark.get_mut(field) {
+                    replace(text);
+                }
+            }
+            for (list, field) in [("alternatives", "answer"), ("options", "text")] {
+                if let Some(entries) = mark.get_mut(list).and_then(Value::as_array_mut) {
+                    for text in entries.iter_mut().filter_map(|entry| entry.get_mut(field)) {
+                        replace(text);
+                    }
+                }
+            }
+        }
+    }
+    if let Some(children) = value.get_mut("content").and_then(Value::as_array_mut) {
+        for child in children {
+            substitute(child, values);
+        }
+    }
+}
+
+/// Replaces every `{{expression}}` in the text by its value. In LaTeX like `\frac{{{a}}}{2}` the
+/// expression starts at the last two of the opening braces.
+pub fn replace_expressions(text: &str, values: &[(String, f64)]) -> String {
+    replace_with(text, &|expression| {
+        expression.evaluate(&|name| {
+            values
+                .iter()
+                .find(|(defined, _)| defined == name)
+                .map(|(_, value)| *value)
+        })
+    })
+}
+
+/// Replaces every `{{expression}}` in the text by a sample value, which shows whether the text
+/// can be read once values are drawn
+pub fn with_sample_values(text: &str) -> String {
+    replace_with(text, &|_| Some(1.0))
+}
+
+fn replace_with(text: &str, value: &dyn Fn(&Expression) -> Option<f64>) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        let braces = rest[start..].chars().take_while(|&c| c == '{').count();
+        let open = start + braces - 2;
+        let end = match rest[open + 2..].find("}}") {
+            Some(end) => open + 2 + end,
+            None => break,
+        };
+        let value =
+            Expression::parse(&rest[open + 2..end]).and_then(|expression| value(&expression));
+        match value {
+            Some(value) => {
+                result.push_str(&rest[..open]);
+                result.push_str(&format_number(value));
+                rest = &rest[end + 2..];
+            }
+            None => {
+                result.push_str(&rest[..open + 2]);
+                rest = &rest[open + 2..];
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+/// Writes the number with a decimal comma, rounded to ten decimals to hide rounding errors
+pub fn format_number(value: f64) -> String {
+    let rounded = (value * 1e10).round() / 1e10;
+    // Avoids `-0`
+    let rounded = if rounded == 0.0 { 0.0 } else { rounded };
+    rounded.to_string().replace('.', ",")
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+
+    #[test]
+    fn parses_definitions() {
+        assert_eq!(
+            Definition::parse("1..10"),
+            Some(Definition::Range {
+                min: 1.0,
+                max: 10.0,
+                step: 1.0
+            })
+        );
+        assert_eq!(
+            Definition::parse("-0.5 .. 2.5 step 0.5"),
+            Some(Definition::Range {
+                min: -0.5,
+                max: 2.5,
+                step: 0.5
+            })
+        );
+        assert_eq!(
+            Definition::parse("[2, 3.5, -1]"),
+            Some(Definition::List(vec![2.0, 3.5, -1.0]))
+        );
+        assert!(matches!(
+            Definition::parse("a * b"),
+            Some(Definition::Formula(_))
+        ));
+        assert_eq!(Definition::parse("10..1"), None);
+        assert_eq!(Definition::parse("1..2 step 0"), None);
+        assert_eq!(Definition::parse("[2, x]"), None);
+        assert!(is_valid_name("a_1"));
+        assert!(!is_valid_name("1a"));
+        assert!(!is_valid_name("sqrt"));
+        assert!(!is_valid_name("pi"));
+    }
+
+    #[test]
+    fn replaces_expressions() {
+        let values = vec![("a".to_string(), 3.0), ("b".to_string(), 0.5)];
+        assert_eq!(
+            replace_expressions("{{a}} + {{b}} = {{a + b}}", &values),
+            "3 + 0,5 = 3,5"
+        );
+        assert_eq!(
+            replace_expressions("\\frac{{{a}}}{2}", &values),
+            "\\frac{3}{2}"
+        );
+        assert_eq!(replace_expressions("{{c}} {{a", &values), "{{c}} {{a");
+        assert_eq!(replace_expressions("{{0.1 + 0.2}}", &values), "0,3");
+    }
+
+    #[test]
+    fn instantiates_sheets() {
+        let sheet = json!({"type": "doc", "content": [
+            {"type": "variables", "variables": [
+                {"name": "a", "definition": "1..10"},
+                {"name": "b", "definition": "[2, 4]"},
+                {"name": "c", "definition": "a * b"},
+            ]},
+            {"type": "paragraph", "content": [
+                {"type": "text", "text": "{{a}} · {{b}} = "},
+                {"type": "text", "text": "{{c}}", "marks": [
+                    {"type": "gap", "solution": "{{c}}", "answer": ""},
+                ]},
+            ]},
+        ]});
+        let mut content = sheet.clone();
+        instantiate(&mut content, &mut StdRng::seed_from_u64(3));
+        let variables = content["content"][0]["variables"].as_array().unwrap();
+        let value = |i: usize| variables[i]["value"].as_f64().unwrap();
+        let (a, b, c) = (value(0), value(1), value(2));
+        assert!((1.0..=10.0).contains(&a) && a.fract() == 0.0);
+        assert!(b == 2.0 || b == 4.0);
+        assert_eq!(c, a * b);
+        let paragraph = &content["content"][1]["content"];
+        assert_eq!(paragraph[0]["text"], json!(format!("{} · {} = ", a, b)));
+        assert_eq!(paragraph[1]["text"], json!(c.to_string()));
+        assert_eq!(paragraph[1]["marks"][0]["solution"], json!(c.to_string()));
+    }
+}
@@ -0,0 +1,318 @@
+use chrono::{DateTime, Utc};
+use rocket_sync_db_pools::diesel;
+
+use crate::db::model::SheetUpdateDiesel;
+use crate::db::schema::{sheet_updates, sheets};
+use crate::Db;
+
+use super::logic::collaboration::{self, SheetUpdate};
+use super::logic::Id;
+use super::Error;
+
+use self::diesel::prelude::*;
+
+impl From<SheetUpdateDiesel> for SheetUpdate {
+    fn from(u: SheetUpdateDiesel) -> SheetUpdate {
+        SheetUpdate {
+            id: u.id,
+            author_id: u.author_id,
+            created: u.created,
+            payload: u.payload,
+        }
+    }
+}
+
+fn load_updates_since(
+    c: &PgConnection,
+    sheet_id: Id,
+    since: i64,
+) -> QueryResult<Vec<SheetUpdateDiesel>> {
+    sheet_updates::table
+        .filter(sheet_updates::sheet_id.eq(sheet_id))
+        .filter(sheet_updates::id.gt(since))
+        .order(sheet_updates::id.asc())
+        .load(c)
+}
+
+pub async fn get_updates_since(
+    db: &Db,
+    sheet_id: Id,
+    since: i64,
+) -> Result<Vec<SheetUpdate>, Error> {
+    let updates = db
+        .run(move |c| load_updates_since(c, sheet_id, since))
+        .await?;
+    Ok(updates.into_iter().map(|u| u.into()).collect())
+}
+
+/// Locks the sheet so that updates and compactions of one sheet are applied one after another.
+fn lock_sheet(c: &PgConnection, sheet_id: Id) -> QueryResult<()> {
+    sheets::table
+        .find(sheet_id)
+        .select(sheets::id)
+        .for_update()
+        .first::<Id>(c)
+        .map(|_| ())
+}
+
+/// Appends an update based on the newest update `base`, which is 0 for the
+/// first update. Nothing is stored if the sheet has received other updates in
+/// the meantime, as the update has to be rebased onto them first.
+fn insert_update(
+    c: &PgConnection,
+    sheet_id: Id,
+    base: i64,
+    author_id: i32,
+    created: DateTime<Utc>,
+    payload: Vec<u8>,
+) -> Result<Option<SheetUpdateDiesel>, Error> {
+    c.transaction(|| {
+        lock_sheet(c, sheet_id)?;
+        let newest: Option<i64> = sheet_updates::table
+            .filter(sheet_updates::sheet_id.eq(sheet_id))
+            .select(diesel::dsl::max(sheet_updates::id))
+            .first(c)?;
+        if newest.unwrap_or(0) != base {
+            return Ok(None);
+        }
+        diesel::insert_into(sheet_updates::table)
+            .values(&(
+                sheet_updates::sheet_id.eq(sheet_id),
+                sheet_updates::author_id.eq(author_id),
+                sheet_updates::created.eq(created),
+                sheet_updates::payload.eq(payload),
+            ))
+            .get_result(c)
+            .map(Some)
+    })
+}
+
+pub async fn create_update(
+    db: &Db,
+    sheet_id: Id,
+    base: i64,
+    author_id: i32,
+    created: DateTime<Utc>,
+    payload: Vec<u8>,
+) -> Result<Option<SheetUpdate>, Error> {
+    let update = db
+        .run(move |c| insert_update(c, sheet_id, base, author_id, created, payload))
+        .await?;
+    Ok(update.map(|u| u.into()))
+}
+
+#[allow(clippy::too_many_arguments)]
+fn replace_updates(
+    c: &PgConnection,
+    sheet_id: Id,
+    up_to: i64,
+    author_id: i32,
+    merged_payload: Vec<u8>,
+    title: String,
+    content: serde_json::Value,
+    changed: DateTime<Utc>,
+) -> Result<Option<SheetUpdateDiesel>, Error> {
+    c.transaction(|| {
+        lock_sheet(c, sheet_id)?;
+        let compacted: Vec<i64> = diesel::delete(
+            sheet_updates::table
+                .filter(sheet_updates::sheet_id.eq(sheet_id))
+                .filter(sheet_updates::id.le(up_to)),
+        )
+        .returning(sheet_updates::id)
+        .get_results(c)?;
+        let id = match collaboration::merged_update_id(&compacted) {
+            Some(id) => id,
+            None => return Ok(None),
+        };
+        diesel::update(sheets::table.find(sheet_id))
+            .set((
+                sheets::title.eq(title),
+                sheets::content.eq(content),
+                sheets::changed.eq(changed),
+            ))
+            .execute(c)?;
+        diesel::insert_into(sheet_updates::table)
+            .values(&(
+                sheet_updates::id.eq(id),
+                sheet_updates::sheet_id.eq(sheet_id),
+                sheet_updates::author_id.eq(author_id),
+                sheet_updates::created.eq(changed),
+                sheet_updates::payload.eq(merged_payload),
+            ))
+            .get_result(c)
+            .map(Some)
+    })
+}
+
+/// Replaces all updates up to and including `up_to` by a single merged update
+/// and stores the corresponding document in the sheet. Nothing is changed if
+/// there are no such updates.
+#[allow(clippy::too_many_arguments)]
+pub async fn compact_updates(
+    db: &Db,
+    sheet_id: Id,
+    up_to: i64,
+    author_id: i32,
+    merged_payload: Vec<u8>,
+    title: String,
+    content: serde_json::Value,
+    changed: DateTime<Utc>,
+) -> Result<Option<SheetUpdate>, Error> {
+    let update = db
+        .run(move |c| {
+            replace_updates(
+                c,
+                sheet_id,
+                up_to,
+                author_id,
+                merged_payload,
+                title,
+                content,
+                changed,
+            )
+        })
+        .await?;
+    Ok(update.map(|u| u.into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::schema::users;
+
+    /// Runs against the PostgreSQL database given in `TEST_DATABASE_URL`. All changes are rolled
+    /// back afterwards.
+    fn with_sheet(test: impl FnOnce(&PgConnection, Id)) {
+        let url = std::env::var("TEST_DATABASE_URL").expect("TEST_DATABASE_URL not set");
+        let c = PgConnection::establish(&url).expect("connecting to test database failed");
+        diesel_migrations::run_pending_migrations(&c).expect("migrating test database failed");
+        c.test_transaction::<_, Error, _>(|| {
+            let owner_id: i32 = diesel::insert_into(users::table)
+                .values(&(
+                    users::username.eq("collaboration-test"),
+                    users::password_hash.eq(""),
+                ))
+                .returning(users::id)
+                .get_result(&c)?;
+            let now = Utc::now();
+            let sheet_id: Id = diesel::insert_into(sheets::table)
+                .values(&(
+                    sheets::title.eq("Entwurf"),
+                    sheets::owner_id.eq(owner_id),
+                    sheets::created.eq(now),
+                    sheets::changed.eq(now),
+                    sheets::content.eq(serde_json::json!({})),
+                ))
+                .returning(sheets::id)
+                .get_result(&c)?;
+            test(&c, sheet_id);
+            Ok(())
+        });
+    }
+
+    fn owner(c: &PgConnection, sheet_id: Id) -> i32 {
+        sheets::table
+            .find(sheet_id)
+            .select(sheets::owner_id)
+            .first(c)
+            .unwrap()
+    }
+
+    fn push(c: &PgConnection, sheet_id: Id, base: i64, payload: &[u8]) -> Option<i64> {
+        insert_update(
+            c,
+            sheet_id,
+            base,
+            owner(c, sheet_id),
+            Utc::now(),
+            payload.to_vec(),
+        )
+        .unwrap()
+        .map(|u| u.id)
+    }
+
+    fn payloads(c: &PgConnection, sheet_id: Id, since: i64) -> Vec<Vec<u8>> {
+        load_updates_since(c, sheet_id, since)
+            .unwrap()
+            .into_iter()
+            .map(|u| u.payload)
+            .collect()
+    }
+
+    #[test]
+    #[ignore = "needs a PostgreSQL database in TEST_DATABASE_URL"]
+    fn rejects_updates_on_outdated_base() {
+        with_sheet(|c, sheet_id| {
+            let first = push(c, sheet_id, 0, b"a").unwrap();
+            let second = push(c, sheet_id, first, b"b").unwrap();
+            assert_eq!(push(c, sheet_id, first, b"c"), None);
+            assert_eq!(push(c, sheet_id, 0, b"c"), None);
+            assert_eq!(push(c, sheet_id, second + 1, b"c"), None);
+            push(c, sheet_id, second, b"c").unwrap();
+            assert_eq!(payloads(c, sheet_id, 0), [b"a", b"b", b"c"]);
+        });
+    }
+
+    #[test]
+    #[ignore = "needs a PostgreSQL database in TEST_DATABASE_URL"]
+    fn resuming_after_compaction_misses_no_update() {
+        with_sheet(|c, sheet_id| {
+            let mut ids = vec![];
+            let mut base = 0;
+            for payload in [b"1", b"2", b"3", b"4", b"5", b"6"].iter() {
+                base = push(c, sheet_id, base, &payload[..]).unwrap();
+                ids.push(base);
+            }
+            let up_to = ids[3];
+            let content = serde_json::json!({ "type": "doc" });
+            let merged = replace_updates(
+                c,
+                sheet_id,
+                up_to,
+                owner(c, sheet_id),
+                b"1234".to_vec(),
+                "Zusammengefasst".into(),
+                content.clone(),
+                Utc::now(),
+            )
+            .unwrap()
+            .unwrap();
+            assert_eq!(merged.id, up_to);
+
+            // Clients that have seen an update folded into the snapshot get the snapshot, all
+            // others only what they miss
+            assert_eq!(payloads(c, sheet_id, 0), [&b"1234"[..], b"5", b"6"]);
+            assert_eq!(payloads(c, sheet_id, ids[1]), [&b"1234"[..], b"5", b"6"]);
+            assert_eq!(payloads(c, sheet_id, up_to), [b"5", b"6"]);
+            assert_eq!(payloads(c, sheet_id, ids[5]), Vec::<Vec<u8>>::new());
+
+            // The snapshot takes the place of the compacted updates as base
+            assert_eq!(push(c, sheet_id, up_to, b"7"), None);
+            push(c, sheet_id, ids[5], b"7").unwrap();
+
+            let (title, stored): (String, serde_json::Value) = sheets::table
+                .find(sheet_id)
+                .select((sheets::title, sheets::content))
+                .first(c)
+                .unwrap();
+            assert_eq!(title, "Zusammengefasst");
+            assert_eq!(stored, content);
+
+            // Compacting again up to already compacted updates changes nothing
+            let outdated = replace_updates(
+                c,
+                sheet_id,
+                ids[2],
+                owner(c, sheet_id),
+                b"123".to_vec(),
+                "Veraltet".into(),
+                serde_json::json!({}),
+                Utc::now(),
+            )
+            .unwrap();
+            assert!(outdated.is_none());
+            assert_eq!(payloads(c, sheet_id, 0)[0], b"1234");
+        });
+    }
+}
@@ -0,0 +1,86 @@
+use rocket_sync_db_pools::diesel;
+
+use crate::db::model::FolderDiesel;
+use crate::db::schema::folders;
+use crate::Db;
+
+use super::logic::folder::Folder;
+use super::Error;
+
+use self::diesel::prelude::*;
+
+impl From<FolderDiesel> for Folder {
+    fn from(f: FolderDiesel) -> Folder {
+        Folder {
+            id: f.id,
+            name: f.name,
+            owner_id: f.owner_id,
+            parent_id: f.parent_id,
+        }
+    }
+}
+
+pub async fn get_folders(db: &Db, owner_id: i32) -> Result<Vec<Folder>, Error> {
+    let folders: Vec<FolderDiesel> = db
+        .run(move |c| {
+            folders::table
+                .filter(folders::owner_id.eq(owner_id))
+                .order(folders::name.asc())
+                .load(c)
+        })
+        .await?;
+    Ok(folders.into_iter().map(|f| f.into()).collect())
+}
+
+pub async fn get_folder_by_id(db: &Db, id: i32) -> Result<Option<Folder>, Error> {
+    let folder: Option<FolderDiesel> = db
+        .run(move |c| folders::table.find(id).first(c).optional())
+        .await?;
+    Ok(folder.map(|f| f.into()))
+}
+
+pub async fn create_folder(
+    db: &Db,
+    name: String,
+    owner_id: i32,
+    parent_id: Option<i32>,
+) -> Result<i32, Error> {
+    let folder: FolderDiesel = db
+        .run(move |c| {
+            diesel::insert_into(folders::table)
+                .values(&(
+                    folders::name.eq(name),
+                    folders::owner_id.eq(owner_id),
+                    folders::parent_id.eq(parent_id),
+                ))
+                .get_result(c)
+        })
+        .await?;
+    Ok(folder.id)
+}
+
+pub async fn rename_folder(db: &Db, id: i32, name: String) -> Result<(), Error> {
+    db.run(move |c| {
+        diesel::update(folders::table.find(id))
+            .set(folders::name.eq(name))
+            .execute(c)
+    })
+    .await?;
+    Ok(())
+}
+
+pub async fn move_folder(db: &Db, id: i32, parent_id: Option<i32>) -> Result<(), Error> {
+    db.run(move |c| {
+        diesel::update(folders::table.find(id))
+            .set(folders::parent_id.eq(parent_id))
+            .execute(c)
+    })
+    .await?;
+    Ok(())
+}
+
+pub async fn delete_folder(db: &Db, id: i32) -> Result<(), Error> {
+    db.run(move |c| diesel::delete(folders::table.find(id)).execute(c))
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,380 @@
+use chrono::{DateTime, Utc};
+use rocket_sync_db_pools::diesel;
+
+use crate::db::model::MediaDiesel;
+use crate::db::schema::{media, media_quotas, sheet_media, solution_media, users};
+use crate::Db;
+
+use super::logic::media::{FreshMedia, Media};
+use super::logic::Id;
+use super::Error;
+
+use self::diesel::prelude::*;
+
+impl From<MediaDiesel> for Media {
+    fn from(m: MediaDiesel) -> Media {
+        Media {
+            id: m.id,
+            owner_id: m.owner_id,
+            filename: m.filename,
+            mimetype: m.mimetype,
+            size: m.size as u64,
+            content_hash: m.content_hash,
+        }
+    }
+}
+
+pub async fn get_media(db: &Db, id: Id) -> Result<Option<Media>, Error> {
+    let media: Option<MediaDiesel> = db
+        .run(move |c| media::table.find(id).first(c).optional())
+        .await?;
+    Ok(media.map(|m| m.into()))
+}
+
+/// Inserts the media unless they would exceed the quota of their owner, `default_quota` applies
+/// if there is no quota for the owner in the database. Returns `None` if the quota is exceeded.
+/// The content is stored with `store` before the transaction commits, so the garbage collection
+/// cannot remove content with the same hash in the meantime.
+fn insert_media<E, F>(
+    c: &PgConnection,
+    fresh_media: FreshMedia,
+    default_quota: u64,
+    store: F,
+) -> Result<Option<MediaDiesel>, E>
+where
+    E: From<Error>,
+    F: FnOnce(&str) -> Result<(), E>,
+{
+    c.transaction(|| {
+        let owner_id = fresh_media.owner_id;
+        // Uploads of the same user are checked against the quota one after another
+        users::table
+            .find(owner_id)
+            .select(users::id)
+            .for_no_key_update()
+            .first::<i32>(c)?;
+        let sizes: Vec<i64> = media::table
+            .select(media::size)
+            .filter(media::owner_id.eq(owner_id))
+            .load(c)?;
+        let quota: Option<i64> = media_quotas::table
+            .select(media_quotas::quota)
+            .find(owner_id)
+            .first(c)
+            .optional()?;
+        let used: u64 = sizes.into_iter().map(|size| size as u64).sum();
+        let quota = quota.map_or(default_quota, |quota| quota as u64);
+        if used + fresh_media.size > quota {
+            return Ok(None);
+        }
+        let media: MediaDiesel = diesel::insert_into(media::table)
+            .values(&(
+                media::owner_id.eq(owner_id),
+                media::filename.eq(fresh_media.filename),
+                media::mimetype.eq(fresh_media.mimetype),
+                media::size.eq(fresh_media.size as i64),
+                media::content_hash.eq(&fresh_media.content_hash),
+                media::created.eq(fresh_media.created),
+            ))
+            .get_result(c)?;
+        store(&fresh_media.content_hash)?;
+        Ok(Some(media))
+    })
+}
+
+pub async fn create_media<E, F>(
+    db: &Db,
+    fresh_media: FreshMedia,
+    default_quota: u64,
+    store: F,
+) -> Result<Option<Media>, E>
+where
+    E: From<Error> + Send + 'static,
+    F: FnOnce(&str) -> Result<(), E> + Send + 'static,
+{
+    let media = db
+        .run(move |c| insert_media(c, fresh_media, default_quota, store))
+        .await?;
+    Ok(media.map(|m| m.into()))
+}
+
+/// Whether the media are used by any sheet or solution
+pub async fn is_used(db: &Db, media_id: Id) -> Result<bool, Error> {
+    db.run(move |c| {
+        diesel::select(
+            diesel::dsl::exists(sheet_media::table.filter(sheet_media::media_id.eq(media_id))).or(
+                diesel::dsl::exists(
+                    solution_media::table.filter(solution_media::media_id.eq(media_id)),
+                ),
+            ),
+        )
+        .get_result(c)
+    })
+    .await
+}
+
+/// Returns those of the given media which are owned by the user or used by any sheet or solution
+pub async fn get_accessible_media(
+    db: &Db,
+    user_id: i32,
+    media_ids: Vec<Id>,
+) -> Result<Vec<Id>, Error> {
+    db.run(move |c| {
+        let in_sheets = sheet_media::table.select(sheet_media::media_id);
+        let in_solutions = solution_media::table.select(solution_media::media_id);
+        media::table
+            .select(media::id)
+            .filter(media::id.eq_any(media_ids))
+            .filter(
+                media::owner_id
+                    .eq(user_id)
+                    .or(media::id.eq_any(in_sheets))
+                    .or(media::id.eq_any(in_solutions)),
+            )
+            .load(c)
+    })
+    .await
+}
+
+pub async fn link_media(db: &Db, sheet_id: Id, media_id: Id) -> Result<(), Error> {
+    db.run(move |c| {
+        diesel::insert_into(sheet_media::table)
+            .values(&(
+                sheet_media::sheet_id.eq(sheet_id),
+                sheet_media::media_id.eq(media_id),
+            ))
+            .on_conflict_do_nothing()
+            .execute(c)
+    })
+    .await?;
+    Ok(())
+}
+
+/// Replaces the media used by the sheet
+pub async fn set_sheet_media(db: &Db, sheet_id: Id, media_ids: Vec<Id>) -> Result<(), Error> {
+    db.run(move |c| {
+        c.transaction::<_, Error, _>(|| {
+            diesel::delete(
+                sheet_media::table
+                    .filter(sheet_media::sheet_id.eq(sheet_id))
+                    .filter(diesel::dsl::not(sheet_media::media_id.eq_any(&media_ids))),
+            )
+            .execute(c)?;
+            let links: Vec<_> = media_ids
+                .iter()
+                .map(|media_id| {
+                    (
+                        sheet_media::sheet_id.eq(sheet_id),
+                        sheet_media::media_id.eq(*media_id),
+                    )
+                })
+                .collect();
+            if !links.is_empty() {
+                diesel::insert_into(sheet_media::table)
+                    .values(&links)
+                    .on_conflict_do_nothing()
+                    .execute(c)?;
+            }
+            Ok(())
+        })
+    })
+    .await
+}
+
+/// Returns the sizes of all media owned by the user
+pub async fn get_media_sizes(db: &Db, user_id: i32) -> Result<Vec<u64>, Error> {
+    let sizes: Vec<i64> = db
+        .run(move |c| {
+            media::table
+                .select(media::size)
+                .filter(media::owner_id.eq(user_id))
+                .load(c)
+        })
+        .await?;
+    Ok(sizes.into_iter().map(|size| size as u64).collect())
+}
+
+pub async fn get_quota(db: &Db, user_id: i32) -> Result<Option<u64>, Error> {
+    let quota: Option<i64> = db
+        .run(move |c| {
+            media_quotas::table
+                .select(media_quotas::quota)
+                .find(user_id)
+                .first(c)
+                .optional()
+        })
+        .await?;
+    Ok(quota.map(|quota| quota as u64))
+}
+
+/// Marks media which are not used by any sheet or solution at the given time and deletes those
+/// which have been unused since before the cutoff. The content which is no longer referenced by
+/// any media is removed with `remove` before the transaction commits, while uploads wait for the
+/// transaction. Returns the number of contents removed.
+fn remove_unused_media<E, F>(
+    c: &PgConnection,
+    now: DateTime<Utc>,
+    cutoff: DateTime<Utc>,
+    mut remove: F,
+) -> Result<usize, E>
+where
+    E: From<Error>,
+    F: FnMut(&str) -> Result<(), E>,
+{
+    c.transaction(|| {
+        diesel::sql_query("LOCK TABLE media IN SHARE ROW EXCLUSIVE MODE").execute(c)?;
+        let used = || {
+            media::id
+                .eq_any(sheet_media::table.select(sheet_media::media_id))
+                .or(media::id.eq_any(solution_media::table.select(solution_media::media_id)))
+        };
+        diesel::update(
+            media::table
+                .filter(used())
+                .filter(media::unused_since.is_not_null()),
+        )
+        .set(media::unused_since.eq(None::<DateTime<Utc>>))
+        .execute(c)?;
+        diesel::update(
+            media::table
+                .filter(diesel::dsl::not(used()))
+                .filter(media::unused_since.is_null()),
+        )
+        .set(media::unused_since.eq(now))
+        .execute(c)?;
+        let mut hashes: Vec<String> = diesel::delete(
+            media::table
+                .filter(diesel::dsl::not(used()))
+                .filter(media::unused_since.lt(cutoff)),
+        )
+        .returning(media::content_hash)
+        .get_results(c)?;
+        let remaining: Vec<String> = media::table
+            .select(media::content_hash)
+            .filter(media::content_hash.eq_any(&hashes))
+            .load(c)?;
+        hashes.sort();
+        hashes.dedup();
+        hashes.retain(|hash| !remaining.contains(hash));
+        for hash in &hashes {
+            remove(hash)?;
+        }
+        Ok(hashes.len())
+    })
+}
+
+pub async fn delete_unused_media<E, F>(
+    db: &Db,
+    now: DateTime<Utc>,
+    cutoff: DateTime<Utc>,
+    remove: F,
+) -> Result<usize, E>
+where
+    E: From<Error> + Send + 'static,
+    F: FnMut(&str) -> Result<(), E> + Send + 'static,
+{
+    db.run(move |c| remove_unused_media(c, now, cutoff, remove))
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::schema::solutions;
+
+    /// Runs against the PostgreSQL database given in `TEST_DATABASE_URL`. All changes are rolled
+    /// back afterwards.
+    fn with_user(test: impl FnOnce(&PgConnection, i32)) {
+        let url = std::env::var("TEST_DATABASE_URL").expect("TEST_DATABASE_URL not set");
+        let c = PgConnection::establish(&url).expect("connecting to test database failed");
+        diesel_migrations::run_pending_migrations(&c).expect("migrating test database failed");
+        c.test_transaction::<_, Error, _>(|| {
+            let user_id: i32 = diesel::insert_into(users::table)
+                .values(&(
+                    users::username.eq("media-test"),
+                    users::password_hash.eq(""),
+                ))
+                .returning(users::id)
+                .get_result(&c)?;
+            test(&c, user_id);
+            Ok(())
+        });
+    }
+
+    fn upload(c: &PgConnection, owner_id: i32, size: u64, hash: &str) -> Option<Id> {
+        let fresh_media = FreshMedia {
+            owner_id,
+            filename: "aufnahme.ogg".into(),
+            mimetype: "audio/ogg".into(),
+            size,
+            content_hash: hash.into(),
+            created: Utc::now(),
+        };
+        insert_media(c, fresh_media, 10, |_| Ok::<_, Error>(()))
+            .unwrap()
+            .map(|m| m.id)
+    }
+
+    #[test]
+    #[ignore = "needs a PostgreSQL database in TEST_DATABASE_URL"]
+    fn refuses_media_beyond_the_quota() {
+        with_user(|c, user_id| {
+            upload(c, user_id, 6, "a").unwrap();
+            assert_eq!(upload(c, user_id, 5, "b"), None);
+            upload(c, user_id, 4, "b").unwrap();
+
+            diesel::insert_into(media_quotas::table)
+                .values(&(
+                    media_quotas::user_id.eq(user_id),
+                    media_quotas::quota.eq(15),
+                ))
+                .execute(c)
+                .unwrap();
+            upload(c, user_id, 5, "c").unwrap();
+        });
+    }
+
+    #[test]
+    #[ignore = "needs a PostgreSQL database in TEST_DATABASE_URL"]
+    fn keeps_media_used_by_solutions() {
+        with_user(|c, user_id| {
+            let in_solution = upload(c, user_id, 1, "gelöst").unwrap();
+            let copy = upload(c, user_id, 1, "gelöst").unwrap();
+            upload(c, user_id, 1, "verwaist").unwrap();
+            let now = Utc::now();
+            let solution_id: i32 = diesel::insert_into(solutions::table)
+                .values(&(
+                    solutions::title.eq("Lösung"),
+                    solutions::owner_id.eq(user_id),
+                    solutions::sheet_version.eq(now),
+                    solutions::created.eq(now),
+                    solutions::changed.eq(now),
+                    solutions::content.eq(serde_json::json!({})),
+                ))
+                .returning(solutions::id)
+                .get_result(c)
+                .unwrap();
+            diesel::insert_into(solution_media::table)
+                .values(&(
+                    solution_media::solution_id.eq(solution_id),
+                    solution_media::media_id.eq(in_solution),
+                ))
+                .execute(c)
+                .unwrap();
+
+            let mut removed = vec![];
+            let later = now + chrono::Duration::hours(1);
+            let remove = |hash: &str| {
+                removed.push(hash.to_string());
+                Ok::<_, Error>(())
+            };
+            remove_unused_media(c, now, later, remove).unwrap();
+            // The content of the unused copy is still needed for the solution
+            assert!(removed.contains(&"verwaist".to_string()));
+            assert!(!removed.contains(&"gelöst".to_string()));
+            let remaining: Vec<Id> = media::table.select(media::id).load(c).unwrap();
+            assert!(remaining.contains(&in_solution));
+            assert!(!remaining.contains(&copy));
+        });
+    }
+}
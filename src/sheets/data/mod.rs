@@ -2,7 +2,13 @@ use rocket_sync_db_pools::diesel;
 
 use super::logic;
 
+pub mod collaboration;
+pub mod folder;
+pub mod media;
+pub mod search;
+pub mod sharing;
 pub mod sheet;
 pub mod solution;
+pub mod tag;
 
 pub type Error = diesel::result::Error;
@@ -0,0 +1,316 @@
+use rocket_sync_db_pools::diesel;
+
+use crate::db::model::{SheetSearchResultDiesel, SolutionSearchResultDiesel};
+use crate::login::transport::UserInfo;
+use crate::Db;
+
+use super::logic::search::{
+    parse_snippet, Scope, SheetHit, SolutionHit, HIGHLIGHT_START, HIGHLIGHT_STOP,
+};
+use super::logic::sharing::Permission;
+use super::logic::sheet::SheetMetadata;
+use super::logic::solution::SolutionMetadata;
+use super::Error;
+
+use self::diesel::prelude::*;
+use self::diesel::sql_types::{BigInt, Bool, Int4, Text};
+
+const MAX_RESULTS: i64 = 50;
+
+impl From<SheetSearchResultDiesel> for SheetHit {
+    fn from(r: SheetSearchResultDiesel) -> SheetHit {
+        SheetHit {
+            metadata: SheetMetadata {
+                id: r.id,
+                title: r.title,
+                owner: UserInfo {
+                    id: r.owner_id,
+                    username: r.owner_name,
+                },
+                created: r.created,
+                changed: r.changed,
+                trashed: r.trashed,
+                folder_id: r.folder_id,
+            },
+            // Sheets without share are owned by the searching user
+            permission: r.permission.map_or(Permission::Owner, |p| p.into()),
+            rank: r.rank,
+            snippet: parse_snippet(&r.snippet),
+        }
+    }
+}
+
+impl From<SolutionSearchResultDiesel> for SolutionHit {
+    fn from(r: SolutionSearchResultDiesel) -> SolutionHit {
+        SolutionHit {
+            metadata: SolutionMetadata {
+                id: r.id,
+                title: r.title,
+                sheet_id: r.sheet_id,
+                sheet_version: r.sheet_version,
+                owner: UserInfo {
+                    id: r.owner_id,
+                    username: r.owner_name,
+                },
+                created: r.created,
+                changed: r.changed,
+                trashed: r.trashed,
+            },
+            rank: r.rank,
+            snippet: parse_snippet(&r.snippet),
+        }
+    }
+}
+
+fn headline_options() -> String {
+    format!(
+        "StartSel={}, StopSel={}, MaxFragments=3, MaxWords=20, MinWords=5",
+        HIGHLIGHT_START, HIGHLIGHT_STOP
+    )
+}
+
+pub async fn search_sheets(
+    db: &Db,
+    user_id: i32,
+    query: String,
+    scope: Scope,
+    include_trashed: bool,
+) -> Result<Vec<SheetHit>, Error> {
+    db.run(move |c| query_sheets(c, user_id, query, scope, include_trashed))
+        .await
+}
+
+fn query_sheets(
+    c: &PgConnection,
+    user_id: i32,
+    query: String,
+    scope: Scope,
+    include_trashed: bool,
+) -> Result<Vec<SheetHit>, Error> {
+    let hits: Vec<SheetSearchResultDiesel> = diesel::sql_query(
+        "WITH q AS (SELECT websearch_to_tsquery('german', $1) AS query),
+        hits AS (
+            SELECT s.id, s.title, s.owner_id, u.username AS owner_name, s.created,
+                s.changed, s.trashed, s.folder_id, s.content, sh.permission,
+                ts_rank(s.search_vector, q.query) AS rank
+            FROM sheets s
+            INNER JOIN users u ON u.id = s.owner_id
+            LEFT JOIN sheet_shares sh ON sh.sheet_id = s.id AND sh.user_id = $3
+            CROSS JOIN q
+            WHERE s.search_vector @@ q.query
+                AND ((s.owner_id = $3 AND $4) OR (sh.user_id IS NOT NULL AND $5))
+                AND (s.trashed IS NULL OR (s.owner_id = $3 AND $6))
+            ORDER BY rank DESC, s.title ASC
+            LIMIT $7
+        )
+        SELECT hits.id, hits.title, hits.owner_id, hits.owner_name, hits.created,
+            hits.changed, hits.trashed, hits.folder_id, hits.permission, hits.rank,
+            ts_headline('german', document_text(hits.content), q.query, $2) AS snippet
+        FROM hits CROSS JOIN q
+        ORDER BY hits.rank DESC, hits.title ASC",
+    )
+    .bind::<Text, _>(query)
+    .bind::<Text, _>(headline_options())
+    .bind::<Int4, _>(user_id)
+    .bind::<Bool, _>(scope.includes_owned())
+    .bind::<Bool, _>(scope.includes_shared())
+    .bind::<Bool, _>(include_trashed)
+    .bind::<BigInt, _>(MAX_RESULTS)
+    .load(c)?;
+    Ok(hits.into_iter().map(|h| h.into()).collect())
+}
+
+/// Searches the answers of students in solutions for sheets the user may grade
+pub async fn search_answers(
+    db: &Db,
+    user_id: i32,
+    query: String,
+    scope: Scope,
+) -> Result<Vec<SolutionHit>, Error> {
+    let hits: Vec<SolutionSearchResultDiesel> = db
+        .run(move |c| {
+            diesel::sql_query(
+                "WITH q AS (SELECT websearch_to_tsquery('german', $1) AS query),
+                hits AS (
+                    SELECT so.id, so.title, so.sheet_id, so.sheet_version, so.owner_id,
+                        u.username AS owner_name, so.created, so.changed, so.trashed, so.content,
+                        ts_rank(so.answer_vector, q.query) AS rank
+                    FROM solutions so
+                    INNER JOIN users u ON u.id = so.owner_id
+                    INNER JOIN sheets s ON s.id = so.sheet_id
+                    LEFT JOIN sheet_shares sh ON sh.sheet_id = s.id AND sh.user_id = $3
+                    CROSS JOIN q
+                    WHERE so.answer_vector @@ q.query
+                        AND so.trashed IS NULL
+                        AND s.trashed IS NULL
+                        AND ((s.owner_id = $3 AND $4)
+                            OR (sh.permission IN ('grader', 'editor') AND $5))
+                    ORDER BY rank DESC, so.changed DESC
+                    LIMIT $6
+                )
+                SELECT hits.id, hits.title, hits.sheet_id, hits.sheet_version, hits.owner_id,
+                    hits.owner_name, hits.created, hits.changed, hits.trashed, hits.rank,
+                    ts_headline('german', solution_answers(hits.content), q.query, $2) AS snippet
+                FROM hits CROSS JOIN q
+                ORDER BY hits.rank DESC, hits.changed DESC",
+            )
+            .bind::<Text, _>(query)
+            .bind::<Text, _>(headline_options())
+            .bind::<Int4, _>(user_id)
+            .bind::<Bool, _>(scope.includes_owned())
+            .bind::<Bool, _>(scope.includes_shared())
+            .bind::<BigInt, _>(MAX_RESULTS)
+            .load(c)
+        })
+        .await?;
+    Ok(hits.into_iter().map(|h| h.into()).collect())
+}
+
+pub async fn search_own_solutions(
+    db: &Db,
+    user_id: i32,
+    query: String,
+    include_trashed: bool,
+) -> Result<Vec<SolutionHit>, Error> {
+    let hits: Vec<SolutionSearchResultDiesel> = db
+        .run(move |c| {
+            diesel::sql_query(
+                "WITH q AS (SELECT websearch_to_tsquery('german', $1) AS query),
+                hits AS (
+                    SELECT so.id, so.title, so.sheet_id, so.sheet_version, so.owner_id,
+                        u.username AS owner_name, so.created, so.changed, so.trashed, so.content,
+                        ts_rank(so.search_vector, q.query) AS rank
+                    FROM solutions so
+                    INNER JOIN users u ON u.id = so.owner_id
+                    CROSS JOIN q
+                    WHERE so.search_vector @@ q.query
+                        AND so.owner_id = $3
+                        AND (so.trashed IS NULL OR $4)
+                    ORDER BY rank DESC, so.changed DESC
+                    LIMIT $5
+                )
+                SELECT hits.id, hits.title, hits.sheet_id, hits.sheet_version, hits.owner_id,
+                    hits.owner_name, hits.created, hits.changed, hits.trashed, hits.rank,
+                    ts_headline(
+                        'german',
+                        solution_answers(hits.content) || ' ' || document_text(hits.content),
+                        q.query,
+                        $2
+                    ) AS snippet
+                FROM hits CROSS JOIN q
+                ORDER BY hits.rank DESC, hits.changed DESC",
+            )
+            .bind::<Text, _>(query)
+            .bind::<Text, _>(headline_options())
+            .bind::<Int4, _>(user_id)
+            .bind::<Bool, _>(include_trashed)
+            .bind::<BigInt, _>(MAX_RESULTS)
+            .load(c)
+        })
+        .await?;
+    Ok(hits.into_iter().map(|h| h.into()).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use super::*;
+    use crate::db::schema::{sheet_shares, sheets, users};
+    use crate::db::sql_types::SheetPermissionDb;
+    use crate::sheets::logic::Id;
+
+    /// Runs against the PostgreSQL database given in `TEST_DATABASE_URL` with a sheet of the first
+    /// of three teachers, shared with the second one. All changes are rolled back afterwards.
+    fn with_shared_sheet(test: impl FnOnce(&PgConnection, &[i32])) {
+        let url = std::env::var("TEST_DATABASE_URL").expect("TEST_DATABASE_URL not set");
+        let c = PgConnection::establish(&url).expect("connecting to test database failed");
+        diesel_migrations::run_pending_migrations(&c).expect("migrating test database failed");
+        c.test_transaction::<_, Error, _>(|| {
+            let mut user_ids = Vec::new();
+            for username in ["search-owner", "search-viewer", "search-other"] {
+                user_ids.push(
+                    diesel::insert_into(users::table)
+                        .values(&(users::username.eq(username), users::password_hash.eq("")))
+                        .returning(users::id)
+                        .get_result::<i32>(&c)?,
+                );
+            }
+            let now = Utc::now();
+            let sheet_id: Id = diesel::insert_into(sheets::table)
+                .values(&(
+                    sheets::title.eq("Bruchrechnung"),
+                    sheets::owner_id.eq(user_ids[0]),
+                    sheets::created.eq(now),
+                    sheets::changed.eq(now),
+                    sheets::content.eq(serde_json::json!({"type": "doc", "content": [
+                        {"type": "paragraph", "content": [
+                            {"type": "text", "text": "Addiere Brüche mit gleichen Nennern."}
+                        ]}
+                    ]})),
+                ))
+                .returning(sheets::id)
+                .get_result(&c)?;
+            diesel::insert_into(sheet_shares::table)
+                .values(&(
+                    sheet_shares::sheet_id.eq(sheet_id),
+                    sheet_shares::user_id.eq(user_ids[1]),
+                    sheet_shares::permission.eq(SheetPermissionDb::Viewer),
+                ))
+                .execute(&c)?;
+            test(&c, &user_ids);
+            Ok(())
+        });
+    }
+
+    fn permissions(c: &PgConnection, user_id: i32, query: &str, scope: Scope) -> Vec<Permission> {
+        query_sheets(c, user_id, query.to_string(), scope, false)
+            .unwrap()
+            .into_iter()
+            .map(|hit| hit.permission)
+            .collect()
+    }
+
+    #[test]
+    #[ignore = "needs a PostgreSQL database in TEST_DATABASE_URL"]
+    fn finds_inflected_german_words() {
+        with_shared_sheet(|c, user_ids| {
+            let owner_id = user_ids[0];
+            assert_eq!(
+                permissions(c, owner_id, "Nenner", Scope::All),
+                [Permission::Owner]
+            );
+            assert_eq!(
+                permissions(c, owner_id, "gleicher Nenner", Scope::All),
+                [Permission::Owner]
+            );
+            assert!(permissions(c, owner_id, "Zähler", Scope::All).is_empty());
+            let hit = query_sheets(c, owner_id, "Nenner".into(), Scope::All, false)
+                .unwrap()
+                .remove(0);
+            assert!(hit
+                .snippet
+                .iter()
+                .any(|part| part.highlighted && part.text == "Nennern"));
+        });
+    }
+
+    #[test]
+    #[ignore = "needs a PostgreSQL database in TEST_DATABASE_URL"]
+    fn finds_only_sheets_visible_to_the_user() {
+        with_shared_sheet(|c, user_ids| {
+            let (owner_id, viewer_id, other_id) = (user_ids[0], user_ids[1], user_ids[2]);
+            assert_eq!(
+                permissions(c, owner_id, "Nenner", Scope::Owned),
+                [Permission::Owner]
+            );
+            assert!(permissions(c, owner_id, "Nenner", Scope::Shared).is_empty());
+            assert_eq!(
+                permissions(c, viewer_id, "Nenner", Scope::Shared),
+                [Permission::Viewer]
+            );
+            assert!(permissions(c, viewer_id, "Nenner", Scope::Owned).is_empty());
+            assert!(permissions(c, other_id, "Nenner", Scope::All).is_empty());
+        });
+    }
+}
@@ -0,0 +1,219 @@
+use rocket_sync_db_pools::diesel;
+
+use crate::db::model::{SheetMetadataDiesel, UserInfoDiesel};
+use crate::db::schema::{roles, sheet_shares, sheets, users};
+use crate::db::sql_types::{RoleDb, SheetPermissionDb};
+use crate::login::transport::UserInfo;
+use crate::Db;
+
+use super::logic::sharing::{Permission, Share, SharedSheet};
+use super::logic::Id;
+use super::Error;
+
+use self::diesel::prelude::*;
+
+impl From<SheetPermissionDb> for Permission {
+    fn from(p: SheetPermissionDb) -> Permission {
+        match p {
+            SheetPermissionDb::Viewer => Self::Viewer,
+            SheetPermissionDb::Grader => Self::Grader,
+            SheetPermissionDb::Editor => Self::Editor,
+        }
+    }
+}
+
+fn permission_to_db(permission: Permission) -> Option<SheetPermissionDb> {
+    match permission {
+        Permission::Viewer => Some(SheetPermissionDb::Viewer),
+        Permission::Grader => Some(SheetPermissionDb::Grader),
+        Permission::Editor => Some(SheetPermissionDb::Editor),
+        // Owners are stored in the sheet itself
+        Permission::Owner => None,
+    }
+}
+
+impl From<(UserInfoDiesel, SheetPermissionDb)> for Share {
+    fn from(t: (UserInfoDiesel, SheetPermissionDb)) -> Share {
+        let (u, p) = t;
+        Share {
+            user: u.into(),
+            permission: p.into(),
+        }
+    }
+}
+
+impl From<(SheetMetadataDiesel, UserInfoDiesel, SheetPermissionDb)> for SharedSheet {
+    fn from(t: (SheetMetadataDiesel, UserInfoDiesel, SheetPermissionDb)) -> SharedSheet {
+        let (s, u, p) = t;
+        SharedSheet {
+            metadata: (s, u).into(),
+            permission: p.into(),
+        }
+    }
+}
+
+pub async fn get_permission(
+    db: &Db,
+    sheet_id: Id,
+    user_id: i32,
+) -> Result<Option<Permission>, Error> {
+    db.run(move |c| load_permission(c, sheet_id, user_id)).await
+}
+
+fn load_permission(
+    c: &PgConnection,
+    sheet_id: Id,
+    user_id: i32,
+) -> Result<Option<Permission>, Error> {
+    let permission: Option<SheetPermissionDb> = sheet_shares::table
+        .select(sheet_shares::permission)
+        .filter(sheet_shares::sheet_id.eq(sheet_id))
+        .filter(sheet_shares::user_id.eq(user_id))
+        .first(c)
+        .optional()?;
+    Ok(permission.map(|p| p.into()))
+}
+
+pub async fn get_shares(db: &Db, sheet_id: Id) -> Result<Vec<Share>, Error> {
+    let shares: Vec<(UserInfoDiesel, SheetPermissionDb)> = db
+        .run(move |c| {
+            sheet_shares::table
+                .inner_join(users::table)
+                .select((UserInfoDiesel::columns(), sheet_shares::permission))
+                .filter(sheet_shares::sheet_id.eq(sheet_id))
+                .order(users::username.asc())
+                .load(c)
+        })
+        .await?;
+    Ok(shares.into_iter().map(|s| s.into()).collect())
+}
+
+pub async fn get_shared_sheets(db: &Db, user_id: i32) -> Result<Vec<SharedSheet>, Error> {
+    let sheets: Vec<(SheetMetadataDiesel, UserInfoDiesel, SheetPermissionDb)> = db
+        .run(move |c| {
+            sheets::table
+                .inner_join(users::table)
+                .inner_join(sheet_shares::table)
+                .select((
+                    SheetMetadataDiesel::columns(),
+                    UserInfoDiesel::columns(),
+                    sheet_shares::permission,
+                ))
+                .filter(sheet_shares::user_id.eq(user_id))
+                .filter(sheets::trashed.is_null())
+                .order(sheets::title.asc())
+                .load(c)
+        })
+        .await?;
+    Ok(sheets.into_iter().map(|s| s.into()).collect())
+}
+
+pub async fn get_teacher_by_name(db: &Db, username: String) -> Result<Option<UserInfo>, Error> {
+    let user: Option<UserInfoDiesel> = db
+        .run(move |c| {
+            users::table
+                .inner_join(roles::table)
+                .select(UserInfoDiesel::columns())
+                .filter(users::username.eq(username))
+                .filter(roles::role.eq(RoleDb::Teacher))
+                .first(c)
+                .optional()
+        })
+        .await?;
+    Ok(user.map(|u| u.into()))
+}
+
+pub async fn set_share(
+    db: &Db,
+    sheet_id: Id,
+    user_id: i32,
+    permission: Permission,
+) -> Result<(), Error> {
+    db.run(move |c| upsert_share(c, sheet_id, user_id, permission))
+        .await
+}
+
+fn upsert_share(
+    c: &PgConnection,
+    sheet_id: Id,
+    user_id: i32,
+    permission: Permission,
+) -> Result<(), Error> {
+    let permission = permission_to_db(permission).expect("ownership should not be stored as share");
+    diesel::insert_into(sheet_shares::table)
+        .values(&(
+            sheet_shares::sheet_id.eq(sheet_id),
+            sheet_shares::user_id.eq(user_id),
+            sheet_shares::permission.eq(&permission),
+        ))
+        .on_conflict((sheet_shares::sheet_id, sheet_shares::user_id))
+        .do_update()
+        .set(sheet_shares::permission.eq(&permission))
+        .execute(c)?;
+    Ok(())
+}
+
+pub async fn delete_share(db: &Db, sheet_id: Id, user_id: i32) -> Result<(), Error> {
+    db.run(move |c| diesel::delete(sheet_shares::table.find((sheet_id, user_id))).execute(c))
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use super::*;
+
+    /// Runs against the PostgreSQL database given in `TEST_DATABASE_URL` with a sheet and a second
+    /// teacher to share it with. All changes are rolled back afterwards.
+    fn with_sheet_and_teacher(test: impl FnOnce(&PgConnection, Id, i32)) {
+        let url = std::env::var("TEST_DATABASE_URL").expect("TEST_DATABASE_URL not set");
+        let c = PgConnection::establish(&url).expect("connecting to test database failed");
+        diesel_migrations::run_pending_migrations(&c).expect("migrating test database failed");
+        c.test_transaction::<_, Error, _>(|| {
+            let mut user_ids = Vec::new();
+            for username in ["sharing-test-owner", "sharing-test-teacher"] {
+                user_ids.push(
+                    diesel::insert_into(users::table)
+                        .values(&(users::username.eq(username), users::password_hash.eq("")))
+                        .returning(users::id)
+                        .get_result::<i32>(&c)?,
+                );
+            }
+            let now = Utc::now();
+            let sheet_id: Id = diesel::insert_into(sheets::table)
+                .values(&(
+                    sheets::title.eq("Geteilt"),
+                    sheets::owner_id.eq(user_ids[0]),
+                    sheets::created.eq(now),
+                    sheets::changed.eq(now),
+                    sheets::content.eq(serde_json::json!({})),
+                ))
+                .returning(sheets::id)
+                .get_result(&c)?;
+            test(&c, sheet_id, user_ids[1]);
+            Ok(())
+        });
+    }
+
+    #[test]
+    #[ignore = "needs a PostgreSQL database in TEST_DATABASE_URL"]
+    fn sharing_again_replaces_the_permission() {
+        with_sheet_and_teacher(|c, sheet_id, teacher_id| {
+            assert_eq!(load_permission(c, sheet_id, teacher_id).unwrap(), None);
+            for permission in [Permission::Viewer, Permission::Grader, Permission::Editor] {
+                upsert_share(c, sheet_id, teacher_id, permission).unwrap();
+                assert_eq!(
+                    load_permission(c, sheet_id, teacher_id).unwrap(),
+                    Some(permission)
+                );
+            }
+            upsert_share(c, sheet_id, teacher_id, Permission::Grader).unwrap();
+            assert_eq!(
+                load_permission(c, sheet_id, teacher_id).unwrap(),
+                Some(Permission::Grader)
+            );
+        });
+    }
+}
@@ -2,7 +2,9 @@ use chrono::{DateTime, Utc};
 use rocket_sync_db_pools::diesel;
 
 use crate::db::model::{SheetDiesel, SheetMetadataDiesel, UserInfoDiesel};
-use crate::db::schema::{sheets, solutions, users};
+use crate::db::schema::{
+    media, sheet_media, sheet_shares, sheet_tags, sheet_updates, sheets, solutions, users,
+};
 use crate::Db;
 
 use super::logic::sheet::{Sheet, SheetMetadata};
@@ -22,6 +24,7 @@ impl From<(SheetDiesel, UserInfoDiesel)> for Sheet {
                 created: s.created,
                 changed: s.changed,
                 trashed: s.trashed,
+                folder_id: s.folder_id,
             },
             content: s.content,
         }
@@ -38,18 +41,54 @@ impl From<(SheetMetadataDiesel, UserInfoDiesel)> for SheetMetadata {
             created: s.created,
             changed: s.changed,
             trashed: s.trashed,
+            folder_id: s.folder_id,
         }
     }
 }
 
-pub async fn get_all_sheets(db: &Db, user_id: i32) -> Result<Vec<SheetMetadata>, Error> {
+pub async fn get_sheets_in_folder(
+    db: &Db,
+    user_id: i32,
+    folder_id: Option<i32>,
+) -> Result<Vec<SheetMetadata>, Error> {
+    db.run(move |c| load_sheets_in_folder(c, user_id, folder_id))
+        .await
+}
+
+fn load_sheets_in_folder(
+    c: &PgConnection,
+    user_id: i32,
+    folder_id: Option<i32>,
+) -> Result<Vec<SheetMetadata>, Error> {
+    let query = sheets::table
+        .inner_join(users::table)
+        .select((SheetMetadataDiesel::columns(), UserInfoDiesel::columns()))
+        .filter(sheets::owner_id.eq(user_id))
+        .filter(sheets::trashed.is_null())
+        .order(sheets::title.asc())
+        .into_boxed();
+    let sheets: Vec<(SheetMetadataDiesel, UserInfoDiesel)> = match folder_id {
+        Some(folder_id) => query.filter(sheets::folder_id.eq(folder_id)),
+        None => query.filter(sheets::folder_id.is_null()),
+    }
+    .load(c)?;
+    Ok(sheets.into_iter().map(|s| s.into()).collect())
+}
+
+pub async fn get_sheets_with_tag(
+    db: &Db,
+    user_id: i32,
+    tag: String,
+) -> Result<Vec<SheetMetadata>, Error> {
     let sheets: Vec<(SheetMetadataDiesel, UserInfoDiesel)> = db
         .run(move |c| {
             sheets::table
                 .inner_join(users::table)
+                .inner_join(sheet_tags::table)
                 .select((SheetMetadataDiesel::columns(), UserInfoDiesel::columns()))
                 .filter(sheets::owner_id.eq(user_id))
                 .filter(sheets::trashed.is_null())
+                .filter(sheet_tags::tag.eq(tag))
                 .order(sheets::title.asc())
                 .load(c)
         })
@@ -143,6 +182,41 @@ pub async fn get_sheet_by_id(db: &Db, id: Id) -> Result<Option<Sheet>, Error> {
     Ok(sheet.map(|s| s.into()))
 }
 
+pub async fn get_owned_sheets(db: &Db, owner_id: i32) -> Result<Vec<Sheet>, Error> {
+    let sheets: Vec<(SheetDiesel, UserInfoDiesel)> = db
+        .run(move |c| {
+            sheets::table
+                .inner_join(users::table)
+                .select((sheets::all_columns, UserInfoDiesel::columns()))
+                .filter(sheets::owner_id.eq(owner_id))
+                .filter(sheets::trashed.is_null())
+                .order(sheets::title.asc())
+                .load(c)
+        })
+        .await?;
+    Ok(sheets.into_iter().map(|s| s.into()).collect())
+}
+
+pub async fn find_imported_sheet(
+    db: &Db,
+    owner_id: i32,
+    import_hash: String,
+) -> Result<Option<Id>, Error> {
+    let id = db
+        .run(move |c| {
+            sheets::table
+                .select(sheets::id)
+                .filter(sheets::owner_id.eq(owner_id))
+                .filter(sheets::import_hash.eq(import_hash))
+                .filter(sheets::trashed.is_null())
+                .first(c)
+                .optional()
+        })
+        .await?;
+    Ok(id)
+}
+
+#[allow(clippy::too_many_arguments)]
 pub async fn create_sheet(
     db: &Db,
     title: String,
@@ -151,6 +225,8 @@ pub async fn create_sheet(
     created: DateTime<Utc>,
     changed: DateTime<Utc>,
     trashed: Option<DateTime<Utc>>,
+    folder_id: Option<i32>,
+    import_hash: Option<String>,
 ) -> Result<Id, Error> {
     let sheet: SheetDiesel = db
         .run(move |c| {
@@ -162,6 +238,8 @@ pub async fn create_sheet(
                     sheets::changed.eq(changed),
                     sheets::content.eq(content),
                     sheets::trashed.eq(trashed),
+                    sheets::folder_id.eq(folder_id),
+                    sheets::import_hash.eq(import_hash),
                 ))
                 .get_result(c)
         })
@@ -169,24 +247,40 @@ pub async fn create_sheet(
     Ok(sheet.id)
 }
 
+/// Returns false without changing the sheet if it is being edited
+/// collaboratively, as its content is then made up of the update log.
 pub async fn update_sheet(
     db: &Db,
     id: Id,
     title: String,
     content: serde_json::Value,
     changed: DateTime<Utc>,
-) -> Result<(), Error> {
+) -> Result<bool, Error> {
     db.run(move |c| {
-        diesel::update(sheets::table.find(id))
-            .set((
-                sheets::title.eq(title),
-                sheets::content.eq(content),
-                sheets::changed.eq(changed),
+        c.transaction(|| {
+            sheets::table
+                .find(id)
+                .select(sheets::id)
+                .for_update()
+                .first::<Id>(c)?;
+            let collaborative: bool = diesel::select(diesel::dsl::exists(
+                sheet_updates::table.filter(sheet_updates::sheet_id.eq(id)),
             ))
-            .execute(c)
+            .get_result(c)?;
+            if collaborative {
+                return Ok(false);
+            }
+            diesel::update(sheets::table.find(id))
+                .set((
+                    sheets::title.eq(title),
+                    sheets::content.eq(content),
+                    sheets::changed.eq(changed),
+                ))
+                .execute(c)
+                .map(|_| true)
+        })
     })
-    .await?;
-    Ok(())
+    .await
 }
 
 pub async fn delete_sheet(db: &Db, id: Id) -> Result<(), Error> {
@@ -214,3 +308,247 @@ pub async fn restore_sheet(db: &Db, id: Id) -> Result<(), Error> {
     .await?;
     Ok(())
 }
+
+/// Transfers the sheet along with the media no other sheet of the previous owner uses
+pub async fn transfer_sheet(db: &Db, id: Id, new_owner_id: i32) -> Result<(), Error> {
+    db.run(move |c| {
+        c.transaction::<_, Error, _>(|| {
+            let owner_id: i32 = sheets::table
+                .find(id)
+                .select(sheets::owner_id)
+                .for_update()
+                .first(c)?;
+            let other_sheets = sheets::table
+                .select(sheets::id)
+                .filter(sheets::owner_id.eq(owner_id))
+                .filter(sheets::id.ne(id));
+            let used_elsewhere = sheet_media::table
+                .select(sheet_media::media_id)
+                .filter(sheet_media::sheet_id.eq_any(other_sheets));
+            let used_here = sheet_media::table
+                .select(sheet_media::media_id)
+                .filter(sheet_media::sheet_id.eq(id));
+            diesel::update(
+                media::table
+                    .filter(media::owner_id.eq(owner_id))
+                    .filter(media::id.eq_any(used_here))
+                    .filter(diesel::dsl::not(media::id.eq_any(used_elsewhere))),
+            )
+            .set(media::owner_id.eq(new_owner_id))
+            .execute(c)?;
+            // The new owner implicitly has all permissions
+            diesel::delete(sheet_shares::table.find((id, new_owner_id))).execute(c)?;
+            // Folders belong to the previous owner
+            diesel::update(sheets::table.find(id))
+                .set((
+                    sheets::owner_id.eq(new_owner_id),
+                    sheets::folder_id.eq(None::<i32>),
+                ))
+                .execute(c)?;
+            Ok(())
+        })
+    })
+    .await
+}
+
+/// Transfers all sheets and media of the owner, returns the number of sheets transferred
+fn move_all_sheets(c: &PgConnection, owner_id: i32, new_owner_id: i32) -> Result<usize, Error> {
+    diesel::delete(
+        sheet_shares::table
+            .filter(sheet_shares::user_id.eq(new_owner_id))
+            .filter(
+                sheet_shares::sheet_id.eq_any(
+                    sheets::table
+                        .select(sheets::id)
+                        .filter(sheets::owner_id.eq(owner_id)),
+                ),
+            ),
+    )
+    .execute(c)?;
+    diesel::update(media::table.filter(media::owner_id.eq(owner_id)))
+        .set(media::owner_id.eq(new_owner_id))
+        .execute(c)?;
+    diesel::update(sheets::table.filter(sheets::owner_id.eq(owner_id)))
+        .set((
+            sheets::owner_id.eq(new_owner_id),
+            sheets::folder_id.eq(None::<i32>),
+        ))
+        .execute(c)
+}
+
+pub async fn transfer_all_sheets(
+    db: &Db,
+    owner_id: i32,
+    new_owner_id: i32,
+) -> Result<usize, Error> {
+    db.run(move |c| c.transaction(|| move_all_sheets(c, owner_id, new_owner_id)))
+        .await
+}
+
+/// Transfers all sheets and media of the owner and deletes their account. Returns the number of
+/// sheets transferred, or `None` if nothing is changed because the owner has solutions.
+pub async fn transfer_all_and_delete_owner(
+    db: &Db,
+    owner_id: i32,
+    new_owner_id: i32,
+) -> Result<Option<usize>, Error> {
+    db.run(move |c| delete_owner_after_transfer(c, owner_id, new_owner_id))
+        .await
+}
+
+fn delete_owner_after_transfer(
+    c: &PgConnection,
+    owner_id: i32,
+    new_owner_id: i32,
+) -> Result<Option<usize>, Error> {
+    c.transaction(|| {
+        let has_solutions: bool = diesel::select(diesel::dsl::exists(
+            solutions::table.filter(solutions::owner_id.eq(owner_id)),
+        ))
+        .get_result(c)?;
+        if has_solutions {
+            return Ok(None);
+        }
+        let transferred = move_all_sheets(c, owner_id, new_owner_id)?;
+        diesel::delete(users::table.find(owner_id)).execute(c)?;
+        Ok(Some(transferred))
+    })
+}
+
+pub async fn move_sheet_to_folder(db: &Db, id: Id, folder_id: Option<i32>) -> Result<(), Error> {
+    db.run(move |c| set_folder(c, id, folder_id)).await
+}
+
+fn set_folder(c: &PgConnection, id: Id, folder_id: Option<i32>) -> Result<(), Error> {
+    diesel::update(sheets::table.find(id))
+        .set(sheets::folder_id.eq(folder_id))
+        .execute(c)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::schema::folders;
+
+    /// Runs against the PostgreSQL database given in `TEST_DATABASE_URL` with two teachers, the
+    /// first one owning a sheet. All changes are rolled back afterwards.
+    fn with_owner_and_teacher(test: impl FnOnce(&PgConnection, i32, i32, Id)) {
+        let url = std::env::var("TEST_DATABASE_URL").expect("TEST_DATABASE_URL not set");
+        let c = PgConnection::establish(&url).expect("connecting to test database failed");
+        diesel_migrations::run_pending_migrations(&c).expect("migrating test database failed");
+        c.test_transaction::<_, Error, _>(|| {
+            let mut user_ids = Vec::new();
+            for username in ["transfer-owner", "transfer-teacher"] {
+                user_ids.push(
+                    diesel::insert_into(users::table)
+                        .values(&(users::username.eq(username), users::password_hash.eq("")))
+                        .returning(users::id)
+                        .get_result::<i32>(&c)?,
+                );
+            }
+            let now = Utc::now();
+            let sheet_id: Id = diesel::insert_into(sheets::table)
+                .values(&(
+                    sheets::title.eq("Übergabe"),
+                    sheets::owner_id.eq(user_ids[0]),
+                    sheets::created.eq(now),
+                    sheets::changed.eq(now),
+                    sheets::content.eq(serde_json::json!({})),
+                ))
+                .returning(sheets::id)
+                .get_result(&c)?;
+            test(&c, user_ids[0], user_ids[1], sheet_id);
+            Ok(())
+        });
+    }
+
+    fn owner(c: &PgConnection, sheet_id: Id) -> i32 {
+        sheets::table
+            .find(sheet_id)
+            .select(sheets::owner_id)
+            .first(c)
+            .unwrap()
+    }
+
+    fn user_exists(c: &PgConnection, user_id: i32) -> bool {
+        diesel::select(diesel::dsl::exists(users::table.find(user_id)))
+            .get_result(c)
+            .unwrap()
+    }
+
+    #[test]
+    #[ignore = "needs a PostgreSQL database in TEST_DATABASE_URL"]
+    fn deletes_the_owner_after_transferring_their_sheets() {
+        with_owner_and_teacher(|c, owner_id, teacher_id, sheet_id| {
+            assert_eq!(
+                delete_owner_after_transfer(c, owner_id, teacher_id).unwrap(),
+                Some(1)
+            );
+            assert_eq!(owner(c, sheet_id), teacher_id);
+            assert!(!user_exists(c, owner_id));
+        });
+    }
+
+    #[test]
+    #[ignore = "needs a PostgreSQL database in TEST_DATABASE_URL"]
+    fn keeps_owners_who_still_own_solutions() {
+        with_owner_and_teacher(|c, owner_id, teacher_id, sheet_id| {
+            let now = Utc::now();
+            diesel::insert_into(solutions::table)
+                .values(&(
+                    solutions::title.eq("Übergabe"),
+                    solutions::sheet_id.eq(sheet_id),
+                    solutions::sheet_version.eq(now),
+                    solutions::owner_id.eq(owner_id),
+                    solutions::created.eq(now),
+                    solutions::changed.eq(now),
+                    solutions::content.eq(serde_json::json!({})),
+                ))
+                .execute(c)
+                .unwrap();
+            assert_eq!(
+                delete_owner_after_transfer(c, owner_id, teacher_id).unwrap(),
+                None
+            );
+            assert_eq!(owner(c, sheet_id), owner_id);
+            assert!(user_exists(c, owner_id));
+        });
+    }
+
+    #[test]
+    #[ignore = "needs a PostgreSQL database in TEST_DATABASE_URL"]
+    fn moves_sheets_between_folders() {
+        with_owner_and_teacher(|c, owner_id, _, sheet_id| {
+            let mut folder_ids = Vec::new();
+            for name in ["Mathe", "Deutsch"] {
+                folder_ids.push(
+                    diesel::insert_into(folders::table)
+                        .values(&(folders::name.eq(name), folders::owner_id.eq(owner_id)))
+                        .returning(folders::id)
+                        .get_result::<i32>(c)
+                        .unwrap(),
+                );
+            }
+            let sheets_in = |folder_id: Option<i32>| -> Vec<Id> {
+                load_sheets_in_folder(c, owner_id, folder_id)
+                    .unwrap()
+                    .into_iter()
+                    .map(|sheet| sheet.id)
+                    .collect()
+            };
+            assert_eq!(sheets_in(None), [sheet_id]);
+            set_folder(c, sheet_id, Some(folder_ids[0])).unwrap();
+            assert!(sheets_in(None).is_empty());
+            assert_eq!(sheets_in(Some(folder_ids[0])), [sheet_id]);
+            set_folder(c, sheet_id, Some(folder_ids[1])).unwrap();
+            assert!(sheets_in(Some(folder_ids[0])).is_empty());
+            assert_eq!(sheets_in(Some(folder_ids[1])), [sheet_id]);
+            // Sheets of a deleted folder are moved to the top level
+            diesel::delete(folders::table.find(folder_ids[1]))
+                .execute(c)
+                .unwrap();
+            assert_eq!(sheets_in(None), [sheet_id]);
+        });
+    }
+}
@@ -2,7 +2,8 @@ use chrono::{DateTime, Utc};
 use rocket_sync_db_pools::diesel;
 
 use crate::db::model::{SolutionDiesel, SolutionMetadataDiesel, UserInfoDiesel};
-use crate::db::schema::{sheets, solutions, users};
+use crate::db::schema::{sheet_media, sheet_shares, sheets, solution_media, solutions, users};
+use crate::db::sql_types::SheetPermissionDb;
 use crate::Db;
 
 use super::logic::solution::{FreshSolution, Solution, SolutionMetadata};
@@ -26,6 +27,7 @@ impl From<(SolutionDiesel, UserInfoDiesel)> for Solution {
                 trashed: s.trashed,
             },
             content: s.content,
+            feedback: s.feedback,
         }
     }
 }
@@ -46,7 +48,13 @@ impl From<(SolutionMetadataDiesel, UserInfoDiesel)> for SolutionMetadata {
     }
 }
 
-pub async fn get_solutions_by_sheet_owner(
+/// Shares which allow grading the solutions of a sheet
+fn grading_permissions() -> Vec<SheetPermissionDb> {
+    vec![SheetPermissionDb::Grader, SheetPermissionDb::Editor]
+}
+
+/// Solutions of the sheets the user owns or was allowed to grade
+pub async fn get_solutions_by_grader(
     db: &Db,
     user_id: i32,
 ) -> Result<Vec<SolutionMetadata>, Error> {
@@ -56,7 +64,14 @@ pub async fn get_solutions_by_sheet_owner(
                 .inner_join(users::table)
                 .inner_join(sheets::table)
                 .select((SolutionMetadataDiesel::columns(), UserInfoDiesel::columns()))
-                .filter(sheets::owner_id.eq(user_id))
+                .filter(
+                    sheets::owner_id.eq(user_id).or(sheets::id.eq_any(
+                        sheet_shares::table
+                            .select(sheet_shares::sheet_id)
+                            .filter(sheet_shares::user_id.eq(user_id))
+                            .filter(sheet_shares::permission.eq_any(grading_permissions())),
+                    )),
+                )
                 .filter(sheets::trashed.is_null())
                 .filter(solutions::trashed.is_null())
                 .order((solutions::changed.desc(), users::username.asc()))
@@ -129,6 +144,53 @@ pub async fn get_all_sheet_solutions(
     Ok(solutions.into_iter().map(|s| s.into()).collect())
 }
 
+/// Latest non-trashed solution of every student for the given sheet
+pub async fn get_latest_sheet_solutions(db: &Db, sheet_id: Id) -> Result<Vec<Solution>, Error> {
+    let solutions: Vec<(SolutionDiesel, UserInfoDiesel)> = db
+        .run(move |c| {
+            solutions::table
+                .inner_join(users::table)
+                .select((solutions::all_columns, UserInfoDiesel::columns()))
+                .filter(solutions::sheet_id.eq(sheet_id))
+                .filter(solutions::trashed.is_null())
+                .distinct_on(solutions::owner_id)
+                .order((solutions::owner_id, solutions::sheet_version.desc()))
+                .load(c)
+        })
+        .await?;
+    Ok(solutions.into_iter().map(|s| s.into()).collect())
+}
+
+/// Latest non-trashed solution of every student for every sheet owned by the given user
+pub async fn get_latest_solutions_by_grader(db: &Db, user_id: i32) -> Result<Vec<Solution>, Error> {
+    let solutions: Vec<(SolutionDiesel, UserInfoDiesel)> = db
+        .run(move |c| {
+            solutions::table
+                .inner_join(users::table)
+                .inner_join(sheets::table)
+                .select((solutions::all_columns, UserInfoDiesel::columns()))
+                .filter(
+                    sheets::owner_id.eq(user_id).or(sheets::id.eq_any(
+                        sheet_shares::table
+                            .select(sheet_shares::sheet_id)
+                            .filter(sheet_shares::user_id.eq(user_id))
+                            .filter(sheet_shares::permission.eq_any(grading_permissions())),
+                    )),
+                )
+                .filter(sheets::trashed.is_null())
+                .filter(solutions::trashed.is_null())
+                .distinct_on((solutions::sheet_id, solutions::owner_id))
+                .order((
+                    solutions::sheet_id,
+                    solutions::owner_id,
+                    solutions::sheet_version.desc(),
+                ))
+                .load(c)
+        })
+        .await?;
+    Ok(solutions.into_iter().map(|s| s.into()).collect())
+}
+
 pub async fn get_sheet_solutions_by_sheet_and_user_id(
     db: &Db,
     sheet_id: Id,
@@ -149,21 +211,44 @@ pub async fn get_sheet_solutions_by_sheet_and_user_id(
     Ok(solutions.into_iter().map(|s| s.into()).collect())
 }
 
+/// Creates the solution, which keeps using the media of the sheet even if the sheet is deleted
 pub async fn create_solution(db: &Db, fresh_solution: FreshSolution) -> Result<i32, Error> {
     let solution: SolutionDiesel = db
         .run(move |c| {
-            diesel::insert_into(solutions::table)
-                .values(&(
-                    solutions::title.eq(fresh_solution.title),
-                    solutions::owner_id.eq(fresh_solution.owner_id),
-                    solutions::sheet_id.eq(fresh_solution.sheet_id),
-                    solutions::sheet_version.eq(fresh_solution.sheet_version),
-                    solutions::created.eq(fresh_solution.created),
-                    solutions::changed.eq(fresh_solution.changed),
-                    solutions::trashed.eq(fresh_solution.trashed),
-                    solutions::content.eq(fresh_solution.content),
-                ))
-                .get_result(c)
+            c.transaction::<_, Error, _>(|| {
+                let sheet_id = fresh_solution.sheet_id;
+                let solution: SolutionDiesel = diesel::insert_into(solutions::table)
+                    .values(&(
+                        solutions::title.eq(fresh_solution.title),
+                        solutions::owner_id.eq(fresh_solution.owner_id),
+                        solutions::sheet_id.eq(sheet_id),
+                        solutions::sheet_version.eq(fresh_solution.sheet_version),
+                        solutions::created.eq(fresh_solution.created),
+                        solutions::changed.eq(fresh_solution.changed),
+                        solutions::trashed.eq(fresh_solution.trashed),
+                        solutions::content.eq(fresh_solution.content),
+                    ))
+                    .get_result(c)?;
+                let media: Vec<Id> = sheet_media::table
+                    .select(sheet_media::media_id)
+                    .filter(sheet_media::sheet_id.eq(sheet_id))
+                    .load(c)?;
+                let links: Vec<_> = media
+                    .into_iter()
+                    .map(|media_id| {
+                        (
+                            solution_media::solution_id.eq(solution.id),
+                            solution_media::media_id.eq(media_id),
+                        )
+                    })
+                    .collect();
+                if !links.is_empty() {
+                    diesel::insert_into(solution_media::table)
+                        .values(&links)
+                        .execute(c)?;
+                }
+                Ok(solution)
+            })
         })
         .await?;
     Ok(solution.id)
@@ -221,6 +306,16 @@ pub async fn update_solution(
     Ok(())
 }
 
+pub async fn update_feedback(db: &Db, solution_id: i32, feedback: String) -> Result<(), Error> {
+    db.run(move |c| {
+        diesel::update(solutions::table.find(solution_id))
+            .set(solutions::feedback.eq(feedback))
+            .execute(c)
+    })
+    .await?;
+    Ok(())
+}
+
 pub async fn delete_solution(db: &Db, id: i32) -> Result<(), Error> {
     db.run(move |c| diesel::delete(solutions::table.find(id)).execute(c))
         .await?;
@@ -0,0 +1,51 @@
+use rocket_sync_db_pools::diesel;
+
+use crate::db::schema::{sheet_tags, sheets};
+use crate::Db;
+
+use super::logic::Id;
+use super::Error;
+
+use self::diesel::prelude::*;
+
+pub async fn get_tags(db: &Db, owner_id: i32) -> Result<Vec<String>, Error> {
+    db.run(move |c| {
+        sheet_tags::table
+            .inner_join(sheets::table)
+            .select(sheet_tags::tag)
+            .filter(sheets::owner_id.eq(owner_id))
+            .distinct()
+            .order(sheet_tags::tag.asc())
+            .load(c)
+    })
+    .await
+}
+
+pub async fn get_sheet_tags(db: &Db, owner_id: i32) -> Result<Vec<(Id, String)>, Error> {
+    db.run(move |c| {
+        sheet_tags::table
+            .inner_join(sheets::table)
+            .select((sheet_tags::sheet_id, sheet_tags::tag))
+            .filter(sheets::owner_id.eq(owner_id))
+            .order(sheet_tags::tag.asc())
+            .load(c)
+    })
+    .await
+}
+
+pub async fn add_tag(db: &Db, sheet_id: Id, tag: String) -> Result<(), Error> {
+    db.run(move |c| {
+        diesel::insert_into(sheet_tags::table)
+            .values(&(sheet_tags::sheet_id.eq(sheet_id), sheet_tags::tag.eq(tag)))
+            .on_conflict_do_nothing()
+            .execute(c)
+    })
+    .await?;
+    Ok(())
+}
+
+pub async fn remove_tag(db: &Db, sheet_id: Id, tag: String) -> Result<(), Error> {
+    db.run(move |c| diesel::delete(sheet_tags::table.find((sheet_id, tag))).execute(c))
+        .await?;
+    Ok(())
+}
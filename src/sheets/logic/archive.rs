@@ -0,0 +1,442 @@
+use std::collections::HashMap;
+use std::io::{Cursor, Read, Write};
+
+use rocket::serde::Serialize;
+use zip::result::{ZipError, ZipResult};
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::Db;
+
+use super::interchange::{self, ImportOutcome, SheetExport};
+use super::media::{self, MediaConfig};
+use super::storage::MediaStorage;
+use super::{sheet, Error, Id, Result};
+
+const SHEET_DIR: &str = "sheets";
+const MEDIA_DIR: &str = "media";
+const MAX_ENTRY_SIZE: u64 = 16 * 1024 * 1024;
+
+/// Bounds for reading an archive as a whole, since a small archive may expand to a lot of data
+#[derive(Debug, Clone, Copy)]
+struct Limits {
+    entries: usize,
+    total_size: u64,
+}
+
+const LIMITS: Limits = Limits {
+    entries: 1000,
+    total_size: 256 * 1024 * 1024,
+};
+
+#[derive(Debug, Serialize)]
+pub struct ImportReport {
+    pub file: String,
+    #[serde(flatten)]
+    pub outcome: ImportReportOutcome,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum ImportReportOutcome {
+    Done(ImportOutcome),
+    Failed {
+        status: &'static str,
+        reason: String,
+    },
+}
+
+impl ImportReportOutcome {
+    fn failed<R: ToString>(reason: R) -> Self {
+        Self::Failed {
+            status: "failed",
+            reason: reason.to_string(),
+        }
+    }
+}
+
+/// Sheets read from an archive together with the media files they use
+pub struct Archive {
+    pub sheets: Vec<(String, std::result::Result<SheetExport, String>)>,
+    media: HashMap<String, Vec<u8>>,
+}
+
+/// Exports the given sheets, or all non-trashed sheets owned by the user if none are given.
+///
+/// Uploaded media used by the sheets are exported alongside them.
+pub async fn export_archive(
+    db: &Db,
+    storage: &MediaStorage,
+    user_id: i32,
+    ids: Vec<Id>,
+) -> Result<Vec<u8>> {
+    let mut sheets: Vec<SheetExport> = if ids.is_empty() {
+        sheet::get_owned_sheets(db, user_id)
+            .await?
+            .into_iter()
+            .map(|s| SheetExport::new(s.metadata.title, s.content))
+            .collect()
+    } else {
+        let mut sheets = Vec::with_capacity(ids.len());
+        for id in ids {
+            sheets.push(interchange::export_sheet(db, user_id, id).await?);
+        }
+        sheets
+    };
+    let mut files = HashMap::new();
+    for sheet in &mut sheets {
+        for reference in &mut sheet.media {
+            let id = match media::media_id(&reference.source) {
+                Some(id) => id,
+                None => continue,
+            };
+            // Media which are gone or not accessible stay mere references
+            let media = match media::get_media(db, Some(user_id), id).await {
+                Ok(media) => media,
+                Err(Error::NotFound(_)) | Err(Error::Forbidden(_)) => continue,
+                Err(e) => return Err(e),
+            };
+            let name = media_file(&media.content_hash, &media.mimetype);
+            if !files.contains_key(&name) {
+                let content = media::read_media(storage, &media, 0..media.size).await?;
+                files.insert(name.clone(), content);
+            }
+            reference.file = Some(name);
+        }
+    }
+    let mut files: Vec<(String, Vec<u8>)> = files.into_iter().collect();
+    files.sort();
+    write_archive(&sheets, &files).map_err(Error::Archive)
+}
+
+fn write_archive(sheets: &[SheetExport], media: &[(String, Vec<u8>)]) -> ZipResult<Vec<u8>> {
+    let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    for (i, sheet) in sheets.iter().enumerate() {
+        let name = format!(
+            "{}/{:03}-{}.json",
+            SHEET_DIR,
+            i + 1,
+            file_stem(&sheet.title)
+        );
+        zip.start_file(name, options)?;
+        zip.write_all(&serde_json::to_vec_pretty(sheet).map_err(std::io::Error::from)?)?;
+    }
+    for (name, content) in media {
+        zip.start_file(name.as_str(), options)?;
+        zip.write_all(content)?;
+    }
+    Ok(zip.finish()?.into_inner())
+}
+
+/// Writes the given files into an archive, used for content packages of other platforms
+pub(super) fn write_files<C: AsRef<[u8]>>(files: &[(String, C)]) -> ZipResult<Vec<u8>> {
+    let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    for (name, content) in files {
+        zip.start_file(name.as_str(), options)?;
+        zip.write_all(content.as_ref())?;
+    }
+    Ok(zip.finish()?.into_inner())
+}
+
+/// Reads all text files with the given extension from an archive
+pub(super) fn read_files(archive: &[u8], extension: &str) -> ZipResult<HashMap<String, String>> {
+    read_files_within(archive, extension, LIMITS)
+}
+
+fn read_files_within(
+    archive: &[u8],
+    extension: &str,
+    limits: Limits,
+) -> ZipResult<HashMap<String, String>> {
+    let mut zip = open_archive(archive, limits)?;
+    let mut remaining = limits.total_size;
+    let mut files = HashMap::new();
+    for i in 0..zip.len() {
+        let file = zip.by_index(i)?;
+        let name = file.name().to_string();
+        if file.is_dir() || !name.ends_with(extension) || file.size() > MAX_ENTRY_SIZE {
+            continue;
+        }
+        let content = String::from_utf8(read_entry(file, &mut remaining)?)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        files.insert(name, content);
+    }
+    Ok(files)
+}
+
+fn open_archive(archive: &[u8], limits: Limits) -> ZipResult<ZipArchive<Cursor<&[u8]>>> {
+    let zip = ZipArchive::new(Cursor::new(archive))?;
+    if zip.len() > limits.entries {
+        return Err(ZipError::UnsupportedArchive("too many entries"));
+    }
+    Ok(zip)
+}
+
+/// Reads an entry of at most `MAX_ENTRY_SIZE` bytes, which are taken from the `remaining` bytes
+/// the whole archive may expand to
+fn read_entry<R: Read>(file: R, remaining: &mut u64) -> ZipResult<Vec<u8>> {
+    let mut content = Vec::new();
+    file.take(MAX_ENTRY_SIZE.min(*remaining + 1))
+        .read_to_end(&mut content)?;
+    let size = content.len() as u64;
+    if size > *remaining {
+        return Err(ZipError::UnsupportedArchive("content too large"));
+    }
+    *remaining -= size;
+    Ok(content)
+}
+
+pub(super) fn file_stem(title: &str) -> String {
+    title
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '-' || c == '_' || c == ' ' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .take(64)
+        .collect()
+}
+
+fn media_file(content_hash: &str, mimetype: &str) -> String {
+    let extension = mimetype
+        .split_once('/')
+        .map(|(_, subtype)| subtype)
+        .unwrap_or("bin");
+    format!("{}/{}.{}", MEDIA_DIR, &content_hash[..16], extension)
+}
+
+/// Reads all sheet documents from an archive, upgrading them to the current format version, and
+/// the media files stored with them.
+///
+/// Failures of individual documents are returned in place, only an unreadable archive is an error.
+/// So is an archive with too many entries or too much content, as it is read into memory.
+pub fn read_archive(archive: &[u8]) -> ZipResult<Archive> {
+    read_archive_within(archive, LIMITS)
+}
+
+fn read_archive_within(archive: &[u8], limits: Limits) -> ZipResult<Archive> {
+    let mut zip = open_archive(archive, limits)?;
+    let mut remaining = limits.total_size;
+    let mut sheets = Vec::new();
+    let mut media = HashMap::new();
+    for i in 0..zip.len() {
+        let file = zip.by_index(i)?;
+        let name = file.name().to_string();
+        if file.is_dir() {
+            continue;
+        }
+        if name.starts_with(&format!("{}/", MEDIA_DIR)) {
+            if file.size() <= MAX_ENTRY_SIZE {
+                media.insert(name, read_entry(file, &mut remaining)?);
+            }
+            continue;
+        }
+        if !name.ends_with(".json") {
+            continue;
+        }
+        if file.size() > MAX_ENTRY_SIZE {
+            sheets.push((name, Err("Datei zu groß".to_string())));
+            continue;
+        }
+        let sheet = match String::from_utf8(read_entry(file, &mut remaining)?) {
+            Ok(document) => interchange::import(&document).map_err(|e| e.to_string()),
+            Err(e) => Err(e.to_string()),
+        };
+        sheets.push((name, sheet));
+    }
+    Ok(Archive { sheets, media })
+}
+
+/// Imports the sheets of an archive. A sheet which cannot be imported is reported as failed, the
+/// remaining sheets are imported nonetheless.
+pub async fn import_archive(
+    db: &Db,
+    storage: &MediaStorage,
+    config: &MediaConfig,
+    user_id: i32,
+    archive: Archive,
+    folder_id: Option<i32>,
+) -> Vec<ImportReport> {
+    let mut uploaded = HashMap::new();
+    let mut reports = Vec::with_capacity(archive.sheets.len());
+    for (file, sheet) in archive.sheets {
+        let outcome = match sheet {
+            Ok(sheet) => match import_sheet(
+                db,
+                storage,
+                config,
+                user_id,
+                sheet,
+                folder_id,
+                &archive.media,
+                &mut uploaded,
+            )
+            .await
+            {
+                Ok(outcome) => ImportReportOutcome::Done(outcome),
+                Err(e) => ImportReportOutcome::failed(e),
+            },
+            Err(reason) => ImportReportOutcome::failed(reason),
+        };
+        reports.push(ImportReport { file, outcome });
+    }
+    reports
+}
+
+/// Uploads the media files of the sheet, each file once per archive, and points the sheet to them
+#[allow(clippy::too_many_arguments)]
+async fn import_sheet(
+    db: &Db,
+    storage: &MediaStorage,
+    config: &MediaConfig,
+    user_id: i32,
+    mut sheet: SheetExport,
+    folder_id: Option<i32>,
+    files: &HashMap<String, Vec<u8>>,
+    uploaded: &mut HashMap<String, Id>,
+) -> Result<ImportOutcome> {
+    if let Some(id) = interchange::find_duplicate(db, user_id, &sheet).await? {
+        return Ok(ImportOutcome::Duplicate { id });
+    }
+    let import_hash = sheet.import_hash();
+    for reference in &sheet.media {
+        let name = match &reference.file {
+            Some(name) => name,
+            None => continue,
+        };
+        let content = match files.get(name) {
+            Some(content) => content,
+            None => continue,
+        };
+        let id = match uploaded.get(name) {
+            Some(id) => *id,
+            None => {
+                let filename = name.rsplit('/').next().unwrap_or(name).to_string();
+                let media =
+                    media::store_media(db, storage, config, user_id, filename, content.clone())
+                        .await?;
+                uploaded.insert(name.clone(), media.id);
+                media.id
+            }
+        };
+        interchange::replace_media_source(
+            &mut sheet.content,
+            &reference.source,
+            &media::media_source(id),
+        );
+    }
+    sheet::create_imported_sheet(
+        db,
+        user_id,
+        sheet.title,
+        sheet.content,
+        folder_id,
+        import_hash,
+    )
+    .await
+    .map(|id| ImportOutcome::Imported { id })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn reports_invalid_sheets_in_place() {
+        let valid = SheetExport::new(
+            "Gültig".into(),
+            json!({"type": "doc", "marks": [], "content": []}),
+        );
+        let invalid = SheetExport::new(
+            "Pool".into(),
+            json!({"type": "doc", "marks": [], "content": [
+                {"type": "pool", "draw": 2, "marks": [], "content": []}
+            ]}),
+        );
+        let archive = write_archive(&[valid, invalid], &[]).unwrap();
+        let archive = read_archive(&archive).unwrap();
+        assert_eq!(archive.sheets.len(), 2);
+        assert_eq!(archive.sheets[0].1.as_ref().unwrap().title, "Gültig");
+        assert!(archive.sheets[1].1.is_err());
+    }
+
+    #[test]
+    fn stores_media_with_the_sheets() {
+        let source = "/media/6a3c2d8e-2f4b-4c1a-9f0e-1d2c3b4a5f6e";
+        let mut sheet = SheetExport::new(
+            "Hörverstehen".into(),
+            json!({"type": "doc", "marks": [], "content": [
+                {"type": "audio", "source": source, "mimetype": "audio/ogg", "marks": [], "content": []}
+            ]}),
+        );
+        let name = media_file("0123456789abcdef0123", "audio/ogg");
+        assert_eq!(name, "media/0123456789abcdef.ogg");
+        sheet.media[0].file = Some(name.clone());
+        let content = b"OggS\0\x02".to_vec();
+        let archive = write_archive(&[sheet], &[(name.clone(), content.clone())]).unwrap();
+
+        let archive = read_archive(&archive).unwrap();
+        assert_eq!(archive.sheets.len(), 1);
+        let sheet = archive.sheets[0].1.as_ref().unwrap();
+        assert_eq!(sheet.media[0].source, source);
+        assert_eq!(sheet.media[0].file.as_ref(), Some(&name));
+        assert_eq!(archive.media.get(&name), Some(&content));
+    }
+
+    fn padded_sheets(count: usize) -> Vec<u8> {
+        let sheets: Vec<_> = (0..count)
+            .map(|_| {
+                SheetExport::new(
+                    "Leer".into(),
+                    json!({"type": "doc", "marks": [], "content": [
+                        {"type": "paragraph", "marks": [], "content": [
+                            {"type": "text", "text": " ".repeat(1000), "marks": [], "content": []}
+                        ]}
+                    ]}),
+                )
+            })
+            .collect();
+        write_archive(&sheets, &[]).unwrap()
+    }
+
+    #[test]
+    fn rejects_archives_with_too_many_entries() {
+        let limits = Limits {
+            entries: 3,
+            total_size: 1024 * 1024,
+        };
+        assert_eq!(
+            read_archive_within(&padded_sheets(3), limits)
+                .unwrap()
+                .sheets
+                .len(),
+            3
+        );
+        assert!(read_archive_within(&padded_sheets(4), limits).is_err());
+        assert!(read_files_within(&padded_sheets(4), ".json", limits).is_err());
+    }
+
+    #[test]
+    fn rejects_archives_expanding_to_too_much_content() {
+        let archive = padded_sheets(10);
+        let size = read_files(&archive, ".json")
+            .unwrap()
+            .values()
+            .map(|file| file.len() as u64)
+            .sum();
+        let limits = |total_size| Limits {
+            entries: 10,
+            total_size,
+        };
+        assert!(read_archive_within(&archive, limits(size)).is_ok());
+        assert!(read_archive_within(&archive, limits(size - 1)).is_err());
+        assert!(read_files_within(&archive, ".json", limits(size - 1)).is_err());
+    }
+}
@@ -0,0 +1,271 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use chrono::{DateTime, Utc};
+use rocket::tokio::sync::broadcast;
+
+use crate::login::transport::UserInfo;
+use crate::Db;
+
+use super::sharing::Permission;
+use super::{data, media, sheet, Error, Id, Result};
+
+const CHANNEL_CAPACITY: usize = 256;
+
+#[derive(Clone, Debug)]
+pub struct SheetUpdate {
+    pub id: i64,
+    pub author_id: Option<i32>,
+    pub created: DateTime<Utc>,
+    pub payload: Vec<u8>,
+}
+
+#[derive(Clone, Debug)]
+pub enum CollaborationEvent {
+    Update(SheetUpdate),
+    Presence(Vec<UserInfo>),
+}
+
+struct Editor {
+    user_info: UserInfo,
+    connections: usize,
+}
+
+struct Session {
+    sender: broadcast::Sender<CollaborationEvent>,
+    editors: HashMap<i32, Editor>,
+}
+
+impl Session {
+    fn new() -> Self {
+        let (sender, _) = broadcast::channel(CHANNEL_CAPACITY);
+        Session {
+            sender,
+            editors: HashMap::new(),
+        }
+    }
+
+    fn broadcast_presence(&self) {
+        let mut editors: Vec<UserInfo> =
+            self.editors.values().map(|e| e.user_info.clone()).collect();
+        editors.sort_by(|a, b| a.username.cmp(&b.username));
+        // Sending only fails if nobody is listening, which is fine
+        let _ = self.sender.send(CollaborationEvent::Presence(editors));
+    }
+}
+
+type Sessions = Arc<Mutex<HashMap<Id, Session>>>;
+
+/// Relays updates and presence information between all editors of a sheet.
+#[derive(Default)]
+pub struct CollaborationHub {
+    sessions: Sessions,
+}
+
+impl CollaborationHub {
+    fn join(&self, sheet_id: Id, user_info: &UserInfo) -> Subscription {
+        let mut sessions = self.sessions.lock().expect("collaboration lock poisoned");
+        let session = sessions.entry(sheet_id).or_insert_with(Session::new);
+        let receiver = session.sender.subscribe();
+        session
+            .editors
+            .entry(user_info.id)
+            .or_insert_with(|| Editor {
+                user_info: user_info.clone(),
+                connections: 0,
+            })
+            .connections += 1;
+        session.broadcast_presence();
+        Subscription {
+            receiver,
+            _presence: Presence {
+                sessions: Arc::clone(&self.sessions),
+                sheet_id,
+                user_id: user_info.id,
+            },
+        }
+    }
+
+    fn publish(&self, sheet_id: Id, event: CollaborationEvent) {
+        let sessions = self.sessions.lock().expect("collaboration lock poisoned");
+        if let Some(session) = sessions.get(&sheet_id) {
+            let _ = session.sender.send(event);
+        }
+    }
+}
+
+/// Keeps a user listed as editor of a sheet until dropped.
+struct Presence {
+    sessions: Sessions,
+    sheet_id: Id,
+    user_id: i32,
+}
+
+impl Drop for Presence {
+    fn drop(&mut self) {
+        let mut sessions = match self.sessions.lock() {
+            Ok(sessions) => sessions,
+            Err(_) => return,
+        };
+        if let Some(session) = sessions.get_mut(&self.sheet_id) {
+            if let Some(editor) = session.editors.get_mut(&self.user_id) {
+                editor.connections -= 1;
+                if editor.connections == 0 {
+                    session.editors.remove(&self.user_id);
+                }
+            }
+            if session.editors.is_empty() {
+                sessions.remove(&self.sheet_id);
+            } else {
+                session.broadcast_presence();
+            }
+        }
+    }
+}
+
+pub struct Subscription {
+    pub receiver: broadcast::Receiver<CollaborationEvent>,
+    _presence: Presence,
+}
+
+async fn check_collaboration_access(db: &Db, user_id: i32, sheet_id: Id) -> Result<()> {
+    sheet::check_sheet_permission(db, user_id, sheet_id, Permission::Editor).await
+}
+
+pub async fn join(
+    db: &Db,
+    hub: &CollaborationHub,
+    user_info: &UserInfo,
+    sheet_id: Id,
+) -> Result<Subscription> {
+    check_collaboration_access(db, user_info.id, sheet_id).await?;
+    Ok(hub.join(sheet_id, user_info))
+}
+
+pub async fn get_updates(
+    db: &Db,
+    user_id: i32,
+    sheet_id: Id,
+    since: i64,
+) -> Result<Vec<SheetUpdate>> {
+    check_collaboration_access(db, user_id, sheet_id).await?;
+    Ok(data::collaboration::get_updates_since(db, sheet_id, since).await?)
+}
+
+/// Updates are only accepted on top of the newest update of the sheet, `base`. Clients that
+/// missed updates get a conflict and have to rebase their changes onto the missing ones.
+pub async fn push_update(
+    db: &Db,
+    hub: &CollaborationHub,
+    user_id: i32,
+    sheet_id: Id,
+    base: i64,
+    payload: Vec<u8>,
+) -> Result<SheetUpdate> {
+    check_collaboration_access(db, user_id, sheet_id).await?;
+    let now = Utc::now();
+    let update = data::collaboration::create_update(db, sheet_id, base, user_id, now, payload)
+        .await?
+        .ok_or_else(|| {
+            Error::Conflict(format!(
+                "sheet {} has updates newer than {}",
+                sheet_id, base
+            ))
+        })?;
+    hub.publish(sheet_id, CollaborationEvent::Update(update.clone()));
+    Ok(update)
+}
+
+/// The merged update takes the id of the newest update it replaces. It thereby stays below all
+/// updates that were not compacted, so clients resuming after a compacted update still receive
+/// everything they have not seen yet.
+pub fn merged_update_id(compacted: &[i64]) -> Option<i64> {
+    compacted.iter().copied().max()
+}
+
+#[allow(clippy::too_many_arguments)]
+pub async fn compact(
+    db: &Db,
+    hub: &CollaborationHub,
+    user_id: i32,
+    sheet_id: Id,
+    up_to: i64,
+    merged_payload: Vec<u8>,
+    title: String,
+    content: serde_json::Value,
+) -> Result<SheetUpdate> {
+    check_collaboration_access(db, user_id, sheet_id).await?;
+    let now = Utc::now();
+    let used_media = media::referenced_media(&content);
+    let update = data::collaboration::compact_updates(
+        db,
+        sheet_id,
+        up_to,
+        user_id,
+        merged_payload,
+        title,
+        content,
+        now,
+    )
+    .await?
+    .ok_or_else(|| {
+        Error::NotFound(format!(
+            "updates up to {} of sheet {} to compact",
+            up_to, sheet_id
+        ))
+    })?;
+    media::set_sheet_media(db, user_id, sheet_id, used_media).await?;
+    hub.publish(sheet_id, CollaborationEvent::Update(update.clone()));
+    Ok(update)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn user(id: i32, username: &str) -> UserInfo {
+        UserInfo {
+            id,
+            username: username.into(),
+        }
+    }
+
+    fn editors(event: CollaborationEvent) -> Vec<String> {
+        match event {
+            CollaborationEvent::Presence(editors) => {
+                editors.into_iter().map(|e| e.username).collect()
+            }
+            CollaborationEvent::Update(update) => panic!("unexpected update {:?}", update),
+        }
+    }
+
+    #[test]
+    fn relays_updates_and_presence() {
+        let hub = CollaborationHub::default();
+        let sheet_id = Id::nil();
+        let mut alice = hub.join(sheet_id, &user(1, "alice"));
+        assert_eq!(editors(alice.receiver.try_recv().unwrap()), ["alice"]);
+        let bob = hub.join(sheet_id, &user(2, "bob"));
+        assert_eq!(
+            editors(alice.receiver.try_recv().unwrap()),
+            ["alice", "bob"]
+        );
+
+        let update = SheetUpdate {
+            id: 1,
+            author_id: Some(2),
+            created: Utc::now(),
+            payload: vec![1, 2, 3],
+        };
+        hub.publish(sheet_id, CollaborationEvent::Update(update));
+        match alice.receiver.try_recv().unwrap() {
+            CollaborationEvent::Update(update) => assert_eq!(update.payload, [1, 2, 3]),
+            event => panic!("unexpected event {:?}", event),
+        }
+
+        drop(bob);
+        assert_eq!(editors(alice.receiver.try_recv().unwrap()), ["alice"]);
+        drop(alice);
+        assert!(hub.sessions.lock().unwrap().is_empty());
+    }
+}
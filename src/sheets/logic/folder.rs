@@ -0,0 +1,227 @@
+use std::collections::HashMap;
+
+use rocket::serde::Serialize;
+
+use crate::Db;
+
+use super::sharing::Permission;
+use super::sheet::{self, SheetMetadata};
+use super::{data, Error, Id, Result};
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Folder {
+    pub id: i32,
+    pub name: String,
+    pub owner_id: i32,
+    pub parent_id: Option<i32>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FolderNode {
+    #[serde(flatten)]
+    pub folder: Folder,
+    pub children: Vec<FolderNode>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TaggedSheet {
+    #[serde(flatten)]
+    pub metadata: SheetMetadata,
+    pub tags: Vec<String>,
+}
+
+/// The contents of a folder or, if a tag is given, all sheets with that tag
+#[derive(Debug, Serialize)]
+pub struct SheetTree {
+    pub tree: Vec<FolderNode>,
+    pub folder: Option<Folder>,
+    /// Path from the top level to the current folder
+    pub path: Vec<Folder>,
+    pub folders: Vec<Folder>,
+    pub sheets: Vec<TaggedSheet>,
+    pub tags: Vec<String>,
+    pub tag: Option<String>,
+}
+
+fn build_tree(folders: &[Folder], parent_id: Option<i32>) -> Vec<FolderNode> {
+    folders
+        .iter()
+        .filter(|f| f.parent_id == parent_id)
+        .map(|f| FolderNode {
+            folder: f.clone(),
+            children: build_tree(folders, Some(f.id)),
+        })
+        .collect()
+}
+
+fn build_path(folders: &[Folder], folder_id: Option<i32>) -> Vec<Folder> {
+    let by_id: HashMap<i32, &Folder> = folders.iter().map(|f| (f.id, f)).collect();
+    let mut path = Vec::new();
+    let mut current = folder_id;
+    while let Some(folder) = current.and_then(|id| by_id.get(&id)) {
+        path.push((*folder).clone());
+        current = folder.parent_id;
+    }
+    path.reverse();
+    path
+}
+
+async fn get_owned_folder(db: &Db, user_id: i32, id: i32) -> Result<Folder> {
+    check_owner(data::folder::get_folder_by_id(db, id).await?, user_id, id)
+}
+
+fn check_owner(folder: Option<Folder>, user_id: i32, id: i32) -> Result<Folder> {
+    let folder = folder.ok_or_else(|| Error::NotFound(format!("folder {}", id)))?;
+    if folder.owner_id == user_id {
+        Ok(folder)
+    } else {
+        Err(Error::Forbidden(format!(
+            "user {} does not own folder {}",
+            user_id, id
+        )))
+    }
+}
+
+pub async fn check_folder_ownership(db: &Db, user_id: i32, id: Option<i32>) -> Result<()> {
+    match id {
+        Some(id) => get_owned_folder(db, user_id, id).await.map(|_| ()),
+        None => Ok(()),
+    }
+}
+
+pub async fn get_sheet_tree(
+    db: &Db,
+    user_id: i32,
+    folder_id: Option<i32>,
+    tag: Option<String>,
+) -> Result<SheetTree> {
+    check_folder_ownership(db, user_id, folder_id).await?;
+    let folders = data::folder::get_folders(db, user_id).await?;
+    let (path, subfolders, sheets) = match &tag {
+        Some(tag) => (
+            Vec::new(),
+            Vec::new(),
+            data::sheet::get_sheets_with_tag(db, user_id, tag.clone()).await?,
+        ),
+        None => (
+            build_path(&folders, folder_id),
+            folders
+                .iter()
+                .filter(|f| f.parent_id == folder_id)
+                .cloned()
+                .collect(),
+            data::sheet::get_sheets_in_folder(db, user_id, folder_id).await?,
+        ),
+    };
+    let mut sheet_tags: HashMap<Id, Vec<String>> = HashMap::new();
+    for (sheet_id, tag) in data::tag::get_sheet_tags(db, user_id).await? {
+        sheet_tags.entry(sheet_id).or_default().push(tag);
+    }
+    let sheets = sheets
+        .into_iter()
+        .map(|metadata| TaggedSheet {
+            tags: sheet_tags.remove(&metadata.id).unwrap_or_default(),
+            metadata,
+        })
+        .collect();
+    Ok(SheetTree {
+        tree: build_tree(&folders, None),
+        folder: path.last().cloned(),
+        path,
+        folders: subfolders,
+        sheets,
+        tags: data::tag::get_tags(db, user_id).await?,
+        tag,
+    })
+}
+
+pub async fn create_folder(
+    db: &Db,
+    user_id: i32,
+    name: String,
+    parent_id: Option<i32>,
+) -> Result<i32> {
+    check_folder_ownership(db, user_id, parent_id).await?;
+    Ok(data::folder::create_folder(db, name, user_id, parent_id).await?)
+}
+
+pub async fn rename_folder(db: &Db, user_id: i32, id: i32, name: String) -> Result<()> {
+    get_owned_folder(db, user_id, id).await?;
+    Ok(data::folder::rename_folder(db, id, name).await?)
+}
+
+pub async fn move_folder(db: &Db, user_id: i32, id: i32, parent_id: Option<i32>) -> Result<()> {
+    get_owned_folder(db, user_id, id).await?;
+    check_folder_ownership(db, user_id, parent_id).await?;
+    let folders = data::folder::get_folders(db, user_id).await?;
+    check_move(&folders, id, parent_id)?;
+    Ok(data::folder::move_folder(db, id, parent_id).await?)
+}
+
+/// A folder cannot be moved into itself or any of its subfolders
+fn check_move(folders: &[Folder], id: i32, parent_id: Option<i32>) -> Result<()> {
+    if build_path(folders, parent_id).iter().any(|f| f.id == id) {
+        return Err(Error::Forbidden(format!(
+            "folder {} cannot be moved into itself",
+            id
+        )));
+    }
+    Ok(())
+}
+
+/// Deletes a folder with all its subfolders. Contained sheets are moved to the top level.
+pub async fn delete_folder(db: &Db, user_id: i32, id: i32) -> Result<Option<i32>> {
+    let folder = get_owned_folder(db, user_id, id).await?;
+    data::folder::delete_folder(db, id).await?;
+    Ok(folder.parent_id)
+}
+
+pub async fn move_sheet(db: &Db, user_id: i32, sheet_id: Id, folder_id: Option<i32>) -> Result<()> {
+    sheet::check_sheet_permission(db, user_id, sheet_id, Permission::Owner).await?;
+    check_folder_ownership(db, user_id, folder_id).await?;
+    Ok(data::sheet::move_sheet_to_folder(db, sheet_id, folder_id).await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn folder(id: i32, parent_id: Option<i32>) -> Folder {
+        Folder {
+            id,
+            name: format!("Ordner {}", id),
+            owner_id: 1,
+            parent_id,
+        }
+    }
+
+    #[test]
+    fn only_owners_may_use_their_folders() {
+        assert_eq!(check_owner(Some(folder(3, None)), 1, 3).unwrap().id, 3);
+        assert!(matches!(
+            check_owner(Some(folder(3, None)), 2, 3),
+            Err(Error::Forbidden(_))
+        ));
+        assert!(matches!(check_owner(None, 1, 3), Err(Error::NotFound(_))));
+    }
+
+    #[test]
+    fn folders_cannot_be_moved_into_themselves() {
+        // 1 contains 2, which contains 3, and 4 is on the top level
+        let folders = [
+            folder(1, None),
+            folder(2, Some(1)),
+            folder(3, Some(2)),
+            folder(4, None),
+        ];
+        for parent_id in [Some(1), Some(2), Some(3)] {
+            assert!(matches!(
+                check_move(&folders, 1, parent_id),
+                Err(Error::Forbidden(_))
+            ));
+        }
+        assert!(check_move(&folders, 1, Some(4)).is_ok());
+        assert!(check_move(&folders, 3, Some(1)).is_ok());
+        assert!(check_move(&folders, 3, None).is_ok());
+    }
+}
@@ -0,0 +1,516 @@
+use std::convert::TryFrom;
+use std::fmt::{self, Display};
+
+use rocket::serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use sha2::{Digest, Sha256};
+
+use crate::Db;
+
+use super::conversion::document::{InvalidTask, Node};
+use super::conversion::{self, markdown, moodle, qti};
+use super::sharing::Permission;
+use super::sheet;
+use super::{archive, data, Id, Result};
+
+pub const FORMAT: &str = "hci-bildung-sheet";
+pub const CURRENT_VERSION: u32 = 2;
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct MediaReference {
+    pub source: String,
+    pub mimetype: Option<String>,
+    /// Path of the media file within an exported archive
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SheetExport {
+    pub format: String,
+    pub version: u32,
+    pub title: String,
+    pub content: Value,
+    pub media: Vec<MediaReference>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum ImportOutcome {
+    Imported { id: Id },
+    Duplicate { id: Id },
+}
+
+#[derive(Debug)]
+pub enum ImportError {
+    Json(serde_json::Error),
+    UnknownFormat(String),
+    UnsupportedVersion(u64),
+    Malformed(&'static str),
+    Invalid(InvalidTask),
+    Conversion(conversion::Error),
+    Archive(zip::result::ZipError),
+}
+
+impl Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Json(e) => write!(f, "Invalid JSON: {}", e),
+            Self::UnknownFormat(format) => write!(f, "Unknown format {}", format),
+            Self::UnsupportedVersion(version) => {
+                write!(f, "Unsupported format version {}", version)
+            }
+            Self::Malformed(msg) => write!(f, "Malformed document: {}", msg),
+            Self::Invalid(task) => write!(f, "Invalid task: {:?}", task),
+            Self::Conversion(e) => write!(f, "{}", e),
+            Self::Archive(e) => write!(f, "Invalid archive: {}", e),
+        }
+    }
+}
+
+impl From<serde_json::Error> for ImportError {
+    fn from(e: serde_json::Error) -> Self {
+        Self::Json(e)
+    }
+}
+
+impl From<zip::result::ZipError> for ImportError {
+    fn from(e: zip::result::ZipError) -> Self {
+        Self::Archive(e)
+    }
+}
+
+impl From<conversion::Error> for ImportError {
+    fn from(e: conversion::Error) -> Self {
+        Self::Conversion(e)
+    }
+}
+
+impl SheetExport {
+    /// Identifies the exported document independent of format version and media list, so that
+    /// importing the same export twice can be detected.
+    pub fn import_hash(&self) -> String {
+        let document = json!({"title": self.title, "content": self.content});
+        hex::encode(Sha256::digest(document.to_string().as_bytes()))
+    }
+
+    pub fn new(title: String, content: Value) -> Self {
+        let media = collect_media(&content);
+        Self {
+            format: FORMAT.to_string(),
+            version: CURRENT_VERSION,
+            title,
+            content,
+            media,
+        }
+    }
+}
+
+pub async fn export_sheet(db: &Db, user_id: i32, id: Id) -> Result<SheetExport> {
+    sheet::check_sheet_permission(db, user_id, id, Permission::Viewer).await?;
+    let sheet = sheet::get_sheet(db, id).await?;
+    Ok(SheetExport::new(sheet.metadata.title, sheet.content))
+}
+
+pub async fn export_markdown(db: &Db, user_id: i32, id: Id) -> Result<(String, String)> {
+    let sheet = export_sheet(db, user_id, id).await?;
+    let content = Node::from_json(&sheet.content);
+    let markdown = markdown::to_markdown(&sheet.title, &content);
+    Ok((sheet.title, markdown))
+}
+
+pub async fn export_moodle(db: &Db, user_id: i32, id: Id) -> Result<(String, String)> {
+    let sheet = export_sheet(db, user_id, id).await?;
+    let content = Node::from_json(&sheet.content);
+    let xml = moodle::to_moodle_xml(&sheet.title, &content);
+    Ok((sheet.title, xml))
+}
+
+pub async fn export_qti(db: &Db, user_id: i32, id: Id) -> Result<(String, Vec<u8>)> {
+    let sheet = export_sheet(db, user_id, id).await?;
+    let content = Node::from_json(&sheet.content);
+    let package = archive::write_files(&qti::to_qti_package(&sheet.title, &content))?;
+    Ok((sheet.title, package))
+}
+
+pub async fn import_sheet(
+    db: &Db,
+    user_id: i32,
+    sheet: SheetExport,
+    folder_id: Option<i32>,
+) -> Result<ImportOutcome> {
+    if let Some(id) = find_duplicate(db, user_id, &sheet).await? {
+        return Ok(ImportOutcome::Duplicate { id });
+    }
+    let import_hash = sheet.import_hash();
+    sheet::create_imported_sheet(
+        db,
+        user_id,
+        sheet.title,
+        sheet.content,
+        folder_id,
+        import_hash,
+    )
+    .await
+    .map(|id| ImportOutcome::Imported { id })
+}
+
+/// Returns the sheet the user has already imported from the same export
+pub async fn find_duplicate(db: &Db, user_id: i32, sheet: &SheetExport) -> Result<Option<Id>> {
+    Ok(data::sheet::find_imported_sheet(db, user_id, sheet.import_hash()).await?)
+}
+
+/// Points all audio nodes with the given source to another one
+pub fn replace_media_source(node: &mut Value, from: &str, to: &str) {
+    if node.get("type").and_then(Value::as_str) == Some("audio")
+        && node.get("source").and_then(Value::as_str) == Some(from)
+    {
+        node["source"] = Value::String(to.to_string());
+    }
+    if let Some(children) = node.get_mut("content").and_then(Value::as_array_mut) {
+        for child in children {
+            replace_media_source(child, from, to);
+        }
+    }
+}
+
+/// Parses an exported sheet of any known format version and upgrades it to the current one.
+///
+/// Version 0 is the unversioned tiptap document, version 1 the unversioned `{title, content}`
+/// document written by the editor's download button.
+pub fn import(document: &str) -> std::result::Result<SheetExport, ImportError> {
+    let mut document: Value = serde_json::from_str(document)?;
+    let mut version = detect_version(&document)?;
+    if version > CURRENT_VERSION {
+        return Err(ImportError::UnsupportedVersion(version.into()));
+    }
+    while version < CURRENT_VERSION {
+        document = match version {
+            0 => upgrade_v0(document)?,
+            1 => upgrade_v1(document)?,
+            _ => unreachable!(),
+        };
+        version += 1;
+    }
+    validated(serde_json::from_value(document)?)
+}
+
+/// Parses a Markdown document, the title is taken from its front matter if present.
+pub fn import_markdown(
+    document: &str,
+    fallback_title: &str,
+) -> std::result::Result<SheetExport, ImportError> {
+    let document = markdown::from_markdown(document);
+    let title = document.title.unwrap_or_else(|| fallback_title.to_string());
+    validated(SheetExport::new(title, document.content.to_json()))
+}
+
+/// Parses a Moodle XML question bank, the title is taken from its category if present.
+///
+/// Returns the questions and content that could not be converted alongside the sheet.
+pub fn import_moodle(
+    document: &str,
+    fallback_title: &str,
+) -> std::result::Result<(SheetExport, Vec<String>), ImportError> {
+    let document = moodle::from_moodle_xml(document)?;
+    let title = document.title.unwrap_or_else(|| fallback_title.to_string());
+    Ok((
+        validated(SheetExport::new(title, document.content.to_json()))?,
+        document.unsupported,
+    ))
+}
+
+/// Reads a QTI content package, the title is taken from its test if present.
+///
+/// Returns the items and content that could not be converted alongside the sheet.
+pub fn import_qti(
+    package: &[u8],
+    fallback_title: &str,
+) -> std::result::Result<(SheetExport, Vec<String>), ImportError> {
+    let files = archive::read_files(package, ".xml")?;
+    let document = qti::from_qti_package(&files)?;
+    let title = document.title.unwrap_or_else(|| fallback_title.to_string());
+    Ok((
+        validated(SheetExport::new(title, document.content.to_json()))?,
+        document.unsupported,
+    ))
+}
+
+/// Imported sheets have to pass the same checks as sheets saved in the editor
+fn validated(sheet: SheetExport) -> std::result::Result<SheetExport, ImportError> {
+    if sheet.title.is_empty() {
+        return Err(ImportError::Malformed("title cannot be empty"));
+    }
+    Node::from_json(&sheet.content)
+        .validate()
+        .map_err(ImportError::Invalid)?;
+    Ok(sheet)
+}
+
+fn detect_version(document: &Value) -> std::result::Result<u32, ImportError> {
+    let object = document
+        .as_object()
+        .ok_or(ImportError::Malformed("document is not an object"))?;
+    if let Some(version) = object.get("version") {
+        match object.get("format").and_then(Value::as_str) {
+            Some(FORMAT) => {}
+            Some(format) => return Err(ImportError::UnknownFormat(format.to_string())),
+            None => return Err(ImportError::Malformed("missing format")),
+        }
+        let version = version
+            .as_u64()
+            .ok_or(ImportError::Malformed("version is not a number"))?;
+        return u32::try_from(version).map_err(|_| ImportError::UnsupportedVersion(version));
+    }
+    let content = object
+        .get("content")
+        .ok_or(ImportError::Malformed("missing content"))?;
+    if content.get("marks").is_some() {
+        Ok(1)
+    } else {
+        Ok(0)
+    }
+}
+
+fn upgrade_v0(mut document: Value) -> std::result::Result<Value, ImportError> {
+    let content = document
+        .get_mut("content")
+        .map(Value::take)
+        .ok_or(ImportError::Malformed("missing content"))?;
+    document["content"] = convert_tiptap_node(&content);
+    Ok(document)
+}
+
+fn upgrade_v1(document: Value) -> std::result::Result<Value, ImportError> {
+    let title = document
+        .get("title")
+        .and_then(Value::as_str)
+        .ok_or(ImportError::Malformed("missing title"))?
+        .to_string();
+    let content = document["content"].clone();
+    Ok(serde_json::to_value(SheetExport::new(title, content))?)
+}
+
+// Mirrors convert_tiptap_node_to_custom from the student_solutions migration
+fn convert_tiptap_node(node: &Value) -> Value {
+    let attr = |name: &str| node.get("attrs").and_then(|attrs| attrs.get(name));
+    let content: Vec<Value> = node
+        .get("content")
+        .and_then(Value::as_array)
+        .map(|children| children.iter().map(convert_tiptap_node).collect())
+        .unwrap_or_default();
+    let marks: Vec<Value> = node
+        .get("marks")
+        .and_then(Value::as_array)
+        .map(|marks| {
+            marks
+                .iter()
+                .map(|mark| convert_tiptap_mark(mark, node))
+                .collect()
+        })
+        .unwrap_or_default();
+    let mut converted = Map::new();
+    converted.insert(
+        "type".into(),
+        node.get("type").cloned().unwrap_or(Value::Null),
+    );
+    converted.insert("content".into(), Value::Array(content));
+    converted.insert("marks".into(), Value::Array(marks));
+    match node.get("type").and_then(Value::as_str) {
+        Some("audio") => {
+            converted.insert(
+                "source".into(),
+                attr("source").cloned().unwrap_or(Value::Null),
+            );
+            converted.insert(
+                "mimetype".into(),
+                attr("mimetype").cloned().unwrap_or(Value::Null),
+            );
+        }
+        Some("codeBlock") => {
+            converted.insert(
+                "language".into(),
+                attr("language")
+                    .filter(|language| !language.is_null())
+                    .cloned()
+                    .unwrap_or_else(|| json!("plain")),
+            );
+        }
+        Some("heading") => {
+            converted.insert(
+                "level".into(),
+                attr("level").cloned().unwrap_or(Value::Null),
+            );
+        }
+        Some("multipleChoiceAnswer") => {
+            let checked = attr("checked").and_then(Value::as_bool).unwrap_or(false);
+            converted.insert("solution".into(), Value::Bool(checked));
+            converted.insert("answer".into(), Value::Bool(false));
+        }
+        Some("text") => {
+            converted.insert(
+                "text".into(),
+                node.get("text").cloned().unwrap_or(Value::Null),
+            );
+        }
+        _ => {}
+    }
+    Value::Object(converted)
+}
+
+fn convert_tiptap_mark(mark: &Value, parent: &Value) -> Value {
+    let text = parent.get("text").and_then(Value::as_str).unwrap_or("");
+    match mark.get("type").and_then(Value::as_str) {
+        Some("gap") => json!({"type": "gap", "solution": text, "answer": ""}),
+        Some("latex") => json!({"type": "latex", "source": text}),
+        _ => json!({"type": mark.get("type").cloned().unwrap_or(Value::Null)}),
+    }
+}
+
+fn collect_media(node: &Value) -> Vec<MediaReference> {
+    let mut media = Vec::new();
+    collect_media_into(node, &mut media);
+    media
+}
+
+fn collect_media_into(node: &Value, media: &mut Vec<MediaReference>) {
+    if node.get("type").and_then(Value::as_str) == Some("audio") {
+        if let Some(source) = node.get("source").and_then(Value::as_str) {
+            let reference = MediaReference {
+                source: source.to_string(),
+                mimetype: node
+                    .get("mimetype")
+                    .and_then(Value::as_str)
+                    .map(str::to_string),
+                file: None,
+            };
+            if !media.contains(&reference) {
+                media.push(reference);
+            }
+        }
+    }
+    if let Some(children) = node.get("content").and_then(Value::as_array) {
+        for child in children {
+            collect_media_into(child, media);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn current(document: &str) -> Value {
+        serde_json::to_value(import(document).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn upgrades_tiptap_documents() {
+        let v0 = r#"{"title": "Brüche", "content": {"type": "doc", "content": [
+            {"type": "paragraph", "content": [
+                {"type": "text", "text": "3/4", "marks": [{"type": "gap"}]}
+            ]},
+            {"type": "audio", "attrs": {"source": "/media/1", "mimetype": "audio/mpeg"}}
+        ]}}"#;
+        let sheet = current(v0);
+        assert_eq!(sheet["format"], json!(FORMAT));
+        assert_eq!(sheet["version"], json!(CURRENT_VERSION));
+        let text = &sheet["content"]["content"][0]["content"][0];
+        assert_eq!(text["text"], json!("3/4"));
+        assert_eq!(
+            text["marks"][0],
+            json!({"type": "gap", "solution": "3/4", "answer": ""})
+        );
+        assert_eq!(
+            sheet["media"],
+            json!([{"source": "/media/1", "mimetype": "audio/mpeg"}])
+        );
+        // The upgraded document is imported unchanged
+        assert_eq!(current(&sheet.to_string()), sheet);
+    }
+
+    #[test]
+    fn upgrades_editor_downloads() {
+        let content = json!({"type": "doc", "marks": [], "content": [
+            {"type": "paragraph", "marks": [], "content": [
+                {"type": "text", "marks": [], "content": [], "text": "Hallo"}
+            ]}
+        ]});
+        let v1 = json!({"title": "Begrüßung", "content": content});
+        let sheet = current(&v1.to_string());
+        assert_eq!(sheet["version"], json!(CURRENT_VERSION));
+        assert_eq!(sheet["title"], json!("Begrüßung"));
+        assert_eq!(sheet["content"], content);
+        assert_eq!(current(&sheet.to_string()), sheet);
+    }
+
+    #[test]
+    fn rejects_invalid_tasks() {
+        let answer = |solution: bool| {
+            json!({"type": "multipleChoiceAnswer", "marks": [], "solution": solution, "answer": false,
+                "content": [{"type": "paragraph", "marks": [], "content": []}]})
+        };
+        let sheet = json!({"title": "Auswahl", "content": {"type": "doc", "marks": [], "content": [
+            {"type": "multipleChoice", "mode": "single", "marks": [], "content": [answer(true), answer(true)]}
+        ]}});
+        assert!(matches!(
+            import(&sheet.to_string()),
+            Err(ImportError::Invalid(InvalidTask::SingleChoice))
+        ));
+        assert!(matches!(
+            import_markdown("<!-- essay: points 2, words 200..50\n-->\n", "Aufsatz"),
+            Err(ImportError::Invalid(InvalidTask::Essay))
+        ));
+        assert!(matches!(
+            import_markdown("Nur Text", ""),
+            Err(ImportError::Malformed(_))
+        ));
+    }
+
+    #[test]
+    fn replaces_media_sources() {
+        let audio = |source: &str| json!({"type": "audio", "source": source, "content": []});
+        let mut content = json!({"type": "doc", "content": [
+            audio("/media/1"),
+            {"type": "pool", "content": [audio("/media/1"), audio("/media/2")]}
+        ]});
+        replace_media_source(&mut content, "/media/1", "/media/3");
+        assert_eq!(
+            content,
+            json!({"type": "doc", "content": [
+                audio("/media/3"),
+                {"type": "pool", "content": [audio("/media/3"), audio("/media/2")]}
+            ]})
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_versions() {
+        let document = |version: Value| {
+            json!({
+                "format": FORMAT,
+                "version": version,
+                "title": "Neu",
+                "content": {"type": "doc", "content": [], "marks": []},
+                "media": [],
+            })
+            .to_string()
+        };
+        assert!(matches!(
+            import(&document(json!(CURRENT_VERSION + 1))),
+            Err(ImportError::UnsupportedVersion(version)) if version == u64::from(CURRENT_VERSION + 1)
+        ));
+        assert!(matches!(
+            import(&document(json!(u64::from(u32::MAX) + 2))),
+            Err(ImportError::UnsupportedVersion(_))
+        ));
+        assert!(matches!(
+            import(&document(json!(-1))),
+            Err(ImportError::Malformed(_))
+        ));
+        assert!(matches!(
+            import(&document(json!(CURRENT_VERSION)).replace(FORMAT, "other")),
+            Err(ImportError::UnknownFormat(_))
+        ));
+    }
+}
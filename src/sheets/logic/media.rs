@@ -0,0 +1,309 @@
+use std::ops::Range;
+
+use chrono::{DateTime, Duration, Utc};
+use rocket::data::ByteUnit;
+use rocket::serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::Db;
+
+use super::conversion::document::{Node, NodeKind};
+use super::sharing::Permission;
+use super::storage::MediaStorage;
+use super::{data, sheet, Error, Id, Result};
+
+#[derive(Debug)]
+pub struct Media {
+    pub id: Id,
+    pub owner_id: i32,
+    pub filename: String,
+    pub mimetype: String,
+    pub size: u64,
+    pub content_hash: String,
+}
+
+pub struct FreshMedia {
+    pub owner_id: i32,
+    pub filename: String,
+    pub mimetype: String,
+    pub size: u64,
+    pub content_hash: String,
+    pub created: DateTime<Utc>,
+}
+
+/// Settings from the `media_*` keys of the Rocket configuration
+#[derive(Debug, Clone, Deserialize)]
+#[serde(crate = "rocket::serde", default)]
+pub struct MediaConfig {
+    pub media_dir: String,
+    /// Storage available to each teacher unless a different quota is set in the database
+    pub media_quota: ByteUnit,
+    /// Hours before media which are no longer used are removed
+    pub media_grace_period: u32,
+    /// Hours between the runs of the garbage collection
+    pub media_gc_interval: u32,
+}
+
+impl Default for MediaConfig {
+    fn default() -> Self {
+        Self {
+            media_dir: "media".to_string(),
+            media_quota: ByteUnit::Mebibyte(200),
+            media_grace_period: 24,
+            media_gc_interval: 6,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct MediaUsage {
+    pub used: u64,
+    pub quota: u64,
+}
+
+/// Only media which can be embedded in sheets are accepted. Media are served from the site's own
+/// origin, so types which can carry scripts, like SVG or HTML, must never be on this list.
+pub const MEDIA_TYPES: [&str; 11] = [
+    "image/png",
+    "image/jpeg",
+    "image/gif",
+    "image/webp",
+    "audio/mpeg",
+    "audio/ogg",
+    "audio/wav",
+    "audio/flac",
+    "audio/mp4",
+    "audio/webm",
+    "audio/aac",
+];
+
+pub fn is_supported(mimetype: &str) -> bool {
+    MEDIA_TYPES.contains(&mimetype)
+}
+
+/// Determines the type of media from its first bytes, the type claimed by the client is not
+/// trusted
+pub fn detect_mimetype(content: &[u8]) -> Option<&'static str> {
+    let at = |offset: usize, magic: &[u8]| content.get(offset..offset + magic.len()) == Some(magic);
+    let mimetype = if at(0, b"\x89PNG\r\n\x1a\n") {
+        "image/png"
+    } else if at(0, b"\xff\xd8\xff") {
+        "image/jpeg"
+    } else if at(0, b"GIF87a") || at(0, b"GIF89a") {
+        "image/gif"
+    } else if at(0, b"RIFF") && at(8, b"WEBP") {
+        "image/webp"
+    } else if at(0, b"RIFF") && at(8, b"WAVE") {
+        "audio/wav"
+    } else if at(0, b"ID3") {
+        "audio/mpeg"
+    } else if at(0, b"OggS") {
+        "audio/ogg"
+    } else if at(0, b"fLaC") {
+        "audio/flac"
+    } else if at(4, b"ftyp") {
+        "audio/mp4"
+    } else if at(0, b"\x1a\x45\xdf\xa3") {
+        "audio/webm"
+    } else {
+        // Frame headers of MPEG audio and ADTS streams without a leading tag
+        match content {
+            [0xff, second, ..] if second & 0xf6 == 0xf0 => "audio/aac",
+            [0xff, second, ..] if second & 0xe0 == 0xe0 => "audio/mpeg",
+            _ => return None,
+        }
+    };
+    Some(mimetype)
+}
+
+pub async fn get_usage(db: &Db, config: &MediaConfig, user_id: i32) -> Result<MediaUsage> {
+    let used = data::media::get_media_sizes(db, user_id)
+        .await?
+        .iter()
+        .sum();
+    let quota = data::media::get_quota(db, user_id)
+        .await?
+        .unwrap_or_else(|| config.media_quota.as_u64());
+    Ok(MediaUsage { used, quota })
+}
+
+pub async fn upload_media(
+    db: &Db,
+    storage: &MediaStorage,
+    config: &MediaConfig,
+    user_id: i32,
+    sheet_id: Id,
+    filename: String,
+    content: Vec<u8>,
+) -> Result<Media> {
+    sheet::check_sheet_permission(db, user_id, sheet_id, Permission::Editor).await?;
+    let media = store_media(db, storage, config, user_id, filename, content).await?;
+    data::media::link_media(db, sheet_id, media.id).await?;
+    Ok(media)
+}
+
+/// Stores media of the user which are not yet used by any sheet
+pub async fn store_media(
+    db: &Db,
+    storage: &MediaStorage,
+    config: &MediaConfig,
+    user_id: i32,
+    filename: String,
+    content: Vec<u8>,
+) -> Result<Media> {
+    let mimetype = detect_mimetype(&content)
+        .ok_or_else(|| Error::UnsupportedMedia(filename.clone()))?
+        .to_string();
+    let fresh_media = FreshMedia {
+        owner_id: user_id,
+        filename,
+        mimetype,
+        size: content.len() as u64,
+        content_hash: hex::encode(Sha256::digest(&content)),
+        created: Utc::now(),
+    };
+    let storage = storage.clone();
+    let store = move |hash: &str| -> Result<()> { Ok(storage.store_blocking(hash, &content)?) };
+    match data::media::create_media(db, fresh_media, config.media_quota.as_u64(), store).await? {
+        Some(media) => Ok(media),
+        None => {
+            let usage = get_usage(db, config, user_id).await?;
+            Err(Error::QuotaExceeded(format!(
+                "user {} uses {} of {} bytes",
+                user_id, usage.used, usage.quota
+            )))
+        }
+    }
+}
+
+/// Sheets can be viewed by everyone who knows their id, so the same goes for the media they use.
+/// Unused media are only available to their owner.
+pub async fn get_media(db: &Db, user_id: Option<i32>, id: Id) -> Result<Media> {
+    let media = data::media::get_media(db, id)
+        .await?
+        .ok_or_else(|| Error::NotFound(format!("media {}", id)))?;
+    if Some(media.owner_id) == user_id || data::media::is_used(db, id).await? {
+        Ok(media)
+    } else {
+        Err(Error::Forbidden(format!(
+            "media {} is not used by any sheet",
+            id
+        )))
+    }
+}
+
+pub async fn read_media(
+    storage: &MediaStorage,
+    media: &Media,
+    range: Range<u64>,
+) -> Result<Vec<u8>> {
+    Ok(storage.load(media.content_hash.clone(), range).await?)
+}
+
+/// Removes media which have not been used by any sheet or solution for the grace period, e.g.
+/// because the sheets were deleted. Returns the number of files removed from the storage.
+pub async fn collect_garbage(
+    db: &Db,
+    storage: &MediaStorage,
+    config: &MediaConfig,
+) -> Result<usize> {
+    let now = Utc::now();
+    let cutoff = now - Duration::hours(config.media_grace_period.into());
+    let storage = storage.clone();
+    let remove = move |hash: &str| -> Result<()> { Ok(storage.remove_blocking(hash)?) };
+    data::media::delete_unused_media(db, now, cutoff, remove).await
+}
+
+/// Records which media a sheet uses. Media the user cannot access are ignored, so referencing
+/// media is no way to gain access to them.
+pub async fn set_sheet_media(db: &Db, user_id: i32, sheet_id: Id, media: Vec<Id>) -> Result<()> {
+    let accessible = data::media::get_accessible_media(db, user_id, media).await?;
+    Ok(data::media::set_sheet_media(db, sheet_id, accessible).await?)
+}
+
+/// Returns the uploaded media the content refers to
+pub fn referenced_media(content: &serde_json::Value) -> Vec<Id> {
+    let mut media = Vec::new();
+    collect_media(&Node::from_json(content), &mut media);
+    media
+}
+
+fn collect_media(node: &Node, media: &mut Vec<Id>) {
+    if let NodeKind::Audio { source, .. } = &node.kind {
+        if let Some(id) = media_id(source) {
+            media.push(id);
+        }
+    }
+    for child in &node.content {
+        collect_media(child, media);
+    }
+}
+
+/// Extracts the id from URLs of uploaded media, which end in `/media/<id>`
+pub fn media_id(source: &str) -> Option<Id> {
+    let mut segments = source.rsplit('/');
+    let id = segments.next()?;
+    match segments.next() {
+        Some("media") => id.parse().ok(),
+        _ => None,
+    }
+}
+
+/// The URL uploaded media are served at
+pub fn media_source(id: Id) -> String {
+    format!("/media/{}", id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_types_from_content() {
+        assert_eq!(
+            detect_mimetype(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR"),
+            Some("image/png")
+        );
+        assert_eq!(detect_mimetype(b"\xff\xd8\xff\xe0"), Some("image/jpeg"));
+        assert_eq!(detect_mimetype(b"RIFF\0\0\0\0WEBPVP8 "), Some("image/webp"));
+        assert_eq!(detect_mimetype(b"RIFF\0\0\0\0WAVEfmt "), Some("audio/wav"));
+        assert_eq!(detect_mimetype(b"ID3\x04\0"), Some("audio/mpeg"));
+        assert_eq!(detect_mimetype(b"\xff\xfb\x90\x64"), Some("audio/mpeg"));
+        assert_eq!(detect_mimetype(b"\xff\xf1\x50\x80"), Some("audio/aac"));
+        assert_eq!(detect_mimetype(b"OggS\0\x02"), Some("audio/ogg"));
+        assert_eq!(detect_mimetype(b"\0\0\0\x20ftypM4A "), Some("audio/mp4"));
+        for mimetype in MEDIA_TYPES {
+            assert!(is_supported(mimetype));
+        }
+    }
+
+    #[test]
+    fn rejects_content_which_can_carry_scripts() {
+        for content in [
+            &b"<svg xmlns=\"http://www.w3.org/2000/svg\"><script>alert(1)</script></svg>"[..],
+            b"<?xml version=\"1.0\"?><svg/>",
+            b"<!DOCTYPE html><html>",
+            b"%PDF-1.7",
+            b"RIFF\0\0\0\0AVI ",
+            b"\xff",
+            b"",
+        ] {
+            assert_eq!(detect_mimetype(content), None);
+        }
+        assert!(!is_supported("image/svg+xml"));
+        assert!(!is_supported("text/html"));
+    }
+
+    #[test]
+    fn finds_uploaded_media_in_sources() {
+        let id: Id = "6a3c2d8e-2f4b-4c1a-9f0e-1d2c3b4a5f6e".parse().unwrap();
+        assert_eq!(media_id(&format!("/media/{}", id)), Some(id));
+        assert_eq!(
+            media_id(&format!("https://example.org/media/{}", id)),
+            Some(id)
+        );
+        assert_eq!(media_id(&media_source(id)), Some(id));
+        assert_eq!(media_id(&format!("/files/{}", id)), None);
+        assert_eq!(media_id("blob:https://example.org/1234"), None);
+    }
+}
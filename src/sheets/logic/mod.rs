@@ -1,9 +1,21 @@
 use std::fmt::{self, Display};
 
+use super::conversion;
 use super::data;
+use super::storage;
 
+pub mod archive;
+pub mod collaboration;
+pub mod folder;
+pub mod interchange;
+pub mod media;
+pub mod pdf;
+pub mod scorm;
+pub mod search;
+pub mod sharing;
 pub mod sheet;
 pub mod solution;
+pub mod tag;
 
 pub type Id = rocket::serde::uuid::Uuid;
 
@@ -14,6 +26,14 @@ pub enum Error {
     Db(data::Error),
     NotFound(String),
     Forbidden(String),
+    UnknownUser(String),
+    Archive(zip::result::ZipError),
+    Conversion(conversion::Error),
+    Io(std::io::Error),
+    UnsupportedMedia(String),
+    QuotaExceeded(String),
+    InvalidPoints(String),
+    Conflict(String),
 }
 
 impl Display for Error {
@@ -22,6 +42,14 @@ impl Display for Error {
             Self::Db(e) => write!(f, "Error interacting with database: {}", e),
             Self::NotFound(msg) => write!(f, "Resource not found: {}", msg),
             Self::Forbidden(msg) => write!(f, "Forbidden resource access: {}", msg),
+            Self::UnknownUser(name) => write!(f, "No suitable user named {}", name),
+            Self::Archive(e) => write!(f, "Error processing archive: {}", e),
+            Self::Conversion(e) => write!(f, "Error converting document: {}", e),
+            Self::Io(e) => write!(f, "Error accessing files: {}", e),
+            Self::UnsupportedMedia(filename) => write!(f, "Unsupported media file {}", filename),
+            Self::QuotaExceeded(msg) => write!(f, "Storage quota exceeded: {}", msg),
+            Self::InvalidPoints(msg) => write!(f, "Invalid points: {}", msg),
+            Self::Conflict(msg) => write!(f, "Conflicting change: {}", msg),
         }
     }
 }
@@ -32,6 +60,24 @@ impl From<data::Error> for Error {
     }
 }
 
+impl From<conversion::Error> for Error {
+    fn from(e: conversion::Error) -> Self {
+        Self::Conversion(e)
+    }
+}
+
+impl From<zip::result::ZipError> for Error {
+    fn from(e: zip::result::ZipError) -> Self {
+        Self::Archive(e)
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
 pub enum DeleteOutcome {
     Deleted,
     Trashed,
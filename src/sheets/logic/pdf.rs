@@ -0,0 +1,123 @@
+use std::io::{Cursor, Write};
+
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use crate::Db;
+
+use super::archive::file_stem;
+use super::conversion::document::Node;
+use super::conversion::pdf::{self, SolutionDocument, Variant};
+use super::sharing::Permission;
+use super::sheet::{self, Sheet};
+use super::solution::{self, Solution};
+use super::{Error, Id, Result};
+
+pub struct Pdf {
+    pub title: String,
+    pub content: Vec<u8>,
+}
+
+fn render_sheet(sheet: Sheet, variant: Variant) -> Result<Pdf> {
+    let content = Node::from_json(&sheet.content);
+    Ok(Pdf {
+        content: pdf::render_sheet(&sheet.metadata.title, &content, variant)?,
+        title: sheet.metadata.title,
+    })
+}
+
+pub async fn worksheet(db: &Db, id: Id) -> Result<Pdf> {
+    let sheet = sheet::get_sheet(db, id).await?;
+    render_sheet(sheet, Variant::Worksheet)
+}
+
+pub async fn answer_key(db: &Db, user_id: i32, id: Id) -> Result<Pdf> {
+    sheet::check_sheet_permission(db, user_id, id, Permission::Viewer).await?;
+    let sheet = sheet::get_sheet(db, id).await?;
+    render_sheet(sheet, Variant::AnswerKey)
+}
+
+fn solution_document(solution: &Solution) -> SolutionDocument {
+    SolutionDocument {
+        student: solution.metadata.owner.username.clone(),
+        changed: solution
+            .metadata
+            .changed
+            .format("%d.%m.%Y %H:%M")
+            .to_string(),
+        content: Node::from_json(&solution.content),
+        feedback: solution.feedback.clone(),
+    }
+}
+
+fn render_solution(solution: Solution, variant: Variant) -> Result<Pdf> {
+    let content = pdf::render_solutions(
+        &solution.metadata.title,
+        &[solution_document(&solution)],
+        variant,
+    )?;
+    Ok(Pdf {
+        title: format!(
+            "{} - {}",
+            solution.metadata.title, solution.metadata.owner.username
+        ),
+        content,
+    })
+}
+
+pub async fn my_solution(db: &Db, user_id: i32, sheet_id: Id, solution_id: i32) -> Result<Pdf> {
+    let solution = solution::get_my_solution(db, user_id, sheet_id, solution_id).await?;
+    render_solution(solution, Variant::OwnSolution)
+}
+
+pub async fn student_solution(
+    db: &Db,
+    teacher_id: i32,
+    sheet_id: Id,
+    student_id: i32,
+    solution_id: i32,
+) -> Result<Pdf> {
+    let solution =
+        solution::get_solution_for_teacher(db, teacher_id, sheet_id, student_id, solution_id)
+            .await?;
+    render_solution(solution, Variant::Solution)
+}
+
+async fn latest_sheet_solutions(db: &Db, teacher_id: i32, sheet_id: Id) -> Result<Vec<Solution>> {
+    let solutions = solution::get_latest_sheet_solutions(db, teacher_id, sheet_id).await?;
+    if solutions.is_empty() {
+        Err(Error::NotFound(format!("solutions for sheet {}", sheet_id)))
+    } else {
+        Ok(solutions)
+    }
+}
+
+/// Renders the latest solution of every student into a single PDF
+pub async fn sheet_solutions(db: &Db, teacher_id: i32, sheet_id: Id) -> Result<Pdf> {
+    let title = sheet::get_sheet_title(db, sheet_id).await?;
+    let solutions = latest_sheet_solutions(db, teacher_id, sheet_id).await?;
+    let documents: Vec<SolutionDocument> = solutions.iter().map(solution_document).collect();
+    Ok(Pdf {
+        content: pdf::render_solutions(&title, &documents, Variant::Solution)?,
+        title,
+    })
+}
+
+/// Renders the latest solution of every student into a separate PDF and bundles them as ZIP
+pub async fn sheet_solutions_archive(db: &Db, teacher_id: i32, sheet_id: Id) -> Result<Pdf> {
+    let title = sheet::get_sheet_title(db, sheet_id).await?;
+    let solutions = latest_sheet_solutions(db, teacher_id, sheet_id).await?;
+    let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    for solution in solutions {
+        let name = format!("{}.pdf", file_stem(&solution.metadata.owner.username));
+        let pdf = render_solution(solution, Variant::Solution)?;
+        zip.start_file(name, options)?;
+        zip.write_all(&pdf.content)
+            .map_err(zip::result::ZipError::from)?;
+    }
+    Ok(Pdf {
+        content: zip.finish()?.into_inner(),
+        title,
+    })
+}
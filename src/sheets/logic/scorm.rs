@@ -0,0 +1,176 @@
+use std::io;
+use std::path::{Path, PathBuf};
+
+use rocket::fs::relative;
+use rocket::tokio::task;
+use serde_json::Value;
+
+use crate::Db;
+
+use super::conversion::essay;
+use super::conversion::scorm::{self, Version};
+use super::{archive, interchange, Error, Id, Result};
+
+const BUNDLE_DIR: &str = "vue";
+
+pub async fn export_scorm(
+    db: &Db,
+    user_id: i32,
+    id: Id,
+    version: Version,
+) -> Result<(String, Vec<u8>)> {
+    let mut sheet = interchange::export_sheet(db, user_id, id).await?;
+    // Packages are handed out to students
+    essay::hide_model_answers(&mut sheet.content);
+    let bundle = task::spawn_blocking(|| read_bundle(Path::new(relative!("vue_dist/vue"))))
+        .await
+        .map_err(|e| io::Error::new(io::ErrorKind::Other, e))??;
+    let package = build_package(id, &sheet.title, &sheet.content, version, bundle)?;
+    Ok((sheet.title, package))
+}
+
+/// Packs the sheet with the Vue app to run it
+fn build_package(
+    id: Id,
+    title: &str,
+    content: &Value,
+    version: Version,
+    bundle: Vec<(String, Vec<u8>)>,
+) -> Result<Vec<u8>> {
+    let bundled = |extension: &str| -> Vec<String> {
+        let mut paths: Vec<String> = bundle
+            .iter()
+            .map(|(path, _)| path.clone())
+            .filter(|path| path.ends_with(extension))
+            .collect();
+        // Vendor chunks have to be loaded before the app
+        paths.sort_by_key(|path| !path.contains("chunk-vendors"));
+        paths
+    };
+    let scripts = bundled(".js");
+    if scripts.is_empty() {
+        return Err(Error::Io(io::Error::new(
+            io::ErrorKind::NotFound,
+            "Vue bundle contains no scripts",
+        )));
+    }
+    let index = scorm::index_html(title, content, &scripts, &bundled(".css"));
+
+    let mut files = vec![(scorm::INDEX.to_string(), index.into_bytes())];
+    files.extend(bundle);
+    let paths: Vec<String> = files.iter().map(|(path, _)| path.clone()).collect();
+    let manifest = scorm::manifest(version, &format!("sheet-{}", id), title, &paths);
+    files.insert(0, (scorm::MANIFEST.to_string(), manifest.into_bytes()));
+    Ok(archive::write_files(&files)?)
+}
+
+/// Reads the built Vue app, source maps are left out
+fn read_bundle(dir: &Path) -> Result<Vec<(String, Vec<u8>)>> {
+    let mut files = Vec::new();
+    let mut pending = vec![PathBuf::from(dir)];
+    while let Some(current) = pending.pop() {
+        for entry in std::fs::read_dir(&current)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                pending.push(path);
+            } else if path.extension().map_or(false, |e| e != "map") {
+                let relative = path
+                    .strip_prefix(dir)
+                    .expect("bundle files are inside the bundle directory")
+                    .components()
+                    .map(|c| c.as_os_str().to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join("/");
+                files.push((
+                    format!("{}/{}", BUNDLE_DIR, relative),
+                    std::fs::read(&path)?,
+                ));
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use roxmltree::Document;
+    use serde_json::json;
+
+    use super::*;
+
+    const ADLCP_12: &str = "http://www.adlnet.org/xsd/adlcp_rootv1p2";
+    const ADLCP_2004: &str = "http://www.adlnet.org/xsd/adlcp_v1p3";
+
+    fn bundle() -> Vec<(String, Vec<u8>)> {
+        [
+            "vue/css/app.css",
+            "vue/js/app.js",
+            "vue/js/chunk-vendors.js",
+        ]
+        .iter()
+        .map(|path| (path.to_string(), b"/* Vue */".to_vec()))
+        .collect()
+    }
+
+    fn content() -> Value {
+        json!({"type": "doc", "marks": [], "content": [
+            {"type": "paragraph", "marks": [], "content": [
+                {"type": "text", "text": "Brüche kürzen", "marks": [], "content": []}
+            ]}
+        ]})
+    }
+
+    #[test]
+    fn manifests_list_every_file_of_the_package() {
+        let id = Id::nil();
+        for (version, adlcp, scorm_type) in [
+            (Version::Scorm12, ADLCP_12, "scormtype"),
+            (Version::Scorm2004, ADLCP_2004, "scormType"),
+        ] {
+            let package = build_package(id, "Brüche & Co", &content(), version, bundle()).unwrap();
+            let mut files = archive::read_files(&package, "").unwrap();
+            let manifest = files.remove(scorm::MANIFEST).unwrap();
+            let manifest = Document::parse(&manifest).unwrap();
+            let root = manifest.root_element();
+            assert_eq!(
+                root.attribute("identifier"),
+                Some("sheet-00000000-0000-0000-0000-000000000000")
+            );
+
+            let item = root
+                .descendants()
+                .find(|node| node.has_tag_name("item"))
+                .unwrap();
+            let title = item
+                .children()
+                .find(|node| node.has_tag_name("title"))
+                .unwrap();
+            assert_eq!(title.text(), Some("Brüche & Co"));
+
+            let resources: Vec<_> = root
+                .descendants()
+                .filter(|node| node.has_tag_name("resource"))
+                .collect();
+            assert_eq!(resources.len(), 1);
+            let resource = resources[0];
+            assert_eq!(
+                resource.attribute("identifier"),
+                item.attribute("identifierref")
+            );
+            assert_eq!(resource.attribute("href"), Some(scorm::INDEX));
+            assert_eq!(resource.attribute((adlcp, scorm_type)), Some("sco"));
+
+            let mut listed: Vec<&str> = resource
+                .children()
+                .filter(|node| node.has_tag_name("file"))
+                .filter_map(|file| file.attribute("href"))
+                .collect();
+            listed.sort_unstable();
+            let mut packed: Vec<&str> = files.keys().map(String::as_str).collect();
+            packed.sort_unstable();
+            assert_eq!(listed, packed);
+            assert!(listed.contains(&scorm::INDEX));
+        }
+    }
+}
@@ -0,0 +1,101 @@
+use rocket::serde::Serialize;
+
+use crate::Db;
+
+use super::sharing::Permission;
+use super::sheet::SheetMetadata;
+use super::solution::SolutionMetadata;
+use super::{data, Result};
+
+/// Marks the start of a highlighted match in snippets returned by the database
+pub const HIGHLIGHT_START: char = '\u{2}';
+/// Marks the end of a highlighted match in snippets returned by the database
+pub const HIGHLIGHT_STOP: char = '\u{3}';
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Scope {
+    All,
+    Owned,
+    Shared,
+}
+
+impl Scope {
+    pub fn includes_owned(self) -> bool {
+        self != Self::Shared
+    }
+
+    pub fn includes_shared(self) -> bool {
+        self != Self::Owned
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct SnippetPart {
+    pub text: String,
+    pub highlighted: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SheetHit {
+    #[serde(flatten)]
+    pub metadata: SheetMetadata,
+    pub permission: Permission,
+    pub rank: f32,
+    pub snippet: Vec<SnippetPart>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SolutionHit {
+    #[serde(flatten)]
+    pub metadata: SolutionMetadata,
+    pub rank: f32,
+    pub snippet: Vec<SnippetPart>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SearchResults {
+    pub sheets: Vec<SheetHit>,
+    pub solutions: Vec<SolutionHit>,
+}
+
+/// Splits a snippet into plain and highlighted parts so that it can be escaped properly
+pub fn parse_snippet(snippet: &str) -> Vec<SnippetPart> {
+    let mut parts = Vec::new();
+    let mut highlighted = false;
+    for text in snippet.split([HIGHLIGHT_START, HIGHLIGHT_STOP]) {
+        if !text.is_empty() {
+            parts.push(SnippetPart {
+                text: text.to_owned(),
+                highlighted,
+            });
+        }
+        highlighted = !highlighted;
+    }
+    parts
+}
+
+pub async fn search_teacher(
+    db: &Db,
+    user_id: i32,
+    query: String,
+    scope: Scope,
+    include_trashed: bool,
+) -> Result<SearchResults> {
+    let sheets =
+        data::search::search_sheets(db, user_id, query.clone(), scope, include_trashed).await?;
+    let solutions = data::search::search_answers(db, user_id, query, scope).await?;
+    Ok(SearchResults { sheets, solutions })
+}
+
+pub async fn search_student(
+    db: &Db,
+    user_id: i32,
+    query: String,
+    include_trashed: bool,
+) -> Result<SearchResults> {
+    let solutions = data::search::search_own_solutions(db, user_id, query, include_trashed).await?;
+    Ok(SearchResults {
+        sheets: Vec::new(),
+        solutions,
+    })
+}
@@ -0,0 +1,78 @@
+use rocket::serde::Serialize;
+
+use crate::login::transport::UserInfo;
+use crate::Db;
+
+use super::sheet::{self, SheetMetadata};
+use super::{data, Error, Id, Result};
+
+/// Permissions on a sheet, ordered from least to most privileged.
+/// Each permission includes all permissions below it.
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd, Serialize)]
+pub enum Permission {
+    /// May view the sheet
+    Viewer,
+    /// May additionally view the solutions of students
+    Grader,
+    /// May additionally edit the sheet
+    Editor,
+    /// May additionally delete the sheet and manage shares
+    Owner,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Share {
+    pub user: UserInfo,
+    pub permission: Permission,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SharedSheet {
+    #[serde(flatten)]
+    pub metadata: SheetMetadata,
+    pub permission: Permission,
+}
+
+pub async fn get_teacher(db: &Db, username: String) -> Result<UserInfo> {
+    data::sharing::get_teacher_by_name(db, username.clone())
+        .await?
+        .ok_or(Error::UnknownUser(username))
+}
+
+pub async fn get_shared_sheets(db: &Db, user_id: i32) -> Result<Vec<SharedSheet>> {
+    Ok(data::sharing::get_shared_sheets(db, user_id).await?)
+}
+
+pub async fn get_shares(db: &Db, user_id: i32, sheet_id: Id) -> Result<Vec<Share>> {
+    sheet::check_sheet_permission(db, user_id, sheet_id, Permission::Owner).await?;
+    Ok(data::sharing::get_shares(db, sheet_id).await?)
+}
+
+pub async fn share_sheet(
+    db: &Db,
+    user_id: i32,
+    sheet_id: Id,
+    username: String,
+    permission: Permission,
+) -> Result<()> {
+    sheet::check_sheet_permission(db, user_id, sheet_id, Permission::Owner).await?;
+    if permission == Permission::Owner {
+        return Err(Error::Forbidden(format!(
+            "ownership of sheet {} cannot be shared",
+            sheet_id
+        )));
+    }
+    let teacher = get_teacher(db, username).await?;
+    if teacher.id == user_id {
+        return Err(Error::Forbidden(format!(
+            "user {} cannot share sheet {} with themselves",
+            user_id, sheet_id
+        )));
+    }
+    Ok(data::sharing::set_share(db, sheet_id, teacher.id, permission).await?)
+}
+
+pub async fn unshare_sheet(db: &Db, user_id: i32, sheet_id: Id, shared_with: i32) -> Result<()> {
+    sheet::check_sheet_permission(db, user_id, sheet_id, Permission::Owner).await?;
+    Ok(data::sharing::delete_share(db, sheet_id, shared_with).await?)
+}
@@ -4,6 +4,11 @@ use rocket::serde::Serialize;
 use crate::login::transport::UserInfo;
 use crate::Db;
 
+use super::conversion::document::{self, Node};
+use super::conversion::render;
+use super::folder;
+use super::media;
+use super::sharing::{self, Permission};
 use super::{data, DeleteOutcome, Error, Id, Result};
 
 #[derive(Debug, Serialize)]
@@ -20,10 +25,18 @@ pub struct SheetMetadata {
     pub created: DateTime<Utc>,
     pub changed: DateTime<Utc>,
     pub trashed: Option<DateTime<Utc>>,
+    pub folder_id: Option<i32>,
 }
 
-pub async fn get_all_sheets(db: &Db, user_id: i32) -> Result<Vec<SheetMetadata>> {
-    Ok(data::sheet::get_all_sheets(db, user_id).await?)
+/// Renders the content as static HTML for browsers without JavaScript and search engines
+pub fn render_content(sheet: &Sheet) -> String {
+    render::render_sheet(&Node::from_json(&sheet.content))
+}
+
+/// Hides the correct dropdown options and model answers from anyone viewing the sheet without
+/// solving it
+pub fn redact_solutions(sheet: &mut Sheet) {
+    document::redact_solutions(&mut sheet.content);
 }
 
 pub async fn get_trash(db: &Db, user_id: i32) -> Result<Vec<SheetMetadata>> {
@@ -38,11 +51,16 @@ pub async fn get_updated(db: &Db, user_id: i32) -> Result<Vec<SheetMetadata>> {
     Ok(data::sheet::get_updated(db, user_id).await?)
 }
 
-pub async fn create_empty_sheet(db: &Db, user_id: i32, title: String) -> Result<Id> {
+pub async fn create_empty_sheet(
+    db: &Db,
+    user_id: i32,
+    title: String,
+    folder_id: Option<i32>,
+) -> Result<Id> {
     let content =
         serde_json::from_str("{\"type\": \"doc\", \"content\": [{\"type\": \"paragraph\", \"content\": [], \"marks\": []}], \"marks\": []}")
             .expect("malformed JSON");
-    create_sheet(db, user_id, title, content).await
+    create_sheet(db, user_id, title, content, folder_id).await
 }
 
 pub async fn create_sheet(
@@ -50,9 +68,51 @@ pub async fn create_sheet(
     user_id: i32,
     title: String,
     content: serde_json::Value,
+    folder_id: Option<i32>,
 ) -> Result<Id> {
+    insert_sheet(db, user_id, title, content, folder_id, None).await
+}
+
+pub async fn create_imported_sheet(
+    db: &Db,
+    user_id: i32,
+    title: String,
+    content: serde_json::Value,
+    folder_id: Option<i32>,
+    import_hash: String,
+) -> Result<Id> {
+    insert_sheet(db, user_id, title, content, folder_id, Some(import_hash)).await
+}
+
+async fn insert_sheet(
+    db: &Db,
+    user_id: i32,
+    title: String,
+    content: serde_json::Value,
+    folder_id: Option<i32>,
+    import_hash: Option<String>,
+) -> Result<Id> {
+    folder::check_folder_ownership(db, user_id, folder_id).await?;
     let now = chrono::Utc::now();
-    Ok(data::sheet::create_sheet(db, title, content, user_id, now, now, None).await?)
+    let used_media = media::referenced_media(&content);
+    let id = data::sheet::create_sheet(
+        db,
+        title,
+        content,
+        user_id,
+        now,
+        now,
+        None,
+        folder_id,
+        import_hash,
+    )
+    .await?;
+    media::set_sheet_media(db, user_id, id, used_media).await?;
+    Ok(id)
+}
+
+pub async fn get_owned_sheets(db: &Db, user_id: i32) -> Result<Vec<Sheet>> {
+    Ok(data::sheet::get_owned_sheets(db, user_id).await?)
 }
 
 pub async fn get_sheet_title(db: &Db, sheet_id: Id) -> Result<String> {
@@ -65,25 +125,48 @@ pub async fn get_sheet(db: &Db, id: Id) -> Result<Sheet> {
         .ok_or_else(|| Error::NotFound(format!("sheet {}", id)))
 }
 
-async fn get_sheet_owned_by_user(db: &Db, user_id: i32, id: Id) -> Result<Sheet> {
-    let sheet = get_sheet(db, id).await?;
+pub async fn get_permission(db: &Db, user_id: i32, sheet: &Sheet) -> Result<Option<Permission>> {
     if sheet.metadata.owner.id == user_id {
+        Ok(Some(Permission::Owner))
+    } else {
+        Ok(data::sharing::get_permission(db, sheet.metadata.id, user_id).await?)
+    }
+}
+
+async fn get_sheet_with_permission(
+    db: &Db,
+    user_id: i32,
+    id: Id,
+    required: Permission,
+) -> Result<Sheet> {
+    let sheet = get_sheet(db, id).await?;
+    if grants(get_permission(db, user_id, &sheet).await?, required) {
         Ok(sheet)
     } else {
         Err(Error::Forbidden(format!(
-            "user {} is not owner of sheet {}",
-            user_id, id
+            "user {} lacks {:?} permission for sheet {}",
+            user_id, required, id
         )))
     }
 }
 
-pub async fn check_sheet_ownership(db: &Db, user_id: i32, id: Id) -> Result<()> {
-    get_sheet_owned_by_user(db, user_id, id).await?; // We don't care about the sheet here, we just need to check ownership
+/// Whether the permission of a user on a sheet, if any, includes the required one
+fn grants(permission: Option<Permission>, required: Permission) -> bool {
+    matches!(permission, Some(permission) if permission >= required)
+}
+
+pub async fn check_sheet_permission(
+    db: &Db,
+    user_id: i32,
+    id: Id,
+    required: Permission,
+) -> Result<()> {
+    get_sheet_with_permission(db, user_id, id, required).await?; // We don't care about the sheet here, we just need to check permissions
     Ok(())
 }
 
 pub async fn get_sheet_for_edit(db: &Db, user_id: i32, id: Id) -> Result<Sheet> {
-    get_sheet_owned_by_user(db, user_id, id).await
+    get_sheet_with_permission(db, user_id, id, Permission::Editor).await
 }
 
 pub async fn update_sheet(
@@ -93,13 +176,20 @@ pub async fn update_sheet(
     title: String,
     content: serde_json::Value,
 ) -> Result<()> {
-    check_sheet_ownership(db, user_id, id).await?;
+    check_sheet_permission(db, user_id, id, Permission::Editor).await?;
     let now = chrono::Utc::now();
-    Ok(data::sheet::update_sheet(db, id, title, content, now).await?)
+    let used_media = media::referenced_media(&content);
+    if !data::sheet::update_sheet(db, id, title, content, now).await? {
+        return Err(Error::Conflict(format!(
+            "sheet {} is edited collaboratively",
+            id
+        )));
+    }
+    media::set_sheet_media(db, user_id, id, used_media).await
 }
 
 pub async fn delete_sheet(db: &Db, user_id: i32, id: Id) -> Result<DeleteOutcome> {
-    let sheet = get_sheet_owned_by_user(db, user_id, id).await?;
+    let sheet = get_sheet_with_permission(db, user_id, id, Permission::Owner).await?;
     if sheet.metadata.trashed.is_some() {
         data::sheet::delete_sheet(db, id).await?;
         Ok(DeleteOutcome::Deleted)
@@ -110,7 +200,110 @@ pub async fn delete_sheet(db: &Db, user_id: i32, id: Id) -> Result<DeleteOutcome
 }
 
 pub async fn restore_sheet(db: &Db, user_id: i32, id: Id) -> Result<()> {
-    check_sheet_ownership(db, user_id, id).await?;
+    check_sheet_permission(db, user_id, id, Permission::Owner).await?;
     data::sheet::restore_sheet(db, id).await?;
     Ok(())
 }
+
+async fn transfer_sheet_unchecked(db: &Db, id: Id, new_owner: String) -> Result<UserInfo> {
+    let new_owner = sharing::get_teacher(db, new_owner).await?;
+    data::sheet::transfer_sheet(db, id, new_owner.id).await?;
+    Ok(new_owner)
+}
+
+pub async fn transfer_sheet(db: &Db, user_id: i32, id: Id, new_owner: String) -> Result<UserInfo> {
+    check_sheet_permission(db, user_id, id, Permission::Owner).await?;
+    transfer_sheet_unchecked(db, id, new_owner).await
+}
+
+pub async fn transfer_sheet_as_admin(db: &Db, id: Id, new_owner: String) -> Result<UserInfo> {
+    get_sheet(db, id).await?; // Make sure the sheet exists
+    transfer_sheet_unchecked(db, id, new_owner).await
+}
+
+pub async fn transfer_all_sheets(db: &Db, user_id: i32, new_owner: String) -> Result<usize> {
+    let new_owner = sharing::get_teacher(db, new_owner).await?;
+    Ok(data::sheet::transfer_all_sheets(db, user_id, new_owner.id).await?)
+}
+
+pub async fn transfer_all_sheets_as_admin(
+    db: &Db,
+    previous_owner: String,
+    new_owner: String,
+) -> Result<usize> {
+    let previous_owner = sharing::get_teacher(db, previous_owner).await?;
+    transfer_all_sheets(db, previous_owner.id, new_owner).await
+}
+
+/// Deletes the account of a teacher who leaves, their sheets and media go to the new owner
+pub async fn delete_teacher_as_admin(
+    db: &Db,
+    previous_owner: String,
+    new_owner: String,
+) -> Result<usize> {
+    let previous_owner = sharing::get_teacher(db, previous_owner).await?;
+    let new_owner = sharing::get_teacher(db, new_owner).await?;
+    check_takeover(&previous_owner, &new_owner)?;
+    data::sheet::transfer_all_and_delete_owner(db, previous_owner.id, new_owner.id)
+        .await?
+        .ok_or_else(|| {
+            Error::Conflict(format!(
+                "user {} still owns solutions",
+                previous_owner.username
+            ))
+        })
+}
+
+/// Deleting a teacher who takes over their own sheets would delete the sheets with them
+fn check_takeover(previous_owner: &UserInfo, new_owner: &UserInfo) -> Result<()> {
+    if previous_owner.id == new_owner.id {
+        return Err(Error::Conflict(format!(
+            "user {} cannot take over their own sheets",
+            previous_owner.username
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn permissions_include_all_lesser_ones() {
+        use Permission::*;
+        assert!(Viewer < Grader && Grader < Editor && Editor < Owner);
+        let all = [Viewer, Grader, Editor, Owner];
+        for (i, &permission) in all.iter().enumerate() {
+            for (j, &required) in all.iter().enumerate() {
+                assert_eq!(grants(Some(permission), required), i >= j);
+            }
+            assert!(!grants(None, permission));
+        }
+    }
+
+    #[test]
+    fn denies_what_needs_more_privileges() {
+        use Permission::*;
+        // Graders may see solutions, but not edit the sheet
+        assert!(grants(Some(Grader), Grader));
+        assert!(!grants(Some(Grader), Editor));
+        // Editors may edit, but not delete, transfer or share the sheet, which needs its owner
+        assert!(grants(Some(Editor), Editor));
+        assert!(!grants(Some(Editor), Owner));
+        assert!(!grants(Some(Viewer), Grader));
+    }
+
+    #[test]
+    fn teachers_cannot_take_over_their_own_sheets() {
+        let teacher = |id: i32, username: &str| UserInfo {
+            id,
+            username: username.into(),
+        };
+        assert!(matches!(
+            check_takeover(&teacher(1, "mueller"), &teacher(1, "mueller")),
+            Err(Error::Conflict(_))
+        ));
+        assert!(check_takeover(&teacher(1, "mueller"), &teacher(2, "schmidt")).is_ok());
+    }
+}
@@ -4,6 +4,9 @@ use rocket::serde::Serialize;
 use crate::login::transport::UserInfo;
 use crate::Db;
 
+use super::conversion::document::{self, Node, NodeKind};
+use super::conversion::{answers, essay, pool, shuffle, variables};
+use super::sharing::Permission;
 use super::sheet::Sheet;
 use super::{data, sheet, DeleteOutcome};
 use super::{Error, Id, Result};
@@ -12,6 +15,21 @@ use super::{Error, Id, Result};
 pub struct Solution {
     pub metadata: SolutionMetadata,
     pub content: serde_json::Value,
+    /// Written by a teacher for the student
+    pub feedback: String,
+}
+
+impl Solution {
+    /// Puts shuffled answers and options back into the order of the sheet, which is how teachers
+    /// see every solution
+    fn into_canonical(mut self) -> Self {
+        shuffle::unshuffle(&mut self.content, shuffle_seed(self.metadata.id));
+        self
+    }
+}
+
+fn shuffle_seed(solution_id: i32) -> u64 {
+    solution_id as u64
 }
 
 #[derive(Debug, Serialize)]
@@ -26,6 +44,24 @@ pub struct SolutionMetadata {
     pub trashed: Option<DateTime<Utc>>,
 }
 
+/// A solution of a sheet the teacher grades with essays still to be graded
+#[derive(Debug, Serialize)]
+pub struct GradingTask {
+    pub solution: SolutionMetadata,
+    pub essays: Vec<UngradedEssay>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UngradedEssay {
+    /// Position among the essays of the solution
+    pub index: usize,
+    pub points: u32,
+    pub word_limits: String,
+    pub words: usize,
+    pub answer: String,
+    pub model_answer: String,
+}
+
 pub struct FreshSolution {
     pub title: String,
     pub sheet_id: Id,
@@ -40,6 +76,10 @@ pub struct FreshSolution {
 impl FreshSolution {
     fn from(sheet: Sheet, user_id: i32) -> FreshSolution {
         let now = Utc::now();
+        let mut content = sheet.content;
+        let mut rng = rand::thread_rng();
+        pool::draw_pools(&mut content, &mut rng);
+        variables::instantiate(&mut content, &mut rng);
         FreshSolution {
             title: sheet.metadata.title,
             sheet_id: sheet.metadata.id,
@@ -48,13 +88,13 @@ impl FreshSolution {
             created: now,
             changed: now,
             trashed: None,
-            content: sheet.content,
+            content,
         }
     }
 }
 
 pub async fn get_solutions_teacher(db: &Db, user_id: i32) -> Result<Vec<SolutionMetadata>> {
-    Ok(data::solution::get_solutions_by_sheet_owner(db, user_id).await?)
+    Ok(data::solution::get_solutions_by_grader(db, user_id).await?)
 }
 
 pub async fn get_solutions_student(db: &Db, user_id: i32) -> Result<Vec<SolutionMetadata>> {
@@ -74,10 +114,109 @@ pub async fn get_sheet_solutions_teacher(
     user_id: i32,
     sheet_id: Id,
 ) -> Result<Vec<SolutionMetadata>> {
-    sheet::check_sheet_ownership(db, user_id, sheet_id).await?;
+    sheet::check_sheet_permission(db, user_id, sheet_id, Permission::Grader).await?;
     Ok(data::solution::get_all_sheet_solutions(db, sheet_id).await?)
 }
 
+pub async fn get_latest_sheet_solutions(
+    db: &Db,
+    user_id: i32,
+    sheet_id: Id,
+) -> Result<Vec<Solution>> {
+    sheet::check_sheet_permission(db, user_id, sheet_id, Permission::Grader).await?;
+    let mut solutions: Vec<Solution> = data::solution::get_latest_sheet_solutions(db, sheet_id)
+        .await?
+        .into_iter()
+        .map(Solution::into_canonical)
+        .collect();
+    solutions.sort_by(|a, b| a.metadata.owner.username.cmp(&b.metadata.owner.username));
+    Ok(solutions)
+}
+
+/// Latest solutions with ungraded essays to the sheets the teacher owns or was allowed to grade,
+/// the oldest first
+pub async fn get_grading_queue(db: &Db, user_id: i32) -> Result<Vec<GradingTask>> {
+    let mut queue: Vec<GradingTask> = data::solution::get_latest_solutions_by_grader(db, user_id)
+        .await?
+        .into_iter()
+        .filter_map(|solution| {
+            let content = Node::from_json(&solution.content);
+            let essays: Vec<UngradedEssay> = essay::essays(&content)
+                .into_iter()
+                .enumerate()
+                .filter_map(|(index, node)| match &node.kind {
+                    NodeKind::Essay {
+                        min_words,
+                        max_words,
+                        points,
+                        model_answer,
+                        answer,
+                        awarded: None,
+                    } => Some(UngradedEssay {
+                        index,
+                        points: *points,
+                        word_limits: essay::word_limits(*min_words, *max_words),
+                        words: essay::count_words(answer),
+                        answer: answer.clone(),
+                        model_answer: model_answer.clone(),
+                    }),
+                    _ => None,
+                })
+                .collect();
+            if essays.is_empty() {
+                None
+            } else {
+                Some(GradingTask {
+                    solution: solution.metadata,
+                    essays,
+                })
+            }
+        })
+        .collect();
+    queue.sort_by_key(|task| task.solution.changed);
+    Ok(queue)
+}
+
+/// Awards points to an essay of a student's solution, which keeps its time of change
+pub async fn grade_essay(
+    db: &Db,
+    teacher_id: i32,
+    sheet_id: Id,
+    student_id: i32,
+    solution_id: i32,
+    index: usize,
+    points: f64,
+) -> Result<()> {
+    sheet::check_sheet_permission(db, teacher_id, sheet_id, Permission::Grader).await?;
+    let mut solution = get_my_solution(db, student_id, sheet_id, solution_id).await?;
+    if !essay::award(&mut solution.content, index, points) {
+        return Err(Error::InvalidPoints(format!(
+            "{} for essay {} of solution {}",
+            points, index, solution_id
+        )));
+    }
+    Ok(data::solution::update_solution(
+        db,
+        solution_id,
+        solution.content,
+        solution.metadata.changed,
+    )
+    .await?)
+}
+
+pub async fn give_feedback(
+    db: &Db,
+    teacher_id: i32,
+    sheet_id: Id,
+    student_id: i32,
+    solution_id: i32,
+    feedback: String,
+) -> Result<()> {
+    sheet::check_sheet_permission(db, teacher_id, sheet_id, Permission::Grader).await?;
+    get_my_solution(db, student_id, sheet_id, solution_id).await?;
+    Ok(data::solution::update_feedback(db, solution_id, feedback.trim().to_string()).await?)
+}
+
 pub async fn get_sheet_solutions_student(
     db: &Db,
     user_id: i32,
@@ -104,7 +243,15 @@ pub async fn start_solve(db: &Db, sheet_id: Id, user_id: i32) -> Result<()> {
 
 async fn create_solution(db: &Db, sheet: Sheet, user_id: i32) -> Result<()> {
     let fresh_solution = FreshSolution::from(sheet, user_id);
-    data::solution::create_solution(db, fresh_solution).await?;
+    let changed = fresh_solution.changed;
+    let mut content = fresh_solution.content.clone();
+    let solution_id = data::solution::create_solution(db, fresh_solution).await?;
+    // The seed is the ID of the solution, which only exists once it is stored
+    let canonical = content.clone();
+    shuffle::shuffle(&mut content, shuffle_seed(solution_id));
+    if content != canonical {
+        data::solution::update_solution(db, solution_id, content, changed).await?;
+    }
     Ok(())
 }
 
@@ -125,8 +272,10 @@ pub async fn get_latest_solution_for_teacher(
     sheet_id: Id,
     student_id: i32,
 ) -> Result<Solution> {
-    sheet::check_sheet_ownership(db, teacher_id, sheet_id).await?;
-    get_latest_solution(db, sheet_id, student_id).await
+    sheet::check_sheet_permission(db, teacher_id, sheet_id, Permission::Grader).await?;
+    Ok(get_latest_solution(db, sheet_id, student_id)
+        .await?
+        .into_canonical())
 }
 
 async fn get_solution(db: &Db, solution_id: i32) -> Result<Solution> {
@@ -165,9 +314,9 @@ pub async fn get_solution_for_teacher(
     student_id: i32,
     solution_id: i32,
 ) -> Result<Solution> {
-    sheet::check_sheet_ownership(db, teacher_id, sheet_id).await?;
+    sheet::check_sheet_permission(db, teacher_id, sheet_id, Permission::Grader).await?;
     let solution = get_my_solution(db, student_id, sheet_id, solution_id).await?;
-    Ok(solution)
+    Ok(solution.into_canonical())
 }
 
 pub async fn get_my_solution(
@@ -198,9 +347,32 @@ pub async fn update_solution(
     solution_id: i32,
     content: serde_json::Value,
 ) -> Result<()> {
-    check_solution_ownership(db, user_id, sheet_id, solution_id).await?;
+    // Students send the whole solution, but only their answers are theirs to change
+    let mut solution = get_my_solution(db, user_id, sheet_id, solution_id).await?;
+    answers::take_answers(&mut solution.content, &content);
     let now = Utc::now();
-    Ok(data::solution::update_solution(db, solution_id, content, now).await?)
+    Ok(data::solution::update_solution(db, solution_id, solution.content, now).await?)
+}
+
+/// Grades the dropdowns for the answers the student currently gives, as their view does not know
+/// the correct options. The answers are not saved.
+pub async fn check_dropdowns(
+    db: &Db,
+    user_id: i32,
+    sheet_id: Id,
+    solution_id: i32,
+    content: serde_json::Value,
+) -> Result<Vec<f64>> {
+    let mut solution = get_my_solution(db, user_id, sheet_id, solution_id).await?;
+    answers::take_answers(&mut solution.content, &content);
+    Ok(answers::dropdown_credits(&solution.content))
+}
+
+/// Hides what only teachers may see from a student looking at their solution
+pub fn redact_for_student(solution: &mut Solution) {
+    essay::hide_model_answers(&mut solution.content);
+    answers::number_dropdowns(&mut solution.content);
+    document::redact_dropdowns(&mut solution.content);
 }
 
 pub async fn delete_solution(
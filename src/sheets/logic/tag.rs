@@ -0,0 +1,22 @@
+use crate::Db;
+
+use super::sharing::Permission;
+use super::{data, sheet, Id, Result};
+
+fn normalize_tag(tag: &str) -> String {
+    tag.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+pub async fn add_tag(db: &Db, user_id: i32, sheet_id: Id, tag: String) -> Result<()> {
+    sheet::check_sheet_permission(db, user_id, sheet_id, Permission::Owner).await?;
+    let tag = normalize_tag(&tag);
+    if !tag.is_empty() {
+        data::tag::add_tag(db, sheet_id, tag).await?;
+    }
+    Ok(())
+}
+
+pub async fn remove_tag(db: &Db, user_id: i32, sheet_id: Id, tag: String) -> Result<()> {
+    sheet::check_sheet_permission(db, user_id, sheet_id, Permission::Owner).await?;
+    Ok(data::tag::remove_tag(db, sheet_id, tag).await?)
+}
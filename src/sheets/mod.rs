@@ -1,4 +1,6 @@
+pub mod conversion;
 mod data;
 pub mod logic;
 pub mod routes;
+pub mod storage;
 pub mod transport;
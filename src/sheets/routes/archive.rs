@@ -0,0 +1,89 @@
+use rocket::form::Form;
+use rocket::http::{ContentType, Status};
+use rocket::serde::Serialize;
+use rocket::State;
+use rocket_dyn_templates::Template;
+
+use crate::download::Download;
+use crate::flash::FlashRedirect;
+use crate::login::guards::{AuthenticatedUser, Teacher};
+use crate::status::ToStatus;
+use crate::Db;
+
+use super::logic;
+use super::logic::archive::ImportReport;
+use super::logic::media::MediaConfig;
+use super::read_upload;
+use super::sheet_tree::folder_uri;
+use super::storage::MediaStorage;
+use super::transport::{ExportSheetsForm, ImportSheetForm};
+
+#[derive(Serialize)]
+struct ImportReportContext<'a> {
+    reports: Vec<ImportReport>,
+    folder_uri: String,
+    user: &'a AuthenticatedUser,
+}
+
+#[derive(Responder)]
+#[allow(clippy::large_enum_variant)]
+pub enum ImportArchiveResponse {
+    Report(Template),
+    Redirect(FlashRedirect),
+}
+
+#[post("/assignments/export", data = "<form>")]
+pub async fn export_sheets(
+    db: Db,
+    storage: &State<MediaStorage>,
+    teacher: Teacher<'_>,
+    form: Form<ExportSheetsForm>,
+) -> Result<Download, Status> {
+    let user = teacher.into_inner();
+    let form = form.into_inner();
+    logic::archive::export_archive(&db, storage, user.user_info.id, form.sheets)
+        .await
+        .map_err(|e| e.to_status())
+        .map(|archive| Download::new("Dokumente.zip", ContentType::ZIP, archive))
+}
+
+#[post("/assignments/import", data = "<form>")]
+pub async fn import_sheets(
+    db: Db,
+    storage: &State<MediaStorage>,
+    config: &State<MediaConfig>,
+    teacher: Teacher<'_>,
+    form: Form<ImportSheetForm<'_>>,
+) -> Result<ImportArchiveResponse, Status> {
+    let user = teacher.into_inner();
+    let form = form.into_inner();
+    let file = read_upload(&form.file).await?;
+    let archive = match logic::archive::read_archive(&file) {
+        Ok(archive) => archive,
+        Err(e) => {
+            error!("Reading sheet archive failed: {}", e);
+            return Ok(ImportArchiveResponse::Redirect(FlashRedirect::with_flash(
+                folder_uri(form.folder),
+                "danger",
+                "Invalides Dateiformat",
+            )));
+        }
+    };
+    let reports = logic::archive::import_archive(
+        &db,
+        storage,
+        config,
+        user.user_info.id,
+        archive,
+        form.folder,
+    )
+    .await;
+    Ok(ImportArchiveResponse::Report(Template::render(
+        "management/sheet/import_report",
+        &ImportReportContext {
+            reports,
+            folder_uri: folder_uri(form.folder),
+            user,
+        },
+    )))
+}
@@ -0,0 +1,113 @@
+use rocket::http::Status;
+use rocket::response::stream::{Event, EventStream};
+use rocket::serde::json::Json;
+use rocket::tokio::select;
+use rocket::tokio::sync::broadcast::error::RecvError;
+use rocket::{Shutdown, State};
+
+use crate::login::guards::Teacher;
+use crate::status::ToStatus;
+use crate::validation::Validate;
+use crate::Db;
+
+use super::logic;
+use super::logic::collaboration::CollaborationHub;
+use super::logic::Id;
+use super::transport::{
+    CollaborationEventTransport, CollaborationSnapshotTransport, NewSheetUpdateTransport,
+    SheetUpdateTransport,
+};
+
+fn decode_update(update: &str) -> Result<Vec<u8>, Status> {
+    base64::decode(update).map_err(|e| {
+        error!("Decoding collaboration update failed: {}", e);
+        Status::BadRequest
+    })
+}
+
+#[get("/<id>/collaboration?<since>")]
+pub async fn collaboration_updates(
+    db: Db,
+    teacher: Teacher<'_>,
+    id: Id,
+    since: Option<i64>,
+) -> Result<Json<Vec<SheetUpdateTransport>>, Status> {
+    let user = teacher.into_inner();
+    logic::collaboration::get_updates(&db, user.user_info.id, id, since.unwrap_or(0))
+        .await
+        .map_err(|e| e.to_status())
+        .map(|updates| Json(updates.into_iter().map(|u| u.into()).collect()))
+}
+
+#[get("/<id>/collaboration/events")]
+pub async fn collaboration_events(
+    db: Db,
+    teacher: Teacher<'_>,
+    hub: &State<CollaborationHub>,
+    id: Id,
+    mut end: Shutdown,
+) -> Result<EventStream![], Status> {
+    let user = teacher.into_inner();
+    let mut subscription = logic::collaboration::join(&db, hub, &user.user_info, id)
+        .await
+        .map_err(|e| e.to_status())?;
+    Ok(EventStream! {
+        loop {
+            let event = select! {
+                event = subscription.receiver.recv() => event,
+                _ = &mut end => break,
+            };
+            let event = match event {
+                Ok(event) => CollaborationEventTransport::from(event),
+                // The client missed updates and has to fetch the log again
+                Err(RecvError::Lagged(_)) => CollaborationEventTransport::Resync,
+                Err(RecvError::Closed) => break,
+            };
+            yield Event::json(&event);
+        }
+    })
+}
+
+#[post("/<id>/collaboration", format = "json", data = "<update>")]
+pub async fn push_collaboration_update(
+    db: Db,
+    teacher: Teacher<'_>,
+    hub: &State<CollaborationHub>,
+    id: Id,
+    update: Json<NewSheetUpdateTransport>,
+) -> Result<Json<SheetUpdateTransport>, Status> {
+    let user = teacher.into_inner();
+    let update = update.into_inner();
+    let payload = decode_update(&update.update)?;
+    logic::collaboration::push_update(&db, hub, user.user_info.id, id, update.base, payload)
+        .await
+        .map_err(|e| e.to_status())
+        .map(|update| Json(update.into()))
+}
+
+#[put("/<id>/collaboration", format = "json", data = "<snapshot>")]
+pub async fn compact_collaboration_updates(
+    db: Db,
+    teacher: Teacher<'_>,
+    hub: &State<CollaborationHub>,
+    id: Id,
+    snapshot: Json<CollaborationSnapshotTransport>,
+) -> Result<Json<SheetUpdateTransport>, Status> {
+    let user = teacher.into_inner();
+    let snapshot = snapshot.into_inner();
+    snapshot.validate().map_err(|e| e.to_status())?;
+    let payload = decode_update(&snapshot.state)?;
+    logic::collaboration::compact(
+        &db,
+        hub,
+        user.user_info.id,
+        id,
+        snapshot.up_to,
+        payload,
+        snapshot.title,
+        snapshot.content,
+    )
+    .await
+    .map_err(|e| e.to_status())
+    .map(|update| Json(update.into()))
+}
@@ -0,0 +1,119 @@
+use rocket::form::Form;
+use rocket::http::Status;
+use rocket::response::Redirect;
+
+use crate::flash::FlashRedirect;
+use crate::login::guards::Teacher;
+use crate::status::ToStatus;
+use crate::Db;
+
+use super::logic;
+use super::logic::Id;
+use super::sheet_tree::folder_uri;
+use super::transport::{FolderForm, MoveForm, NewFolderForm, TagForm};
+
+async fn sheet_folder_uri(db: &Db, sheet_id: Id) -> Result<String, Status> {
+    logic::sheet::get_sheet(db, sheet_id)
+        .await
+        .map_err(|e| e.to_status())
+        .map(|sheet| folder_uri(sheet.metadata.folder_id))
+}
+
+#[post("/assignments/folders", data = "<form>")]
+pub async fn new_folder(
+    db: Db,
+    teacher: Teacher<'_>,
+    form: Form<NewFolderForm>,
+) -> Result<Redirect, Status> {
+    let user = teacher.into_inner();
+    let form = form.into_inner();
+    logic::folder::create_folder(&db, user.user_info.id, form.name, form.parent)
+        .await
+        .map_err(|e| e.to_status())
+        .map(|id| Redirect::to(folder_uri(Some(id))))
+}
+
+#[put("/assignments/folders/<id>", data = "<form>")]
+pub async fn rename_folder(
+    db: Db,
+    teacher: Teacher<'_>,
+    id: i32,
+    form: Form<FolderForm>,
+) -> Result<Redirect, Status> {
+    let user = teacher.into_inner();
+    let form = form.into_inner();
+    logic::folder::rename_folder(&db, user.user_info.id, id, form.name)
+        .await
+        .map_err(|e| e.to_status())
+        .map(|_| Redirect::to(folder_uri(Some(id))))
+}
+
+#[put("/assignments/folders/<id>/parent", data = "<form>")]
+pub async fn move_folder(
+    db: Db,
+    teacher: Teacher<'_>,
+    id: i32,
+    form: Form<MoveForm>,
+) -> Result<Redirect, Status> {
+    let user = teacher.into_inner();
+    let form = form.into_inner();
+    logic::folder::move_folder(&db, user.user_info.id, id, form.folder)
+        .await
+        .map_err(|e| e.to_status())
+        .map(|_| Redirect::to(folder_uri(form.folder)))
+}
+
+#[delete("/assignments/folders/<id>")]
+pub async fn delete_folder(db: Db, teacher: Teacher<'_>, id: i32) -> Result<FlashRedirect, Status> {
+    let user = teacher.into_inner();
+    logic::folder::delete_folder(&db, user.user_info.id, id)
+        .await
+        .map_err(|e| e.to_status())
+        .map(|parent_id| {
+            FlashRedirect::with_flash(folder_uri(parent_id), "success", "Ordner gelöscht")
+        })
+}
+
+#[put("/<id>/folder", data = "<form>")]
+pub async fn move_sheet(
+    db: Db,
+    teacher: Teacher<'_>,
+    id: Id,
+    form: Form<MoveForm>,
+) -> Result<Redirect, Status> {
+    let user = teacher.into_inner();
+    let form = form.into_inner();
+    logic::folder::move_sheet(&db, user.user_info.id, id, form.folder)
+        .await
+        .map_err(|e| e.to_status())
+        .map(|_| Redirect::to(folder_uri(form.folder)))
+}
+
+#[post("/<id>/tags", data = "<form>")]
+pub async fn add_tag(
+    db: Db,
+    teacher: Teacher<'_>,
+    id: Id,
+    form: Form<TagForm>,
+) -> Result<Redirect, Status> {
+    let user = teacher.into_inner();
+    let form = form.into_inner();
+    logic::tag::add_tag(&db, user.user_info.id, id, form.tag)
+        .await
+        .map_err(|e| e.to_status())?;
+    sheet_folder_uri(&db, id).await.map(Redirect::to)
+}
+
+#[delete("/<id>/tags/<tag>")]
+pub async fn remove_tag(
+    db: Db,
+    teacher: Teacher<'_>,
+    id: Id,
+    tag: String,
+) -> Result<Redirect, Status> {
+    let user = teacher.into_inner();
+    logic::tag::remove_tag(&db, user.user_info.id, id, tag)
+        .await
+        .map_err(|e| e.to_status())?;
+    sheet_folder_uri(&db, id).await.map(Redirect::to)
+}
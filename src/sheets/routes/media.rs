@@ -0,0 +1,188 @@
+use std::time::Duration;
+
+use log::{error, info};
+use rocket::form::Form;
+use rocket::http::{ContentType, RawStr, Status};
+use rocket::request::Request;
+use rocket::response::{self, Responder};
+use rocket::serde::json::Json;
+use rocket::tokio::{self, time};
+use rocket::{Build, Orbit, Rocket, State};
+
+use crate::login::guards::{AuthenticatedUser, Teacher};
+use crate::partial::{PartialContent, RequestedRange, Resolved};
+use crate::status::ToStatus;
+use crate::Db;
+
+use super::logic;
+use super::logic::media::{Media, MediaConfig};
+use super::logic::Id;
+use super::read_upload;
+use super::storage::{LocalStorage, MediaStorage};
+use super::transport::{MediaTransport, MediaUploadForm};
+
+pub const MOUNT: &str = "/media";
+
+pub fn media_uri(uri: rocket::http::uri::Origin) -> String {
+    format!("{}{}", MOUNT, uri)
+}
+
+impl From<Media> for MediaTransport {
+    fn from(media: Media) -> Self {
+        MediaTransport {
+            url: media_uri(uri!(media(media.id))),
+            id: media.id,
+            filename: media.filename,
+            mimetype: media.mimetype,
+            size: media.size,
+        }
+    }
+}
+
+/// Media are served from the site's own origin, so browsers must neither guess their type nor
+/// run anything they contain
+pub struct MediaResponse {
+    content: PartialContent,
+    attachment: Option<String>,
+}
+
+impl<'r> Responder<'r, 'static> for MediaResponse {
+    fn respond_to(self, request: &'r Request<'_>) -> response::Result<'static> {
+        let mut response = self.content.respond_to(request)?;
+        response.set_raw_header("X-Content-Type-Options", "nosniff");
+        response.set_raw_header("Content-Security-Policy", "default-src 'none'; sandbox");
+        if let Some(filename) = self.attachment {
+            response.set_raw_header(
+                "Content-Disposition",
+                format!(
+                    "attachment; filename*=UTF-8''{}",
+                    RawStr::new(&filename).percent_encode()
+                ),
+            );
+        }
+        Ok(response)
+    }
+}
+
+/// Mounted below the sheets as uploads belong to the sheet they are uploaded for
+#[post("/<id>/media", data = "<form>")]
+pub async fn upload_media(
+    db: Db,
+    storage: &State<MediaStorage>,
+    config: &State<MediaConfig>,
+    teacher: Teacher<'_>,
+    id: Id,
+    form: Form<MediaUploadForm<'_>>,
+) -> Result<Json<MediaTransport>, Status> {
+    let user = teacher.into_inner();
+    let form = form.into_inner();
+    let filename = form
+        .file
+        .raw_name()
+        .map(|name| name.dangerous_unsafe_unsanitized_raw().as_str())
+        .and_then(|name| name.rsplit(|c| c == '/' || c == '\\').next())
+        .filter(|name| !name.is_empty())
+        .unwrap_or("upload")
+        .to_string();
+    let content = read_upload(&form.file).await?;
+    logic::media::upload_media(
+        &db,
+        storage,
+        config,
+        user.user_info.id,
+        id,
+        filename,
+        content,
+    )
+    .await
+    .map_err(|e| e.to_status())
+    .map(|media| Json(media.into()))
+}
+
+#[get("/<id>")]
+pub async fn media(
+    db: Db,
+    storage: &State<MediaStorage>,
+    user: Option<&AuthenticatedUser>,
+    range: RequestedRange,
+    id: Id,
+) -> Result<MediaResponse, Status> {
+    let media = logic::media::get_media(&db, user.map(|user| user.user_info.id), id)
+        .await
+        .map_err(|e| e.to_status())?;
+    // Types outside the allowlist are served as binary attachments, never rendered inline
+    let (content_type, attachment) = if logic::media::is_supported(&media.mimetype) {
+        let content_type = ContentType::parse_flexible(&media.mimetype);
+        (content_type.unwrap_or(ContentType::Binary), None)
+    } else {
+        (ContentType::Binary, Some(media.filename.clone()))
+    };
+    let content = match range.resolve(media.size) {
+        Resolved::Complete => PartialContent::Complete {
+            content_type,
+            body: logic::media::read_media(storage, &media, 0..media.size)
+                .await
+                .map_err(|e| e.to_status())?,
+        },
+        Resolved::Partial(range) => PartialContent::Partial {
+            content_type,
+            range: range.clone(),
+            length: media.size,
+            body: logic::media::read_media(storage, &media, range)
+                .await
+                .map_err(|e| e.to_status())?,
+        },
+        Resolved::Unsatisfiable => PartialContent::Unsatisfiable { length: media.size },
+    };
+    Ok(MediaResponse {
+        content,
+        attachment,
+    })
+}
+
+/// Periodically removes unused media. The garbage collection keeps one database connection for
+/// itself.
+pub async fn configure(rocket: Rocket<Build>) -> Result<Rocket<Build>, Rocket<Build>> {
+    match rocket.figment().extract::<MediaConfig>() {
+        Ok(config) => {
+            let storage = MediaStorage::new(LocalStorage::new(config.media_dir.clone()));
+            Ok(rocket.manage(storage).manage(config))
+        }
+        Err(e) => {
+            error!("Invalid media configuration: {}", e);
+            Err(rocket)
+        }
+    }
+}
+
+pub async fn spawn_garbage_collection(rocket: &Rocket<Orbit>) {
+    let (storage, config) = match (
+        rocket.state::<MediaStorage>(),
+        rocket.state::<MediaConfig>(),
+    ) {
+        (Some(storage), Some(config)) => (storage.clone(), config.clone()),
+        _ => {
+            error!("Media storage is not configured, not collecting garbage");
+            return;
+        }
+    };
+    let db = match Db::get_one(rocket).await {
+        Some(db) => db,
+        None => {
+            error!("No database connection available, not collecting media garbage");
+            return;
+        }
+    };
+    let hours = u64::from(config.media_gc_interval.max(1));
+    tokio::spawn(async move {
+        let mut interval = time::interval(Duration::from_secs(hours * 60 * 60));
+        loop {
+            interval.tick().await;
+            match logic::media::collect_garbage(&db, &storage, &config).await {
+                Ok(0) => {}
+                Ok(removed) => info!("Removed {} unused media files", removed),
+                Err(e) => error!("Collecting media garbage failed: {}", e),
+            }
+        }
+    });
+}
@@ -1,3 +1,4 @@
+use rocket::fs::TempFile;
 use rocket::http::Status;
 
 use crate::flash::FlashRedirect;
@@ -6,18 +7,26 @@ use crate::login::guards::AuthenticatedUser;
 use crate::status::ToStatus;
 
 use super::logic;
+use super::storage;
 use super::transport;
 
+pub mod archive;
+pub mod collaboration;
+pub mod folder;
+pub mod media;
+pub mod search;
+pub mod sharing;
 pub mod sheet;
 pub mod sheet_tree;
 pub mod solution;
+pub mod transfer;
 
 pub const MOUNT: &str = "/sheets";
 
 impl ToStatus for logic::Error {
     fn to_status(self) -> Status {
         match self {
-            Self::NotFound(_) => {
+            Self::NotFound(_) | Self::UnknownUser(_) => {
                 debug!("{}", self);
                 Status::NotFound
             }
@@ -25,6 +34,22 @@ impl ToStatus for logic::Error {
                 info!("{}", self);
                 Status::Forbidden
             }
+            Self::UnsupportedMedia(_) => {
+                info!("{}", self);
+                Status::UnsupportedMediaType
+            }
+            Self::QuotaExceeded(_) => {
+                info!("{}", self);
+                Status::PayloadTooLarge
+            }
+            Self::InvalidPoints(_) => {
+                info!("{}", self);
+                Status::BadRequest
+            }
+            Self::Conflict(_) => {
+                debug!("{}", self);
+                Status::Conflict
+            }
             _ => {
                 error!("{}", self);
                 Status::InternalServerError
@@ -59,3 +84,17 @@ fn handle_insufficient_permissions(
         )),
     }
 }
+
+async fn read_upload(file: &TempFile<'_>) -> Result<Vec<u8>, Status> {
+    let content = match file {
+        TempFile::File { .. } => match file.path() {
+            Some(path) => rocket::tokio::fs::read(path).await,
+            None => Ok(Vec::new()),
+        },
+        TempFile::Buffered { content } => Ok(content.as_bytes().to_vec()),
+    };
+    content.map_err(|e| {
+        error!("Reading uploaded file failed: {}", e);
+        Status::InternalServerError
+    })
+}
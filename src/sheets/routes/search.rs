@@ -0,0 +1,96 @@
+use rocket::http::Status;
+use rocket::serde::Serialize;
+use rocket_dyn_templates::Template;
+
+use crate::flash::FlashRedirect;
+use crate::login::guards::{AuthenticatedUser, Student, Teacher};
+use crate::status::ToStatus;
+use crate::Db;
+
+use super::handle_insufficient_permissions;
+use super::logic;
+use super::logic::search::{Scope, SearchResults};
+use super::transport::{SearchForm, SearchScope};
+
+#[derive(Serialize)]
+struct SearchContext<'a> {
+    query: String,
+    scope: SearchScope,
+    trashed: bool,
+    results: Option<SearchResults>,
+    user: &'a AuthenticatedUser,
+}
+
+fn normalize_query(form: &SearchForm) -> Option<String> {
+    form.q
+        .as_deref()
+        .map(str::trim)
+        .filter(|q| !q.is_empty())
+        .map(String::from)
+}
+
+#[get("/search?<form..>")]
+pub async fn search_teacher(
+    db: Db,
+    teacher: Teacher<'_>,
+    form: SearchForm,
+) -> Result<Template, Status> {
+    let user = teacher.into_inner();
+    let scope = form.scope.unwrap_or(SearchScope::All);
+    let results = match normalize_query(&form) {
+        Some(query) => Some(
+            logic::search::search_teacher(
+                &db,
+                user.user_info.id,
+                query,
+                Scope::from(scope),
+                form.trashed,
+            )
+            .await
+            .map_err(|e| e.to_status())?,
+        ),
+        None => None,
+    };
+    Ok(Template::render(
+        "management/search",
+        &SearchContext {
+            query: form.q.unwrap_or_default(),
+            scope,
+            trashed: form.trashed,
+            results,
+            user,
+        },
+    ))
+}
+
+#[get("/search?<form..>", rank = 2)]
+pub async fn search_student(
+    db: Db,
+    student: Student<'_>,
+    form: SearchForm,
+) -> Result<Template, Status> {
+    let user = student.into_inner();
+    let results = match normalize_query(&form) {
+        Some(query) => Some(
+            logic::search::search_student(&db, user.user_info.id, query, form.trashed)
+                .await
+                .map_err(|e| e.to_status())?,
+        ),
+        None => None,
+    };
+    Ok(Template::render(
+        "management/search",
+        &SearchContext {
+            query: form.q.unwrap_or_default(),
+            scope: SearchScope::All,
+            trashed: form.trashed,
+            results,
+            user,
+        },
+    ))
+}
+
+#[get("/search", rank = 3)]
+pub fn login_search(user: Option<&AuthenticatedUser>) -> Result<FlashRedirect, Status> {
+    handle_insufficient_permissions(user)
+}
@@ -0,0 +1,66 @@
+use rocket::form::Form;
+use rocket::http::Status;
+
+use crate::flash::FlashRedirect;
+use crate::login::guards::Teacher;
+use crate::status::ToStatus;
+use crate::Db;
+
+use super::logic;
+use super::logic::Id;
+use super::sheet;
+use super::sheets_uri;
+use super::transport::ShareSheetForm;
+
+#[post("/<id>/shares", data = "<form>")]
+pub async fn share_sheet(
+    db: Db,
+    teacher: Teacher<'_>,
+    id: Id,
+    form: Form<ShareSheetForm>,
+) -> Result<FlashRedirect, Status> {
+    let user = teacher.into_inner();
+    let form = form.into_inner();
+    let redirect_uri = sheets_uri(uri!(sheet::edit_sheet(id)));
+    match logic::sharing::share_sheet(
+        &db,
+        user.user_info.id,
+        id,
+        form.username.clone(),
+        form.permission.into(),
+    )
+    .await
+    {
+        Ok(()) => Ok(FlashRedirect::with_flash(
+            redirect_uri,
+            "success",
+            format!("Dokument für {} freigegeben", form.username),
+        )),
+        Err(logic::Error::UnknownUser(_)) => Ok(FlashRedirect::with_flash(
+            redirect_uri,
+            "danger",
+            format!("Keine Lehrkraft mit Nutzername {} gefunden", form.username),
+        )),
+        Err(e) => Err(e.to_status()),
+    }
+}
+
+#[delete("/<id>/shares/<user_id>")]
+pub async fn unshare_sheet(
+    db: Db,
+    teacher: Teacher<'_>,
+    id: Id,
+    user_id: i32,
+) -> Result<FlashRedirect, Status> {
+    let user = teacher.into_inner();
+    logic::sharing::unshare_sheet(&db, user.user_info.id, id, user_id)
+        .await
+        .map_err(|e| e.to_status())
+        .map(|_| {
+            FlashRedirect::with_flash(
+                sheets_uri(uri!(sheet::edit_sheet(id))),
+                "success",
+                "Freigabe entfernt",
+            )
+        })
+}
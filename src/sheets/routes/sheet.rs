@@ -1,10 +1,13 @@
 use rocket::form::Form;
-use rocket::http::Status;
+use rocket::http::{ContentType, Status};
+use rocket::request::FlashMessage;
 use rocket::response::Redirect;
 use rocket::serde::json::Json;
 use rocket::serde::Serialize;
+use rocket::State;
 use rocket_dyn_templates::Template;
 
+use crate::download::Download;
 use crate::flash::{FlashContext, FlashRedirect};
 use crate::login::guards::{AuthenticatedUser, Student, Teacher};
 use crate::status::ToStatus;
@@ -12,29 +15,47 @@ use crate::validation::Validate;
 use crate::Db;
 
 use super::logic;
+use super::logic::interchange::{ImportOutcome, SheetExport};
+use super::logic::media::{MediaConfig, MediaUsage};
+use super::logic::sharing::Share;
 use super::logic::sheet::{Sheet, SheetMetadata};
 use super::logic::solution::SolutionMetadata;
 use super::logic::Id;
 use super::sheet_tree;
-use super::transport::{ImportSheetForm, NewSheetForm, SheetTransport};
-use super::{handle_insufficient_permissions, sheets_uri};
+use super::transport::{ImportSheetForm, NewSheetForm, ScormVersion, SheetTransport};
+use super::{handle_insufficient_permissions, read_upload, sheets_uri};
 
 #[derive(Serialize)]
 struct SheetContext<'a> {
     sheet: Sheet,
+    rendered: String,
     user: Option<&'a AuthenticatedUser>,
 }
 
+#[derive(Serialize)]
+struct EditSheetContext<'a> {
+    flash: Option<FlashContext>,
+    sheet: Sheet,
+    can_share: bool,
+    shares: Vec<Share>,
+    user: &'a AuthenticatedUser,
+}
+
 #[derive(Serialize)]
 struct SheetOverviewContext<'a> {
     flash: Option<FlashContext>,
     sheets: Vec<SheetMetadata>,
     solutions: Vec<SolutionMetadata>,
+    media_usage: Option<MediaUsage>,
     user: &'a AuthenticatedUser,
 }
 
 #[get("/")]
-pub async fn sheet_overview_teacher(db: Db, teacher: Teacher<'_>) -> Result<Template, Status> {
+pub async fn sheet_overview_teacher(
+    db: Db,
+    config: &State<MediaConfig>,
+    teacher: Teacher<'_>,
+) -> Result<Template, Status> {
     let user = teacher.into_inner();
     let user_id = user.user_info.id;
     let recent_sheets = logic::sheet::get_recent(&db, user_id)
@@ -43,12 +64,16 @@ pub async fn sheet_overview_teacher(db: Db, teacher: Teacher<'_>) -> Result<Temp
     let recent_solutions = logic::solution::get_solutions_teacher(&db, user_id)
         .await
         .map_err(|e| e.to_status())?;
+    let media_usage = logic::media::get_usage(&db, config, user_id)
+        .await
+        .map_err(|e| e.to_status())?;
     Ok(Template::render(
         "management/overview/teacher",
         &SheetOverviewContext {
             flash: None,
             sheets: recent_sheets,
             solutions: recent_solutions,
+            media_usage: Some(media_usage),
             user,
         },
     ))
@@ -70,6 +95,7 @@ pub async fn sheet_overview_student(db: Db, student: Student<'_>) -> Result<Temp
             flash: None,
             sheets: updated_sheets,
             solutions: recent_solutions,
+            media_usage: None,
             user,
         },
     ))
@@ -88,7 +114,7 @@ pub async fn new_sheet(
 ) -> Result<Redirect, Status> {
     let user = teacher.into_inner();
     let form = form.into_inner();
-    logic::sheet::create_empty_sheet(&db, user.user_info.id, form.title)
+    logic::sheet::create_empty_sheet(&db, user.user_info.id, form.title, form.folder)
         .await
         .map_err(|e| e.to_status())
         .map(|id| Redirect::to(sheets_uri(uri!(edit_sheet(id)))))
@@ -98,36 +124,69 @@ pub async fn new_sheet(
 pub async fn import_sheet(
     db: Db,
     teacher: Teacher<'_>,
-    form: Form<ImportSheetForm>,
+    form: Form<ImportSheetForm<'_>>,
 ) -> Result<FlashRedirect, Status> {
     let user = teacher.into_inner();
     let form = form.into_inner();
-    match parse_sheet(&form.file) {
-        Ok(sheet) => logic::sheet::create_sheet(&db, user.user_info.id, sheet.title, sheet.content)
-            .await
-            .map_err(|e| e.to_status())
-            .map(|id| FlashRedirect::no_flash(sheets_uri(uri!(edit_sheet(id))))),
-        Err(redirect) => Ok(redirect),
-    }
-}
-
-fn parse_sheet(sheet: &str) -> Result<SheetTransport, FlashRedirect> {
-    let get_error_redirect = || {
-        FlashRedirect::with_flash(
+    let file = read_upload(&form.file).await?;
+    match parse_sheet(&file, form.file.name().unwrap_or_default()) {
+        Some((sheet, unsupported)) => {
+            logic::interchange::import_sheet(&db, user.user_info.id, sheet, form.folder)
+                .await
+                .map_err(|e| e.to_status())
+                .map(|outcome| match outcome {
+                    ImportOutcome::Imported { id } if !unsupported.is_empty() => {
+                        FlashRedirect::with_flash(
+                            sheets_uri(uri!(edit_sheet(id))),
+                            "warning",
+                            format!(
+                                "Nicht alle Inhalte konnten übernommen werden: {}",
+                                unsupported.join("; ")
+                            ),
+                        )
+                    }
+                    ImportOutcome::Imported { id } => {
+                        FlashRedirect::no_flash(sheets_uri(uri!(edit_sheet(id))))
+                    }
+                    ImportOutcome::Duplicate { id } => FlashRedirect::with_flash(
+                        sheets_uri(uri!(edit_sheet(id))),
+                        "warning",
+                        "Dieses Dokument wurde bereits importiert",
+                    ),
+                })
+        }
+        None => Ok(FlashRedirect::with_flash(
             sheets_uri(uri!(sheet_tree::assignment_overview)),
             "danger",
             "Invalides Dateiformat",
-        )
+        )),
+    }
+}
+
+/// Sheet exports are JSON objects, Moodle question banks XML documents and QTI packages ZIP
+/// archives, everything else is read as Markdown. Returns the content that could not be
+/// converted alongside the sheet, or `None` if the file cannot be read at all.
+fn parse_sheet(file: &[u8], name: &str) -> Option<(SheetExport, Vec<String>)> {
+    if file.starts_with(b"PK\x03\x04") {
+        return logic::interchange::import_qti(file, name)
+            .map_err(|e| error!("Import of QTI package failed: {}", e))
+            .ok();
+    }
+    let sheet = std::str::from_utf8(file)
+        .map_err(|e| error!("Decoding of sheet failed: {}", e))
+        .ok()?;
+    let sheet = sheet.trim_start_matches('\u{feff}');
+    let start = sheet.trim_start();
+    let sheet = if start.starts_with('{') {
+        logic::interchange::import(sheet).map(|sheet| (sheet, Vec::new()))
+    } else if start.starts_with('<') {
+        logic::interchange::import_moodle(start, name)
+    } else {
+        logic::interchange::import_markdown(sheet, name).map(|sheet| (sheet, Vec::new()))
     };
-    let sheet: SheetTransport = serde_json::from_str(sheet).map_err(|e| {
-        error!("JSON deserialization of sheet failed: {}", e);
-        get_error_redirect()
-    })?;
-    sheet.validate().map_err(|e| {
-        error!("Sheet validation failed: {}", e);
-        get_error_redirect()
-    })?;
-    Ok(sheet)
+    sheet
+        .map_err(|e| error!("Import of sheet failed: {}", e))
+        .ok()
 }
 
 #[get("/<id>")]
@@ -139,26 +198,194 @@ pub async fn view_sheet(
     logic::sheet::get_sheet(&db, id)
         .await
         .map_err(|e| e.to_status())
-        .map(|sheet| Template::render("sheet/view_sheet", &SheetContext { sheet, user }))
+        .map(|mut sheet| {
+            logic::sheet::redact_solutions(&mut sheet);
+            let rendered = logic::sheet::render_content(&sheet);
+            Template::render(
+                "sheet/view_sheet",
+                &SheetContext {
+                    sheet,
+                    rendered,
+                    user,
+                },
+            )
+        })
 }
 
-#[get("/<id>/edit")]
-pub async fn edit_sheet(db: Db, teacher: Teacher<'_>, id: Id) -> Result<Template, Status> {
+#[get("/<id>/pdf")]
+pub async fn worksheet_pdf(db: Db, id: Id) -> Result<Download, Status> {
+    logic::pdf::worksheet(&db, id)
+        .await
+        .map_err(|e| e.to_status())
+        .map(|pdf| Download::new(format!("{}.pdf", pdf.title), ContentType::PDF, pdf.content))
+}
+
+#[get("/<id>/pdf/solution")]
+pub async fn answer_key_pdf(db: Db, teacher: Teacher<'_>, id: Id) -> Result<Download, Status> {
     let user = teacher.into_inner();
-    logic::sheet::get_sheet_for_edit(&db, user.user_info.id, id)
+    logic::pdf::answer_key(&db, user.user_info.id, id)
         .await
         .map_err(|e| e.to_status())
-        .map(|sheet| {
-            Template::render(
-                "sheet/edit_sheet",
-                &SheetContext {
-                    sheet,
-                    user: Some(user),
-                },
+        .map(|pdf| {
+            Download::new(
+                format!("{} - Lösung.pdf", pdf.title),
+                ContentType::PDF,
+                pdf.content,
+            )
+        })
+}
+
+#[get("/<_id>/pdf/solution", rank = 2)]
+pub fn login_answer_key_pdf(
+    user: Option<&AuthenticatedUser>,
+    _id: Id,
+) -> Result<FlashRedirect, Status> {
+    handle_insufficient_permissions(user)
+}
+
+#[get("/<id>/export")]
+pub async fn export_sheet(db: Db, teacher: Teacher<'_>, id: Id) -> Result<Download, Status> {
+    let user = teacher.into_inner();
+    let export = logic::interchange::export_sheet(&db, user.user_info.id, id)
+        .await
+        .map_err(|e| e.to_status())?;
+    let body = serde_json::to_vec_pretty(&export).map_err(|e| {
+        error!("JSON serialization of sheet export failed: {}", e);
+        Status::InternalServerError
+    })?;
+    Ok(Download::new(
+        format!("{}.json", export.title),
+        ContentType::JSON,
+        body,
+    ))
+}
+
+#[get("/<id>/export/markdown")]
+pub async fn export_markdown(db: Db, teacher: Teacher<'_>, id: Id) -> Result<Download, Status> {
+    let user = teacher.into_inner();
+    logic::interchange::export_markdown(&db, user.user_info.id, id)
+        .await
+        .map_err(|e| e.to_status())
+        .map(|(title, markdown)| {
+            Download::new(
+                format!("{}.md", title),
+                ContentType::new("text", "markdown"),
+                markdown.into_bytes(),
             )
         })
 }
 
+#[get("/<_id>/export/markdown", rank = 2)]
+pub fn login_export_markdown(
+    user: Option<&AuthenticatedUser>,
+    _id: Id,
+) -> Result<FlashRedirect, Status> {
+    handle_insufficient_permissions(user)
+}
+
+#[get("/<id>/export/moodle")]
+pub async fn export_moodle(db: Db, teacher: Teacher<'_>, id: Id) -> Result<Download, Status> {
+    let user = teacher.into_inner();
+    logic::interchange::export_moodle(&db, user.user_info.id, id)
+        .await
+        .map_err(|e| e.to_status())
+        .map(|(title, xml)| {
+            Download::new(format!("{}.xml", title), ContentType::XML, xml.into_bytes())
+        })
+}
+
+#[get("/<_id>/export/moodle", rank = 2)]
+pub fn login_export_moodle(
+    user: Option<&AuthenticatedUser>,
+    _id: Id,
+) -> Result<FlashRedirect, Status> {
+    handle_insufficient_permissions(user)
+}
+
+#[get("/<id>/export/qti")]
+pub async fn export_qti(db: Db, teacher: Teacher<'_>, id: Id) -> Result<Download, Status> {
+    let user = teacher.into_inner();
+    logic::interchange::export_qti(&db, user.user_info.id, id)
+        .await
+        .map_err(|e| e.to_status())
+        .map(|(title, package)| {
+            Download::new(format!("{} - QTI.zip", title), ContentType::ZIP, package)
+        })
+}
+
+#[get("/<_id>/export/qti", rank = 2)]
+pub fn login_export_qti(
+    user: Option<&AuthenticatedUser>,
+    _id: Id,
+) -> Result<FlashRedirect, Status> {
+    handle_insufficient_permissions(user)
+}
+
+#[get("/<id>/export/scorm?<version>")]
+pub async fn export_scorm(
+    db: Db,
+    teacher: Teacher<'_>,
+    id: Id,
+    version: ScormVersion,
+) -> Result<Download, Status> {
+    let user = teacher.into_inner();
+    logic::scorm::export_scorm(&db, user.user_info.id, id, version.into())
+        .await
+        .map_err(|e| e.to_status())
+        .map(|(title, package)| {
+            Download::new(format!("{} - SCORM.zip", title), ContentType::ZIP, package)
+        })
+}
+
+#[get("/<_id>/export/scorm?<_version>", rank = 2)]
+pub fn login_export_scorm(
+    user: Option<&AuthenticatedUser>,
+    _id: Id,
+    _version: ScormVersion,
+) -> Result<FlashRedirect, Status> {
+    handle_insufficient_permissions(user)
+}
+
+#[get("/<_id>/export", rank = 2)]
+pub fn login_export_sheet(
+    user: Option<&AuthenticatedUser>,
+    _id: Id,
+) -> Result<FlashRedirect, Status> {
+    handle_insufficient_permissions(user)
+}
+
+#[get("/<id>/edit")]
+pub async fn edit_sheet(
+    db: Db,
+    teacher: Teacher<'_>,
+    flash: Option<FlashMessage<'_>>,
+    id: Id,
+) -> Result<Template, Status> {
+    let user = teacher.into_inner();
+    let user_id = user.user_info.id;
+    let sheet = logic::sheet::get_sheet_for_edit(&db, user_id, id)
+        .await
+        .map_err(|e| e.to_status())?;
+    let can_share = sheet.metadata.owner.id == user_id;
+    let shares = if can_share {
+        logic::sharing::get_shares(&db, user_id, id)
+            .await
+            .map_err(|e| e.to_status())?
+    } else {
+        Vec::new()
+    };
+    Ok(Template::render(
+        "sheet/edit_sheet",
+        &EditSheetContext {
+            flash: flash.map(|f| f.into()),
+            sheet,
+            can_share,
+            shares,
+            user,
+        },
+    ))
+}
+
 #[get("/<_id>/edit", rank = 2)]
 pub fn login_edit_sheet(
     user: Option<&AuthenticatedUser>,
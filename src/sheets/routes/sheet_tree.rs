@@ -8,8 +8,10 @@ use crate::login::guards::{AuthenticatedUser, Teacher};
 use crate::status::ToStatus;
 use crate::Db;
 
+use super::logic::folder::SheetTree;
+use super::logic::sharing::SharedSheet;
 use super::logic::sheet::SheetMetadata;
-use super::{handle_insufficient_permissions, logic};
+use super::{handle_insufficient_permissions, logic, sheets_uri};
 
 #[derive(Serialize)]
 struct SheetManagementContext<'a> {
@@ -18,28 +20,59 @@ struct SheetManagementContext<'a> {
     user: &'a AuthenticatedUser,
 }
 
-#[get("/assignments")]
-pub async fn assignment_overview(
-    db: Db,
-    teacher: Teacher<'_>,
+#[derive(Serialize)]
+struct SheetTreeContext<'a> {
+    flash: Option<FlashContext>,
+    #[serde(flatten)]
+    tree: SheetTree,
+    user: &'a AuthenticatedUser,
+}
+
+#[derive(Serialize)]
+struct SharedSheetsContext<'a> {
+    flash: Option<FlashContext>,
+    sheets: Vec<SharedSheet>,
+    user: &'a AuthenticatedUser,
+}
+
+async fn render_sheet_tree(
+    db: &Db,
+    user: &AuthenticatedUser,
     flash: Option<FlashMessage<'_>>,
+    folder_id: Option<i32>,
+    tag: Option<String>,
 ) -> Result<Template, Status> {
-    let user = teacher.into_inner();
-    logic::sheet::get_all_sheets(&db, user.user_info.id)
+    logic::folder::get_sheet_tree(db, user.user_info.id, folder_id, tag)
         .await
         .map_err(|e| e.to_status())
-        .map(|sheets| {
+        .map(|tree| {
             Template::render(
                 "management/sheet/my_sheets",
-                &SheetManagementContext {
+                &SheetTreeContext {
                     flash: flash.map(|f| f.into()),
-                    sheets,
+                    tree,
                     user,
                 },
             )
         })
 }
 
+pub fn folder_uri(folder_id: Option<i32>) -> String {
+    match folder_id {
+        Some(folder_id) => sheets_uri(uri!(folder_overview(folder_id))),
+        None => sheets_uri(uri!(assignment_overview)),
+    }
+}
+
+#[get("/assignments")]
+pub async fn assignment_overview(
+    db: Db,
+    teacher: Teacher<'_>,
+    flash: Option<FlashMessage<'_>>,
+) -> Result<Template, Status> {
+    render_sheet_tree(&db, teacher.into_inner(), flash, None, None).await
+}
+
 #[get("/assignments", rank = 2)]
 pub fn login_assignment_overview(
     user: Option<&AuthenticatedUser>,
@@ -47,6 +80,42 @@ pub fn login_assignment_overview(
     handle_insufficient_permissions(user)
 }
 
+#[get("/assignments/folders/<folder_id>")]
+pub async fn folder_overview(
+    db: Db,
+    teacher: Teacher<'_>,
+    flash: Option<FlashMessage<'_>>,
+    folder_id: i32,
+) -> Result<Template, Status> {
+    render_sheet_tree(&db, teacher.into_inner(), flash, Some(folder_id), None).await
+}
+
+#[get("/assignments/folders/<_folder_id>", rank = 2)]
+pub fn login_folder_overview(
+    user: Option<&AuthenticatedUser>,
+    _folder_id: i32,
+) -> Result<FlashRedirect, Status> {
+    handle_insufficient_permissions(user)
+}
+
+#[get("/assignments/tags/<tag>")]
+pub async fn tag_overview(
+    db: Db,
+    teacher: Teacher<'_>,
+    flash: Option<FlashMessage<'_>>,
+    tag: String,
+) -> Result<Template, Status> {
+    render_sheet_tree(&db, teacher.into_inner(), flash, None, Some(tag)).await
+}
+
+#[get("/assignments/tags/<_tag>", rank = 2)]
+pub fn login_tag_overview(
+    user: Option<&AuthenticatedUser>,
+    _tag: String,
+) -> Result<FlashRedirect, Status> {
+    handle_insufficient_permissions(user)
+}
+
 #[get("/assignments/trash")]
 pub async fn trashed_sheets(db: Db, teacher: Teacher<'_>) -> Result<Template, Status> {
     let user = teacher.into_inner();
@@ -92,3 +161,26 @@ pub async fn recent_sheets(db: Db, teacher: Teacher<'_>) -> Result<Template, Sta
 pub fn login_recent_sheets(user: Option<&AuthenticatedUser>) -> Result<FlashRedirect, Status> {
     handle_insufficient_permissions(user)
 }
+
+#[get("/assignments/shared")]
+pub async fn shared_sheets(db: Db, teacher: Teacher<'_>) -> Result<Template, Status> {
+    let user = teacher.into_inner();
+    logic::sharing::get_shared_sheets(&db, user.user_info.id)
+        .await
+        .map_err(|e| e.to_status())
+        .map(|sheets| {
+            Template::render(
+                "management/sheet/shared_sheets",
+                &SharedSheetsContext {
+                    flash: None,
+                    sheets,
+                    user,
+                },
+            )
+        })
+}
+
+#[get("/assignments/shared", rank = 2)]
+pub fn login_shared_sheets(user: Option<&AuthenticatedUser>) -> Result<FlashRedirect, Status> {
+    handle_insufficient_permissions(user)
+}
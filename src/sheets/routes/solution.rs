@@ -1,19 +1,21 @@
-use rocket::http::Status;
+use rocket::form::Form;
+use rocket::http::{ContentType, Status};
 use rocket::request::FlashMessage;
 use rocket::response::Redirect;
 use rocket::serde::json::Json;
 use rocket::serde::Serialize;
 use rocket_dyn_templates::Template;
 
+use crate::download::Download;
 use crate::flash::{FlashContext, FlashRedirect};
 use crate::login::guards::{AuthenticatedUser, Student, Teacher};
 use crate::status::ToStatus;
 use crate::Db;
 
 use super::logic;
-use super::logic::solution::{Solution, SolutionMetadata};
+use super::logic::solution::{GradingTask, Solution, SolutionMetadata};
 use super::logic::Id;
-use super::transport::SolutionTransport;
+use super::transport::{FeedbackForm, GradeEssayForm, SolutionExportFormat, SolutionTransport};
 use super::{handle_insufficient_permissions, sheets_uri};
 
 #[derive(Serialize)]
@@ -30,6 +32,13 @@ struct SolutionManagementContext<'a> {
     user: &'a AuthenticatedUser,
 }
 
+#[derive(Serialize)]
+struct GradingQueueContext<'a> {
+    flash: Option<FlashContext>,
+    queue: Vec<GradingTask>,
+    user: &'a AuthenticatedUser,
+}
+
 #[get("/solutions")]
 pub async fn solution_overview(db: Db, teacher: Teacher<'_>) -> Result<Template, Status> {
     let user = teacher.into_inner();
@@ -54,6 +63,33 @@ pub fn login_solution_overview(user: Option<&AuthenticatedUser>) -> Result<Flash
     handle_insufficient_permissions(user)
 }
 
+#[get("/solutions/grading")]
+pub async fn grading_queue(
+    db: Db,
+    teacher: Teacher<'_>,
+    flash: Option<FlashMessage<'_>>,
+) -> Result<Template, Status> {
+    let user = teacher.into_inner();
+    logic::solution::get_grading_queue(&db, user.user_info.id)
+        .await
+        .map_err(|e| e.to_status())
+        .map(|queue| {
+            Template::render(
+                "management/solution/grading_queue",
+                &GradingQueueContext {
+                    flash: flash.map(|f| f.into()),
+                    queue,
+                    user,
+                },
+            )
+        })
+}
+
+#[get("/solutions/grading", rank = 2)]
+pub fn login_grading_queue(user: Option<&AuthenticatedUser>) -> Result<FlashRedirect, Status> {
+    handle_insufficient_permissions(user)
+}
+
 #[get("/solutions/my")]
 pub async fn my_solution_overview(db: Db, student: Student<'_>) -> Result<Template, Status> {
     let user = student.into_inner();
@@ -163,6 +199,48 @@ pub fn login_sheet_solutions(
     handle_insufficient_permissions(user)
 }
 
+#[get("/<sheet_id>/solutions/pdf?<format>")]
+pub async fn sheet_solutions_pdf(
+    db: Db,
+    teacher: Teacher<'_>,
+    sheet_id: Id,
+    format: SolutionExportFormat,
+) -> Result<Download, Status> {
+    let user = teacher.into_inner();
+    match format {
+        SolutionExportFormat::Pdf => logic::pdf::sheet_solutions(&db, user.user_info.id, sheet_id)
+            .await
+            .map(|pdf| {
+                Download::new(
+                    format!("{} - Lösungen.pdf", pdf.title),
+                    ContentType::PDF,
+                    pdf.content,
+                )
+            }),
+        SolutionExportFormat::Zip => {
+            logic::pdf::sheet_solutions_archive(&db, user.user_info.id, sheet_id)
+                .await
+                .map(|archive| {
+                    Download::new(
+                        format!("{} - Lösungen.zip", archive.title),
+                        ContentType::ZIP,
+                        archive.content,
+                    )
+                })
+        }
+    }
+    .map_err(|e| e.to_status())
+}
+
+#[get("/<_id>/solutions/pdf?<_format>", rank = 2)]
+pub fn login_sheet_solutions_pdf(
+    user: Option<&AuthenticatedUser>,
+    _id: Id,
+    _format: &str,
+) -> Result<FlashRedirect, Status> {
+    handle_insufficient_permissions(user)
+}
+
 #[post("/<sheet_id>/solve")]
 pub async fn start_solve(db: Db, student: Student<'_>, sheet_id: Id) -> Result<Redirect, Status> {
     let user = student.into_inner();
@@ -216,7 +294,8 @@ pub async fn latest_solution(
     logic::solution::get_latest_solution(&db, sheet_id, user.user_info.id)
         .await
         .map_err(|e| e.to_status())
-        .map(|solution| {
+        .map(|mut solution| {
+            logic::solution::redact_for_student(&mut solution);
             Template::render(
                 "sheet/solution/my_solution",
                 &SolutionContext { solution, user },
@@ -243,7 +322,8 @@ pub async fn my_solution(
     logic::solution::get_my_solution(&db, user.user_info.id, sheet_id, solution_id)
         .await
         .map_err(|e| e.to_status())
-        .map(|solution| {
+        .map(|mut solution| {
+            logic::solution::redact_for_student(&mut solution);
             Template::render(
                 "sheet/solution/my_solution",
                 &SolutionContext { solution, user },
@@ -260,6 +340,29 @@ pub fn login_my_solution(
     handle_insufficient_permissions(user)
 }
 
+#[get("/<sheet_id>/solutions/my/<solution_id>/pdf")]
+pub async fn my_solution_pdf(
+    db: Db,
+    student: Student<'_>,
+    sheet_id: Id,
+    solution_id: i32,
+) -> Result<Download, Status> {
+    let user = student.into_inner();
+    logic::pdf::my_solution(&db, user.user_info.id, sheet_id, solution_id)
+        .await
+        .map_err(|e| e.to_status())
+        .map(|pdf| Download::new(format!("{}.pdf", pdf.title), ContentType::PDF, pdf.content))
+}
+
+#[get("/<_sheet_id>/solutions/my/<_solution_id>/pdf", rank = 2)]
+pub fn login_my_solution_pdf(
+    user: Option<&AuthenticatedUser>,
+    _sheet_id: Id,
+    _solution_id: i32,
+) -> Result<FlashRedirect, Status> {
+    handle_insufficient_permissions(user)
+}
+
 #[put(
     "/<sheet_id>/solutions/my/<solution_id>",
     format = "json",
@@ -285,6 +388,32 @@ pub async fn save_solution(
     .map_err(|e| e.to_status())
 }
 
+#[post(
+    "/<sheet_id>/solutions/my/<solution_id>/check",
+    format = "json",
+    data = "<solution>"
+)]
+pub async fn check_solution(
+    db: Db,
+    student: Student<'_>,
+    sheet_id: Id,
+    solution_id: i32,
+    solution: Json<SolutionTransport>,
+) -> Result<Json<Vec<f64>>, Status> {
+    let user = student.into_inner();
+    let solution = solution.into_inner();
+    logic::solution::check_dropdowns(
+        &db,
+        user.user_info.id,
+        sheet_id,
+        solution_id,
+        solution.content,
+    )
+    .await
+    .map_err(|e| e.to_status())
+    .map(Json)
+}
+
 #[delete("/<sheet_id>/solutions/my/<solution_id>")]
 pub async fn delete_solution(
     db: Db,
@@ -316,6 +445,72 @@ pub async fn restore_solution(
         .map(|_| Redirect::to(sheets_uri(uri!(my_solution_overview))))
 }
 
+#[post(
+    "/<sheet_id>/solutions/<student_id>/<solution_id>/grade",
+    data = "<form>"
+)]
+pub async fn grade_essay(
+    db: Db,
+    teacher: Teacher<'_>,
+    sheet_id: Id,
+    student_id: i32,
+    solution_id: i32,
+    form: Form<GradeEssayForm>,
+) -> Result<FlashRedirect, Status> {
+    let user = teacher.into_inner();
+    logic::solution::grade_essay(
+        &db,
+        user.user_info.id,
+        sheet_id,
+        student_id,
+        solution_id,
+        form.essay,
+        form.points,
+    )
+    .await
+    .map_err(|e| e.to_status())
+    .map(|_| {
+        FlashRedirect::with_flash(
+            sheets_uri(uri!(grading_queue)),
+            "success",
+            "Bewertung gespeichert",
+        )
+    })
+}
+
+#[post(
+    "/<sheet_id>/solutions/<student_id>/<solution_id>/feedback",
+    data = "<form>"
+)]
+pub async fn give_feedback(
+    db: Db,
+    teacher: Teacher<'_>,
+    sheet_id: Id,
+    student_id: i32,
+    solution_id: i32,
+    form: Form<FeedbackForm>,
+) -> Result<FlashRedirect, Status> {
+    let user = teacher.into_inner();
+    let form = form.into_inner();
+    logic::solution::give_feedback(
+        &db,
+        user.user_info.id,
+        sheet_id,
+        student_id,
+        solution_id,
+        form.feedback,
+    )
+    .await
+    .map_err(|e| e.to_status())
+    .map(|_| {
+        FlashRedirect::with_flash(
+            sheets_uri(uri!(student_solution(sheet_id, student_id, solution_id))),
+            "success",
+            "Rückmeldung gespeichert",
+        )
+    })
+}
+
 #[get("/<sheet_id>/solutions/<student_id>/latest", rank = 5)]
 pub async fn latest_student_solution(
     db: Db,
@@ -379,3 +574,28 @@ pub fn login_student_solution(
 ) -> Result<FlashRedirect, Status> {
     handle_insufficient_permissions(user)
 }
+
+#[get("/<sheet_id>/solutions/<student_id>/<solution_id>/pdf", rank = 3)]
+pub async fn student_solution_pdf(
+    db: Db,
+    teacher: Teacher<'_>,
+    sheet_id: Id,
+    student_id: i32,
+    solution_id: i32,
+) -> Result<Download, Status> {
+    let user = teacher.into_inner();
+    logic::pdf::student_solution(&db, user.user_info.id, sheet_id, student_id, solution_id)
+        .await
+        .map_err(|e| e.to_status())
+        .map(|pdf| Download::new(format!("{}.pdf", pdf.title), ContentType::PDF, pdf.content))
+}
+
+#[get("/<_sheet_id>/solutions/<_student_id>/<_solution_id>/pdf", rank = 4)]
+pub fn login_student_solution_pdf(
+    user: Option<&AuthenticatedUser>,
+    _sheet_id: Id,
+    _student_id: i32,
+    _solution_id: i32,
+) -> Result<FlashRedirect, Status> {
+    handle_insufficient_permissions(user)
+}
@@ -0,0 +1,182 @@
+use rocket::form::Form;
+use rocket::http::Status;
+use rocket::request::FlashMessage;
+use rocket::serde::Serialize;
+use rocket_dyn_templates::Template;
+
+use crate::flash::{FlashContext, FlashRedirect};
+use crate::login::guards::{Admin, AuthenticatedUser, Teacher};
+use crate::status::ToStatus;
+use crate::Db;
+
+use super::logic;
+use super::logic::Id;
+use super::sheet_tree;
+use super::transport::{TransferAllSheetsForm, TransferSheetAdminForm, TransferSheetForm};
+use super::{handle_insufficient_permissions, sheets_uri};
+
+#[derive(Serialize)]
+struct TransferContext<'a> {
+    flash: Option<FlashContext>,
+    user: &'a AuthenticatedUser,
+}
+
+fn unknown_user_flash(redirect_uri: String, username: &str) -> FlashRedirect {
+    FlashRedirect::with_flash(
+        redirect_uri,
+        "danger",
+        format!("Keine Lehrkraft mit Nutzername {} gefunden", username),
+    )
+}
+
+#[post("/<id>/transfer", data = "<form>")]
+pub async fn transfer_sheet(
+    db: Db,
+    teacher: Teacher<'_>,
+    id: Id,
+    form: Form<TransferSheetForm>,
+) -> Result<FlashRedirect, Status> {
+    let user = teacher.into_inner();
+    let form = form.into_inner();
+    let redirect_uri = sheets_uri(uri!(sheet_tree::assignment_overview));
+    match logic::sheet::transfer_sheet(&db, user.user_info.id, id, form.new_owner.clone()).await {
+        Ok(new_owner) => Ok(FlashRedirect::with_flash(
+            redirect_uri,
+            "success",
+            format!("Dokument an {} übertragen", new_owner.username),
+        )),
+        Err(logic::Error::UnknownUser(_)) => Ok(unknown_user_flash(redirect_uri, &form.new_owner)),
+        Err(e) => Err(e.to_status()),
+    }
+}
+
+#[post("/assignments/transfer", data = "<form>")]
+pub async fn transfer_all_sheets(
+    db: Db,
+    teacher: Teacher<'_>,
+    form: Form<TransferSheetForm>,
+) -> Result<FlashRedirect, Status> {
+    let user = teacher.into_inner();
+    let form = form.into_inner();
+    let redirect_uri = sheets_uri(uri!(sheet_tree::assignment_overview));
+    match logic::sheet::transfer_all_sheets(&db, user.user_info.id, form.new_owner.clone()).await {
+        Ok(count) => Ok(FlashRedirect::with_flash(
+            redirect_uri,
+            "success",
+            format!("{} Dokumente an {} übertragen", count, form.new_owner),
+        )),
+        Err(logic::Error::UnknownUser(_)) => Ok(unknown_user_flash(redirect_uri, &form.new_owner)),
+        Err(e) => Err(e.to_status()),
+    }
+}
+
+#[get("/transfer")]
+pub fn transfer_overview(admin: Admin<'_>, flash: Option<FlashMessage<'_>>) -> Template {
+    Template::render(
+        "management/admin/transfer",
+        &TransferContext {
+            flash: flash.map(|f| f.into()),
+            user: admin.into_inner(),
+        },
+    )
+}
+
+#[get("/transfer", rank = 2)]
+pub fn login_transfer_overview(user: Option<&AuthenticatedUser>) -> Result<FlashRedirect, Status> {
+    handle_insufficient_permissions(user)
+}
+
+#[post("/transfer/sheet", data = "<form>")]
+pub async fn transfer_sheet_as_admin(
+    db: Db,
+    _admin: Admin<'_>,
+    form: Form<TransferSheetAdminForm>,
+) -> Result<FlashRedirect, Status> {
+    let form = form.into_inner();
+    let redirect_uri = sheets_uri(uri!(transfer_overview));
+    match logic::sheet::transfer_sheet_as_admin(&db, form.sheet_id, form.new_owner.clone()).await {
+        Ok(new_owner) => Ok(FlashRedirect::with_flash(
+            redirect_uri,
+            "success",
+            format!("Dokument an {} übertragen", new_owner.username),
+        )),
+        Err(logic::Error::UnknownUser(_)) => Ok(unknown_user_flash(redirect_uri, &form.new_owner)),
+        Err(logic::Error::NotFound(_)) => Ok(FlashRedirect::with_flash(
+            redirect_uri,
+            "danger",
+            format!("Kein Dokument mit ID {} gefunden", form.sheet_id),
+        )),
+        Err(e) => Err(e.to_status()),
+    }
+}
+
+#[post("/transfer", data = "<form>")]
+pub async fn transfer_all_sheets_as_admin(
+    db: Db,
+    _admin: Admin<'_>,
+    form: Form<TransferAllSheetsForm>,
+) -> Result<FlashRedirect, Status> {
+    let form = form.into_inner();
+    let redirect_uri = sheets_uri(uri!(transfer_overview));
+    match logic::sheet::transfer_all_sheets_as_admin(
+        &db,
+        form.previous_owner.clone(),
+        form.new_owner.clone(),
+    )
+    .await
+    {
+        Ok(count) => Ok(FlashRedirect::with_flash(
+            redirect_uri,
+            "success",
+            format!(
+                "{} Dokumente von {} an {} übertragen",
+                count, form.previous_owner, form.new_owner
+            ),
+        )),
+        Err(logic::Error::UnknownUser(username)) => Ok(unknown_user_flash(redirect_uri, &username)),
+        Err(e) => Err(e.to_status()),
+    }
+}
+
+#[post("/transfer/delete", data = "<form>")]
+pub async fn delete_teacher_as_admin(
+    db: Db,
+    _admin: Admin<'_>,
+    form: Form<TransferAllSheetsForm>,
+) -> Result<FlashRedirect, Status> {
+    let form = form.into_inner();
+    let redirect_uri = sheets_uri(uri!(transfer_overview));
+    match logic::sheet::delete_teacher_as_admin(
+        &db,
+        form.previous_owner.clone(),
+        form.new_owner.clone(),
+    )
+    .await
+    {
+        Ok(count) => Ok(FlashRedirect::with_flash(
+            redirect_uri,
+            "success",
+            format!(
+                "Konto von {} gelöscht, {} Dokumente an {} übertragen",
+                form.previous_owner, count, form.new_owner
+            ),
+        )),
+        Err(logic::Error::UnknownUser(username)) => Ok(unknown_user_flash(redirect_uri, &username)),
+        Err(logic::Error::Conflict(_)) if form.previous_owner == form.new_owner => {
+            Ok(FlashRedirect::with_flash(
+                redirect_uri,
+                "danger",
+                "Die Dokumente müssen an eine andere Lehrkraft übertragen werden",
+            ))
+        }
+        Err(logic::Error::Conflict(_)) => Ok(FlashRedirect::with_flash(
+            redirect_uri,
+            "danger",
+            format!(
+                "{} besitzt Lösungen, das Konto kann nicht gelöscht werden",
+                form.previous_owner
+            ),
+        )),
+        Err(e) => Err(e.to_status()),
+    }
+}
@@ -0,0 +1,103 @@
+//! Storage of uploaded media files, which are addressed by the hash of their content.
+
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::ops::Range;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use rocket::tokio::task;
+
+pub trait Storage: Send + Sync {
+    /// Stores the content under the key, existing content is kept as keys identify the content
+    fn store(&self, key: &str, content: &[u8]) -> io::Result<()>;
+    fn load(&self, key: &str, range: Range<u64>) -> io::Result<Vec<u8>>;
+    /// Removes the content if it exists
+    fn remove(&self, key: &str) -> io::Result<()>;
+}
+
+/// Stores files in a directory on the local disk
+pub struct LocalStorage {
+    root: PathBuf,
+}
+
+impl LocalStorage {
+    pub fn new<P: Into<PathBuf>>(root: P) -> Self {
+        Self { root: root.into() }
+    }
+
+    // Spread the files over subdirectories to keep directories small
+    fn path(&self, key: &str) -> PathBuf {
+        let prefix: String = key.chars().take(2).collect();
+        self.root.join(prefix).join(key)
+    }
+}
+
+impl Storage for LocalStorage {
+    fn store(&self, key: &str, content: &[u8]) -> io::Result<()> {
+        let path = self.path(key);
+        if path.exists() {
+            return Ok(());
+        }
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        // Write to a temporary file first so partially written files are never served
+        let partial = path.with_extension("partial");
+        fs::write(&partial, content)?;
+        fs::rename(&partial, &path)
+    }
+
+    fn load(&self, key: &str, range: Range<u64>) -> io::Result<Vec<u8>> {
+        let mut file = File::open(self.path(key))?;
+        file.seek(SeekFrom::Start(range.start))?;
+        let mut content = Vec::new();
+        file.take(range.end.saturating_sub(range.start))
+            .read_to_end(&mut content)?;
+        Ok(content)
+    }
+
+    fn remove(&self, key: &str) -> io::Result<()> {
+        match fs::remove_file(self.path(key)) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Shared handle to the configured storage, which performs the blocking file accesses on
+/// dedicated threads unless the caller already runs on one
+#[derive(Clone)]
+pub struct MediaStorage(Arc<dyn Storage>);
+
+impl MediaStorage {
+    pub fn new<S: Storage + 'static>(storage: S) -> Self {
+        Self(Arc::new(storage))
+    }
+
+    async fn blocking<T, F>(&self, f: F) -> io::Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&dyn Storage) -> io::Result<T> + Send + 'static,
+    {
+        let storage = self.0.clone();
+        task::spawn_blocking(move || f(storage.as_ref()))
+            .await
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?
+    }
+
+    pub async fn load(&self, key: String, range: Range<u64>) -> io::Result<Vec<u8>> {
+        self.blocking(move |storage| storage.load(&key, range))
+            .await
+    }
+
+    /// Stores the content on the current thread, e.g. within a database transaction
+    pub fn store_blocking(&self, key: &str, content: &[u8]) -> io::Result<()> {
+        self.0.store(key, content)
+    }
+
+    /// Removes the content on the current thread, e.g. within a database transaction
+    pub fn remove_blocking(&self, key: &str) -> io::Result<()> {
+        self.0.remove(key)
+    }
+}
@@ -1,9 +1,19 @@
 use std::fmt::{self, Display};
 
+use chrono::{DateTime, Utc};
+use rocket::fs::TempFile;
 use rocket::serde::{Deserialize, Serialize};
 
+use crate::login::transport::UserInfo;
 use crate::validation::Validate;
 
+use super::conversion::document::{InvalidTask, Node};
+use super::conversion::scorm::Version;
+use super::logic::collaboration::{CollaborationEvent, SheetUpdate};
+use super::logic::search::Scope;
+use super::logic::sharing::Permission;
+use super::logic::Id;
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct SheetTransport {
     pub title: String,
@@ -13,12 +23,68 @@ pub struct SheetTransport {
 #[derive(Debug)]
 pub enum SheetTransportValidationError {
     TitleEmpty,
+    InvalidDropdown,
+    InvalidGapAlternative,
+    InvalidNumeric,
+    InvalidFormula,
+    InvalidSingleChoice,
+    InvalidPool,
+    InvalidVariable,
+    InvalidEssay,
 }
 
 impl Display for SheetTransportValidationError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Self::TitleEmpty => write!(f, "Title cannot be empty"),
+            Self::InvalidDropdown => {
+                write!(f, "Dropdowns need a correct option among their options")
+            }
+            Self::InvalidGapAlternative => {
+                write!(
+                    f,
+                    "Alternative answers to gaps need valid regular expressions"
+                )
+            }
+            Self::InvalidNumeric => {
+                write!(f, "Numeric tasks need a number as solution")
+            }
+            Self::InvalidFormula => {
+                write!(f, "Formulas need a readable expression as solution")
+            }
+            Self::InvalidSingleChoice => {
+                write!(f, "Single choice tasks need exactly one correct answer")
+            }
+            Self::InvalidPool => {
+                write!(
+                    f,
+                    "Pools must draw at least one and at most all of their items"
+                )
+            }
+            Self::InvalidVariable => {
+                write!(f, "Variables need a valid, unique name and definition")
+            }
+            Self::InvalidEssay => {
+                write!(
+                    f,
+                    "Essays need at least one point and a word minimum not above the maximum"
+                )
+            }
+        }
+    }
+}
+
+impl From<InvalidTask> for SheetTransportValidationError {
+    fn from(e: InvalidTask) -> Self {
+        match e {
+            InvalidTask::Dropdown => Self::InvalidDropdown,
+            InvalidTask::GapAlternative => Self::InvalidGapAlternative,
+            InvalidTask::Numeric => Self::InvalidNumeric,
+            InvalidTask::Formula => Self::InvalidFormula,
+            InvalidTask::SingleChoice => Self::InvalidSingleChoice,
+            InvalidTask::Pool => Self::InvalidPool,
+            InvalidTask::Variable => Self::InvalidVariable,
+            InvalidTask::Essay => Self::InvalidEssay,
         }
     }
 }
@@ -30,7 +96,9 @@ impl Validate for SheetTransport {
         if self.title.is_empty() {
             return Err(Self::ValidationError::TitleEmpty);
         }
-        Ok(())
+        Node::from_json(&self.content)
+            .validate()
+            .map_err(SheetTransportValidationError::from)
     }
 }
 
@@ -38,14 +106,226 @@ impl Validate for SheetTransport {
 pub struct NewSheetForm {
     #[field(validate = neq(""))]
     pub title: String,
+    pub folder: Option<i32>,
+}
+
+#[derive(Debug, FromForm)]
+pub struct ImportSheetForm<'r> {
+    pub file: TempFile<'r>,
+    pub folder: Option<i32>,
+}
+
+#[derive(Debug, FromForm)]
+pub struct MediaUploadForm<'r> {
+    pub file: TempFile<'r>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MediaTransport {
+    pub id: Id,
+    pub url: String,
+    pub filename: String,
+    pub mimetype: String,
+    pub size: u64,
+}
+
+#[derive(Debug, FromForm)]
+pub struct ExportSheetsForm {
+    pub sheets: Vec<Id>,
+}
+
+#[derive(Debug, FromForm)]
+pub struct FolderForm {
+    #[field(validate = neq(""))]
+    pub name: String,
+}
+
+#[derive(Debug, FromForm)]
+pub struct NewFolderForm {
+    #[field(validate = neq(""))]
+    pub name: String,
+    pub parent: Option<i32>,
+}
+
+#[derive(Debug, FromForm)]
+pub struct MoveForm {
+    pub folder: Option<i32>,
+}
+
+#[derive(Debug, FromForm)]
+pub struct TagForm {
+    #[field(validate = neq(""))]
+    pub tag: String,
+}
+
+#[derive(Debug, FromFormField)]
+pub enum SharePermission {
+    Viewer,
+    Grader,
+    Editor,
+}
+
+impl From<SharePermission> for Permission {
+    fn from(p: SharePermission) -> Permission {
+        match p {
+            SharePermission::Viewer => Self::Viewer,
+            SharePermission::Grader => Self::Grader,
+            SharePermission::Editor => Self::Editor,
+        }
+    }
+}
+
+#[derive(Debug, FromFormField)]
+pub enum ScormVersion {
+    #[field(value = "1.2")]
+    Scorm12,
+    #[field(value = "2004")]
+    Scorm2004,
+}
+
+impl From<ScormVersion> for Version {
+    fn from(v: ScormVersion) -> Version {
+        match v {
+            ScormVersion::Scorm12 => Version::Scorm12,
+            ScormVersion::Scorm2004 => Version::Scorm2004,
+        }
+    }
+}
+
+#[derive(Debug, FromFormField)]
+pub enum SolutionExportFormat {
+    Pdf,
+    Zip,
 }
 
 #[derive(Debug, FromForm)]
-pub struct ImportSheetForm {
-    pub file: String,
+pub struct ShareSheetForm {
+    #[field(validate = neq(""))]
+    pub username: String,
+    pub permission: SharePermission,
+}
+
+#[derive(Debug, FromForm)]
+pub struct TransferSheetForm {
+    #[field(validate = neq(""))]
+    pub new_owner: String,
+}
+
+#[derive(Debug, FromForm)]
+pub struct TransferSheetAdminForm {
+    pub sheet_id: Id,
+    #[field(validate = neq(""))]
+    pub new_owner: String,
+}
+
+#[derive(Debug, FromForm)]
+pub struct TransferAllSheetsForm {
+    #[field(validate = neq(""))]
+    pub previous_owner: String,
+    #[field(validate = neq(""))]
+    pub new_owner: String,
+}
+
+#[derive(Clone, Copy, Debug, FromFormField, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchScope {
+    All,
+    Owned,
+    Shared,
+}
+
+impl From<SearchScope> for Scope {
+    fn from(s: SearchScope) -> Scope {
+        match s {
+            SearchScope::All => Self::All,
+            SearchScope::Owned => Self::Owned,
+            SearchScope::Shared => Self::Shared,
+        }
+    }
+}
+
+#[derive(Debug, FromForm, Serialize)]
+pub struct SearchForm {
+    pub q: Option<String>,
+    pub scope: Option<SearchScope>,
+    pub trashed: bool,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct SolutionTransport {
     pub content: serde_json::Value,
 }
+
+#[derive(Debug, FromForm)]
+pub struct GradeEssayForm {
+    pub essay: usize,
+    pub points: f64,
+}
+
+#[derive(Debug, FromForm)]
+pub struct FeedbackForm {
+    pub feedback: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct NewSheetUpdateTransport {
+    pub update: String,
+    pub base: i64,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SheetUpdateTransport {
+    pub id: i64,
+    pub author_id: Option<i32>,
+    pub created: DateTime<Utc>,
+    pub update: String,
+}
+
+impl From<SheetUpdate> for SheetUpdateTransport {
+    fn from(update: SheetUpdate) -> Self {
+        SheetUpdateTransport {
+            id: update.id,
+            author_id: update.author_id,
+            created: update.created,
+            update: base64::encode(update.payload),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum CollaborationEventTransport {
+    Update(SheetUpdateTransport),
+    Presence { editors: Vec<UserInfo> },
+    Resync,
+}
+
+impl From<CollaborationEvent> for CollaborationEventTransport {
+    fn from(event: CollaborationEvent) -> Self {
+        match event {
+            CollaborationEvent::Update(update) => Self::Update(update.into()),
+            CollaborationEvent::Presence(editors) => Self::Presence { editors },
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct CollaborationSnapshotTransport {
+    pub title: String,
+    pub content: serde_json::Value,
+    pub state: String,
+    pub up_to: i64,
+}
+
+impl Validate for CollaborationSnapshotTransport {
+    type ValidationError = SheetTransportValidationError;
+
+    fn validate(&self) -> Result<(), Self::ValidationError> {
+        if self.title.is_empty() {
+            return Err(Self::ValidationError::TitleEmpty);
+        }
+        Node::from_json(&self.content)
+            .validate()
+            .map_err(SheetTransportValidationError::from)
+    }
+}
@@ -0,0 +1,34 @@
+// Port of the multiple choice grading in src/sheets/conversion/grading.rs, both must agree
+
+export type ChoiceMode = "single" | "multiple";
+
+export type Scoring = "allOrNothing" | "partialCredit" | "negativeMarking";
+
+export interface Tick {
+  solution: boolean;
+  answer: boolean;
+}
+
+export function choiceCredit(
+  mode: ChoiceMode,
+  scoring: Scoring,
+  ticks: Tick[]
+): number {
+  // Only one answer can be ticked, so there is nothing to split
+  if (mode === "single") {
+    scoring = "allOrNothing";
+  }
+  const correct = ticks.filter((tick) => tick.solution).length;
+  const wrong = ticks.length - correct;
+  const tickedCorrect = ticks.filter((t) => t.solution && t.answer).length;
+  const tickedWrong = ticks.filter((t) => !t.solution && t.answer).length;
+  // Without correct answers the only right choice is to tick nothing
+  if (scoring === "allOrNothing" || correct === 0) {
+    return tickedCorrect === correct && tickedWrong === 0 ? 1 : 0;
+  }
+  const achieved = tickedCorrect / correct;
+  if (scoring === "negativeMarking" && wrong > 0) {
+    return Math.max(0, achieved - tickedWrong / wrong);
+  }
+  return achieved;
+}
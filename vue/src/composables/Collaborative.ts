@@ -0,0 +1,342 @@
+import { inject, onBeforeUnmount, onMounted, ref, Ref, watch } from "vue";
+import { Extension, JSONContent } from "@tiptap/core";
+import { Node as ProseMirrorNode } from "prosemirror-model";
+import { Plugin, PluginKey, Transaction } from "prosemirror-state";
+import { Step } from "prosemirror-transform";
+import { EditorView } from "prosemirror-view";
+import debounce from "lodash/debounce";
+
+import { SaveStatus } from "../enums";
+
+export interface CollaborationEditor {
+  id: number;
+  username: string;
+}
+
+interface UpdateJSON {
+  id: number;
+  author_id?: number;
+  created: string;
+  update: string;
+}
+
+type EventJSON =
+  | ({ type: "update" } & UpdateJSON)
+  | { type: "presence"; editors: CollaborationEditor[] }
+  | { type: "resync" };
+
+// An update either carries the steps a client made on top of the update `base`
+// or, after compaction, the document resulting from all updates it replaces
+interface SharedUpdate {
+  client: string;
+  base: number;
+  title?: string;
+  steps?: { [key: string]: unknown }[];
+  doc?: JSONContent;
+}
+
+// A local step the server has not accepted yet, with its inverse for rebasing
+interface Unconfirmed {
+  step: Step;
+  inverted: Step;
+}
+
+const collaborationKey = new PluginKey<Unconfirmed[]>("collaboration");
+
+// The accepted updates are saved to the sheet once the editor is idle
+const COMPACT_DELAY = 2000;
+
+function encode(update: SharedUpdate): string {
+  let binary = "";
+  new TextEncoder()
+    .encode(JSON.stringify(update))
+    .forEach((byte) => (binary += String.fromCharCode(byte)));
+  return btoa(binary);
+}
+
+function decode(update: string): SharedUpdate {
+  const bytes = Uint8Array.from(atob(update), (c) => c.charCodeAt(0));
+  return JSON.parse(new TextDecoder().decode(bytes));
+}
+
+// Undoes the unconfirmed steps, applies the steps of others and then redoes the
+// unconfirmed steps mapped over them, as done by prosemirror-collab
+function rebase(
+  unconfirmed: Unconfirmed[],
+  over: Step[],
+  tr: Transaction
+): Unconfirmed[] {
+  for (let i = unconfirmed.length - 1; i >= 0; i--) {
+    tr.step(unconfirmed[i].inverted);
+  }
+  over.forEach((step) => tr.step(step));
+  const rebased: Unconfirmed[] = [];
+  let mapFrom = unconfirmed.length;
+  for (const { step } of unconfirmed) {
+    const mapped = step.map(tr.mapping.slice(mapFrom));
+    mapFrom--;
+    if (mapped && !tr.maybeStep(mapped).failed) {
+      tr.mapping.setMirror(mapFrom, tr.steps.length - 1);
+      rebased.push({
+        step: mapped,
+        inverted: mapped.invert(tr.docs[tr.docs.length - 1]),
+      });
+    }
+  }
+  return rebased;
+}
+
+export function useCollaborative<T>(
+  title: Ref<string>,
+  toSheet: (content: JSONContent) => T
+) {
+  const url = inject<string>("collaborationURL", "");
+  const enabled = url !== "";
+  const client = Math.random().toString(36).substring(2);
+
+  const editors = ref<CollaborationEditor[]>([]);
+  const saveStatus = ref(enabled ? SaveStatus.SAVED : SaveStatus.DISABLED);
+
+  let view: EditorView | null = null;
+  let lastSeen = 0;
+  // Title sent with the last own update or received with one of others
+  let knownTitle = title.value;
+  let sending = false;
+  let fetching: Promise<void> | null = null;
+  let backoff = 0;
+
+  function unconfirmed(): Unconfirmed[] {
+    return view === null ? [] : collaborationKey.getState(view.state) ?? [];
+  }
+
+  function hasLocalChanges(): boolean {
+    return unconfirmed().length > 0 || title.value !== knownTitle;
+  }
+
+  function receive(update: UpdateJSON) {
+    if (view === null || update.id <= lastSeen) {
+      return;
+    }
+    const shared = decode(update.update);
+    const schema = view.state.schema;
+    const tr = view.state.tr;
+    const pending = unconfirmed();
+    if (shared.doc !== undefined) {
+      // Steps based on updates replaced by a snapshot cannot be rebased anymore
+      if (pending.length > 0) {
+        console.log("Discarding local changes superseded by a snapshot");
+      }
+      const doc = ProseMirrorNode.fromJSON(schema, shared.doc);
+      tr.replaceWith(0, tr.doc.content.size, doc.content);
+      tr.setMeta(collaborationKey, []);
+    } else if (shared.base !== lastSeen) {
+      // Updates in between are missing, so fetch them in order
+      fetchUpdates();
+      return;
+    } else if (shared.client === client) {
+      const confirmed = shared.steps?.length ?? 0;
+      tr.setMeta(collaborationKey, pending.slice(confirmed));
+    } else {
+      const steps = (shared.steps ?? []).map((step) =>
+        Step.fromJSON(schema, step)
+      );
+      tr.setMeta(collaborationKey, rebase(pending, steps, tr));
+      tr.setMeta("rebased", pending.length);
+    }
+    lastSeen = update.id;
+    if (shared.title !== undefined && shared.client !== client) {
+      knownTitle = shared.title;
+      title.value = shared.title;
+    }
+    tr.setMeta("addToHistory", false);
+    view.dispatch(tr);
+  }
+
+  async function fetchUpdatesHelper() {
+    try {
+      const response = await fetch(`${url}?since=${lastSeen}`);
+      const updates: UpdateJSON[] = await response.json();
+      updates.forEach(receive);
+    } catch (e) {
+      console.log("Error while fetching updates:", e);
+    }
+  }
+
+  function fetchUpdates(): Promise<void> {
+    if (fetching === null) {
+      fetching = fetchUpdatesHelper().finally(() => (fetching = null));
+    }
+    return fetching;
+  }
+
+  async function compact() {
+    if (view === null || sending || hasLocalChanges() || title.value === "") {
+      return;
+    }
+    const doc = view.state.doc.toJSON();
+    saveStatus.value = SaveStatus.SAVING;
+    try {
+      const response = await fetch(url, {
+        method: "PUT",
+        headers: {
+          "Content-Type": "application/json",
+        },
+        body: JSON.stringify({
+          title: title.value,
+          content: toSheet(doc),
+          state: encode({ client, base: lastSeen, title: title.value, doc }),
+          up_to: lastSeen,
+        }),
+      });
+      // Someone else has compacted these updates already
+      const saved = response.ok || response.status === 404;
+      if (saveStatus.value === SaveStatus.SAVING) {
+        saveStatus.value = saved ? SaveStatus.SAVED : SaveStatus.FAILED;
+      }
+    } catch (e) {
+      console.log("Error while compacting updates:", e);
+      saveStatus.value = SaveStatus.FAILED;
+    }
+  }
+
+  const scheduleCompaction = debounce(compact, COMPACT_DELAY);
+
+  async function send() {
+    if (view === null || sending || !hasLocalChanges()) {
+      return;
+    }
+    if (title.value === "") {
+      saveStatus.value = SaveStatus.FAILED;
+      return;
+    }
+    sending = true;
+    saveStatus.value = SaveStatus.WAITING;
+    const steps = unconfirmed().map(({ step }) => step.toJSON());
+    const sentTitle = title.value;
+    let retry = false;
+    let failed = false;
+    try {
+      const response = await fetch(url, {
+        method: "POST",
+        headers: {
+          "Content-Type": "application/json",
+        },
+        body: JSON.stringify({
+          update: encode({ client, base: lastSeen, title: sentTitle, steps }),
+          base: lastSeen,
+        }),
+      });
+      if (response.status === 409) {
+        // Others were faster, rebase onto their updates and try again
+        await fetchUpdates();
+        retry = true;
+      } else if (response.ok) {
+        knownTitle = sentTitle;
+        receive(await response.json());
+        backoff = 0;
+      } else {
+        throw new Error(`unexpected status ${response.status}`);
+      }
+    } catch (e) {
+      console.log("Error while sending update:", e);
+      failed = true;
+    }
+    sending = false;
+    if (failed) {
+      backoff = Math.min(backoff + 1000, 5000);
+      saveStatus.value = SaveStatus.FAILED;
+      setTimeout(send, backoff);
+    } else if (retry || hasLocalChanges()) {
+      send();
+    } else {
+      scheduleCompaction();
+    }
+  }
+
+  const scheduleSend = debounce(send, 500);
+
+  function changed() {
+    if (hasLocalChanges()) {
+      saveStatus.value = SaveStatus.WAITING;
+      scheduleSend();
+    }
+  }
+
+  const extension = Extension.create({
+    name: "collaboration",
+
+    addProseMirrorPlugins() {
+      return [
+        new Plugin<Unconfirmed[]>({
+          key: collaborationKey,
+          state: {
+            init: () => [],
+            apply(tr, steps) {
+              const replaced = tr.getMeta(collaborationKey);
+              if (replaced !== undefined) {
+                return replaced;
+              }
+              if (!tr.docChanged) {
+                return steps;
+              }
+              return steps.concat(
+                tr.steps.map((step, i) => ({
+                  step,
+                  inverted: step.invert(tr.docs[i]),
+                }))
+              );
+            },
+          },
+          view(editorView) {
+            view = editorView;
+            return {
+              update: () => {
+                if (enabled) {
+                  changed();
+                }
+              },
+              destroy: () => (view = null),
+            };
+          },
+        }),
+      ];
+    },
+  });
+
+  let events: EventSource | null = null;
+
+  onMounted(() => {
+    if (!enabled) {
+      return;
+    }
+    events = new EventSource(`${url}/events`);
+    events.onmessage = (message) => {
+      const event: EventJSON = JSON.parse(message.data);
+      switch (event.type) {
+        case "update":
+          receive(event);
+          break;
+        case "presence":
+          editors.value = event.editors;
+          break;
+        case "resync":
+          fetchUpdates();
+          break;
+      }
+    };
+    // The editor is mounted before, so its document can be brought up to date
+    fetchUpdates();
+  });
+
+  if (enabled) {
+    watch(title, changed);
+  }
+
+  onBeforeUnmount(() => {
+    if (events !== null) {
+      events.close();
+    }
+  });
+
+  return { editors, enabled, extension, saveStatus };
+}
@@ -12,4 +12,5 @@ export enum AppMode {
   EDIT_SOLUTION = "edit_solution",
   VIEW_SOLUTION = "view_solution",
   DEMO = "demo",
+  SCORM = "scorm",
 }
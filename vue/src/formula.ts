@@ -0,0 +1,552 @@
+// Checks answers to formula tasks like the grader of the backend does, by comparing the values of
+// solution and answer at fixed sample points
+
+type Expression =
+  | { kind: "number"; value: number }
+  | { kind: "variable"; name: string }
+  | { kind: "negate"; operand: Expression }
+  | { kind: "binary"; operator: string; left: Expression; right: Expression }
+  | { kind: "call"; name: string; args: Expression[] };
+
+type Token =
+  | { kind: "number"; value: number }
+  | { kind: "name"; name: string }
+  | { kind: "operator"; operator: string }
+  | { kind: "open" }
+  | { kind: "close" }
+  | { kind: "comma" };
+
+const FUNCTIONS = [
+  "sqrt",
+  "abs",
+  "round",
+  "floor",
+  "ceil",
+  "min",
+  "max",
+  "sin",
+  "cos",
+  "tan",
+  "asin",
+  "acos",
+  "atan",
+  "exp",
+  "ln",
+  "log",
+];
+
+// Values the variables take at the sample points, the same as in the backend
+const SAMPLES = [
+  0.37, 1.21, -0.83, 2.54, -1.67, 0.58, 3.13, -2.29, 1.79, -0.41, 2.87, -3.05,
+];
+const MIN_POINTS = 4;
+
+function isFunction(name: string): boolean {
+  return FUNCTIONS.includes(name);
+}
+
+function tokenize(text: string): Token[] | null {
+  const chars = Array.from(text);
+  const tokens: Token[] = [];
+  let i = 0;
+  while (i < chars.length) {
+    const c = chars[i];
+    if (/\s/.test(c)) {
+      i++;
+    } else if (/[0-9.]/.test(c)) {
+      const start = i;
+      while (i < chars.length && /[0-9.]/.test(chars[i])) {
+        i++;
+      }
+      const number = chars.slice(start, i).join("");
+      if (!/^(\d+\.?\d*|\.\d+)$/.test(number)) {
+        return null;
+      }
+      tokens.push({ kind: "number", value: parseFloat(number) });
+    } else if (/[\p{L}_]/u.test(c)) {
+      const start = i;
+      while (i < chars.length && /[\p{L}\p{N}_]/u.test(chars[i])) {
+        i++;
+      }
+      const name = chars.slice(start, i).join("");
+      if (name === "pi" || name === "π") {
+        tokens.push({ kind: "number", value: Math.PI });
+      } else if (name === "e") {
+        tokens.push({ kind: "number", value: Math.E });
+      } else {
+        tokens.push({ kind: "name", name });
+      }
+    } else {
+      i++;
+      if (c === "+") {
+        tokens.push({ kind: "operator", operator: "+" });
+      } else if (c === "-" || c === "−") {
+        tokens.push({ kind: "operator", operator: "-" });
+      } else if (c === "*" || c === "·" || c === "×") {
+        tokens.push({ kind: "operator", operator: "*" });
+      } else if (c === "/" || c === ":" || c === "÷") {
+        tokens.push({ kind: "operator", operator: "/" });
+      } else if (c === "^") {
+        tokens.push({ kind: "operator", operator: "^" });
+      } else if ("([{".includes(c)) {
+        tokens.push({ kind: "open" });
+      } else if (")]}".includes(c)) {
+        tokens.push({ kind: "close" });
+      } else if (c === "," || c === ";") {
+        tokens.push({ kind: "comma" });
+      } else {
+        return null;
+      }
+    }
+  }
+  return tokens;
+}
+
+class Parser {
+  tokens: Token[];
+  next = 0;
+
+  constructor(tokens: Token[]) {
+    this.tokens = tokens;
+  }
+
+  peek(): Token | undefined {
+    return this.tokens[this.next];
+  }
+
+  eat(kind: string, operator?: string): boolean {
+    const token = this.peek();
+    if (
+      token?.kind === kind &&
+      (operator === undefined ||
+        (token.kind === "operator" && token.operator === operator))
+    ) {
+      this.next++;
+      return true;
+    }
+    return false;
+  }
+
+  sum(): Expression | null {
+    let left = this.product();
+    for (;;) {
+      const token = this.peek();
+      if (
+        left === null ||
+        token?.kind !== "operator" ||
+        !"+-".includes(token.operator)
+      ) {
+        return left;
+      }
+      this.next++;
+      const right = this.product();
+      if (right === null) {
+        return null;
+      }
+      left = { kind: "binary", operator: token.operator, left, right };
+    }
+  }
+
+  product(): Expression | null {
+    let left = this.unary();
+    for (;;) {
+      const token = this.peek();
+      if (left === null || token === undefined) {
+        return left;
+      }
+      let right;
+      if (token.kind === "operator" && "*/".includes(token.operator)) {
+        this.next++;
+        right = this.unary();
+      } else if (["number", "name", "open"].includes(token.kind)) {
+        // Implicit multiplication like `2x` or `(a + 1)(a - 1)`
+        right = this.power();
+      } else {
+        return left;
+      }
+      if (right === null) {
+        return null;
+      }
+      const operator = token.kind === "operator" ? token.operator : "*";
+      left = { kind: "binary", operator, left, right };
+    }
+  }
+
+  unary(): Expression | null {
+    if (this.eat("operator", "-")) {
+      const operand = this.unary();
+      return operand === null ? null : { kind: "negate", operand };
+    }
+    if (this.eat("operator", "+")) {
+      return this.unary();
+    }
+    return this.power();
+  }
+
+  power(): Expression | null {
+    const base = this.primary();
+    if (base !== null && this.eat("operator", "^")) {
+      // Right associative and binding tighter than a sign in front
+      const exponent = this.unary();
+      return exponent === null
+        ? null
+        : { kind: "binary", operator: "^", left: base, right: exponent };
+    }
+    return base;
+  }
+
+  primary(): Expression | null {
+    const token = this.peek();
+    this.next++;
+    if (token?.kind === "number") {
+      return { kind: "number", value: token.value };
+    }
+    if (token?.kind === "name" && isFunction(token.name)) {
+      const args: Expression[] = [];
+      if (this.eat("open")) {
+        for (;;) {
+          const arg = this.sum();
+          if (arg === null) {
+            return null;
+          }
+          args.push(arg);
+          if (this.eat("close")) {
+            break;
+          }
+          if (!this.eat("comma")) {
+            return null;
+          }
+        }
+      } else {
+        // `sin x` takes the next factor as its argument
+        const arg = this.power();
+        if (arg === null) {
+          return null;
+        }
+        args.push(arg);
+      }
+      return { kind: "call", name: token.name, args };
+    }
+    if (token?.kind === "name") {
+      return { kind: "variable", name: token.name };
+    }
+    if (token?.kind === "open") {
+      const inner = this.sum();
+      return inner !== null && this.eat("close") ? inner : null;
+    }
+    return null;
+  }
+}
+
+function parseExpression(text: string): Expression | null {
+  const tokens = tokenize(text);
+  if (tokens === null) {
+    return null;
+  }
+  const parser = new Parser(tokens);
+  const expression = parser.sum();
+  return parser.next === tokens.length ? expression : null;
+}
+
+function call(name: string, args: number[]): number | null {
+  const [x, y] = args;
+  if (["min", "max"].includes(name) && args.length > 0) {
+    return name === "min" ? Math.min(...args) : Math.max(...args);
+  }
+  if (name === "round" && args.length === 2) {
+    const factor = Math.pow(10, Math.trunc(y));
+    return roundHalfAway(x * factor) / factor;
+  }
+  if (args.length !== 1) {
+    return null;
+  }
+  switch (name) {
+    case "sqrt":
+      return Math.sqrt(x);
+    case "abs":
+      return Math.abs(x);
+    case "round":
+      return roundHalfAway(x);
+    case "floor":
+      return Math.floor(x);
+    case "ceil":
+      return Math.ceil(x);
+    case "sin":
+      return Math.sin(x);
+    case "cos":
+      return Math.cos(x);
+    case "tan":
+      return Math.tan(x);
+    case "asin":
+      return Math.asin(x);
+    case "acos":
+      return Math.acos(x);
+    case "atan":
+      return Math.atan(x);
+    case "exp":
+      return Math.exp(x);
+    case "ln":
+      return Math.log(x);
+    case "log":
+      return Math.log10(x);
+    default:
+      return null;
+  }
+}
+
+// Rounds like Rust, halves away from zero
+function roundHalfAway(x: number): number {
+  return Math.sign(x) * Math.round(Math.abs(x));
+}
+
+function evaluate(
+  expression: Expression,
+  variable: (name: string) => number | null
+): number | null {
+  let value: number | null = null;
+  switch (expression.kind) {
+    case "number":
+      value = expression.value;
+      break;
+    case "variable":
+      value = variable(expression.name);
+      break;
+    case "negate": {
+      const operand = evaluate(expression.operand, variable);
+      value = operand === null ? null : -operand;
+      break;
+    }
+    case "binary": {
+      const left = evaluate(expression.left, variable);
+      const right = evaluate(expression.right, variable);
+      if (left === null || right === null) {
+        return null;
+      }
+      value = {
+        "+": left + right,
+        "-": left - right,
+        "*": left * right,
+        "/": left / right,
+        "^": Math.pow(left, right),
+      }[expression.operator] as number;
+      break;
+    }
+    case "call": {
+      const args = expression.args.map((arg) => evaluate(arg, variable));
+      if (args.some((arg) => arg === null)) {
+        return null;
+      }
+      value = call(expression.name, args as number[]);
+      break;
+    }
+  }
+  return value !== null && Number.isFinite(value) ? value : null;
+}
+
+function variables(expression: Expression, names: string[] = []): string[] {
+  switch (expression.kind) {
+    case "variable":
+      if (!names.includes(expression.name)) {
+        names.push(expression.name);
+      }
+      break;
+    case "negate":
+      variables(expression.operand, names);
+      break;
+    case "binary":
+      variables(expression.left, names);
+      variables(expression.right, names);
+      break;
+    case "call":
+      expression.args.forEach((arg) => variables(arg, names));
+      break;
+  }
+  return names;
+}
+
+// The argument of a LaTeX command, either in braces or a single character
+function group(chars: string[], start: number): [string, number] | null {
+  let i = start;
+  while (i < chars.length && /\s/.test(chars[i])) {
+    i++;
+  }
+  if (i >= chars.length) {
+    return null;
+  }
+  if (chars[i] !== "{") {
+    return [chars[i], i + 1];
+  }
+  let depth = 0;
+  for (let j = i; j < chars.length; j++) {
+    if (chars[j] === "{") {
+      depth++;
+    } else if (chars[j] === "}") {
+      depth--;
+      if (depth === 0) {
+        return [chars.slice(i + 1, j).join(""), j + 1];
+      }
+    }
+  }
+  return null;
+}
+
+const COMMANDS: Record<string, string> = {
+  cdot: "*",
+  times: "*",
+  ast: "*",
+  div: "/",
+  left: "",
+  right: "",
+  mathrm: "",
+  text: "",
+  operatorname: "",
+  pi: " pi ",
+  arcsin: " asin ",
+  arccos: " acos ",
+  arctan: " atan ",
+  lg: " log ",
+};
+
+// Rewrites the LaTeX commands of school mathematics in plain syntax
+function latexToPlain(latex: string): string | null {
+  const chars = Array.from(latex);
+  let plain = "";
+  let i = 0;
+  while (i < chars.length) {
+    if (chars[i] !== "\\") {
+      plain += chars[i];
+      i++;
+      continue;
+    }
+    i++;
+    const start = i;
+    while (i < chars.length && /[a-zA-Z]/.test(chars[i])) {
+      i++;
+    }
+    const command = chars.slice(start, i).join("");
+    if (command === "") {
+      // `\,` and the like are spaces, `\{` is a brace
+      if (i >= chars.length) {
+        return null;
+      }
+      plain += "{}|".includes(chars[i]) ? chars[i] : " ";
+      i++;
+    } else if (["frac", "dfrac", "tfrac"].includes(command)) {
+      const numerator = group(chars, i);
+      const denominator = numerator && group(chars, numerator[1]);
+      if (!numerator || !denominator) {
+        return null;
+      }
+      const top = latexToPlain(numerator[0]);
+      const bottom = latexToPlain(denominator[0]);
+      if (top === null || bottom === null) {
+        return null;
+      }
+      plain += `((${top})/(${bottom}))`;
+      i = denominator[1];
+    } else if (command === "sqrt") {
+      let index: string | null = null;
+      if (chars[i] === "[") {
+        const end = chars.indexOf("]", i);
+        if (end < 0) {
+          return null;
+        }
+        index = latexToPlain(chars.slice(i + 1, end).join(""));
+        if (index === null) {
+          return null;
+        }
+        i = end + 1;
+      }
+      const radicand = group(chars, i);
+      const inner = radicand && latexToPlain(radicand[0]);
+      if (!radicand || inner === null) {
+        return null;
+      }
+      plain +=
+        index === null ? `sqrt(${inner})` : `((${inner})^(1/(${index})))`;
+      i = radicand[1];
+    } else if (command in COMMANDS) {
+      plain += COMMANDS[command];
+    } else if (isFunction(command)) {
+      plain += ` ${command} `;
+    } else {
+      return null;
+    }
+  }
+  return plain;
+}
+
+// Splits names which are neither functions nor constants into single letters
+function splitName(name: string): string {
+  if (isFunction(name) || name === "pi") {
+    return ` ${name} `;
+  }
+  // `sinx` is the sine of x
+  const prefixes = [
+    "sqrt",
+    "asin",
+    "acos",
+    "atan",
+    "sin",
+    "cos",
+    "tan",
+    "exp",
+    "abs",
+    "ln",
+  ];
+  const prefix = prefixes.find(
+    (f) => name.length > f.length && name.startsWith(f)
+  );
+  if (prefix !== undefined) {
+    return ` ${prefix} ${splitName(name.slice(prefix.length))}`;
+  }
+  return ` ${Array.from(name).join(" ")} `;
+}
+
+export function parseFormula(text: string): Expression | null {
+  const plain = latexToPlain(text);
+  if (plain === null) {
+    return null;
+  }
+  const prepared = plain
+    .replace(/(\d),(?=\d)/g, "$1.")
+    .replace(/\p{L}+/gu, splitName);
+  return parseExpression(prepared);
+}
+
+function close(expected: number, actual: number): boolean {
+  const scale = Math.max(Math.abs(expected), Math.abs(actual), 1);
+  return Math.abs(expected - actual) <= 1e-9 * scale;
+}
+
+function equivalent(solution: Expression, answer: Expression): boolean {
+  const names = variables(answer, variables(solution));
+  let defined = 0;
+  for (let point = 0; point < SAMPLES.length; point++) {
+    const value = (name: string) => {
+      const k = names.indexOf(name);
+      return k < 0 ? null : SAMPLES[(point * 5 + k * 7) % SAMPLES.length];
+    };
+    const expected = evaluate(solution, value);
+    if (expected === null) {
+      continue;
+    }
+    defined++;
+    const actual = evaluate(answer, value);
+    if (actual === null || !close(expected, actual)) {
+      return false;
+    }
+  }
+  return defined >= Math.min(MIN_POINTS, SAMPLES.length);
+}
+
+// Whether the answer is equivalent to the solution, answers to solutions which
+// cannot be read are compared as text
+export function checkFormula(solution: string, answer: string): boolean {
+  const expected = parseFormula(solution);
+  if (expected === null) {
+    return solution.trim() === answer.trim();
+  }
+  const actual = parseFormula(answer);
+  return actual !== null && equivalent(expected, actual);
+}
@@ -0,0 +1,64 @@
+// Port of the gap grading in src/sheets/conversion/grading.rs, both must agree
+
+export interface Alternative {
+  answer: string;
+  // Fraction of the gap's point, between 0 and 1
+  credit: number;
+  regex: boolean;
+}
+
+export interface GapMatching {
+  alternatives: Alternative[];
+  caseSensitive: boolean;
+  normalize: boolean;
+}
+
+const punctuation = /[!-/:-@[-`{-~„“”‚‘’«»–—…]/;
+
+function normalizeAnswer(text: string): string {
+  return text
+    .split(/\s/)
+    .flatMap((word) => word.split(punctuation))
+    .filter((word) => word.length > 0)
+    .join(" ");
+}
+
+function matchesRegex(
+  pattern: string,
+  caseSensitive: boolean,
+  answer: string
+): boolean {
+  try {
+    return new RegExp(`^(?:${pattern})$`, caseSensitive ? "u" : "iu").test(
+      answer
+    );
+  } catch {
+    // Invalid expressions match nothing
+    return false;
+  }
+}
+
+export function gapCredit(
+  solution: string,
+  matching: GapMatching,
+  answer: string
+): number {
+  const prepare = (text: string) => {
+    const normalized = matching.normalize ? normalizeAnswer(text) : text;
+    return matching.caseSensitive ? normalized : normalized.toLowerCase();
+  };
+  const prepared = prepare(answer);
+  let best = prepare(solution) === prepared ? 1 : 0;
+  for (const alternative of matching.alternatives) {
+    if (alternative.credit <= best) {
+      continue;
+    }
+    const matches = alternative.regex
+      ? matchesRegex(alternative.answer, matching.caseSensitive, prepared)
+      : prepare(alternative.answer) === prepared;
+    if (matches) {
+      best = Math.min(Math.max(alternative.credit, 0), 1);
+    }
+  }
+  return best;
+}
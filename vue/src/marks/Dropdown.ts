@@ -0,0 +1,117 @@
+import { Mark, mergeAttributes } from "@tiptap/core";
+
+export interface DropdownOption {
+  text: string;
+  correct: boolean;
+}
+
+export interface DropdownOptions {
+  HTMLAttributes: Record<string, unknown>;
+}
+
+declare module "@tiptap/core" {
+  interface Commands<ReturnType> {
+    dropdown: {
+      /**
+       * Set a dropdown mark, the selected text becomes its correct option
+       */
+      setDropdown: () => ReturnType;
+      /**
+       * Toggle a dropdown mark
+       */
+      toggleDropdown: () => ReturnType;
+      /**
+       * Unset a dropdown mark
+       */
+      unsetDropdown: () => ReturnType;
+    };
+  }
+}
+
+export default Mark.create<DropdownOptions>({
+  name: "dropdown",
+
+  addOptions() {
+    return {
+      HTMLAttributes: {},
+    };
+  },
+
+  excludes: "_",
+
+  addAttributes() {
+    return {
+      options: {
+        default: [],
+        parseHTML: (element): DropdownOption[] => {
+          try {
+            return JSON.parse(element.getAttribute("data-options") ?? "[]");
+          } catch {
+            return [];
+          }
+        },
+        renderHTML: (attributes) => ({
+          "data-options": JSON.stringify(attributes.options),
+        }),
+      },
+      shuffle: {
+        default: false,
+        parseHTML: (element) =>
+          element.getAttribute("data-shuffle") === "true",
+        renderHTML: (attributes) => ({ "data-shuffle": attributes.shuffle }),
+      },
+    };
+  },
+
+  parseHTML() {
+    return [
+      {
+        tag: `span[data-type="${this.name}"]`,
+      },
+    ];
+  },
+
+  renderHTML({ HTMLAttributes }) {
+    return [
+      "span",
+      mergeAttributes(
+        { "data-type": this.name },
+        this.options.HTMLAttributes,
+        HTMLAttributes
+      ),
+      0,
+    ];
+  },
+
+  addCommands() {
+    return {
+      setDropdown:
+        () =>
+        ({ state, commands }) => {
+          const { from, to } = state.selection;
+          const text = state.doc.textBetween(from, to, " ").trim();
+          return commands.setMark(this.name, {
+            options: [{ text, correct: true }],
+          });
+        },
+      toggleDropdown:
+        () =>
+        ({ commands }) => {
+          return this.editor.isActive(this.name)
+            ? commands.unsetMark(this.name)
+            : commands.setDropdown();
+        },
+      unsetDropdown:
+        () =>
+        ({ commands }) => {
+          return commands.unsetMark(this.name);
+        },
+    };
+  },
+
+  addKeyboardShortcuts() {
+    return {
+      "Mod-Shift-d": () => this.editor.commands.toggleDropdown(),
+    };
+  },
+});
@@ -0,0 +1,108 @@
+import {
+  Mark,
+  markInputRule,
+  markPasteRule,
+  mergeAttributes,
+} from "@tiptap/core";
+
+export interface FormulaOptions {
+  HTMLAttributes: Record<string, unknown>;
+}
+
+declare module "@tiptap/core" {
+  interface Commands<ReturnType> {
+    formula: {
+      /**
+       * Set a formula mark
+       */
+      setFormula: () => ReturnType;
+      /**
+       * Toggle a formula mark
+       */
+      toggleFormula: () => ReturnType;
+      /**
+       * Unset a formula mark
+       */
+      unsetFormula: () => ReturnType;
+    };
+  }
+}
+
+export const percentInputRegex = /(?:^|\s)((?:%%)((?:[^%]+))(?:%%))$/;
+export const percentPasteRegex = /(?:^|\s)((?:%%)((?:[^%]+))(?:%%))/g;
+
+export default Mark.create<FormulaOptions>({
+  name: "formula",
+
+  addOptions() {
+    return {
+      HTMLAttributes: {},
+    };
+  },
+
+  excludes: "_",
+
+  parseHTML() {
+    return [
+      {
+        tag: `span[data-type="${this.name}"]`,
+      },
+    ];
+  },
+
+  renderHTML({ HTMLAttributes }) {
+    return [
+      "span",
+      mergeAttributes(
+        { "data-type": this.name },
+        this.options.HTMLAttributes,
+        HTMLAttributes
+      ),
+      0,
+    ];
+  },
+
+  addCommands() {
+    return {
+      setFormula:
+        () =>
+        ({ commands }) => {
+          return commands.setMark(this.name);
+        },
+      toggleFormula:
+        () =>
+        ({ commands }) => {
+          return commands.toggleMark(this.name);
+        },
+      unsetFormula:
+        () =>
+        ({ commands }) => {
+          return commands.unsetMark(this.name);
+        },
+    };
+  },
+
+  addKeyboardShortcuts() {
+    return {
+      "Mod-Shift-f": () => this.editor.commands.toggleFormula(),
+    };
+  },
+
+  addInputRules() {
+    return [
+      markInputRule({
+        find: percentInputRegex,
+        type: this.type,
+      }),
+    ];
+  },
+
+  addPasteRules() {
+    return [
+      markPasteRule({
+        find: percentPasteRegex,
+        type: this.type,
+      }),
+    ];
+  },
+});
@@ -5,6 +5,8 @@ import {
   mergeAttributes,
 } from "@tiptap/core";
 
+import { Alternative } from "../gap";
+
 export interface GapOptions {
   HTMLAttributes: Record<string, unknown>;
 }
@@ -42,6 +44,42 @@ export default Mark.create<GapOptions>({
 
   excludes: "_",
 
+  addAttributes() {
+    return {
+      alternatives: {
+        default: [],
+        parseHTML: (element): Alternative[] => {
+          try {
+            return JSON.parse(
+              element.getAttribute("data-alternatives") ?? "[]"
+            );
+          } catch {
+            return [];
+          }
+        },
+        renderHTML: (attributes) => ({
+          "data-alternatives": JSON.stringify(attributes.alternatives),
+        }),
+      },
+      caseSensitive: {
+        default: true,
+        parseHTML: (element) =>
+          element.getAttribute("data-case-sensitive") !== "false",
+        renderHTML: (attributes) => ({
+          "data-case-sensitive": attributes.caseSensitive ? "true" : "false",
+        }),
+      },
+      normalize: {
+        default: false,
+        parseHTML: (element) =>
+          element.getAttribute("data-normalize") === "true",
+        renderHTML: (attributes) => ({
+          "data-normalize": attributes.normalize ? "true" : "false",
+        }),
+      },
+    };
+  },
+
   parseHTML() {
     return [
       {
@@ -0,0 +1,108 @@
+import {
+  Mark,
+  markInputRule,
+  markPasteRule,
+  mergeAttributes,
+} from "@tiptap/core";
+
+export interface NumericOptions {
+  HTMLAttributes: Record<string, unknown>;
+}
+
+declare module "@tiptap/core" {
+  interface Commands<ReturnType> {
+    numeric: {
+      /**
+       * Set a numeric mark
+       */
+      setNumeric: () => ReturnType;
+      /**
+       * Toggle a numeric mark
+       */
+      toggleNumeric: () => ReturnType;
+      /**
+       * Unset a numeric mark
+       */
+      unsetNumeric: () => ReturnType;
+    };
+  }
+}
+
+export const hashInputRegex = /(?:^|\s)((?:##)((?:[^#]+))(?:##))$/;
+export const hashPasteRegex = /(?:^|\s)((?:##)((?:[^#]+))(?:##))/g;
+
+export default Mark.create<NumericOptions>({
+  name: "numeric",
+
+  addOptions() {
+    return {
+      HTMLAttributes: {},
+    };
+  },
+
+  excludes: "_",
+
+  parseHTML() {
+    return [
+      {
+        tag: `span[data-type="${this.name}"]`,
+      },
+    ];
+  },
+
+  renderHTML({ HTMLAttributes }) {
+    return [
+      "span",
+      mergeAttributes(
+        { "data-type": this.name },
+        this.options.HTMLAttributes,
+        HTMLAttributes
+      ),
+      0,
+    ];
+  },
+
+  addCommands() {
+    return {
+      setNumeric:
+        () =>
+        ({ commands }) => {
+          return commands.setMark(this.name);
+        },
+      toggleNumeric:
+        () =>
+        ({ commands }) => {
+          return commands.toggleMark(this.name);
+        },
+      unsetNumeric:
+        () =>
+        ({ commands }) => {
+          return commands.unsetMark(this.name);
+        },
+    };
+  },
+
+  addKeyboardShortcuts() {
+    return {
+      "Mod-Shift-g": () => this.editor.commands.toggleNumeric(),
+    };
+  },
+
+  addInputRules() {
+    return [
+      markInputRule({
+        find: hashInputRegex,
+        type: this.type,
+      }),
+    ];
+  },
+
+  addPasteRules() {
+    return [
+      markPasteRule({
+        find: hashPasteRegex,
+        type: this.type,
+      }),
+    ];
+  },
+});
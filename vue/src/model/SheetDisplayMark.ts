@@ -1,5 +1,8 @@
 import { JSONContent } from "@tiptap/vue-3";
 
+import { Alternative, GapMatching } from "../gap";
+import { DropdownOption } from "../marks/Dropdown";
+
 type JSONContentMark = {
   type: string;
   attrs?: Record<string, unknown>;
@@ -27,7 +30,13 @@ export class Mark {
   ): Mark {
     switch (tiptapMark.type) {
       case "gap":
-        return Gap.fromTiptap(parentNode);
+        return Gap.fromTiptap(tiptapMark, parentNode);
+      case "dropdown":
+        return Dropdown.fromTiptap(tiptapMark, parentNode);
+      case "numeric":
+        return Numeric.fromTiptap(parentNode);
+      case "formula":
+        return Formula.fromTiptap(parentNode);
       case "latex":
         return Latex.fromTiptap(parentNode);
       default:
@@ -39,6 +48,12 @@ export class Mark {
     switch (json.type) {
       case "gap":
         return Gap.fromJSON(json);
+      case "dropdown":
+        return Dropdown.fromJSON(json);
+      case "numeric":
+        return Numeric.fromJSON(json);
+      case "formula":
+        return Formula.fromJSON(json);
       case "latex":
         return Latex.fromJSON(json);
       default:
@@ -53,22 +68,156 @@ export class Mark {
   }
 }
 
-export class Gap extends Mark {
+export class Gap extends Mark implements GapMatching {
   solution: string;
   answer: string;
+  alternatives: Alternative[];
+  caseSensitive: boolean;
+  normalize: boolean;
 
-  constructor(solution: string, answer: string) {
+  constructor(
+    solution: string,
+    answer: string,
+    alternatives: Alternative[] = [],
+    caseSensitive = true,
+    normalize = false
+  ) {
     super("gap");
     this.solution = solution;
     this.answer = answer;
+    this.alternatives = alternatives;
+    this.caseSensitive = caseSensitive;
+    this.normalize = normalize;
   }
 
-  public static fromTiptap(parentNode: JSONContent): Gap {
-    return new Gap(parentNode.text ?? "", "");
+  public static fromTiptap(
+    tiptapMark: JSONContentMark,
+    parentNode: JSONContent
+  ): Gap {
+    return new Gap(
+      parentNode.text ?? "",
+      "",
+      (tiptapMark.attrs?.alternatives as Alternative[]) ?? [],
+      (tiptapMark.attrs?.caseSensitive as boolean) ?? true,
+      (tiptapMark.attrs?.normalize as boolean) ?? false
+    );
   }
 
   public static fromJSON(json: MarkJSON): Gap {
-    return new Gap(json.solution ?? "", json.answer ?? "");
+    return new Gap(
+      json.solution ?? "",
+      json.answer ?? "",
+      (json.alternatives as Alternative[]) ?? [],
+      (json.caseSensitive as boolean) ?? true,
+      (json.normalize as boolean) ?? false
+    );
+  }
+
+  public toTiptap(): JSONContentMark {
+    return {
+      type: this.type,
+      attrs: {
+        alternatives: this.alternatives,
+        caseSensitive: this.caseSensitive,
+        normalize: this.normalize,
+      },
+    };
+  }
+}
+
+export class Dropdown extends Mark {
+  options: DropdownOption[];
+  answer: string;
+  // Kept in saved solutions, the server restores the order of options with it
+  shuffle: boolean;
+  // Set by the server for students, who are graded by the server
+  index?: number;
+
+  constructor(
+    options: DropdownOption[],
+    answer: string,
+    shuffle: boolean,
+    index?: number
+  ) {
+    super("dropdown");
+    this.options = options;
+    this.answer = answer;
+    this.shuffle = shuffle;
+    this.index = index;
+  }
+
+  public static fromTiptap(
+    tiptapMark: JSONContentMark,
+    parentNode: JSONContent
+  ): Dropdown {
+    const options = (tiptapMark.attrs?.options as DropdownOption[]) ?? [];
+    return new Dropdown(
+      // Text marked without the command has no options yet
+      options.length > 0
+        ? options
+        : [{ text: parentNode.text ?? "", correct: true }],
+      "",
+      tiptapMark.attrs?.shuffle ?? false
+    );
+  }
+
+  public static fromJSON(json: MarkJSON): Dropdown {
+    return new Dropdown(
+      (json.options as DropdownOption[]) ?? [],
+      json.answer ?? "",
+      (json.shuffle as boolean) ?? false,
+      json.index as number | undefined
+    );
+  }
+
+  public toTiptap(): JSONContentMark {
+    return {
+      type: this.type,
+      attrs: {
+        options: this.options,
+        shuffle: this.shuffle,
+      },
+    };
+  }
+}
+
+// The solution is written like `3,5 ± 0,1 cm`, see numeric.ts
+export class Numeric extends Mark {
+  solution: string;
+  answer: string;
+
+  constructor(solution: string, answer: string) {
+    super("numeric");
+    this.solution = solution;
+    this.answer = answer;
+  }
+
+  public static fromTiptap(parentNode: JSONContent): Numeric {
+    return new Numeric(parentNode.text ?? "", "");
+  }
+
+  public static fromJSON(json: MarkJSON): Numeric {
+    return new Numeric(json.solution ?? "", json.answer ?? "");
+  }
+}
+
+// The solution is an expression like `2(x + 1)` in LaTeX or plain, see formula.ts
+export class Formula extends Mark {
+  solution: string;
+  answer: string;
+
+  constructor(solution: string, answer: string) {
+    super("formula");
+    this.solution = solution;
+    this.answer = answer;
+  }
+
+  public static fromTiptap(parentNode: JSONContent): Formula {
+    return new Formula(parentNode.text ?? "", "");
+  }
+
+  public static fromJSON(json: MarkJSON): Formula {
+    return new Formula(json.solution ?? "", json.answer ?? "");
   }
 }
 
@@ -1,7 +1,14 @@
 import { JSONContent } from "@tiptap/vue-3";
 
+import { ChoiceMode, Scoring } from "../choice";
 import { Mark, MarkJSON } from "./SheetDisplayMark";
 
+export interface VariableJSON {
+  name: string;
+  definition: string;
+  value?: number;
+}
+
 export interface NodeJSON {
   type?: string;
   content: NodeJSON[];
@@ -10,8 +17,19 @@ export interface NodeJSON {
   mimetype?: string;
   language?: string;
   level?: number;
+  mode?: ChoiceMode;
+  scoring?: Scoring;
+  shuffle?: boolean;
   solution?: boolean;
-  answer?: boolean;
+  answer?: boolean | string;
+  awarded?: number;
+  minWords?: number;
+  maxWords?: number;
+  modelAnswer?: string;
+  points?: number;
+  draw?: number;
+  drawn?: number[];
+  variables?: VariableJSON[];
   text?: string;
   [key: string]: unknown;
 }
@@ -33,12 +51,18 @@ export class Node {
         return Audio.fromTiptap(tiptapNode);
       case "codeBlock":
         return Codeblock.fromTiptap(tiptapNode);
+      case "essay":
+        return Essay.fromTiptap(tiptapNode);
       case "heading":
         return Heading.fromTiptap(tiptapNode);
       case "multipleChoice":
         return MultipleChoice.fromTiptap(tiptapNode);
       case "multipleChoiceAnswer":
         return MultipleChoiceAnswer.fromTiptap(tiptapNode);
+      case "pool":
+        return Pool.fromTiptap(tiptapNode);
+      case "variables":
+        return Variables.fromTiptap(tiptapNode);
       case "text":
         return Text.fromTiptap(tiptapNode);
       default:
@@ -66,12 +90,18 @@ export class Node {
         return Audio.fromJSON(json);
       case "codeBlock":
         return Codeblock.fromJSON(json);
+      case "essay":
+        return Essay.fromJSON(json);
       case "heading":
         return Heading.fromJSON(json);
       case "multipleChoice":
         return MultipleChoice.fromJSON(json);
       case "multipleChoiceAnswer":
         return MultipleChoiceAnswer.fromJSON(json);
+      case "pool":
+        return Pool.fromJSON(json);
+      case "variables":
+        return Variables.fromJSON(json);
       case "text":
         return Text.fromJSON(json);
       default:
@@ -188,6 +218,73 @@ export class Codeblock extends Node {
   }
 }
 
+export class Essay extends Node {
+  minWords?: number;
+  maxWords?: number;
+  points: number;
+  // Hidden from students by the server
+  modelAnswer: string;
+  answer: string;
+  // Points awarded by a teacher, unset until graded
+  awarded?: number;
+
+  constructor(
+    content: Node[],
+    marks: Mark[],
+    points: number,
+    modelAnswer: string,
+    answer: string,
+    minWords?: number,
+    maxWords?: number,
+    awarded?: number
+  ) {
+    super(content, marks, "essay");
+    this.minWords = minWords;
+    this.maxWords = maxWords;
+    this.points = points;
+    this.modelAnswer = modelAnswer;
+    this.answer = answer;
+    this.awarded = awarded;
+  }
+
+  public static fromTiptap(tiptapNode: JSONContent): Essay {
+    return new Essay(
+      Node.contentFromTiptap(tiptapNode),
+      Node.marksFromTiptap(tiptapNode),
+      tiptapNode.attrs?.points ?? 1,
+      tiptapNode.attrs?.modelAnswer ?? "",
+      "",
+      tiptapNode.attrs?.minWords ?? undefined,
+      tiptapNode.attrs?.maxWords ?? undefined
+    );
+  }
+
+  public static fromJSON(json: NodeJSON): Essay {
+    return new Essay(
+      Node.contentFromJSON(json),
+      Node.marksFromJSON(json),
+      json.points ?? 1,
+      json.modelAnswer ?? "",
+      typeof json.answer === "string" ? json.answer : "",
+      json.minWords,
+      json.maxWords,
+      json.awarded
+    );
+  }
+
+  public toTiptap(): JSONContent {
+    return {
+      attrs: {
+        minWords: this.minWords ?? null,
+        maxWords: this.maxWords ?? null,
+        points: this.points,
+        modelAnswer: this.modelAnswer,
+      },
+      ...super.toTiptap(),
+    };
+  }
+}
+
 export class Heading extends Node {
   level: number;
 
@@ -224,15 +321,31 @@ export class Heading extends Node {
 
 export class MultipleChoice extends Node {
   declare content: MultipleChoiceAnswer[];
+  mode: ChoiceMode;
+  scoring: Scoring;
+  // Kept in saved solutions, the server restores the order of answers with it
+  shuffle: boolean;
 
-  constructor(content: MultipleChoiceAnswer[], marks: Mark[]) {
+  constructor(
+    content: MultipleChoiceAnswer[],
+    marks: Mark[],
+    mode: ChoiceMode,
+    scoring: Scoring,
+    shuffle: boolean
+  ) {
     super(content, marks, "multipleChoice");
+    this.mode = mode;
+    this.scoring = scoring;
+    this.shuffle = shuffle;
   }
 
   public static fromTiptap(tiptapNode: JSONContent): MultipleChoice {
     return new MultipleChoice(
       MultipleChoice.contentFromTiptap(tiptapNode),
-      Node.marksFromTiptap(tiptapNode)
+      Node.marksFromTiptap(tiptapNode),
+      tiptapNode.attrs?.mode ?? "multiple",
+      tiptapNode.attrs?.scoring ?? "allOrNothing",
+      tiptapNode.attrs?.shuffle ?? false
     );
   }
 
@@ -257,10 +370,24 @@ export class MultipleChoice extends Node {
   public static fromJSON(json: NodeJSON): MultipleChoice {
     return new MultipleChoice(
       MultipleChoice.contentFromJSON(json),
-      Node.marksFromJSON(json)
+      Node.marksFromJSON(json),
+      json.mode ?? "multiple",
+      json.scoring ?? "allOrNothing",
+      json.shuffle ?? false
     );
   }
 
+  public toTiptap(): JSONContent {
+    return {
+      attrs: {
+        mode: this.mode,
+        scoring: this.scoring,
+        shuffle: this.shuffle,
+      },
+      ...super.toTiptap(),
+    };
+  }
+
   protected static contentFromJSON(json: NodeJSON): MultipleChoiceAnswer[] {
     if (!json.content.every((node) => node.type === "multipleChoiceAnswer")) {
       throw new Error(
@@ -300,7 +427,7 @@ export class MultipleChoiceAnswer extends Node {
       Node.contentFromJSON(json),
       Node.marksFromJSON(json),
       json.solution ?? false,
-      json.answer ?? false
+      json.answer === true
     );
   }
 
@@ -314,6 +441,87 @@ export class MultipleChoiceAnswer extends Node {
   }
 }
 
+export class Pool extends Node {
+  draw: number;
+  // Positions of the items drawn into a solution, unset on sheets
+  drawn?: number[];
+
+  constructor(
+    content: Node[],
+    marks: Mark[],
+    draw: number,
+    drawn?: number[]
+  ) {
+    super(content, marks, "pool");
+    this.draw = draw;
+    this.drawn = drawn;
+  }
+
+  public static fromTiptap(tiptapNode: JSONContent): Pool {
+    return new Pool(
+      Node.contentFromTiptap(tiptapNode),
+      Node.marksFromTiptap(tiptapNode),
+      tiptapNode.attrs?.draw ?? 1
+    );
+  }
+
+  public static fromJSON(json: NodeJSON): Pool {
+    return new Pool(
+      Node.contentFromJSON(json),
+      Node.marksFromJSON(json),
+      json.draw ?? 1,
+      json.drawn
+    );
+  }
+
+  public toTiptap(): JSONContent {
+    return {
+      attrs: {
+        draw: this.draw,
+      },
+      ...super.toTiptap(),
+    };
+  }
+}
+
+export class Variables extends Node {
+  // Values are drawn by the server when a student starts solving
+  variables: VariableJSON[];
+
+  constructor(content: Node[], marks: Mark[], variables: VariableJSON[]) {
+    super(content, marks, "variables");
+    this.variables = variables;
+  }
+
+  public static fromTiptap(tiptapNode: JSONContent): Variables {
+    return new Variables(
+      Node.contentFromTiptap(tiptapNode),
+      Node.marksFromTiptap(tiptapNode),
+      tiptapNode.attrs?.variables ?? []
+    );
+  }
+
+  public static fromJSON(json: NodeJSON): Variables {
+    return new Variables(
+      Node.contentFromJSON(json),
+      Node.marksFromJSON(json),
+      json.variables ?? []
+    );
+  }
+
+  public toTiptap(): JSONContent {
+    return {
+      attrs: {
+        variables: this.variables.map(({ name, definition }) => ({
+          name,
+          definition,
+        })),
+      },
+      ...super.toTiptap(),
+    };
+  }
+}
+
 export class Text extends Node {
   text: string;
 
@@ -0,0 +1,84 @@
+import { Node, mergeAttributes } from "@tiptap/core";
+
+declare module "@tiptap/core" {
+  interface Commands<ReturnType> {
+    essay: {
+      /**
+       * Insert a free-text task graded by teachers
+       */
+      insertEssay: () => ReturnType;
+    };
+  }
+}
+
+function optionalNumber(value: string | null): number | null {
+  return value === null || value === "" ? null : parseInt(value, 10);
+}
+
+export default Node.create({
+  name: "essay",
+
+  content: "",
+  group: "block",
+  marks: "_",
+  atom: true,
+
+  addAttributes() {
+    return {
+      minWords: {
+        default: null as number | null,
+        parseHTML: (element) =>
+          optionalNumber(element.getAttribute("data-min-words")),
+        renderHTML: (attributes) =>
+          attributes.minWords === null
+            ? {}
+            : { "data-min-words": attributes.minWords },
+      },
+      maxWords: {
+        default: null as number | null,
+        parseHTML: (element) =>
+          optionalNumber(element.getAttribute("data-max-words")),
+        renderHTML: (attributes) =>
+          attributes.maxWords === null
+            ? {}
+            : { "data-max-words": attributes.maxWords },
+      },
+      points: {
+        default: 1,
+        parseHTML: (element) =>
+          optionalNumber(element.getAttribute("data-points")) ?? 1,
+        renderHTML: (attributes) => ({ "data-points": attributes.points }),
+      },
+      modelAnswer: {
+        default: "",
+        parseHTML: (element) =>
+          element.getAttribute("data-model-answer") ?? "",
+        renderHTML: (attributes) => ({
+          "data-model-answer": attributes.modelAnswer,
+        }),
+      },
+    };
+  },
+
+  parseHTML() {
+    return [{ tag: `div[data-type="${this.name}"]` }];
+  },
+
+  renderHTML({ node, HTMLAttributes }) {
+    const points = node.attrs.points as number;
+    return [
+      "div",
+      mergeAttributes(HTMLAttributes, { "data-type": this.name }),
+      `Freitextaufgabe (${points} ${points === 1 ? "Punkt" : "Punkte"})`,
+    ];
+  },
+
+  addCommands() {
+    return {
+      insertEssay:
+        () =>
+        ({ commands }) =>
+          commands.insertContent({ type: this.name }),
+    };
+  },
+});
@@ -1,5 +1,7 @@
 import { Node, mergeAttributes } from "@tiptap/core";
 
+import { ChoiceMode, Scoring } from "../choice";
+
 export interface ChoiceOptions {
   answerTypeName: string;
   HTMLAttributes: Record<string, unknown>;
@@ -32,6 +34,31 @@ export default Node.create<ChoiceOptions>({
     return `${this.options.answerTypeName}+`;
   },
 
+  addAttributes() {
+    return {
+      mode: {
+        default: "multiple" as ChoiceMode,
+        parseHTML: (element) =>
+          element.getAttribute("data-mode") === "single"
+            ? "single"
+            : "multiple",
+        renderHTML: (attributes) => ({ "data-mode": attributes.mode }),
+      },
+      scoring: {
+        default: "allOrNothing" as Scoring,
+        parseHTML: (element) =>
+          element.getAttribute("data-scoring") ?? "allOrNothing",
+        renderHTML: (attributes) => ({ "data-scoring": attributes.scoring }),
+      },
+      shuffle: {
+        default: false,
+        parseHTML: (element) =>
+          element.getAttribute("data-shuffle") === "true",
+        renderHTML: (attributes) => ({ "data-shuffle": attributes.shuffle }),
+      },
+    };
+  },
+
   parseHTML() {
     return [
       {
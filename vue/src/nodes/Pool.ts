@@ -0,0 +1,98 @@
+import { Node, mergeAttributes } from "@tiptap/core";
+
+export interface PoolOptions {
+  itemTypeName: string;
+  HTMLAttributes: Record<string, unknown>;
+}
+
+declare module "@tiptap/core" {
+  interface Commands<ReturnType> {
+    pool: {
+      /**
+       * Insert a pool of two exercises from which one is drawn per student
+       */
+      insertPool: () => ReturnType;
+      /**
+       * Add an exercise to the pool around the selection
+       */
+      addPoolItem: () => ReturnType;
+    };
+  }
+}
+
+export default Node.create<PoolOptions>({
+  name: "pool",
+
+  addOptions() {
+    return {
+      itemTypeName: "poolItem",
+      HTMLAttributes: {},
+    };
+  },
+
+  group: "block",
+
+  content() {
+    return `${this.options.itemTypeName}+`;
+  },
+
+  defining: true,
+
+  addAttributes() {
+    return {
+      draw: {
+        default: 1,
+        parseHTML: (element) =>
+          parseInt(element.getAttribute("data-draw") ?? "1", 10) || 1,
+        renderHTML: (attributes) => ({ "data-draw": attributes.draw }),
+      },
+    };
+  },
+
+  parseHTML() {
+    return [{ tag: `div[data-type="${this.name}"]` }];
+  },
+
+  renderHTML({ HTMLAttributes }) {
+    return [
+      "div",
+      mergeAttributes(
+        { "data-type": this.name },
+        this.options.HTMLAttributes,
+        HTMLAttributes
+      ),
+      0,
+    ];
+  },
+
+  addCommands() {
+    const item = () => ({
+      type: this.options.itemTypeName,
+      content: [{ type: "paragraph" }],
+    });
+    return {
+      insertPool:
+        () =>
+        ({ commands }) =>
+          commands.insertContent({
+            type: this.name,
+            attrs: { draw: 1 },
+            content: [item(), item()],
+          }),
+      addPoolItem:
+        () =>
+        ({ state, chain }) => {
+          const { $from } = state.selection;
+          for (let depth = $from.depth; depth > 0; depth--) {
+            if ($from.node(depth).type.name === this.name) {
+              return chain()
+                .insertContentAt($from.end(depth), item())
+                .focus()
+                .run();
+            }
+          }
+          return false;
+        },
+    };
+  },
+});
@@ -0,0 +1,35 @@
+import { Node, mergeAttributes } from "@tiptap/core";
+
+export interface PoolItemOptions {
+  HTMLAttributes: Record<string, unknown>;
+}
+
+export default Node.create<PoolItemOptions>({
+  name: "poolItem",
+
+  addOptions() {
+    return {
+      HTMLAttributes: {},
+    };
+  },
+
+  content: "block+",
+
+  defining: true,
+
+  parseHTML() {
+    return [{ tag: `div[data-type="${this.name}"]` }];
+  },
+
+  renderHTML({ HTMLAttributes }) {
+    return [
+      "div",
+      mergeAttributes(
+        { "data-type": this.name },
+        this.options.HTMLAttributes,
+        HTMLAttributes
+      ),
+      0,
+    ];
+  },
+});
@@ -0,0 +1,75 @@
+import { Node, mergeAttributes } from "@tiptap/core";
+
+export interface Variable {
+  name: string;
+  definition: string;
+}
+
+declare module "@tiptap/core" {
+  interface Commands<ReturnType> {
+    variables: {
+      /**
+       * Insert a list of variables drawn for every student
+       */
+      insertVariables: () => ReturnType;
+    };
+  }
+}
+
+export default Node.create({
+  name: "variables",
+
+  content: "",
+  group: "block",
+  marks: "_",
+  atom: true,
+
+  addAttributes() {
+    return {
+      variables: {
+        default: [] as Variable[],
+        parseHTML: (element) =>
+          (element.textContent ?? "")
+            .split("\n")
+            .filter((line) => line.includes("="))
+            .map((line) => {
+              const [name, ...definition] = line.split("=");
+              return {
+                name: name.trim(),
+                definition: definition.join("=").trim(),
+              };
+            }),
+        renderHTML: () => ({}),
+      },
+    };
+  },
+
+  parseHTML() {
+    return [{ tag: `pre[data-type="${this.name}"]` }];
+  },
+
+  renderHTML({ node }) {
+    const variables = node.attrs.variables as Variable[];
+    return [
+      "pre",
+      mergeAttributes({ "data-type": this.name }),
+      variables.length === 0
+        ? "Variablen"
+        : variables
+            .map((variable) => `${variable.name} = ${variable.definition}`)
+            .join("\n"),
+    ];
+  },
+
+  addCommands() {
+    return {
+      insertVariables:
+        () =>
+        ({ commands }) =>
+          commands.insertContent({
+            type: this.name,
+            attrs: { variables: [{ name: "a", definition: "1..10" }] },
+          }),
+    };
+  },
+});
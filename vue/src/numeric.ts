@@ -0,0 +1,374 @@
+// Checks answers to numeric tasks like the grader of the backend does
+
+type Dimension = number[];
+
+interface Unit {
+  factor: number;
+  dimension: Dimension;
+}
+
+interface NumberValue {
+  value: number;
+  // Trailing zeros of integers may or may not be significant
+  significantFigures: [number, number];
+}
+
+export interface NumericTask {
+  value: number;
+  absoluteTolerance: number;
+  unit?: string;
+  significantFigures?: number;
+}
+
+const METRE = [1, 0, 0, 0, 0, 0, 0];
+const KILOGRAM = [0, 1, 0, 0, 0, 0, 0];
+const SECOND = [0, 0, 1, 0, 0, 0, 0];
+const AMPERE = [0, 0, 0, 1, 0, 0, 0];
+const KELVIN = [0, 0, 0, 0, 1, 0, 0];
+const CELSIUS = [0, 0, 0, 0, 0, 1, 0];
+const RADIAN = [0, 0, 0, 0, 0, 0, 1];
+const NONE = [0, 0, 0, 0, 0, 0, 0];
+const NEWTON = [1, 1, -2, 0, 0, 0, 0];
+const JOULE = [2, 1, -2, 0, 0, 0, 0];
+const WATT = [2, 1, -3, 0, 0, 0, 0];
+const PASCAL = [-1, 1, -2, 0, 0, 0, 0];
+const COULOMB = [0, 0, 1, 1, 0, 0, 0];
+const VOLT = [2, 1, -3, -1, 0, 0, 0];
+const OHM = [2, 1, -3, -2, 0, 0, 0];
+const HERTZ = [0, 0, -1, 0, 0, 0, 0];
+const LITRE = [3, 0, 0, 0, 0, 0, 0];
+
+// Symbol, factor to SI units, dimension and whether the unit takes SI prefixes
+const UNITS: [string, number, Dimension, boolean][] = [
+  ["m", 1, METRE, true],
+  ["g", 1e-3, KILOGRAM, true],
+  ["t", 1e3, KILOGRAM, false],
+  ["s", 1, SECOND, true],
+  ["min", 60, SECOND, false],
+  ["h", 3600, SECOND, false],
+  ["d", 86400, SECOND, false],
+  ["A", 1, AMPERE, true],
+  ["K", 1, KELVIN, true],
+  ["°C", 1, CELSIUS, false],
+  ["rad", 1, RADIAN, true],
+  ["°", Math.PI / 180, RADIAN, false],
+  ["%", 0.01, NONE, false],
+  ["N", 1, NEWTON, true],
+  ["J", 1, JOULE, true],
+  ["Wh", 3600, JOULE, true],
+  ["eV", 1.602176634e-19, JOULE, true],
+  ["W", 1, WATT, true],
+  ["Pa", 1, PASCAL, true],
+  ["bar", 1e5, PASCAL, true],
+  ["C", 1, COULOMB, true],
+  ["V", 1, VOLT, true],
+  ["Ω", 1, OHM, true],
+  ["Ohm", 1, OHM, true],
+  ["Hz", 1, HERTZ, true],
+  ["l", 1e-3, LITRE, true],
+  ["L", 1e-3, LITRE, true],
+  ["a", 100, [2, 0, 0, 0, 0, 0, 0], true],
+];
+
+const PREFIXES: [string, number][] = [
+  ["da", 1e1],
+  ["T", 1e12],
+  ["G", 1e9],
+  ["M", 1e6],
+  ["k", 1e3],
+  ["h", 1e2],
+  ["d", 1e-1],
+  ["c", 1e-2],
+  ["m", 1e-3],
+  ["µ", 1e-6],
+  ["μ", 1e-6],
+  ["u", 1e-6],
+  ["n", 1e-9],
+  ["p", 1e-12],
+];
+
+const SUPERSCRIPTS = "⁰¹²³⁴⁵⁶⁷⁸⁹";
+
+function splitSignificantFigures(spec: string): [string, number?] | null {
+  const match = /^(.*)\(\s*(\d*)\s*sig[^)]*\)$/.exec(spec.trim());
+  if (match === null) {
+    return [spec.trim()];
+  }
+  if (match[2] === "") {
+    return null;
+  }
+  return [match[1].trim(), parseInt(match[2])];
+}
+
+function splitTolerance(spec: string): [string, string] | null {
+  for (const separator of ["±", "+/-", "+-"]) {
+    const i = spec.indexOf(separator);
+    if (i >= 0) {
+      return [spec.slice(0, i), spec.slice(i + separator.length)];
+    }
+  }
+  return null;
+}
+
+// The last separator is the decimal separator if it is the only one or both
+// kinds are used, repeated separators group thousands
+function normalizeSeparators(digits: string): string | null {
+  if (!/\d/.test(digits)) {
+    return null;
+  }
+  const points = digits.split(".").length - 1;
+  const commas = digits.split(",").length - 1;
+  const grouping =
+    points + commas === 0 ||
+    (commas === 0 && points > 1) ||
+    (points === 0 && commas > 1);
+  const decimal = grouping
+    ? -1
+    : Math.max(digits.lastIndexOf("."), digits.lastIndexOf(","));
+  let normalized = "";
+  for (let i = 0; i < digits.length; i++) {
+    const c = digits[i];
+    if (i === decimal) {
+      normalized += ".";
+    } else if (c === "." || c === ",") {
+      if (decimal >= 0 && i > decimal) {
+        return null;
+      }
+    } else {
+      normalized += c;
+    }
+  }
+  return normalized;
+}
+
+// Parses exponents like `e-3`, `·10^5` or `× 10⁻³`
+function parseExponent(text: string): [number, string] {
+  const match =
+    /^[eE]\{?([-−⁻+⁺]?[0-9⁰¹²³⁴⁵⁶⁷⁸⁹]+)\}?/.exec(text) ??
+    /^\s*[·*×x⋅]\s*10\^?\{?([-−⁻+⁺]?[0-9⁰¹²³⁴⁵⁶⁷⁸⁹]+)\}?/.exec(text);
+  if (match === null) {
+    return [0, text];
+  }
+  const exponent = Array.from(match[1])
+    .map((c) => {
+      if ("-−⁻".includes(c)) return "-";
+      if ("+⁺".includes(c)) return "+";
+      const superscript = SUPERSCRIPTS.indexOf(c);
+      return superscript >= 0 ? superscript.toString() : c;
+    })
+    .join("");
+  return [parseInt(exponent), text.slice(match[0].length)];
+}
+
+function significantFigures(mantissa: string): [number, number] {
+  const digits = mantissa.replace(/^[0.]+/, "");
+  const count = digits.replace(".", "").length;
+  if (count === 0) {
+    // Zero has as many significant figures as it has decimals
+    const figures = Math.max(mantissa.split(".")[1]?.length ?? 0, 1);
+    return [figures, figures];
+  } else if (mantissa.includes(".")) {
+    return [count, count];
+  } else {
+    const trailing = digits.length - digits.replace(/0+$/, "").length;
+    return [count - trailing, count];
+  }
+}
+
+function parseQuantity(text: string): [NumberValue, string?] | null {
+  const trimmed = text.trim();
+  const sign = /^[-−+]\s*/.exec(trimmed);
+  const unsigned = sign === null ? trimmed : trimmed.slice(sign[0].length);
+  const length = /^[\d.,]*/.exec(unsigned)?.[0].length ?? 0;
+  const mantissa = normalizeSeparators(unsigned.slice(0, length));
+  if (mantissa === null) {
+    return null;
+  }
+  const [exponent, rest] = parseExponent(unsigned.slice(length));
+  const value = parseFloat(mantissa) * Math.pow(10, exponent);
+  const unit = rest.trim();
+  return [
+    {
+      value: sign !== null && sign[0][0] !== "+" ? -value : value,
+      significantFigures: significantFigures(mantissa),
+    },
+    unit === "" ? undefined : unit,
+  ];
+}
+
+function baseUnit(symbol: string): Unit | null {
+  const base = UNITS.find(([name]) => name === symbol);
+  if (base !== undefined) {
+    return { factor: base[1], dimension: base[2] };
+  }
+  for (const [prefix, scale] of PREFIXES) {
+    if (symbol.startsWith(prefix)) {
+      const rest = symbol.slice(prefix.length);
+      const prefixed = UNITS.find(
+        ([name, , , prefixable]) => prefixable && name === rest
+      );
+      if (prefixed !== undefined) {
+        return { factor: prefixed[1] * scale, dimension: prefixed[2] };
+      }
+    }
+  }
+  return null;
+}
+
+function pow(unit: Unit, exponent: number): Unit {
+  return {
+    factor: Math.pow(unit.factor, exponent),
+    dimension: unit.dimension.map((d) => d * exponent),
+  };
+}
+
+function mul(a: Unit, b: Unit): Unit {
+  return {
+    factor: a.factor * b.factor,
+    dimension: a.dimension.map((d, i) => d + b.dimension[i]),
+  };
+}
+
+// Parses a factor like `m`, `cm²`, `s^-2` or `m2`
+function parseFactor(factor: string): Unit | null {
+  const match = /^([^\^\d⁻²³]+)(.*)$/.exec(factor);
+  if (match === null) {
+    return null;
+  }
+  const exponents: Record<string, number> = {
+    "": 1,
+    "²": 2,
+    "³": 3,
+    "⁻¹": -1,
+    "⁻²": -2,
+    "⁻³": -3,
+  };
+  const written = match[2].replace(/^\^+/, "");
+  const integer = /^[-−]?\d+$/.test(written);
+  const exponent =
+    exponents[written] ??
+    (integer ? parseInt(written.replace("−", "-")) : NaN);
+  const unit = baseUnit(match[1]);
+  return unit === null || isNaN(exponent) ? null : pow(unit, exponent);
+}
+
+function parseProduct(text: string): Unit | null {
+  let product: Unit | null = { factor: 1, dimension: NONE };
+  for (const factor of text.split(/[·*⋅\s]/).filter((f) => f !== "")) {
+    const unit = parseFactor(factor);
+    product = unit === null || product === null ? null : mul(product, unit);
+  }
+  return product;
+}
+
+function parseUnit(text: string): Unit | null {
+  const slash = text.indexOf("/");
+  const numerator = (slash >= 0 ? text.slice(0, slash) : text).trim();
+  let unit = parseProduct(numerator === "1" ? "" : numerator);
+  if (slash >= 0 && unit !== null) {
+    const denominator = parseProduct(
+      text
+        .slice(slash + 1)
+        .trim()
+        .replace(/^\(/, "")
+        .replace(/\)$/, "")
+    );
+    unit = denominator === null ? null : mul(unit, pow(denominator, -1));
+  }
+  return unit;
+}
+
+// Units which are not known only match themselves
+function convert(value: number, from: string, to: string): number | null {
+  const strip = (unit: string) => unit.replace(/\s+/g, "");
+  if (strip(from) === strip(to)) {
+    return value;
+  }
+  const [fromUnit, toUnit] = [parseUnit(from), parseUnit(to)];
+  if (
+    fromUnit === null ||
+    toUnit === null ||
+    fromUnit.dimension.some((d, i) => d !== toUnit.dimension[i])
+  ) {
+    return null;
+  }
+  return (value * fromUnit.factor) / toUnit.factor;
+}
+
+export function parseNumericTask(spec: string): NumericTask | null {
+  const split = splitSignificantFigures(spec);
+  if (split === null) {
+    return null;
+  }
+  const [rest, significantFigures] = split;
+  const [quantity, toleranceText] = splitTolerance(rest) ?? [rest, undefined];
+  const parsed = parseQuantity(quantity);
+  if (parsed === null) {
+    return null;
+  }
+  const [number] = parsed;
+  let unit = parsed[1];
+  let absoluteTolerance = 0;
+  if (toleranceText !== undefined) {
+    const tolerance = parseQuantity(toleranceText);
+    if (tolerance === null) {
+      return null;
+    }
+    const [amount, toleranceUnit] = tolerance;
+    if (toleranceUnit === undefined) {
+      absoluteTolerance = amount.value;
+    } else if (toleranceUnit === "%") {
+      absoluteTolerance = (amount.value / 100) * number.value;
+    } else if (unit === undefined) {
+      // The unit may be given once after the tolerance
+      unit = toleranceUnit;
+      absoluteTolerance = amount.value;
+    } else {
+      const converted = convert(amount.value, toleranceUnit, unit);
+      if (converted === null) {
+        return null;
+      }
+      absoluteTolerance = converted;
+    }
+  }
+  return {
+    value: number.value,
+    absoluteTolerance: Math.abs(absoluteTolerance),
+    unit,
+    significantFigures,
+  };
+}
+
+export function checkNumericAnswer(task: NumericTask, answer: string): boolean {
+  const parsed = parseQuantity(answer);
+  if (parsed === null) {
+    return false;
+  }
+  const [number, unit] = parsed;
+  let value: number | null = number.value;
+  if (task.unit !== undefined && unit !== undefined) {
+    value = convert(number.value, unit, task.unit);
+  } else if (task.unit !== undefined || unit !== undefined) {
+    value = null;
+  }
+  if (value === null) {
+    return false;
+  }
+  if (task.significantFigures !== undefined) {
+    const [min, max] = number.significantFigures;
+    if (task.significantFigures < min || task.significantFigures > max) {
+      return false;
+    }
+  }
+  // Allow for the rounding errors of unit conversions
+  const epsilon = 1e-9 * Math.max(Math.abs(task.value), Number.MIN_VALUE);
+  return Math.abs(value - task.value) <= task.absoluteTolerance + epsilon;
+}
+
+// Solutions which cannot be parsed are compared like gaps
+export function checkNumeric(solution: string, answer: string): boolean {
+  const task = parseNumericTask(solution);
+  return task === null
+    ? answer.trim() === solution.trim()
+    : checkNumericAnswer(task, answer);
+}
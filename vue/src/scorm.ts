@@ -0,0 +1,102 @@
+// Minimal wrapper around the SCORM 1.2 and 2004 runtime APIs provided by the LMS
+// See https://scorm.com/scorm-explained/technical-scorm/run-time/
+
+interface Scorm12API {
+  LMSInitialize(parameter: ""): string;
+  LMSFinish(parameter: ""): string;
+  LMSSetValue(element: string, value: string): string;
+  LMSCommit(parameter: ""): string;
+}
+
+interface Scorm2004API {
+  Initialize(parameter: ""): string;
+  Terminate(parameter: ""): string;
+  SetValue(element: string, value: string): string;
+  Commit(parameter: ""): string;
+}
+
+type ScormWindow = Window & {
+  API?: Scorm12API;
+  API_1484_11?: Scorm2004API;
+};
+
+type Runtime =
+  | { version: "1.2"; api: Scorm12API }
+  | { version: "2004"; api: Scorm2004API };
+
+// The LMS provides the API in one of the parent frames or the window that opened the package
+function findRuntime(): Runtime | null {
+  const candidates: Window[] = [];
+  let current: Window = window;
+  for (let depth = 0; depth < 10; depth += 1) {
+    candidates.push(current);
+    if (current.parent === current) break;
+    current = current.parent;
+  }
+  if (window.opener) candidates.push(window.opener as Window);
+
+  for (const candidate of candidates) {
+    try {
+      const scormWindow = candidate as ScormWindow;
+      if (scormWindow.API_1484_11) {
+        return { version: "2004", api: scormWindow.API_1484_11 };
+      }
+      if (scormWindow.API) {
+        return { version: "1.2", api: scormWindow.API };
+      }
+    } catch (e) {
+      // Frames of other origins cannot be accessed
+    }
+  }
+  return null;
+}
+
+let runtime: Runtime | null = null;
+let finished = false;
+
+export function initialize() {
+  runtime = findRuntime();
+  if (runtime === null) {
+    console.log("No SCORM runtime found, results will not be reported");
+  } else if (runtime.version === "1.2") {
+    runtime.api.LMSInitialize("");
+    runtime.api.LMSSetValue("cmi.core.lesson_status", "incomplete");
+    runtime.api.LMSCommit("");
+  } else {
+    runtime.api.Initialize("");
+    runtime.api.SetValue("cmi.completion_status", "incomplete");
+    runtime.api.Commit("");
+  }
+  window.addEventListener("pagehide", finish);
+}
+
+export function reportScore(achievedPoints: number, totalPoints: number) {
+  if (runtime === null || finished) return;
+  const raw = achievedPoints.toString();
+  const max = totalPoints.toString();
+  if (runtime.version === "1.2") {
+    runtime.api.LMSSetValue("cmi.core.score.min", "0");
+    runtime.api.LMSSetValue("cmi.core.score.max", max);
+    runtime.api.LMSSetValue("cmi.core.score.raw", raw);
+    runtime.api.LMSSetValue("cmi.core.lesson_status", "completed");
+    runtime.api.LMSCommit("");
+  } else {
+    const scaled = totalPoints > 0 ? achievedPoints / totalPoints : 1;
+    runtime.api.SetValue("cmi.score.min", "0");
+    runtime.api.SetValue("cmi.score.max", max);
+    runtime.api.SetValue("cmi.score.raw", raw);
+    runtime.api.SetValue("cmi.score.scaled", scaled.toFixed(4));
+    runtime.api.SetValue("cmi.completion_status", "completed");
+    runtime.api.Commit("");
+  }
+}
+
+export function finish() {
+  if (runtime === null || finished) return;
+  finished = true;
+  if (runtime.version === "1.2") {
+    runtime.api.LMSFinish("");
+  } else {
+    runtime.api.Terminate("");
+  }
+}
@@ -1,17 +1,39 @@
+let uploadURL = "";
+
+// Without an upload URL (e.g. in the demo) files are only kept in the browser
+export function setUploadURL(url: string) {
+  uploadURL = url;
+}
+
+async function store(file: File): Promise<{ url: string; mimetype: string }> {
+  if (!uploadURL) {
+    const blob = new Blob([file], { type: file.type });
+    return { url: URL.createObjectURL(blob), mimetype: blob.type };
+  }
+  const body = new FormData();
+  body.append("file", file);
+  const response = await fetch(uploadURL, { method: "POST", body });
+  if (!response.ok) {
+    throw new Error(`Upload failed with status ${response.status}`);
+  }
+  return await response.json();
+}
+
 export function upload(callback: (url: string, mimetype: string) => void) {
   const input = document.getElementById("file-input") as HTMLInputElement;
 
   input.value = "";
 
-  input.onchange = () => {
+  input.onchange = async () => {
     if (!input.value || !input.files) return;
-
-    const file = new Blob([input.files[0]], { type: input.files[0].type });
-    const url = URL.createObjectURL(file);
-
-    callback(url, file.type);
-
     input.onchange = null;
+
+    try {
+      const media = await store(input.files[0]);
+      callback(media.url, media.mimetype);
+    } catch (e) {
+      console.log("Error while uploading:", e);
+    }
   };
 
   input.click();